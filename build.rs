@@ -0,0 +1,42 @@
+fn 
+main() {
+    // gRPC stubs are only generated for builds with the `grpc` feature; the
+    // vendored protoc means no system protobuf install is needed
+    #[cfg(feature = "grpc")]
+    {
+        std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path().expect("vendored protoc"));
+        tonic_build::compile_protos("proto/c2pa_walkthrough.proto").expect("compile protos");
+    }
+
+    // Link flags for the Node.js addon in `node`, which takes N-API from the node binary loading it
+    #[cfg(feature = "node")]
+    napi_build::setup();
+
+    // The C header for the `ffi` module, kept in include/ for C and C++ callers
+    #[cfg(feature = "ffi")]
+    {
+        println!("cargo:rerun-if-changed=src/ffi.rs");
+        let config = cbindgen::Config {
+            language: cbindgen::Language::C,
+            include_guard: Some("C2PA_WALKTHROUGH_H".to_owned()),
+            autogen_warning: Some("/* Generated from src/ffi.rs by build.rs; don't edit */".to_owned()),
+            header: Some(concat!(
+                "/*\n",
+                " * Strings are NUL-terminated UTF-8. Functions return 0 or a string on\n",
+                " * success and -1 or NULL on failure; c2pa_walkthrough_last_error() then\n",
+                " * says why. Returned strings are freed with c2pa_walkthrough_free_string().\n",
+                " * See src/ffi.rs for each function.\n",
+                " */"
+            ).to_owned()),
+            // cbindgen can't reflow the /** */ comments in src/ffi.rs
+            documentation: false,
+            ..Default::default()
+        };
+        cbindgen::Builder::new()
+            .with_config(config)
+            .with_src("src/ffi.rs")
+            .generate()
+            .expect("generate the C header")
+            .write_to_file("include/c2pa_walkthrough.h");
+    }
+}
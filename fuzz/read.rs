@@ -0,0 +1,6 @@
+#![no_main]
+
+/* libFuzzer target over the reading path, taking each input as a JPEG; seed it with a `gen-corpus` directory */
+libfuzzer_sys::fuzz_target!(|data: &[u8]| {
+    c2pa_walkthrough::corpus::read_all("jpg", data);
+});
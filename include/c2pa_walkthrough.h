@@ -0,0 +1,31 @@
+/*
+ * Strings are NUL-terminated UTF-8. Functions return 0 or a string on
+ * success and -1 or NULL on failure; c2pa_walkthrough_last_error() then
+ * says why. Returned strings are freed with c2pa_walkthrough_free_string().
+ * See src/ffi.rs for each function.
+ */
+
+#ifndef C2PA_WALKTHROUGH_H
+#define C2PA_WALKTHROUGH_H
+
+/* Generated from src/ffi.rs by build.rs; don't edit */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+int c2pa_walkthrough_sign_file(const char *source,
+                               const char *dest,
+                               const char *manifest_json,
+                               const char *signer_profile);
+
+char *c2pa_walkthrough_verify_file(const char *path);
+
+char *c2pa_walkthrough_read_manifest_json(const char *path);
+
+char *c2pa_walkthrough_last_error(void);
+
+void c2pa_walkthrough_free_string(char *s);
+
+#endif  /* C2PA_WALKTHROUGH_H */
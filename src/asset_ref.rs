@@ -0,0 +1,89 @@
+use serde::{Deserialize, Serialize};
+
+use crate::storage::{percent_encode, read_asset};
+
+/*
+ * Asset reference assertions (c2pa.asset-ref), recording where an asset
+ * is published, e.g. on a CDN or a newsroom's archive. Copies travel far
+ * from where they were published, often re-encoded with their manifest
+ * stripped or kept; the assertion lets whoever holds one find the
+ * authoritative copy and compare it. URLs are given as templates, where
+ * {name} stands for the file name of each signed asset, so one template
+ * covers a batch.
+ */
+
+pub const ASSET_REF_LABEL: &str = "c2pa.asset-ref";
+
+// Placeholder for the signed asset's file name in a published URL template
+pub const NAME_PLACEHOLDER: &str = "{name}";
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Uri {
+    pub uri: String
+}
+
+/* One location an asset is published at */
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AssetReference {
+    pub reference: Uri,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>
+}
+
+/* The asset reference assertion */
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AssetRef {
+    pub references: Vec<AssetReference>
+}
+
+/* Checks a published URL template, e.g. https://cdn.example.com/photos/{name} */
+pub fn 
+parse_published_url (template: &str) -> Result<String, c2pa::Error> {
+    match template.starts_with("https://") || template.starts_with("http://") {
+        true => Ok(template.to_owned()),
+        false => Err(c2pa::Error::BadParam(format!("'{}' isn't an http(s) URL for the published asset", template)))
+    }
+}
+
+/* A published URL template filled in with the file name an asset is signed as */
+pub fn 
+published_url (template: &str, file_name: &str) -> String {
+    template.replace(NAME_PLACEHOLDER, &percent_encode(file_name))
+}
+
+/**
+ * The asset reference assertion recording the URLs an asset is published
+ * at, as a manifest-definition entry.
+ */
+pub fn 
+asset_ref_assertion (urls: &[String]) -> Result<serde_json::Value, c2pa::Error> {
+    let asset_ref = AssetRef {
+        references: urls.iter()
+            .map(|url| AssetReference { reference: Uri { uri: url.clone() }, description: None })
+            .collect()
+    };
+
+    Ok(serde_json::json!({"label": ASSET_REF_LABEL, "data": serde_json::to_value(&asset_ref)?}))
+}
+
+/* The published URLs recorded in the active manifest of a store, as `read` prints it */
+pub fn 
+published_urls (store_json: &serde_json::Value) -> Vec<String> {
+    let active = store_json["active_manifest"].as_str().unwrap_or_default();
+    let assertions = store_json["manifests"][active]["assertions"].as_array().cloned().unwrap_or_default();
+
+    assertions.into_iter()
+        .filter(|assertion| assertion["label"] == ASSET_REF_LABEL)
+        .filter_map(|assertion| serde_json::from_value::<AssetRef>(assertion["data"].clone()).ok())
+        .flat_map(|asset_ref| asset_ref.references.into_iter().map(|reference| reference.reference.uri))
+        .collect()
+}
+
+/**
+ * Whether the copy published at `url` is byte for byte `asset`. Errors if
+ * it can't be fetched.
+ */
+pub fn 
+matches_published (url: &str, asset: &[u8]) -> Result<bool, c2pa::Error> {
+    Ok(read_asset(url)? == asset)
+}
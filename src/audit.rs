@@ -0,0 +1,154 @@
+/*
+ * SQLite audit log of signing operations, with the `audit` feature. Once a
+ * log is opened with `open`, every asset signed by this process (CLI,
+ * server, daemon or worker) is recorded with the hashes of its input and
+ * output, the active manifest label, the fingerprint of the signing
+ * certificate, the time and the operator. The operator is taken from
+ * `C2PA_OPERATOR`, falling back to the login name in `USER`.
+ */
+
+use std::path::Path;
+use std::sync::Mutex;
+
+use c2pa::Signer;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::read::load_manifest_store;
+
+// The log every signing operation is written to, once opened
+static AUDIT_LOG: Mutex<Option<Connection>> = Mutex::new(None);
+
+const SCHEMA: &str = "CREATE TABLE IF NOT EXISTS signings (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    signed_at TEXT NOT NULL,
+    operator TEXT NOT NULL,
+    format TEXT NOT NULL,
+    input_sha256 TEXT NOT NULL,
+    output_sha256 TEXT NOT NULL,
+    manifest_label TEXT,
+    signer_fingerprint TEXT NOT NULL
+)";
+
+const COLUMNS: &str = "id, signed_at, operator, format, input_sha256, output_sha256, manifest_label, signer_fingerprint";
+
+#[derive(Debug, Serialize)]
+/* One recorded signing operation */
+pub struct AuditEntry {
+    pub id: i64,
+    pub signed_at: String,
+    pub operator: String,
+    pub format: String,
+    pub input_sha256: String,
+    pub output_sha256: String,
+    pub manifest_label: Option<String>,
+    // SHA-256 of the DER encoding of the end-entity signing certificate
+    pub signer_fingerprint: String
+}
+
+impl AuditEntry {
+    fn 
+    from_row (row: &Row) -> rusqlite::Result<AuditEntry> {
+        Ok(AuditEntry {
+            id: row.get(0)?,
+            signed_at: row.get(1)?,
+            operator: row.get(2)?,
+            format: row.get(3)?,
+            input_sha256: row.get(4)?,
+            output_sha256: row.get(5)?,
+            manifest_label: row.get(6)?,
+            signer_fingerprint: row.get(7)?
+        })
+    }
+}
+
+fn 
+db_error (e: rusqlite::Error) -> c2pa::Error {
+    c2pa::Error::OtherError(Box::new(e))
+}
+
+fn 
+sha256_hex (bytes: &[u8]) -> String {
+    Sha256::digest(bytes).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn 
+operator () -> String {
+    std::env::var("C2PA_OPERATOR")
+        .or_else(|_| std::env::var("USER"))
+        .unwrap_or_else(|_| "unknown".to_owned())
+}
+
+fn 
+connect (path: &Path) -> Result<Connection, c2pa::Error> {
+    let conn = Connection::open(path).map_err(db_error)?;
+    conn.execute(SCHEMA, []).map_err(db_error)?;
+    Ok(conn)
+}
+
+/**
+ * Opens (creating if needed) the audit log at `path`; every subsequent
+ * signing operation in this process is recorded to it.
+ */
+pub fn 
+open (path: &Path) -> Result<(), c2pa::Error> {
+    let conn = connect(path)?;
+    *AUDIT_LOG.lock().unwrap() = Some(conn);
+    Ok(())
+}
+
+/**
+ * Records the signing of `input` into `output` if an audit log is open.
+ * An asset whose signing can't be recorded is treated as a failed signing.
+ */
+pub fn 
+record_signing (format: &str, input: &[u8], output: &[u8], signer: &dyn Signer) -> Result<(), c2pa::Error> {
+    let mut log = AUDIT_LOG.lock().unwrap();
+    let Some(conn) = log.as_mut() else {
+        return Ok(());
+    };
+
+    let certs = signer.certs()?;
+    let signing_cert = certs.first()
+        .ok_or_else(|| c2pa::Error::BadParam("signer has no certificates".to_owned()))?;
+
+    // The label is assigned while embedding, so read it back from the output
+    let manifest_label = load_manifest_store(format, output).ok()
+        .and_then(|store| store.active_label().map(str::to_owned));
+
+    conn.execute(
+        "INSERT INTO signings (signed_at, operator, format, input_sha256, output_sha256, manifest_label, signer_fingerprint)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            chrono::Utc::now().to_rfc3339(),
+            operator(),
+            format,
+            sha256_hex(input),
+            sha256_hex(output),
+            manifest_label,
+            sha256_hex(signing_cert)
+        ]
+    ).map_err(db_error)?;
+
+    Ok(())
+}
+
+/* Every entry in the audit log at `path`, oldest first */
+pub fn 
+list (path: &Path) -> Result<Vec<AuditEntry>, c2pa::Error> {
+    let conn = connect(path)?;
+    let mut stmt = conn.prepare(&format!("SELECT {} FROM signings ORDER BY id", COLUMNS)).map_err(db_error)?;
+    let entries = stmt.query_map([], AuditEntry::from_row).map_err(db_error)?;
+
+    entries.collect::<rusqlite::Result<Vec<_>>>().map_err(db_error)
+}
+
+/* The entry with the given id in the audit log at `path`, if there is one */
+pub fn 
+show (path: &Path, id: i64) -> Result<Option<AuditEntry>, c2pa::Error> {
+    let conn = connect(path)?;
+    conn.query_row(&format!("SELECT {} FROM signings WHERE id = ?1", COLUMNS), [id], AuditEntry::from_row)
+        .optional()
+        .map_err(db_error)
+}
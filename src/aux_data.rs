@@ -0,0 +1,193 @@
+use std::path::Path;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::namespace;
+use crate::sign::append_assertions;
+use crate::storage::{read_asset, read_file};
+
+/*
+ * Auxiliary data from computational-photography pipelines, such as depth
+ * maps and segmentation masks, recorded in a manifest so the claim covers
+ * it. Each piece is an assertion labelled `aux.` plus its kind in the
+ * label namespace, e.g. com.mikecvet.aux.depth-map, giving its media type,
+ * size and SHA-256 hash. Embedded data is carried in the assertion itself, base64 encoded so
+ * it reads back as it went in; linked data is kept in a file beside the
+ * asset, which the assertion names, and is tied to the claim by its hash.
+ * Linking suits large data such as full-resolution depth maps, which would
+ * otherwise dwarf the rest of the manifest.
+ */
+
+// Name of auxiliary data assertions in the label namespace, before their kind
+pub const AUX_DATA_NAME: &str = "aux";
+
+// The kinds pipelines most often attach; any lowercase name of letters, digits and hyphens is accepted
+pub const KNOWN_KINDS: [&str; 4] = ["depth-map", "segmentation-mask", "confidence-map", "normal-map"];
+
+/* The assertion recording one piece of auxiliary data */
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AuxData {
+    // Media type, e.g. image/png
+    pub format: String,
+    // The data, base64 encoded, when it is embedded
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<String>,
+    // Where linked data is kept, relative to the asset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    pub alg: String,
+    // Hash of the data, base64 encoded
+    pub hash: String,
+    pub size: u64
+}
+
+/* Auxiliary data found in a manifest */
+pub struct FoundAuxData {
+    // Label of the assertion, e.g. com.mikecvet.aux.depth-map or, for a second one, com.mikecvet.aux.depth-map__1
+    pub label: String,
+    pub kind: String,
+    pub aux: AuxData
+}
+
+/* The part of an auxiliary data assertion's label after `<namespace>.aux.`, e.g. depth-map__1, or None for other labels */
+pub fn 
+aux_name (label: &str) -> Option<&str> {
+    namespace::strip_namespace(label)?.strip_prefix(AUX_DATA_NAME)?.strip_prefix('.')
+}
+
+fn 
+check_kind (kind: &str) -> Result<(), c2pa::Error> {
+    match !kind.is_empty() && kind.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-') {
+        true => Ok(()),
+        false => Err(c2pa::Error::BadParam(format!("'{}' isn't a kind of auxiliary data; use lowercase letters, digits and hyphens, e.g. {}", kind, KNOWN_KINDS.join(", "))))
+    }
+}
+
+/**
+ * Media type of a file, from its extension. The SDK knows the media
+ * formats; JSON, for edit histories and the like, is added, and anything
+ * else is application/octet-stream.
+ */
+pub fn 
+media_type (path: &Path) -> String {
+    match c2pa::format_from_path(path) {
+        Some(format) if format.contains('/') => format,
+        Some(format) if format == "json" => "application/json".to_owned(),
+        _ => "application/octet-stream".to_owned()
+    }
+}
+
+/* Parses a `kind=path` argument, e.g. depth-map=depth.png */
+pub fn 
+parse_aux_arg (arg: &str) -> Result<(String, String), c2pa::Error> {
+    let (kind, path) = arg.split_once('=')
+        .filter(|(_, path)| !path.is_empty())
+        .ok_or_else(|| c2pa::Error::BadParam(format!("'{}' isn't KIND=PATH, e.g. depth-map=depth.png", arg)))?;
+    check_kind(kind)?;
+
+    Ok((kind.to_owned(), path.to_owned()))
+}
+
+/**
+ * The assertion, as a manifest-definition entry, recording the file at
+ * `path` as auxiliary data of `kind`, embedded or, with `link`, referenced
+ * by its file name.
+ */
+pub fn 
+aux_assertion (kind: &str, path: &Path, link: bool) -> Result<serde_json::Value, c2pa::Error> {
+    check_kind(kind)?;
+    let data = read_file(path).map_err(|e| c2pa::Error::BadParam(format!("{}: {}", path.display(), e)))?;
+    let format = media_type(path);
+    let file_name = path.file_name().and_then(|name| name.to_str())
+        .ok_or_else(|| c2pa::Error::BadParam(format!("{} has no file name to link it by", path.display())))?;
+
+    let aux = AuxData {
+        format,
+        data: (!link).then(|| BASE64.encode(&data)),
+        url: link.then(|| file_name.to_owned()),
+        alg: "sha256".to_owned(),
+        hash: BASE64.encode(Sha256::digest(&data)),
+        size: data.len() as u64
+    };
+
+    Ok(serde_json::json!({"label": namespace::label(&format!("{}.{}", AUX_DATA_NAME, kind)), "data": serde_json::to_value(&aux)?}))
+}
+
+/**
+ * Adds auxiliary data to a manifest definition, one assertion per `(kind,
+ * path)`, after any assertions the definition already has. Returns how
+ * many were added.
+ */
+pub fn 
+add_aux_data (definition: &mut serde_json::Value, aux: &[(String, String)], link: bool) -> Result<usize, c2pa::Error> {
+    let assertions = aux.iter()
+        .map(|(kind, path)| aux_assertion(kind, Path::new(path), link))
+        .collect::<Result<Vec<_>, _>>()?;
+    append_assertions(definition, assertions)?;
+
+    Ok(aux.len())
+}
+
+/* The auxiliary data in the active manifest of a store, as `read` prints it */
+pub fn 
+aux_data (store_json: &serde_json::Value) -> Result<Vec<FoundAuxData>, c2pa::Error> {
+    let active = store_json["active_manifest"].as_str().unwrap_or_default();
+    let assertions = store_json["manifests"][active]["assertions"].as_array().cloned().unwrap_or_default();
+
+    assertions.into_iter()
+        .filter_map(|assertion| {
+            let base_label = assertion["label"].as_str()?;
+            let kind = aux_name(base_label)?.to_owned();
+            // The SDK gives repeated labels an instance number, as the manifest store numbers them with __1 and on
+            let label = match assertion["instance"].as_u64() {
+                Some(instance) if instance > 0 => format!("{}__{}", base_label, instance),
+                _ => base_label.to_owned()
+            };
+            Some(serde_json::from_value::<AuxData>(assertion["data"].clone())
+                .map(|aux| FoundAuxData { label, kind, aux })
+                .map_err(|e| c2pa::Error::BadParam(format!("{} isn't valid auxiliary data: {}", assertion["label"], e))))
+        })
+        .collect()
+}
+
+/* Location of linked data named `url`, beside the asset at `asset_location` unless the URL is absolute */
+pub fn 
+linked_location (asset_location: &str, url: &str) -> String {
+    if url.contains("://") || url.starts_with('/') {
+        return url.to_owned();
+    }
+    let path = asset_location.split(['?', '#']).next().unwrap_or(asset_location);
+    match path.rfind(['/', '\\']) {
+        Some(separator) => format!("{}{}", &path[..=separator], url),
+        None => url.to_owned()
+    }
+}
+
+/**
+ * The bytes of a piece of auxiliary data, decoded or fetched from beside
+ * the asset at `asset_location`, once checked against the size and hash
+ * the claim recorded for them.
+ */
+pub fn 
+load (aux: &AuxData, asset_location: &str) -> Result<Vec<u8>, c2pa::Error> {
+    if aux.alg != "sha256" {
+        return Err(c2pa::Error::BadParam(format!("auxiliary data hashed with {}, which isn't supported", aux.alg)));
+    }
+    let data = match (&aux.data, &aux.url) {
+        (Some(data), _) => BASE64.decode(data).map_err(|e| c2pa::Error::BadParam(format!("embedded data isn't base64: {}", e)))?,
+        (None, Some(url)) => {
+            let location = linked_location(asset_location, url);
+            read_asset(&location).map_err(|e| c2pa::Error::BadParam(format!("can't read linked data {}: {}", location, e)))?
+        },
+        (None, None) => return Err(c2pa::Error::BadParam("neither embedded nor linked".to_owned()))
+    };
+
+    if data.len() as u64 != aux.size || BASE64.encode(Sha256::digest(&data)) != aux.hash {
+        return Err(c2pa::Error::HashMismatch(format!("the data doesn't match the hash the claim records ({} bytes, expected {})", data.len(), aux.size)));
+    }
+
+    Ok(data)
+}
@@ -0,0 +1,282 @@
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+
+use glob::{MatchOptions, Pattern};
+
+/*
+ * Selection of the files to process when a batch command is given a
+ * directory. Directories are walked recursively and each file is kept or
+ * skipped by include/exclude globs and size limits. A pattern without a
+ * slash matches file names anywhere (`*.jpg`); one with a slash matches the
+ * path relative to the walked directory, where `**` spans any number of
+ * directories, as in .gitignore. The files selected can then be worked
+ * through on several threads at once.
+ */
+
+const MATCH_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: false
+};
+
+/* Which files found in a directory are processed */
+#[derive(Default)]
+pub struct Filters {
+    // if any are given, a file must match one of them
+    pub include: Vec<Pattern>,
+    pub exclude: Vec<Pattern>,
+    pub min_size: Option<u64>,
+    pub max_size: Option<u64>
+}
+
+impl Filters {
+    pub fn new(include: &[String], exclude: &[String], min_size: Option<u64>, max_size: Option<u64>) -> Result<Filters, c2pa::Error> {
+        Ok(Filters {
+            include: include.iter().map(|pattern| compile(pattern)).collect::<Result<_, _>>()?,
+            exclude: exclude.iter().map(|pattern| compile(pattern)).collect::<Result<_, _>>()?,
+            min_size,
+            max_size
+        })
+    }
+
+    /* Whether a file at `relative` (with / separators) of `size` bytes is processed */
+    pub fn matches(&self, relative: &str, size: u64) -> bool {
+        let matching = |pattern: &Pattern| {
+            let name = if pattern.as_str().contains('/') { relative } else { relative.rsplit('/').next().unwrap_or(relative) };
+            pattern.matches_with(name, MATCH_OPTIONS)
+        };
+
+        (self.include.is_empty() || self.include.iter().any(matching))
+            && !self.exclude.iter().any(matching)
+            && self.min_size.is_none_or(|min| size >= min)
+            && self.max_size.is_none_or(|max| size <= max)
+    }
+}
+
+fn 
+compile (pattern: &str) -> Result<Pattern, c2pa::Error> {
+    Pattern::new(pattern).map_err(|e| c2pa::Error::BadParam(format!("bad pattern '{}': {}", pattern, e)))
+}
+
+/* Parses a size such as 4096, 500k, 20M or 1G; suffixes are binary multiples */
+pub fn 
+parse_size (size: &str) -> Result<u64, c2pa::Error> {
+    let size = size.trim();
+    let (digits, multiple) = match size.char_indices().last() {
+        Some((i, 'k' | 'K')) => (&size[..i], 1 << 10),
+        Some((i, 'm' | 'M')) => (&size[..i], 1 << 20),
+        Some((i, 'g' | 'G')) => (&size[..i], 1 << 30),
+        _ => (size, 1)
+    };
+
+    digits.parse::<u64>().ok()
+        .and_then(|n| n.checked_mul(multiple))
+        .ok_or_else(|| c2pa::Error::BadParam(format!("'{}' is not a size, e.g. 4096, 500k or 20M", size)))
+}
+
+/* How directories are walked */
+#[derive(Clone, Copy, Default)]
+pub struct WalkOptions {
+    pub follow_symlinks: bool,
+    // levels of subdirectories to descend into; 0 keeps to the directory itself
+    pub max_depth: Option<usize>
+}
+
+/* Why an entry found while walking wasn't processed */
+#[derive(Debug)]
+pub enum Skip {
+    Filtered,
+    Symlink,
+    BrokenSymlink,
+    TooDeep,
+    Loop,
+    AlreadyWalked,
+    NotAFile,
+    Unreadable(String)
+}
+
+impl std::fmt::Display for Skip {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Skip::Filtered => write!(f, "excluded by the filters"),
+            Skip::Symlink => write!(f, "symbolic link; pass --follow-symlinks to follow it"),
+            Skip::BrokenSymlink => write!(f, "broken symbolic link"),
+            Skip::TooDeep => write!(f, "directory deeper than --max-depth"),
+            Skip::Loop => write!(f, "symbolic link loop back to a directory containing it"),
+            Skip::AlreadyWalked => write!(f, "directory already walked through another link"),
+            Skip::NotAFile => write!(f, "not a regular file"),
+            Skip::Unreadable(e) => write!(f, "unreadable: {}", e)
+        }
+    }
+}
+
+/* The files a walk found, and everything it passed over */
+#[derive(Default)]
+pub struct Walked {
+    pub files: Vec<PathBuf>,
+    pub skipped: Vec<(PathBuf, Skip)>
+}
+
+/**
+ * Every file under `root` that `filters` keeps, as paths relative to
+ * `root` in the order walked, along with the entries skipped and why. Linked
+ * directories are followed only with `follow_symlinks`, and each directory
+ * is walked at most once, so links back up the tree can't loop. Only an
+ * unreadable `root` is an error.
+ */
+pub fn 
+walk (root: &Path, filters: &Filters, options: WalkOptions) -> Result<Walked, c2pa::Error> {
+    let canonical_root = std::fs::canonicalize(root)?;
+    let mut walker = Walker {
+        root,
+        filters,
+        options,
+        visited: HashSet::from([canonical_root.clone()]),
+        walked: Walked::default()
+    };
+
+    let entries = std::fs::read_dir(root)?;
+    walker.walk_entries(entries, Path::new(""), 0, &mut vec![canonical_root]);
+
+    Ok(walker.walked)
+}
+
+struct Walker<'a> {
+    root: &'a Path,
+    filters: &'a Filters,
+    options: WalkOptions,
+    // canonical paths of every directory walked so far
+    visited: HashSet<PathBuf>,
+    walked: Walked
+}
+
+impl Walker<'_> {
+    fn walk_entries(&mut self, entries: std::fs::ReadDir, relative: &Path, depth: usize, ancestors: &mut Vec<PathBuf>) {
+        // In name order, so which of two links to a directory is walked doesn't vary from run to run
+        let mut readable = Vec::new();
+        for entry in entries {
+            match entry {
+                Ok(entry) => readable.push(entry),
+                Err(e) => self.skip(relative.to_owned(), Skip::Unreadable(e.to_string()))
+            }
+        }
+        readable.sort_by_key(|entry| entry.file_name());
+
+        for entry in readable {
+            let path = relative.join(entry.file_name());
+
+            // Symlink metadata is that of the link unless it is followed
+            let metadata = match entry.file_type() {
+                Ok(file_type) if file_type.is_symlink() && !self.options.follow_symlinks => {
+                    self.skip(path, Skip::Symlink);
+                    continue;
+                },
+                Ok(file_type) if file_type.is_symlink() => match std::fs::metadata(self.root.join(&path)) {
+                    Ok(metadata) => metadata,
+                    Err(_) => {
+                        self.skip(path, Skip::BrokenSymlink);
+                        continue;
+                    }
+                },
+                _ => match entry.metadata() {
+                    Ok(metadata) => metadata,
+                    Err(e) => {
+                        self.skip(path, Skip::Unreadable(e.to_string()));
+                        continue;
+                    }
+                }
+            };
+
+            if metadata.is_dir() {
+                self.walk_dir(path, depth + 1, ancestors);
+            } else if !metadata.is_file() {
+                self.skip(path, Skip::NotAFile);
+            } else if self.filters.matches(&slash_path(&path), metadata.len()) {
+                self.walked.files.push(path);
+            } else {
+                self.skip(path, Skip::Filtered);
+            }
+        }
+    }
+
+    fn walk_dir(&mut self, relative: PathBuf, depth: usize, ancestors: &mut Vec<PathBuf>) {
+        if self.options.max_depth.is_some_and(|max| depth > max) {
+            return self.skip(relative, Skip::TooDeep);
+        }
+
+        let canonical = match std::fs::canonicalize(self.root.join(&relative)) {
+            Ok(canonical) => canonical,
+            Err(e) => return self.skip(relative, Skip::Unreadable(e.to_string()))
+        };
+        if ancestors.contains(&canonical) {
+            return self.skip(relative, Skip::Loop);
+        }
+        if !self.visited.insert(canonical.clone()) {
+            return self.skip(relative, Skip::AlreadyWalked);
+        }
+
+        match std::fs::read_dir(self.root.join(&relative)) {
+            Ok(entries) => {
+                ancestors.push(canonical);
+                self.walk_entries(entries, &relative, depth, ancestors);
+                ancestors.pop();
+            },
+            Err(e) => self.skip(relative, Skip::Unreadable(e.to_string()))
+        }
+    }
+
+    fn skip(&mut self, path: PathBuf, reason: Skip) {
+        self.walked.skipped.push((path, reason));
+    }
+}
+
+/* A relative path with / separators on every platform, for matching and naming outputs */
+pub fn 
+slash_path (path: &Path) -> String {
+    path.components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/**
+ * Runs `work` on each of `items` on up to `jobs` threads, handing each
+ * result to `done` on the calling thread in the order of `items`, as soon
+ * as it and every result before it are in.
+ */
+pub fn 
+in_parallel<T: Sync, R: Send> (items: &[T], jobs: usize, work: impl Fn(&T) -> R + Sync, mut done: impl FnMut(&T, R)) {
+    let next = AtomicUsize::new(0);
+    let (sender, receiver) = mpsc::channel();
+
+    thread::scope(|scope| {
+        for _ in 0..jobs.clamp(1, items.len().max(1)) {
+            let sender = sender.clone();
+            let (next, work) = (&next, &work);
+            scope.spawn(move || loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let Some(item) = items.get(i) else {
+                    break;
+                };
+                if sender.send((i, work(item))).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(sender);
+
+        // Results finishing early wait here for those before them
+        let mut finished = BTreeMap::new();
+        let mut reported = 0;
+        for (i, result) in receiver {
+            finished.insert(i, result);
+            while let Some(result) = finished.remove(&reported) {
+                done(&items[reported], result);
+                reported += 1;
+            }
+        }
+    });
+}
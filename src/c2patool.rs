@@ -0,0 +1,360 @@
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use clap::{arg, ArgMatches, Command};
+
+use crate::sign::{ALG_VAR, PKEY_PATH_VAR, SIGNCERT_PATH_VAR, TSA_URL_VAR};
+
+/*
+ * A c2patool compatibility mode, so scripts written for c2patool can run
+ * this tool in its place. Invoked as `c2patool` (through a symlink or a
+ * renamed copy) or as `c2pa-walkthrough c2patool ...`, the command line is
+ * read with c2patool's flags and carried out by this tool's own commands:
+ *
+ * - `<path>` prints the manifest store, as `read`; `-d`/`--detailed` prints
+ *   the SDK's detailed report, as `read --detailed`.
+ * - `-m <manifest>` or `-c <json>` with `-o <output>` signs, as `sign
+ *   --manifest`, then prints the output's manifest store as c2patool does.
+ *   Without `-f`/`--force`, an output which already exists is an error.
+ * - `--certs` prints the signing certificate chain, as `export-certs`.
+ * - `trust --trust_anchors --allowed_list --trust_config`, or the
+ *   C2PATOOL_TRUST_* variables, have the SDK check signers against them.
+ *
+ * A c2patool manifest definition may name its signer with `alg`,
+ * `private_key`, `sign_cert` and `ta_url`, paths relative to the
+ * definition; these become the C2PA_* signer variables. Without them the
+ * default signer is used, where c2patool uses its built-in test one.
+ * c2patool flags with no counterpart here are refused by name rather than
+ * ignored.
+ */
+
+// c2patool flags which take a value, and those which don't, that aren't carried out here
+const UNSUPPORTED_OPTIONS: [&str; 4] = ["parent", "remote", "reserve-size", "signer-path"];
+const UNSUPPORTED_FLAGS: [&str; 4] = ["ingredient", "info", "sidecar", "tree"];
+
+/* The native commands carrying out a c2patool command line, and what they need set up first */
+pub struct Translation {
+    // Command lines to run in order, each starting with the program name
+    pub commands: Vec<Vec<OsString>>,
+    // Signer variables taken from the manifest definition
+    pub env: Vec<(&'static str, String)>,
+    pub trust: Option<TrustBundles>,
+    // A definition given inline with -c, written out for `sign --manifest` and removed on drop
+    inline_definition: Option<PathBuf>
+}
+
+impl Drop for Translation {
+    fn 
+    drop (&mut self) {
+        if let Some(path) = &self.inline_definition {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+/* The locations c2patool's `trust` takes, each a path or URL */
+pub struct TrustBundles {
+    pub trust_anchors: Option<String>,
+    pub allowed_list: Option<String>,
+    pub trust_config: Option<String>
+}
+
+/**
+ * The c2patool command line in `args`, program name first, if this tool
+ * was invoked as c2patool or with `c2patool` as its first argument.
+ */
+pub fn 
+invocation (args: &[OsString]) -> Option<Vec<OsString>> {
+    let program = Path::new(args.first()?).file_stem()?;
+    if program == "c2patool" {
+        return Some(args.to_vec());
+    }
+
+    (args.get(1)? == "c2patool").then(|| args[1..].to_vec())
+}
+
+/* c2patool's command line, as far as its flags go */
+pub fn 
+command () -> Command {
+    Command::new("c2patool")
+    .about("c2patool compatibility mode: reads, signs and checks trust with c2patool's flags, carried out by c2pa-walkthrough's own commands")
+    .arg(arg!([path] "asset to read or sign"))
+    .arg(arg!(-m --manifest <FILE>).required(false).help("manifest definition to sign with; may name the signer with alg, private_key, sign_cert and ta_url"))
+    .arg(arg!(-c --config <JSON>).required(false).conflicts_with("manifest").help("manifest definition given inline"))
+    .arg(arg!(-o --output <FILE>).required(false).help("where to write the signed asset"))
+    .arg(arg!(-f --force).help("replaces an output which already exists"))
+    .arg(arg!(-d --detailed).help("prints the SDK's detailed manifest store report"))
+    .arg(arg!(--certs).help("prints the certificate chain of the active manifest"))
+    .arg(arg!(--no_signing_verify).help("accepted for compatibility; signed assets aren't verified here anyway"))
+    .arg(arg!(-p --parent <FILE>).required(false).hide(true))
+    .arg(arg!(-r --remote <URL>).required(false).hide(true))
+    .arg(arg!(--"reserve-size" <SIZE>).required(false).hide(true))
+    .arg(arg!(--"signer-path" <PATH>).required(false).hide(true))
+    .arg(arg!(-i --ingredient).hide(true))
+    .arg(arg!(--info).hide(true))
+    .arg(arg!(-s --sidecar).hide(true))
+    .arg(arg!(--tree).hide(true))
+    .subcommand(
+        Command::new("trust")
+        .about("checks signers against these trust settings")
+        .arg(arg!(--trust_anchors <LOCATION>).required(false).env("C2PATOOL_TRUST_ANCHORS").help("PEM bundle of trust anchors; a path or URL"))
+        .arg(arg!(--allowed_list <LOCATION>).required(false).env("C2PATOOL_ALLOWED_LIST").help("PEM bundle of signing certificates trusted on their own; a path or URL"))
+        .arg(arg!(--trust_config <LOCATION>).required(false).env("C2PATOOL_TRUST_CONFIG").help("allowed extended key usage OIDs, one per line; a path or URL"))
+    )
+}
+
+/* A definition path field resolved against the definition's directory */
+fn 
+resolve (base: Option<&Path>, path: &str) -> String {
+    match base {
+        Some(base) if Path::new(path).is_relative() => base.join(path).to_string_lossy().into_owned(),
+        _ => path.to_owned()
+    }
+}
+
+/* The signer variables for a c2patool definition's alg, private_key, sign_cert and ta_url */
+fn 
+signer_env (definition: &serde_json::Value, base: Option<&Path>) -> Result<Vec<(&'static str, String)>, c2pa::Error> {
+    let field = |name: &str| definition[name].as_str();
+    let mut env = match (field("private_key"), field("sign_cert")) {
+        (Some(private_key), Some(sign_cert)) => vec![
+            (PKEY_PATH_VAR, resolve(base, private_key)),
+            (SIGNCERT_PATH_VAR, resolve(base, sign_cert)),
+            // c2patool's own default
+            (ALG_VAR, field("alg").unwrap_or("es256").to_owned())
+        ],
+        (None, None) => Vec::new(),
+        _ => return Err(c2pa::Error::BadParam("the manifest definition must give private_key and sign_cert together".to_owned()))
+    };
+    if let Some(ta_url) = field("ta_url") {
+        env.push((TSA_URL_VAR, ta_url.to_owned()));
+    }
+
+    Ok(env)
+}
+
+/* The native commands for a c2patool command line parsed with `command()` */
+pub fn 
+translate (matches: &ArgMatches) -> Result<Translation, c2pa::Error> {
+    if let Some(flag) = UNSUPPORTED_OPTIONS.iter().find(|id| matches.contains_id(id))
+        .or_else(|| UNSUPPORTED_FLAGS.iter().find(|id| matches.get_flag(id)))
+    {
+        return Err(c2pa::Error::BadParam(format!("c2patool's --{} isn't supported in compatibility mode", flag)));
+    }
+    let path = matches.get_one::<String>("path")
+        .ok_or_else(|| c2pa::Error::BadParam("no asset given".to_owned()))?;
+
+    let program = OsString::from("c2pa-walkthrough");
+    let native = |args: &[&str]| -> Vec<OsString> {
+        std::iter::once(program.clone()).chain(args.iter().map(OsString::from)).collect()
+    };
+    let read = |path: &str| match matches.get_flag("detailed") {
+        true => native(&["read", path, "--detailed"]),
+        false => native(&["read", path])
+    };
+
+    let trust = matches.subcommand_matches("trust").map(|trust| {
+        let location = |id: &str| trust.get_one::<String>(id).cloned();
+        TrustBundles {
+            trust_anchors: location("trust_anchors"),
+            allowed_list: location("allowed_list"),
+            trust_config: location("trust_config")
+        }
+    });
+    let mut translation = Translation { commands: Vec::new(), env: Vec::new(), trust, inline_definition: None };
+
+    if matches.get_flag("certs") {
+        translation.commands.push(native(&["export-certs", path]));
+        return Ok(translation);
+    }
+
+    let (definition_json, base) = match (matches.get_one::<String>("manifest"), matches.get_one::<String>("config")) {
+        (Some(manifest), _) => (std::fs::read_to_string(manifest)?, Path::new(manifest).parent().map(Path::to_owned)),
+        (None, Some(config)) => (config.clone(), None),
+        (None, None) => {
+            if matches.contains_id("output") {
+                return Err(c2pa::Error::BadParam("-o needs a manifest definition, given with -m or -c".to_owned()));
+            }
+            translation.commands.push(read(path));
+            return Ok(translation);
+        }
+    };
+    let output = matches.get_one::<String>("output")
+        .ok_or_else(|| c2pa::Error::BadParam("signing needs an output, given with -o".to_owned()))?;
+    if Path::new(output).exists() && !matches.get_flag("force") {
+        return Err(c2pa::Error::BadParam(format!("{} already exists; use -f/--force to replace it", output)));
+    }
+
+    let definition: serde_json::Value = serde_json::from_str(&definition_json)?;
+    if definition["ingredient_paths"].as_array().is_some_and(|paths| !paths.is_empty()) {
+        return Err(c2pa::Error::BadParam("ingredient_paths in the manifest definition isn't supported in compatibility mode".to_owned()));
+    }
+    translation.env = signer_env(&definition, base.as_deref())?;
+
+    let definition_path = match matches.get_one::<String>("manifest") {
+        Some(manifest) => manifest.clone(),
+        None => {
+            let inline = std::env::temp_dir().join(format!("c2patool-definition-{}.json", std::process::id()));
+            std::fs::write(&inline, &definition_json)?;
+            let inline_path = inline.to_string_lossy().into_owned();
+            translation.inline_definition = Some(inline);
+            inline_path
+        }
+    };
+
+    // The existing output was allowed by --force above, so sign replaces it
+    translation.commands.push(native(&["sign", path, "--manifest", &definition_path, "-o", output, "--overwrite", "--quiet"]));
+    translation.commands.push(read(output));
+    Ok(translation)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /* The native command lines for a c2patool command line, without the program name */
+    fn 
+    translate_args (args: &[&str]) -> Result<Translation, c2pa::Error> {
+        let matches = command().try_get_matches_from(std::iter::once("c2patool").chain(args.iter().copied())).unwrap();
+        translate(&matches)
+    }
+
+    fn 
+    commands (translation: &Translation) -> Vec<Vec<&str>> {
+        translation.commands.iter()
+            .map(|command| command.iter().map(|arg| arg.to_str().unwrap()).collect())
+            .collect()
+    }
+
+    /* A directory holding a manifest definition, for the signing command lines to name */
+    fn 
+    definition_dir (test: &str, definition: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("c2patool-{}-{}", test, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("manifest.json"), definition).unwrap();
+        dir
+    }
+
+    #[test]
+    fn invoked_by_program_name_or_subcommand() {
+        let args = |args: &[&str]| args.iter().map(OsString::from).collect::<Vec<_>>();
+        assert_eq!(invocation(&args(&["/usr/local/bin/c2patool", "photo.jpg"])), Some(args(&["/usr/local/bin/c2patool", "photo.jpg"])));
+        assert_eq!(invocation(&args(&["c2pa-walkthrough", "c2patool", "photo.jpg", "-d"])), Some(args(&["c2patool", "photo.jpg", "-d"])));
+        assert_eq!(invocation(&args(&["c2pa-walkthrough", "read", "photo.jpg"])), None);
+    }
+
+    #[test]
+    fn path_reads() {
+        let translation = translate_args(&["photo.jpg"]).unwrap();
+        assert_eq!(commands(&translation), [["c2pa-walkthrough", "read", "photo.jpg"]]);
+        assert!(translation.env.is_empty());
+        assert!(translation.trust.is_none());
+    }
+
+    #[test]
+    fn detailed_reads_detailed() {
+        for detailed in ["-d", "--detailed"] {
+            let translation = translate_args(&["photo.jpg", detailed]).unwrap();
+            assert_eq!(commands(&translation), [["c2pa-walkthrough", "read", "photo.jpg", "--detailed"]]);
+        }
+    }
+
+    #[test]
+    fn certs_exports_certs() {
+        let translation = translate_args(&["signed.jpg", "--certs"]).unwrap();
+        assert_eq!(commands(&translation), [["c2pa-walkthrough", "export-certs", "signed.jpg"]]);
+    }
+
+    #[test]
+    fn manifest_and_output_sign_then_read() {
+        let dir = definition_dir("manifest", r#"{"claim_generator_info": [{"name": "tests"}]}"#);
+        let manifest = dir.join("manifest.json");
+        let output = dir.join("signed.jpg");
+        let (manifest, output) = (manifest.to_str().unwrap(), output.to_str().unwrap());
+
+        let translation = translate_args(&["photo.jpg", "-m", manifest, "-o", output]).unwrap();
+        assert_eq!(commands(&translation), [
+            vec!["c2pa-walkthrough", "sign", "photo.jpg", "--manifest", manifest, "-o", output, "--overwrite", "--quiet"],
+            vec!["c2pa-walkthrough", "read", output]
+        ]);
+        assert!(translation.env.is_empty());
+
+        let translation = translate_args(&["photo.jpg", "--manifest", manifest, "--output", output, "--detailed"]).unwrap();
+        assert_eq!(commands(&translation)[1], ["c2pa-walkthrough", "read", output, "--detailed"]);
+    }
+
+    #[test]
+    fn existing_output_needs_force() {
+        let dir = definition_dir("force", "{}");
+        let manifest = dir.join("manifest.json");
+        let output = dir.join("signed.jpg");
+        std::fs::write(&output, b"existing").unwrap();
+        let (manifest, output) = (manifest.to_str().unwrap(), output.to_str().unwrap());
+
+        assert!(matches!(translate_args(&["photo.jpg", "-m", manifest, "-o", output]), Err(c2pa::Error::BadParam(_))));
+        for force in ["-f", "--force"] {
+            let translation = translate_args(&["photo.jpg", "-m", manifest, "-o", output, force]).unwrap();
+            assert_eq!(commands(&translation)[0], ["c2pa-walkthrough", "sign", "photo.jpg", "--manifest", manifest, "-o", output, "--overwrite", "--quiet"]);
+        }
+    }
+
+    #[test]
+    fn definition_signer_becomes_signer_variables() {
+        let dir = definition_dir("signer", r#"{"alg": "ps256", "private_key": "keys/ps256.pem", "sign_cert": "/certs/ps256.pub", "ta_url": "http://timestamp.example.com"}"#);
+        let manifest = dir.join("manifest.json");
+        let output = dir.join("signed.jpg");
+
+        let translation = translate_args(&["photo.jpg", "-m", manifest.to_str().unwrap(), "-o", output.to_str().unwrap()]).unwrap();
+        assert_eq!(translation.env, [
+            (PKEY_PATH_VAR, dir.join("keys/ps256.pem").to_string_lossy().into_owned()),
+            (SIGNCERT_PATH_VAR, "/certs/ps256.pub".to_owned()),
+            (ALG_VAR, "ps256".to_owned()),
+            (TSA_URL_VAR, "http://timestamp.example.com".to_owned())
+        ]);
+    }
+
+    #[test]
+    fn inline_definition_is_written_for_sign_and_removed() {
+        let dir = definition_dir("inline", "{}");
+        let output = dir.join("signed.jpg");
+        let definition = r#"{"claim_generator_info": [{"name": "inline"}]}"#;
+
+        let translation = translate_args(&["photo.jpg", "-c", definition, "-o", output.to_str().unwrap()]).unwrap();
+        let sign = &commands(&translation)[0];
+        assert_eq!(sign[..3], ["c2pa-walkthrough", "sign", "photo.jpg"]);
+        assert_eq!(sign[3], "--manifest");
+        let written = PathBuf::from(sign[4]);
+        assert_eq!(std::fs::read_to_string(&written).unwrap(), definition);
+
+        drop(translation);
+        assert!(!written.exists());
+    }
+
+    #[test]
+    fn trust_flags_become_trust_bundles() {
+        let translation = translate_args(&[
+            "photo.jpg", "trust",
+            "--trust_anchors", "anchors.pem",
+            "--allowed_list", "https://example.com/allowed.pem",
+            "--trust_config", "store.cfg"
+        ]).unwrap();
+        assert_eq!(commands(&translation), [["c2pa-walkthrough", "read", "photo.jpg"]]);
+        let trust = translation.trust.as_ref().unwrap();
+        assert_eq!(trust.trust_anchors.as_deref(), Some("anchors.pem"));
+        assert_eq!(trust.allowed_list.as_deref(), Some("https://example.com/allowed.pem"));
+        assert_eq!(trust.trust_config.as_deref(), Some("store.cfg"));
+    }
+
+    #[test]
+    fn output_needs_a_definition() {
+        assert!(matches!(translate_args(&["photo.jpg", "-o", "signed.jpg"]), Err(c2pa::Error::BadParam(_))));
+    }
+
+    #[test]
+    fn unsupported_flags_are_refused() {
+        for args in [&["photo.jpg", "--parent", "parent.jpg"][..], &["photo.jpg", "--tree"], &["photo.jpg", "-s"]] {
+            assert!(matches!(translate_args(args), Err(c2pa::Error::BadParam(_))), "{:?} was accepted", args);
+        }
+    }
+}
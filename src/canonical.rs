@@ -0,0 +1,137 @@
+use std::collections::HashMap;
+
+use chrono::DateTime;
+use serde_json::{Map, Value};
+
+/*
+ * Manifest JSON with the fields which differ from run to run normalized,
+ * so what a signing run produces can be diffed against a checked-in golden
+ * file. UUIDs, wherever they appear in keys or strings, become placeholder
+ * UUIDs numbered in order of first appearance, so a manifest label and the
+ * URIs and ingredient references pointing at it keep agreeing. RFC 3339
+ * times become TIME_PLACEHOLDER, and hashes, which cover signatures and
+ * labels, become HASH_PLACEHOLDER. Object keys are sorted, as the SDK
+ * writes manifests in no fixed order.
+ */
+
+pub const TIME_PLACEHOLDER: &str = "<time>";
+pub const HASH_PLACEHOLDER: &str = "<hash>";
+
+const UUID_LEN: usize = 36;
+
+/* Whether `s` holds a hyphenated UUID at byte `start` */
+fn 
+uuid_at (s: &[u8], start: usize) -> bool {
+    s.len() >= start + UUID_LEN && s[start..start + UUID_LEN].iter().enumerate().all(|(i, b)| match i {
+        8 | 13 | 18 | 23 => *b == b'-',
+        _ => b.is_ascii_hexdigit()
+    })
+}
+
+/* The UUIDs in `s`, as byte ranges, skipping any run of hex digits too long to be one */
+fn 
+uuids (s: &str) -> Vec<(usize, usize)> {
+    let bytes = s.as_bytes();
+    let mut found = Vec::new();
+    let mut start = 0;
+    while start < bytes.len() {
+        let bounded = start == 0 || !bytes[start - 1].is_ascii_hexdigit();
+        if bounded && uuid_at(bytes, start) && bytes.get(start + UUID_LEN).is_none_or(|b| !b.is_ascii_hexdigit()) {
+            found.push((start, start + UUID_LEN));
+            start += UUID_LEN;
+        } else {
+            start += 1;
+        }
+    }
+
+    found
+}
+
+/* The placeholder standing in for the `n`th UUID seen, itself a valid random (version 4) UUID */
+fn 
+placeholder (n: usize) -> String {
+    format!("00000000-0000-4000-8000-{:012}", n)
+}
+
+struct Canonicalizer {
+    // Each UUID seen, lowercased, and its placeholder number
+    ids: HashMap<String, usize>
+}
+
+impl Canonicalizer {
+    /* `s` with its UUIDs replaced, numbering any not seen before */
+    fn 
+    string (&mut self, s: &str) -> String {
+        let mut replaced = String::with_capacity(s.len());
+        let mut end = 0;
+        for (start, uuid_end) in uuids(s) {
+            let next = self.ids.len() + 1;
+            let n = *self.ids.entry(s[start..uuid_end].to_ascii_lowercase()).or_insert(next);
+            replaced.push_str(&s[end..start]);
+            replaced.push_str(&placeholder(n));
+            end = uuid_end;
+        }
+        replaced.push_str(&s[end..]);
+
+        replaced
+    }
+
+    /**
+     * The order an object's keys are visited in: by the key with its UUIDs
+     * blanked, then by the numbers its UUIDs already have. A store's
+     * manifests are then visited active manifest first, each ingredient's
+     * manifest after the manifest which references it, and so numbered the
+     * same way on every run. Keys with UUIDs not yet seen fall back on
+     * their own order.
+     */
+    fn 
+    visit_order (&self, key: &str) -> (String, Vec<usize>, String) {
+        let mut blanked = String::with_capacity(key.len());
+        let mut numbers = Vec::new();
+        let mut end = 0;
+        for (start, uuid_end) in uuids(key) {
+            blanked.push_str(&key[end..start]);
+            blanked.push_str(&placeholder(0));
+            numbers.push(self.ids.get(&key[start..uuid_end].to_ascii_lowercase()).copied().unwrap_or(usize::MAX));
+            end = uuid_end;
+        }
+        blanked.push_str(&key[end..]);
+
+        (blanked, numbers, key.to_owned())
+    }
+
+    fn 
+    value (&mut self, value: &Value) -> Value {
+        match value {
+            Value::String(s) if DateTime::parse_from_rfc3339(s).is_ok() => Value::from(TIME_PLACEHOLDER),
+            Value::String(s) => Value::String(self.string(s)),
+            Value::Array(items) => Value::Array(items.iter().map(|item| self.value(item)).collect()),
+            Value::Object(fields) => {
+                let mut remaining: Vec<(&String, &Value)> = fields.iter().collect();
+                let mut canonical = Vec::with_capacity(remaining.len());
+                while !remaining.is_empty() {
+                    // Re-ranked each time, as visiting a field can number UUIDs in the keys left
+                    let next = (0..remaining.len()).min_by_key(|&i| self.visit_order(remaining[i].0)).unwrap();
+                    let (key, field) = remaining.remove(next);
+                    // The key first, so a manifest's label is numbered before the labels its ingredients reference
+                    let canonical_key = self.string(key);
+                    let field = match field {
+                        Value::String(_) if key == "hash" => Value::from(HASH_PLACEHOLDER),
+                        field => self.value(field)
+                    };
+                    canonical.push((canonical_key, field));
+                }
+                canonical.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+                Value::Object(canonical.into_iter().collect::<Map<_, _>>())
+            },
+            value => value.clone()
+        }
+    }
+}
+
+/* `value` in canonical form, for comparison against a golden file */
+pub fn 
+canonical_json (value: &Value) -> Value {
+    Canonicalizer { ids: HashMap::new() }.value(value)
+}
@@ -0,0 +1,334 @@
+use openssl::nid::Nid;
+use openssl::pkey::{Id, PKey, Private, Public};
+use openssl::x509::X509;
+use serde::Serialize;
+use x509_parser::prelude::{FromDer, X509Certificate};
+
+use crate::clock;
+use crate::profiles::cert_expiry;
+use crate::secret::SecretBytes;
+use crate::sign::SignerConfig;
+use crate::trust::name_string;
+
+/*
+ * Health checks of a configured signer, for scheduled monitoring: whether
+ * its certificate chain would pass the C2PA certificate profile that
+ * validators hold signing certificates to, whether anything in it is
+ * about to expire, and whether its key, algorithm and certificate go
+ * together. The profile checks follow the SDK's: a v3 certificate, signed
+ * with an allowed algorithm, with a P-256, P-384 or P-521 EC key, an RSA
+ * key of at least 2048 bits or an Ed25519 key, the digitalSignature key
+ * usage, an extended key usage for signing (id-kp-emailProtection,
+ * id-kp-documentSigning or c2pa-kp-claimSigning) and not anyExtendedKeyUsage,
+ * an authority key identifier, and not self-signed nor a CA; and of the
+ * certificates after it, that they are CAs with the keyCertSign key usage.
+ */
+
+// EKUs which mark a certificate for signing manifests
+const SIGNING_EKUS: [(&str, &str); 4] = [
+    ("1.3.6.1.5.5.7.3.4", "id-kp-emailProtection"),
+    ("1.3.6.1.5.5.7.3.36", "id-kp-documentSigning"),
+    ("1.3.6.1.4.1.62558.2.1", "c2pa-kp-claimSigning"),
+    ("1.3.6.1.4.1.311.76.59.1.9", "Microsoft C2PA signing")
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Ok,
+    Warn,
+    Fail
+}
+
+impl Status {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Status::Ok => "ok",
+            Status::Warn => "warn",
+            Status::Fail => "FAIL"
+        }
+    }
+}
+
+/* The outcome of one check, of one certificate or of the signer as a whole */
+#[derive(Serialize)]
+pub struct Finding {
+    pub status: Status,
+    pub check: &'static str,
+    // Subject of the certificate checked, if the check is of one
+    pub certificate: Option<String>,
+    pub detail: String
+}
+
+/* Every check of a signer, in the order made */
+#[derive(Serialize)]
+pub struct CertReport {
+    pub signcert_path: String,
+    pub alg: String,
+    pub status: Status,
+    pub findings: Vec<Finding>
+}
+
+fn 
+ssl_error (e: openssl::error::ErrorStack) -> c2pa::Error {
+    c2pa::Error::OtherError(Box::new(e))
+}
+
+/* Collects the findings about one certificate */
+struct Checks<'a> {
+    findings: &'a mut Vec<Finding>,
+    subject: String
+}
+
+impl Checks<'_> {
+    fn add(&mut self, status: Status, check: &'static str, detail: String) {
+        self.findings.push(Finding { status, check, certificate: Some(self.subject.clone()), detail });
+    }
+}
+
+/* A certificate's key, e.g. "EC P-256" or "RSA 3072 bits", and whether the profile allows it */
+fn 
+describe_key (key: &PKey<Public>) -> (Status, String) {
+    match key.id() {
+        Id::EC => {
+            let curve = key.ec_key().ok().and_then(|ec| ec.group().curve_name());
+            match curve {
+                Some(Nid::X9_62_PRIME256V1) => (Status::Ok, "EC P-256".to_owned()),
+                Some(Nid::SECP384R1) => (Status::Ok, "EC P-384".to_owned()),
+                Some(Nid::SECP521R1) => (Status::Ok, "EC P-521".to_owned()),
+                other => (Status::Fail, format!("EC {}, not P-256, P-384 or P-521", other.and_then(|nid| nid.short_name().ok()).unwrap_or("(unnamed curve)")))
+            }
+        },
+        Id::RSA | Id::RSA_PSS if key.bits() < 2048 => (Status::Fail, format!("RSA {} bits, under the 2048 required", key.bits())),
+        Id::RSA | Id::RSA_PSS => (Status::Ok, format!("RSA {} bits", key.bits())),
+        Id::ED25519 => (Status::Ok, "Ed25519".to_owned()),
+        _ => (Status::Fail, "a key type C2PA doesn't allow".to_owned())
+    }
+}
+
+/* Whether a signing algorithm is made with `key`, e.g. es256 with a P-256 key */
+fn 
+alg_fits_key (alg: &str, key: &PKey<Public>) -> bool {
+    let curve = key.ec_key().ok().and_then(|ec| ec.group().curve_name());
+    match alg {
+        "es256" => curve == Some(Nid::X9_62_PRIME256V1),
+        "es384" => curve == Some(Nid::SECP384R1),
+        "es512" => curve == Some(Nid::SECP521R1),
+        "ps256" | "ps384" | "ps512" => matches!(key.id(), Id::RSA | Id::RSA_PSS),
+        "ed25519" => key.id() == Id::ED25519,
+        _ => false
+    }
+}
+
+fn 
+self_signed (cert: &X509, parsed: &X509Certificate) -> bool {
+    parsed.is_ca() && cert.subject_name().to_der().ok() == cert.issuer_name().to_der().ok()
+}
+
+/* The checks which apply to every certificate in the chain */
+fn 
+check_certificate (checks: &mut Checks, cert: &X509, parsed: &X509Certificate) -> Result<(), c2pa::Error> {
+    if cert.version() != 2 {
+        checks.add(Status::Fail, "version", format!("v{}, not v3", cert.version() + 1));
+    }
+
+    let sig_alg = cert.signature_algorithm().object().nid();
+    let allowed = [
+        Nid::SHA256WITHRSAENCRYPTION, Nid::SHA384WITHRSAENCRYPTION, Nid::SHA512WITHRSAENCRYPTION,
+        Nid::ECDSA_WITH_SHA256, Nid::ECDSA_WITH_SHA384, Nid::ECDSA_WITH_SHA512, Nid::RSASSAPSS,
+        // Ed25519 has no Nid constant of its own; its key type shares the number
+        Nid::from_raw(Id::ED25519.as_raw())
+    ];
+    let sig_alg_name = sig_alg.long_name().unwrap_or("unknown").to_owned();
+    match allowed.contains(&sig_alg) {
+        true => checks.add(Status::Ok, "signature algorithm", sig_alg_name),
+        false => checks.add(Status::Fail, "signature algorithm", format!("{}, which C2PA doesn't allow", sig_alg_name))
+    }
+
+    let (status, key) = describe_key(&cert.public_key().map_err(ssl_error)?);
+    checks.add(status, "key", key);
+
+    // Roots sign themselves, so have no authority key to name
+    if !self_signed(cert, parsed) && cert.authority_key_id().is_none() {
+        checks.add(Status::Fail, "authority key identifier", "missing".to_owned());
+    }
+
+    Ok(())
+}
+
+/**
+ * The key usage and basic constraints rules of the C2PA certificate
+ * profile: a signing certificate is no CA, and has the digitalSignature key
+ * usage and an extended key usage for signing; the certificates after it
+ * are CAs with the keyCertSign key usage.
+ */
+fn 
+check_usage (checks: &mut Checks, parsed: &X509Certificate, signing: bool) {
+    if !signing {
+        match parsed.is_ca() {
+            true => checks.add(Status::Ok, "basic constraints", "a CA".to_owned()),
+            false => checks.add(Status::Fail, "basic constraints", "not a CA, yet it follows the signing certificate".to_owned())
+        }
+        match parsed.key_usage() {
+            Ok(Some(usage)) if usage.value.key_cert_sign() => checks.add(Status::Ok, "key usage", "keyCertSign".to_owned()),
+            Ok(Some(_)) => checks.add(Status::Fail, "key usage", "no keyCertSign, so it can't issue certificates".to_owned()),
+            Ok(None) => checks.add(Status::Fail, "key usage", "missing".to_owned()),
+            Err(e) => checks.add(Status::Fail, "key usage", format!("unreadable: {}", e))
+        }
+        return;
+    }
+
+    if parsed.is_ca() {
+        checks.add(Status::Fail, "basic constraints", "a CA certificate, which can't sign manifests".to_owned());
+    }
+
+    match parsed.key_usage() {
+        Ok(Some(usage)) if usage.value.digital_signature() => checks.add(Status::Ok, "key usage", "digitalSignature".to_owned()),
+        Ok(Some(_)) => checks.add(Status::Fail, "key usage", "no digitalSignature".to_owned()),
+        Ok(None) => checks.add(Status::Fail, "key usage", "missing".to_owned()),
+        Err(e) => checks.add(Status::Fail, "key usage", format!("unreadable: {}", e))
+    }
+
+    match parsed.extended_key_usage() {
+        Ok(Some(eku)) if eku.value.any => checks.add(Status::Fail, "extended key usage", "anyExtendedKeyUsage, which C2PA forbids".to_owned()),
+        Ok(Some(eku)) => {
+            let mut signing: Vec<&str> = Vec::new();
+            if eku.value.email_protection {
+                signing.push("id-kp-emailProtection");
+            }
+            for oid in &eku.value.other {
+                let oid = oid.to_id_string();
+                signing.extend(SIGNING_EKUS.iter().filter(|(id, _)| *id == oid).map(|(_, name)| *name));
+            }
+            if signing.is_empty() {
+                checks.add(Status::Fail, "extended key usage", "none of id-kp-emailProtection, id-kp-documentSigning or c2pa-kp-claimSigning".to_owned());
+            } else if eku.value.time_stamping || eku.value.ocsp_signing {
+                checks.add(Status::Warn, "extended key usage", format!("{}, mixed with timestamping or OCSP signing", signing.join(", ")));
+            } else {
+                checks.add(Status::Ok, "extended key usage", signing.join(", "));
+            }
+        },
+        Ok(None) => checks.add(Status::Fail, "extended key usage", "missing".to_owned()),
+        Err(e) => checks.add(Status::Fail, "extended key usage", format!("unreadable: {}", e))
+    }
+}
+
+/* The checks which only apply to the signing certificate */
+fn 
+check_signing_certificate (checks: &mut Checks, cert: &X509, parsed: &X509Certificate, alg: &str) -> Result<(), c2pa::Error> {
+    if self_signed(cert, parsed) {
+        checks.add(Status::Fail, "self-signed", "validators reject self-signed signing certificates".to_owned());
+    }
+
+    match alg_fits_key(alg, &cert.public_key().map_err(ssl_error)?) {
+        true => checks.add(Status::Ok, "algorithm", format!("{} fits the key", alg)),
+        false => checks.add(Status::Fail, "algorithm", format!("{} can't be used with this key", alg))
+    }
+
+    Ok(())
+}
+
+/* The signer's private key, from wherever it was given */
+fn 
+private_key (config: &SignerConfig) -> Result<PKey<Private>, c2pa::Error> {
+    let pem = match &config.pkey {
+        Some(pkey) => PKey::private_key_from_pem(pkey),
+        None => PKey::private_key_from_pem(&SecretBytes::read(&config.pkey_path)?)
+    };
+    pem.map_err(ssl_error)
+}
+
+/**
+ * Checks the signer `config` describes: each certificate in its chain
+ * against the C2PA certificate profile and for expiry within
+ * `window_days`, each link of the chain, and its key against its
+ * signing certificate.
+ */
+pub fn 
+check_signer (config: &SignerConfig, window_days: u32) -> Result<CertReport, c2pa::Error> {
+    let chain = X509::stack_from_pem(&std::fs::read(&config.signcert_path)?).map_err(ssl_error)?;
+    if chain.is_empty() {
+        return Err(c2pa::Error::BadParam(format!("{} has no certificates", config.signcert_path)));
+    }
+    let alg = config.alg.to_string();
+    let mut findings = Vec::new();
+    let now = clock::now().timestamp();
+
+    for (i, cert) in chain.iter().enumerate() {
+        let der = cert.to_der().map_err(ssl_error)?;
+        let (_, parsed) = X509Certificate::from_der(&der)
+            .map_err(|e| c2pa::Error::BadParam(format!("certificate {} of {}: {}", i + 1, config.signcert_path, e)))?;
+        let mut checks = Checks { findings: &mut findings, subject: name_string(cert.subject_name()) };
+
+        if now < parsed.validity().not_before.timestamp() {
+            checks.add(Status::Fail, "validity", format!("not valid until {}", cert.not_before()));
+        } else if let Some(expiring) = cert_expiry(cert, i == 0, window_days)? {
+            let status = if expiring.days_left < 0 { Status::Fail } else { Status::Warn };
+            checks.add(status, "validity", expiring.when());
+        } else {
+            checks.add(Status::Ok, "validity", format!("until {}", cert.not_after()));
+        }
+
+        check_certificate(&mut checks, cert, &parsed)?;
+        check_usage(&mut checks, &parsed, i == 0);
+        if i == 0 {
+            check_signing_certificate(&mut checks, cert, &parsed, &alg)?;
+        }
+
+        // Each certificate is issued by the one after it
+        if let Some(issuer) = chain.get(i + 1) {
+            let issuer_key = issuer.public_key().map_err(ssl_error)?;
+            let verified = cert.verify(&issuer_key).unwrap_or(false);
+            match verified {
+                true => checks.add(Status::Ok, "chain", format!("issued by {}", name_string(issuer.subject_name()))),
+                false => checks.add(Status::Fail, "chain", format!("not signed by the next certificate, {}", name_string(issuer.subject_name())))
+            }
+        }
+    }
+
+    let signing_key = chain[0].public_key().map_err(ssl_error)?;
+    match private_key(config) {
+        Ok(key) if key.public_eq(&signing_key) => findings.push(Finding {
+            status: Status::Ok, check: "private key", certificate: None, detail: "matches the signing certificate".to_owned()
+        }),
+        Ok(_) => findings.push(Finding {
+            status: Status::Fail, check: "private key", certificate: None, detail: "doesn't match the signing certificate".to_owned()
+        }),
+        Err(e) => findings.push(Finding {
+            status: Status::Fail, check: "private key", certificate: None, detail: format!("unreadable: {}", e)
+        })
+    }
+
+    Ok(CertReport {
+        signcert_path: config.signcert_path.clone(),
+        alg,
+        status: findings.iter().map(|finding| finding.status).max().unwrap_or(Status::Ok),
+        findings
+    })
+}
+
+/**
+ * What in the certificate chain `chain_pem`, signing certificate first,
+ * breaks the C2PA profile's extended key usage, key usage and basic
+ * constraints rules, each as "<subject>: <check>: <problem>". Validators
+ * reject such a chain with a generic signing credential error; this says
+ * which certificate is at fault and why.
+ */
+pub fn 
+profile_violations (chain_pem: &[u8]) -> Result<Vec<String>, c2pa::Error> {
+    let chain = X509::stack_from_pem(chain_pem).map_err(ssl_error)?;
+    let mut findings = Vec::new();
+
+    for (i, cert) in chain.iter().enumerate() {
+        let der = cert.to_der().map_err(ssl_error)?;
+        let (_, parsed) = X509Certificate::from_der(&der)
+            .map_err(|e| c2pa::Error::BadParam(format!("certificate {} of the chain: {}", i + 1, e)))?;
+        let mut checks = Checks { findings: &mut findings, subject: name_string(cert.subject_name()) };
+        check_usage(&mut checks, &parsed, i == 0);
+    }
+
+    Ok(findings.into_iter()
+        .filter(|finding| finding.status == Status::Fail)
+        .map(|finding| format!("{}: {}: {}", finding.certificate.unwrap_or_default(), finding.check, finding.detail))
+        .collect())
+}
@@ -0,0 +1,137 @@
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use c2pa::Builder;
+use chrono::{DateTime, FixedOffset, Local, Utc};
+use uuid::Uuid;
+
+/*
+ * The time recorded in actions and assertions, and the label given to
+ * signed manifests, which can be pinned so the same inputs produce the same
+ * manifest JSON, e.g. for golden-file comparisons in CI. Times are pinned
+ * with `pin_time` or the C2PA_FAKE_TIME environment variable (an RFC 3339
+ * time), and labels with `pin_instance_id`. Times are recorded in UTC
+ * unless `capture_local_time` asks for the local time with its offset; a
+ * pinned time keeps the offset it was given with. Signatures still differ from
+ * run to run, and the SDK assigns each manifest's `instance_id` itself when
+ * embedding, so that field does too. `pin_vendor` puts a vendor name in
+ * front of generated labels, e.g. `acme:urn:uuid:<uuid>`.
+ */
+
+// Environment hook pinning the time when `pin_time` hasn't been called
+pub const FAKE_TIME_VAR: &str = "C2PA_FAKE_TIME";
+
+static PINNED_TIME: Mutex<Option<DateTime<FixedOffset>>> = Mutex::new(None);
+static LOCAL_TIME: Mutex<bool> = Mutex::new(false);
+static PINNED_INSTANCE_ID: Mutex<Option<Uuid>> = Mutex::new(None);
+static PINNED_VENDOR: Mutex<Option<String>> = Mutex::new(None);
+
+pub fn 
+pin_time (when: DateTime<FixedOffset>) {
+    *PINNED_TIME.lock().unwrap() = Some(when);
+}
+
+/* Records times in the local timezone, with its offset, rather than in UTC */
+pub fn 
+capture_local_time () {
+    *LOCAL_TIME.lock().unwrap() = true;
+}
+
+pub fn 
+pin_instance_id (id: Uuid) {
+    *PINNED_INSTANCE_ID.lock().unwrap() = Some(id);
+}
+
+pub fn 
+pin_vendor (vendor: &str) {
+    *PINNED_VENDOR.lock().unwrap() = Some(vendor.to_owned());
+}
+
+/* Checks a vendor name for manifest labels, which the label's other parts are separated from by colons */
+pub fn 
+parse_vendor (vendor: &str) -> Result<String, c2pa::Error> {
+    let valid = !vendor.is_empty() && vendor.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || "._-".contains(c));
+    match valid {
+        true => Ok(vendor.to_owned()),
+        false => Err(c2pa::Error::BadParam(format!("'{}' isn't a vendor name; use lowercase letters, digits, '.', '_' and '-', e.g. com.example", vendor)))
+    }
+}
+
+/* Parses an RFC 3339 time such as 2024-01-01T00:00:00Z or 2024-01-01T09:00:00+09:00, keeping its offset */
+pub fn 
+parse_time (when: &str) -> Result<DateTime<FixedOffset>, c2pa::Error> {
+    DateTime::parse_from_rfc3339(when)
+        .map_err(|e| c2pa::Error::BadParam(format!("'{}' is not an RFC 3339 time: {}", when, e)))
+}
+
+/**
+ * The pinned time, else the time in C2PA_FAKE_TIME, else the current time.
+ * A C2PA_FAKE_TIME which doesn't parse is ignored; callers wanting that
+ * reported check it with `fake_time` first.
+ */
+pub fn 
+now () -> DateTime<Utc> {
+    pinned_time().map(|when| when.with_timezone(&Utc)).unwrap_or_else(Utc::now)
+}
+
+/* The pinned time, else the time in C2PA_FAKE_TIME, each with the offset it was given with */
+pub fn 
+pinned_time () -> Option<DateTime<FixedOffset>> {
+    PINNED_TIME.lock().unwrap().or_else(|| fake_time().ok().flatten())
+}
+
+/**
+ * The time to record in actions and assertions, as RFC 3339: `now`, in
+ * UTC, or with `capture_local_time` in the local timezone, e.g.
+ * 2026-10-15T17:30:00+02:00. A pinned time is recorded as it was given.
+ */
+pub fn 
+recorded_time () -> String {
+    match pinned_time() {
+        Some(when) => when.to_rfc3339(),
+        None if *LOCAL_TIME.lock().unwrap() => Local::now().fixed_offset().to_rfc3339(),
+        None => Utc::now().to_rfc3339()
+    }
+}
+
+/**
+ * What looks wrong about recording `when` for an action on a file last
+ * modified at `modified`: a time still to come, or one before the file was
+ * last changed, when the action can't yet have been done to it.
+ */
+pub fn 
+time_warnings (when: &DateTime<FixedOffset>, modified: Option<SystemTime>) -> Vec<String> {
+    let mut warnings = Vec::new();
+    if *when > Utc::now() {
+        warnings.push(format!("{} is in the future", when.to_rfc3339()));
+    }
+    if let Some(modified) = modified.map(DateTime::<Utc>::from).filter(|modified| when < modified) {
+        warnings.push(format!("{} is before the source was last modified, at {}", when.to_rfc3339(), modified.to_rfc3339()));
+    }
+
+    warnings
+}
+
+/* The time in C2PA_FAKE_TIME, if it is set */
+pub fn 
+fake_time () -> Result<Option<DateTime<FixedOffset>>, c2pa::Error> {
+    match std::env::var(FAKE_TIME_VAR) {
+        Ok(when) if !when.is_empty() => parse_time(&when).map(Some),
+        _ => Ok(None)
+    }
+}
+
+/**
+ * Gives a manifest the label `urn:uuid:<id>` for the pinned ID; without one
+ * it keeps the random label the SDK generates, after the pinned vendor if
+ * there is one. `Builder` takes the bare UUID and adds the `urn:uuid:`
+ * itself, and ignores the vendor for a label given this way.
+ */
+pub fn 
+apply_instance_id (builder: &mut Builder) {
+    if let Some(id) = *PINNED_INSTANCE_ID.lock().unwrap() {
+        builder.definition.label = Some(id.to_string());
+    } else if let Some(vendor) = PINNED_VENDOR.lock().unwrap().clone() {
+        builder.definition.vendor = Some(vendor);
+    }
+}
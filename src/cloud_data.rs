@@ -0,0 +1,134 @@
+use std::path::Path;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use c2pa::assertions::labels;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::aux_data::media_type;
+use crate::share::ShareConfig;
+use crate::sign::append_assertions;
+use crate::storage::{read_asset, read_file};
+
+/*
+ * Cloud-data assertions, the spec's pattern for payloads too big to carry
+ * in a manifest, such as raw sensor data or a full edit history. The
+ * payload is uploaded to a URL and the manifest records, in place of the
+ * assertion it stands for, that assertion's label, the payload's size and
+ * media type, and its URL and SHA-256 hash. The claim covers the hash, so a
+ * reader who fetches the payload can tell it is the one that was signed.
+ * The hash is kept as base64 text rather than a CBOR byte string, as
+ * assertions pass through manifest-definition JSON on their way to the SDK.
+ */
+
+/* Where a payload is kept, and its hash */
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HashedExtUri {
+    pub url: String,
+    pub alg: String,
+    pub hash: String
+}
+
+/* The cloud-data assertion */
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CloudData {
+    // Label of the assertion the payload stands for, e.g. com.example.raw-sensor
+    pub label: String,
+    pub size: u64,
+    pub location: HashedExtUri,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>
+}
+
+/* Parses a `label=path` argument, e.g. com.example.edit-history=history.json */
+pub fn 
+parse_cloud_data_arg (arg: &str) -> Result<(String, String), c2pa::Error> {
+    match arg.split_once('=') {
+        Some((label, path)) if !label.is_empty() && !path.is_empty() && !label.contains(char::is_whitespace) => Ok((label.to_owned(), path.to_owned())),
+        _ => Err(c2pa::Error::BadParam(format!("'{}' isn't LABEL=PATH, e.g. com.example.raw-sensor=capture.dng", arg)))
+    }
+}
+
+/**
+ * Uploads the file at `path` through `share` and returns the cloud-data
+ * assertion, as a manifest-definition entry, recording it as the payload
+ * of the assertion `label`.
+ */
+pub fn 
+upload (label: &str, path: &Path, share: &ShareConfig) -> Result<serde_json::Value, c2pa::Error> {
+    let payload = read_file(path).map_err(|e| c2pa::Error::BadParam(format!("{}: {}", path.display(), e)))?;
+    let file_name = path.file_name().and_then(|name| name.to_str())
+        .ok_or_else(|| c2pa::Error::BadParam(format!("{} has no file name to upload it as", path.display())))?;
+    let url = share.upload(file_name, &payload)?;
+
+    let cloud_data = CloudData {
+        label: label.to_owned(),
+        size: payload.len() as u64,
+        location: HashedExtUri { url, alg: "sha256".to_owned(), hash: BASE64.encode(Sha256::digest(&payload)) },
+        content_type: Some(media_type(path))
+    };
+
+    Ok(serde_json::json!({"label": labels::CLOUD_DATA, "data": serde_json::to_value(&cloud_data)?}))
+}
+
+/**
+ * Uploads each `(label, path)` payload and adds its cloud-data assertion to
+ * a manifest definition, after any assertions the definition already has.
+ * Returns how many were added.
+ */
+pub fn 
+add_cloud_data (definition: &mut serde_json::Value, payloads: &[(String, String)], share: &ShareConfig) -> Result<usize, c2pa::Error> {
+    let assertions = payloads.iter()
+        .map(|(label, path)| upload(label, Path::new(path), share))
+        .collect::<Result<Vec<_>, _>>()?;
+    append_assertions(definition, assertions)?;
+
+    Ok(payloads.len())
+}
+
+/* Fetches a cloud-data payload, once checked against the size and hash the claim records for it */
+pub fn 
+fetch (cloud_data: &CloudData) -> Result<Vec<u8>, c2pa::Error> {
+    if cloud_data.location.alg != "sha256" {
+        return Err(c2pa::Error::BadParam(format!("payload hashed with {}, which isn't supported", cloud_data.location.alg)));
+    }
+    let payload = read_asset(&cloud_data.location.url)?;
+
+    if payload.len() as u64 != cloud_data.size || BASE64.encode(Sha256::digest(&payload)) != cloud_data.location.hash {
+        return Err(c2pa::Error::HashMismatch(format!("{} doesn't match the hash the claim records ({} bytes, expected {})", cloud_data.location.url, payload.len(), cloud_data.size)));
+    }
+
+    Ok(payload)
+}
+
+/**
+ * Fetches the payload of every cloud-data assertion in a store, as `read`
+ * prints it, and records beside each assertion a `payload` status:
+ * verified, mismatch if what was fetched isn't what was signed, or
+ * unavailable if it couldn't be fetched, e.g. offline. Returns how many
+ * payloads didn't match.
+ */
+pub fn 
+verify_payloads (store_json: &mut serde_json::Value) -> usize {
+    let mut mismatched = 0;
+    let manifests = store_json["manifests"].as_object_mut().into_iter().flat_map(|manifests| manifests.values_mut());
+    let assertions = manifests.flat_map(|manifest| manifest["assertions"].as_array_mut().into_iter().flatten());
+
+    for assertion in assertions.filter(|assertion| assertion["label"] == labels::CLOUD_DATA) {
+        let status = match serde_json::from_value::<CloudData>(assertion["data"].clone()) {
+            Err(e) => serde_json::json!({"status": "mismatch", "explanation": format!("not a valid cloud-data assertion: {}", e)}),
+            Ok(cloud_data) => match fetch(&cloud_data) {
+                Ok(_) => serde_json::json!({"status": "verified"}),
+                Err(e @ c2pa::Error::HashMismatch(_)) => serde_json::json!({"status": "mismatch", "explanation": e.to_string()}),
+                Err(e) => serde_json::json!({"status": "unavailable", "explanation": e.to_string()})
+            }
+        };
+        if status["status"] == "mismatch" {
+            mismatched += 1;
+        }
+        assertion["payload"] = status;
+    }
+
+    mismatched
+}
@@ -0,0 +1,105 @@
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/*
+ * ANSI colors for terminal output. Colors are on only when stdout is a
+ * terminal, NO_COLOR is unset and `--no-color` wasn't given, so piped and
+ * redirected output stays plain text.
+ */
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+const GREEN: &str = "\x1b[32m";
+const YELLOW: &str = "\x1b[33m";
+const RED: &str = "\x1b[31m";
+const CYAN: &str = "\x1b[36m";
+const RESET: &str = "\x1b[0m";
+
+/* Turns colors on if stdout is a terminal and neither `no_color` nor NO_COLOR asks otherwise */
+pub fn 
+init (no_color: bool) {
+    let no_color = no_color || std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+    ENABLED.store(!no_color && std::io::stdout().is_terminal(), Ordering::Relaxed);
+}
+
+pub fn 
+enabled () -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+fn 
+paint (color: &str, text: &str) -> String {
+    if enabled() {
+        format!("{}{}{}", color, text, RESET)
+    } else {
+        text.to_owned()
+    }
+}
+
+/* A passing verdict */
+pub fn 
+green (text: &str) -> String {
+    paint(GREEN, text)
+}
+
+/* A verdict that couldn't be reached, e.g. an unreadable asset */
+pub fn 
+yellow (text: &str) -> String {
+    paint(YELLOW, text)
+}
+
+/* A failing verdict */
+pub fn 
+red (text: &str) -> String {
+    paint(RED, text)
+}
+
+/**
+ * Highlights the value of every "label" field in JSON text, pretty-printed
+ * or compact, so assertion and manifest labels stand out.
+ */
+pub fn 
+highlight_labels (json: &str) -> String {
+    if !enabled() {
+        return json.to_owned();
+    }
+
+    let mut highlighted = String::with_capacity(json.len());
+    let mut rest = json;
+    while let Some(i) = rest.find("\"label\":") {
+        let (before, after) = rest.split_at(i + "\"label\":".len());
+        highlighted.push_str(before);
+
+        let value_start = after.len() - after.trim_start_matches(' ').len();
+        highlighted.push_str(&after[..value_start]);
+        rest = &after[value_start..];
+
+        if let Some(len) = string_token_len(rest) {
+            highlighted.push_str(&paint(CYAN, &rest[..len]));
+            rest = &rest[len..];
+        }
+    }
+    highlighted.push_str(rest);
+
+    highlighted
+}
+
+/* Length of the JSON string token at the start of `text`, quotes included */
+fn 
+string_token_len (text: &str) -> Option<usize> {
+    if !text.starts_with('"') {
+        return None;
+    }
+
+    let mut escaped = false;
+    for (i, c) in text.char_indices().skip(1) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => return Some(i + 1),
+            _ => ()
+        }
+    }
+
+    None
+}
@@ -0,0 +1,146 @@
+use crate::exiftool::exiftool_json;
+use crate::jumbf::{self, BoxEntry};
+use crate::query::manifest_store_json;
+use crate::read::{cert_chain_pem, claim_versions, hash_bindings, load_manifest_store, validation_errors};
+use crate::tamper::findings;
+use crate::timeline::timeline;
+use crate::timestamp::timestamps;
+
+/*
+ * Malformed manifest stores for exercising the reading path. Each mutant
+ * is a signed seed asset whose manifest store has had one box broken:
+ * truncated, given a length which overruns or undercuts it, given content
+ * which isn't valid CBOR or JSON, or, for assertions, grown far past any
+ * real one's size with every enclosing length kept consistent. Mutants are
+ * named after the box they break, numbered as `boxes` lists them, so a
+ * failure can be traced back with `boxes --extract` on the seed.
+ * `read_all` runs an asset through everything that reads manifests, for
+ * the corpus and the fuzz target alike.
+ */
+
+// The size assertions are grown to, well past any real one
+pub const OVERSIZED_ASSERTION: usize = 1 << 20;
+
+/* One malformed asset, and how it was made */
+pub struct Mutant {
+    pub mutation: &'static str,
+    // The box broken, by its index in the seed's `boxes` listing
+    pub index: usize,
+    pub path: Option<String>,
+    pub asset: Vec<u8>
+}
+
+/* Whether `entry` lies within `ancestor` in the store */
+fn 
+contains (ancestor: &BoxEntry, entry: &BoxEntry) -> bool {
+    ancestor.offset <= entry.offset && entry.offset + entry.len <= ancestor.offset + ancestor.len && ancestor.index != entry.index
+}
+
+/* The store with its bytes from `start` to `end` replaced */
+fn 
+splice (jumbf: &[u8], start: usize, end: usize, replacement: &[u8]) -> Vec<u8> {
+    let mut spliced = jumbf[..start].to_vec();
+    spliced.extend_from_slice(replacement);
+    spliced.extend_from_slice(&jumbf[end..]);
+
+    spliced
+}
+
+/* The store with the 32-bit length of the box at `offset` set to `len` */
+fn 
+with_length (jumbf: &[u8], offset: usize, len: u32) -> Vec<u8> {
+    splice(jumbf, offset, offset + 4, &len.to_be_bytes())
+}
+
+/**
+ * The store with the payload of the content box `entry` replaced by a CBOR
+ * byte string of OVERSIZED_ASSERTION bytes, and the length of the box and
+ * each superbox around it grown to match. None if a length is 64-bit.
+ */
+fn 
+oversized (jumbf: &[u8], entries: &[BoxEntry], entry: &BoxEntry) -> Option<Vec<u8>> {
+    let mut payload = vec![0x5a];
+    payload.extend_from_slice(&(OVERSIZED_ASSERTION as u32).to_be_bytes());
+    payload.resize(payload.len() + OVERSIZED_ASSERTION, 0);
+    let growth = (payload.len() + 8).checked_sub(entry.len)?;
+
+    let mut grown = jumbf.to_vec();
+    for resized in entries.iter().filter(|ancestor| contains(ancestor, entry)).chain([entry]) {
+        let len = u32::from_be_bytes(grown[resized.offset..resized.offset + 4].try_into().ok()?);
+        if len < 8 {
+            return None;
+        }
+        grown = with_length(&grown, resized.offset, u32::try_from(len as usize + growth).ok()?);
+    }
+
+    Some(splice(&grown, entry.offset + 8, entry.offset + entry.len, &payload))
+}
+
+/**
+ * Malformed variants of the manifest store in a signed asset, each
+ * embedded back in the asset. Variants the asset's format can't embed are
+ * left out.
+ */
+pub fn 
+mutate (format: &str, asset: &[u8]) -> Result<Vec<Mutant>, c2pa::Error> {
+    let jumbf = c2pa::jumbf_io::load_jumbf_from_memory(format, asset)?;
+    let entries = jumbf::list(&jumbf)?;
+
+    let mut stores: Vec<(&'static str, &BoxEntry, Vec<u8>)> = Vec::new();
+    for entry in &entries {
+        let end = entry.offset + entry.len;
+        // Cut off halfway, with every length around it left as it was
+        stores.push(("truncated", entry, splice(&jumbf, entry.offset + entry.len / 2, end, &[])));
+        stores.push(("length-overrun", entry, with_length(&jumbf, entry.offset, u32::MAX)));
+        // Shorter than the box header itself
+        stores.push(("length-undercut", entry, with_length(&jumbf, entry.offset, 7)));
+
+        if matches!(entry.kind.as_str(), "cbor" | "json") && entry.len > 8 {
+            // A CBOR break code, and for JSON a byte which can't start a value
+            stores.push(("bad-content", entry, splice(&jumbf, entry.offset + 8, entry.offset + 9, &[0xff])));
+            // A byte string claiming 2^64 - 1 bytes
+            stores.push(("huge-cbor-length", entry, splice(&jumbf, entry.offset + 8, end, &[0x5b, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff])));
+
+            let in_assertion = entries.iter().any(|ancestor| contains(ancestor, entry) && ancestor.label.as_deref() == Some("c2pa.assertions"));
+            if let Some(grown) = in_assertion.then(|| oversized(&jumbf, &entries, entry)).flatten() {
+                stores.push(("oversized-assertion", entry, grown));
+            }
+        }
+    }
+
+    Ok(stores.into_iter()
+        .filter_map(|(mutation, entry, store)| {
+            let asset = c2pa::jumbf_io::save_jumbf_to_memory(format, asset, &store).ok()?;
+            Some(Mutant { mutation, index: entry.index, path: entry.path.clone(), asset })
+        })
+        .collect())
+}
+
+/**
+ * Runs an asset through everything that reads manifest stores, discarding
+ * what they find: the SDK's reader and validation, the JSON `read` prints
+ * and what is derived from it, and this crate's own JUMBF, hash binding,
+ * claim version, timestamp and certificate parsing. Malformed input should
+ * only ever make these return errors.
+ */
+pub fn 
+read_all (format: &str, asset: &[u8]) {
+    if let Ok(jumbf) = c2pa::jumbf_io::load_jumbf_from_memory(format, asset) {
+        let _ = jumbf::list(&jumbf);
+        let _ = jumbf::manifests(&jumbf);
+    }
+    let _ = hash_bindings(format, asset);
+    let _ = claim_versions(format, asset);
+    let _ = timestamps(format, asset);
+    let _ = cert_chain_pem(format, asset);
+
+    let Ok(manifest_store) = load_manifest_store(format, asset) else {
+        return;
+    };
+    let _ = validation_errors(&manifest_store);
+    let _ = findings(&manifest_store);
+    if let Ok(store_json) = manifest_store_json(&manifest_store) {
+        let _ = timeline(&store_json);
+        let _ = exiftool_json(&store_json, "");
+    }
+}
@@ -0,0 +1,257 @@
+use std::fs;
+use std::io::{self, Read, Write};
+use std::os::unix::fs::{DirBuilderExt, FileTypeExt, PermissionsExt};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::Path;
+
+use c2pa::Signer;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::metrics;
+use crate::read::{is_valid, load_manifest_store, validation_errors};
+use crate::sign::{sign_asset_with_signer, SignerConfig};
+use crate::webhook;
+
+// Upper bound on a single frame, so a bad length prefix can't exhaust memory
+const MAX_FRAME_SIZE: u32 = 256 * 1024 * 1024;
+
+#[derive(Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+/* The JSON header frame which starts every request */
+enum DaemonRequest {
+    Sign { format: String, manifest: serde_json::Value },
+    Verify { format: String }
+}
+
+/*
+ * Protocol: every frame is a 4-byte big-endian length followed by that many
+ * bytes. A request is two frames, a JSON header (`{"op": "sign", "format":
+ * "jpg", "manifest": {...}}` or `{"op": "verify", "format": "jpg"}`) and the
+ * asset bytes. A response is also two frames, a JSON status (`{"ok": true,
+ * ...}` or `{"ok": false, "error": "..."}`) and the signed asset, which is
+ * empty for verification and errors. A connection may carry any number of
+ * requests.
+ */
+
+fn 
+read_frame (stream: &mut UnixStream) -> io::Result<Option<Vec<u8>>> {
+    let mut len = [0u8; 4];
+    match stream.read_exact(&mut len) {
+        Ok(()) => (),
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e)
+    }
+
+    let len = u32::from_be_bytes(len);
+    if len > MAX_FRAME_SIZE {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("frame of {} bytes exceeds limit", len)));
+    }
+
+    let mut frame = vec![0u8; len as usize];
+    stream.read_exact(&mut frame)?;
+
+    Ok(Some(frame))
+}
+
+fn 
+write_frame (stream: &mut UnixStream, frame: &[u8]) -> io::Result<()> {
+    stream.write_all(&(frame.len() as u32).to_be_bytes())?;
+    stream.write_all(frame)
+}
+
+fn 
+handle_request (header: &[u8], asset: &[u8], signer: &dyn Signer) -> Result<(serde_json::Value, Vec<u8>), c2pa::Error> {
+    match serde_json::from_slice(header)? {
+        DaemonRequest::Sign { format, manifest } => {
+            let signed = metrics::timed("sign", || sign_asset_with_signer(&format, asset, &manifest.to_string(), signer))?;
+            webhook::notify_in_background(&format, asset, &signed, None);
+            Ok((json!({ "ok": true }), signed))
+        },
+        DaemonRequest::Verify { format } => {
+            let manifest_store = metrics::timed("verify", || load_manifest_store(&format, asset))?;
+            let errors = validation_errors(&manifest_store);
+            metrics::record_validation_errors(&errors);
+
+            Ok((json!({
+                "ok": true,
+                "valid": is_valid(&manifest_store),
+                "validation_errors": errors,
+                "active_manifest": manifest_store.active_label()
+            }), Vec::new()))
+        }
+    }
+}
+
+fn 
+handle_connection (mut stream: UnixStream, signer: &dyn Signer) -> io::Result<()> {
+    while let Some(header) = read_frame(&mut stream)? {
+        let asset = read_frame(&mut stream)?
+            .ok_or_else(|| io::Error::new(io::ErrorKind::UnexpectedEof, "request header without asset frame"))?;
+
+        let (status, payload) = match handle_request(&header, &asset, signer) {
+            Ok(response) => response,
+            Err(e) => (json!({ "ok": false, "error": e.to_string() }), Vec::new())
+        };
+
+        write_frame(&mut stream, status.to_string().as_bytes())?;
+        write_frame(&mut stream, &payload)?;
+    }
+
+    Ok(())
+}
+
+/**
+ * Binds a Unix socket at `socket_path`, owner-only from the moment it
+ * exists since anyone who can connect can sign. Its directory is created
+ * owner-only if missing. A socket left behind by a daemon no longer
+ * listening is replaced; anything else already there, including the socket
+ * of a daemon still running, is an error rather than removed.
+ */
+fn 
+bind (socket_path: &Path) -> io::Result<UnixListener> {
+    let dir = socket_path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    fs::DirBuilder::new().recursive(true).mode(0o700).create(dir)?;
+
+    match fs::symlink_metadata(socket_path) {
+        Ok(metadata) if !metadata.file_type().is_socket() => {
+            return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("{} exists and isn't a socket", socket_path.display())));
+        },
+        Ok(_) => match UnixStream::connect(socket_path) {
+            Ok(_) => return Err(io::Error::new(io::ErrorKind::AddrInUse, format!("a daemon is already listening on {}", socket_path.display()))),
+            Err(e) if e.kind() == io::ErrorKind::ConnectionRefused => fs::remove_file(socket_path)?,
+            Err(e) => return Err(e)
+        },
+        Err(e) if e.kind() == io::ErrorKind::NotFound => (),
+        Err(e) => return Err(e)
+    }
+
+    // Bound in an owner-only directory of its own first, so no one else can reach it before it is owner-only too
+    let staging = dir.join(format!(".bind-{}", std::process::id()));
+    fs::DirBuilder::new().mode(0o700).create(&staging)?;
+    let staged = staging.join("s");
+    let listener = UnixListener::bind(&staged).and_then(|listener| {
+        fs::set_permissions(&staged, fs::Permissions::from_mode(0o600))?;
+        fs::rename(&staged, socket_path)?;
+        Ok(listener)
+    });
+    let _ = fs::remove_dir_all(&staging);
+    listener
+}
+
+/**
+ * Listens on a Unix socket at `socket_path` and serves sign/verify requests
+ * until the process is terminated. The signer, and with it the unlocked
+ * private key, is constructed once up front.
+ */
+pub fn 
+run (socket_path: &Path, signer_config: &SignerConfig) -> Result<(), c2pa::Error> {
+    let signer = signer_config.create_signer()?;
+    let listener = bind(socket_path)?;
+
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                if let Err(e) = handle_connection(stream, &*signer) {
+                    eprintln!("daemon connection failed: {}", e);
+                }
+            },
+            Err(e) => eprintln!("daemon accept failed: {}", e)
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    /* An empty directory of the test's own */
+    fn scratch_dir(test: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("c2pa-walkthrough-daemon-{}-{}", test, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn socket_is_owner_only_in_an_owner_only_directory() {
+        let socket_path = scratch_dir("permissions").join("run").join("daemon.sock");
+        let _listener = bind(&socket_path).unwrap();
+
+        assert_eq!(fs::metadata(&socket_path).unwrap().permissions().mode() & 0o777, 0o600);
+        assert_eq!(fs::metadata(socket_path.parent().unwrap()).unwrap().permissions().mode() & 0o777, 0o700);
+    }
+
+    #[test]
+    fn file_in_the_way_is_left_alone() {
+        let socket_path = scratch_dir("regular-file").join("daemon.sock");
+        fs::write(&socket_path, b"not a socket").unwrap();
+
+        assert_eq!(bind(&socket_path).unwrap_err().kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(fs::read(&socket_path).unwrap(), b"not a socket");
+    }
+
+    #[test]
+    fn live_daemon_socket_is_left_alone() {
+        let socket_path = scratch_dir("live").join("daemon.sock");
+        let _listener = bind(&socket_path).unwrap();
+
+        assert_eq!(bind(&socket_path).unwrap_err().kind(), io::ErrorKind::AddrInUse);
+        assert!(UnixStream::connect(&socket_path).is_ok());
+    }
+
+    #[test]
+    fn stale_socket_is_replaced() {
+        let socket_path = scratch_dir("stale").join("daemon.sock");
+        drop(bind(&socket_path).unwrap());
+        assert!(fs::symlink_metadata(&socket_path).unwrap().file_type().is_socket());
+
+        let _listener = bind(&socket_path).unwrap();
+        assert!(UnixStream::connect(&socket_path).is_ok());
+    }
+
+    #[test]
+    fn frames_round_trip() {
+        let (mut client, mut server) = UnixStream::pair().unwrap();
+        write_frame(&mut client, b"{\"op\": \"verify\", \"format\": \"jpg\"}").unwrap();
+        write_frame(&mut client, b"").unwrap();
+        drop(client);
+
+        assert_eq!(read_frame(&mut server).unwrap().unwrap(), b"{\"op\": \"verify\", \"format\": \"jpg\"}");
+        assert_eq!(read_frame(&mut server).unwrap().unwrap(), b"");
+        assert!(read_frame(&mut server).unwrap().is_none());
+    }
+
+    #[test]
+    fn oversized_frame_is_refused_before_it_is_read() {
+        let (mut client, mut server) = UnixStream::pair().unwrap();
+        client.write_all(&(MAX_FRAME_SIZE + 1).to_be_bytes()).unwrap();
+
+        let e = read_frame(&mut server).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+        assert!(e.to_string().contains("exceeds limit"));
+    }
+
+    #[test]
+    fn truncated_frame_is_an_error() {
+        let (mut client, mut server) = UnixStream::pair().unwrap();
+        client.write_all(&16u32.to_be_bytes()).unwrap();
+        client.write_all(b"short").unwrap();
+        drop(client);
+
+        assert_eq!(read_frame(&mut server).unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn truncated_length_prefix_is_an_end_of_stream() {
+        let (mut client, mut server) = UnixStream::pair().unwrap();
+        client.write_all(&[0, 0]).unwrap();
+        drop(client);
+
+        assert!(read_frame(&mut server).unwrap().is_none());
+    }
+}
@@ -0,0 +1,123 @@
+use c2pa::Reader;
+use image::{GenericImageView, ImageFormat, Rgb, RgbImage};
+
+/*
+ * Rough visual diff of a tampered image against the thumbnail its manifest
+ * carries, with the `visual-diff` feature. The claim thumbnail shows the
+ * asset as signed, so it is preferred; failing that, the parent
+ * ingredient's thumbnail shows it before its last edit, which also marks
+ * the edit itself. Both are scaled down to a common grid, compared cell by
+ * cell, and the differing cells drawn in red over a greyscale copy of the
+ * asset. Thumbnails are small and lossy, so this only points analysts at
+ * a region, it doesn't prove anything changed.
+ */
+
+// Width of the grid images are compared at
+const GRID_WIDTH: u32 = 64;
+
+// Heatmaps are no wider than this
+const HEATMAP_WIDTH: u32 = 1024;
+
+// Mean per-channel difference (of 255) above which a cell counts as changed
+const THRESHOLD: f32 = 24.0;
+
+/* Result of a visual diff */
+pub struct VisualDiff {
+    // Which thumbnail the asset was compared with
+    pub reference: String,
+    // Share of the image which differs, from 0 to 1
+    pub changed: f32,
+    // Bounding box of the differing area in asset pixels: x, y, width, height
+    pub region: Option<(u32, u32, u32, u32)>,
+    // PNG heatmap of the differences, at most HEATMAP_WIDTH wide
+    pub png: Vec<u8>
+}
+
+fn 
+image_error (e: image::ImageError) -> c2pa::Error {
+    c2pa::Error::OtherError(Box::new(e))
+}
+
+/* The claim thumbnail of the active manifest, else its parent ingredient's, with a description */
+fn 
+reference_thumbnail (manifest_store: &Reader) -> Option<(String, Vec<u8>)> {
+    let manifest = manifest_store.active_manifest()?;
+    if let Some((_, bytes)) = manifest.thumbnail() {
+        return Some(("claim thumbnail".to_owned(), bytes.into_owned()));
+    }
+
+    manifest.ingredients().iter()
+        .filter(|ingredient| ingredient.is_parent())
+        .find_map(|ingredient| {
+            let (_, bytes) = ingredient.thumbnail()?;
+            Some((format!("thumbnail of parent ingredient {}", ingredient.title().unwrap_or_default()), bytes.into_owned()))
+        })
+}
+
+/**
+ * Compares an image asset with the thumbnail in its active manifest.
+ * Returns None if the manifest has no thumbnail to compare against.
+ */
+pub fn 
+visual_diff (asset: &[u8], manifest_store: &Reader) -> Result<Option<VisualDiff>, c2pa::Error> {
+    let Some((reference, thumbnail)) = reference_thumbnail(manifest_store) else {
+        return Ok(None);
+    };
+
+    let image = image::load_from_memory(asset).map_err(image_error)?;
+    let thumbnail = image::load_from_memory(&thumbnail).map_err(image_error)?;
+    let (width, height) = image.dimensions();
+    let grid_height = (GRID_WIDTH * height / width.max(1)).max(1);
+
+    let asset_grid = image.thumbnail_exact(GRID_WIDTH, grid_height).to_rgb8();
+    let thumbnail_grid = thumbnail.thumbnail_exact(GRID_WIDTH, grid_height).to_rgb8();
+
+    let mut changed_cells = 0;
+    let mut bounds: Option<(u32, u32, u32, u32)> = None;
+    let mut mask = vec![false; (GRID_WIDTH * grid_height) as usize];
+    for (x, y, pixel) in asset_grid.enumerate_pixels() {
+        let other = thumbnail_grid.get_pixel(x, y);
+        let difference = pixel.0.iter().zip(other.0).map(|(a, b)| a.abs_diff(b) as f32).sum::<f32>() / 3.0;
+        if difference > THRESHOLD {
+            changed_cells += 1;
+            mask[(y * GRID_WIDTH + x) as usize] = true;
+            bounds = Some(match bounds {
+                None => (x, y, x, y),
+                Some((x0, y0, x1, y1)) => (x0.min(x), y0.min(y), x1.max(x), y1.max(y))
+            });
+        }
+    }
+
+    // Grid cells back to asset pixels
+    let cell = |x: u32, y: u32| (x * width / GRID_WIDTH, y * height / grid_height);
+    let region = bounds.map(|(x0, y0, x1, y1)| {
+        let (left, top) = cell(x0, y0);
+        let (right, bottom) = cell(x1 + 1, y1 + 1);
+        (left, top, right - left, bottom - top)
+    });
+
+    let grey = if width > HEATMAP_WIDTH {
+        image.thumbnail(HEATMAP_WIDTH, HEATMAP_WIDTH * height / width).to_luma8()
+    } else {
+        image.to_luma8()
+    };
+    let (heatmap_width, heatmap_height) = grey.dimensions();
+    let heatmap = RgbImage::from_fn(heatmap_width, heatmap_height, |x, y| {
+        let luma = grey.get_pixel(x, y).0[0] / 2;
+        let (gx, gy) = (x * GRID_WIDTH / heatmap_width, y * grid_height / heatmap_height);
+        if mask[(gy * GRID_WIDTH + gx) as usize] {
+            Rgb([128 + luma, luma / 2, luma / 2])
+        } else {
+            Rgb([luma, luma, luma])
+        }
+    });
+    let mut png = std::io::Cursor::new(Vec::new());
+    heatmap.write_to(&mut png, ImageFormat::Png).map_err(image_error)?;
+
+    Ok(Some(VisualDiff {
+        reference,
+        changed: changed_cells as f32 / mask.len() as f32,
+        region,
+        png: png.into_inner()
+    }))
+}
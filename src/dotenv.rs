@@ -0,0 +1,113 @@
+use std::path::{Path, PathBuf};
+
+/*
+ * Settings from a `.env` file, for container deployments where flags are
+ * awkward. Each `KEY=value` line, optionally after `export`, sets an
+ * environment variable unless it is already set, so the real environment
+ * wins over the file and flags win over both. Values may be single quoted,
+ * taken literally, or double quoted, with \n, \t, \" and \\ escapes;
+ * unquoted values end at a ` #` comment. Blank lines and `#` comment lines
+ * are skipped.
+ */
+
+// Environment variable naming the file to load, in place of .env in the working directory
+pub const ENV_FILE_VAR: &str = "C2PA_ENV_FILE";
+
+/* A double-quoted value's contents, with its escapes replaced */
+fn 
+unescape (value: &str) -> String {
+    let mut unescaped = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('n') => unescaped.push('\n'),
+                Some('t') => unescaped.push('\t'),
+                Some(other) => unescaped.push(other),
+                None => unescaped.push('\\')
+            },
+            c => unescaped.push(c)
+        }
+    }
+    unescaped
+}
+
+/* The value of one line, after its `=` */
+fn 
+value (raw: &str, line_number: usize) -> Result<String, c2pa::Error> {
+    let raw = raw.trim();
+    let unterminated = || c2pa::Error::BadParam(format!("line {}: unterminated quoted value", line_number));
+
+    match raw.chars().next() {
+        Some(quote @ ('"' | '\'')) => {
+            let rest = &raw[1..];
+            // The closing quote is the first not escaped by a backslash, in double quotes
+            let mut escaped = false;
+            let end = rest.char_indices().find(|(_, c)| {
+                let closes = *c == quote && !escaped;
+                escaped = quote == '"' && *c == '\\' && !escaped;
+                closes
+            });
+            let (end, _) = end.ok_or_else(unterminated)?;
+            match quote {
+                '"' => Ok(unescape(&rest[..end])),
+                _ => Ok(rest[..end].to_owned())
+            }
+        },
+        _ => Ok(raw.split(" #").next().unwrap_or_default().trim_end().to_owned())
+    }
+}
+
+/* The variables a `.env` file sets, in order */
+pub fn 
+parse (contents: &str) -> Result<Vec<(String, String)>, c2pa::Error> {
+    let mut vars = Vec::new();
+
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let (key, raw) = line.split_once('=')
+            .ok_or_else(|| c2pa::Error::BadParam(format!("line {}: expected KEY=value", index + 1)))?;
+        let key = key.trim();
+        if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') || key.starts_with(|c: char| c.is_ascii_digit()) {
+            return Err(c2pa::Error::BadParam(format!("line {}: '{}' isn't a variable name", index + 1, key)));
+        }
+        vars.push((key.to_owned(), value(raw, index + 1)?));
+    }
+
+    Ok(vars)
+}
+
+/**
+ * Sets the variables of the `.env` file at `path` which aren't already
+ * set, returning their names. Call it before anything reads the
+ * environment or starts threads.
+ */
+pub fn 
+load (path: &Path) -> Result<Vec<String>, c2pa::Error> {
+    let mut set = Vec::new();
+
+    for (key, value) in parse(&std::fs::read_to_string(path)?)? {
+        if std::env::var_os(&key).is_none() {
+            std::env::set_var(&key, value);
+            set.push(key);
+        }
+    }
+
+    Ok(set)
+}
+
+/**
+ * The `.env` file to load: the one named by `C2PA_ENV_FILE`, which must
+ * exist, else `.env` in the working directory if there is one.
+ */
+pub fn 
+env_file () -> Option<PathBuf> {
+    match std::env::var_os(ENV_FILE_VAR).filter(|path| !path.is_empty()) {
+        Some(path) => Some(PathBuf::from(path)),
+        None => Some(PathBuf::from(".env")).filter(|path| path.is_file())
+    }
+}
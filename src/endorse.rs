@@ -0,0 +1,167 @@
+use std::path::Path;
+
+use c2pa::assertions::{c2pa_action, labels, Action, Actions, ReviewRating};
+use c2pa::{Builder, Reader, Signer};
+use chrono::DateTime;
+use serde::{Deserialize, Serialize};
+
+use crate::clock;
+use crate::manifest::{parent_ingredient, software_agent};
+use crate::namespace;
+use crate::read::{load_manifest_store, signature_validated, validation_errors};
+use crate::review::review_ingredient;
+use crate::sign::embed;
+
+/*
+ * Endorsements, where a second party such as an editor or a fact-checking
+ * desk vouches for, or disputes, an asset someone else signed. The
+ * endorsement is a new manifest, signed with the endorser's own
+ * credentials, which takes the asset and its manifest as its parent
+ * ingredient and records the verdict in an endorsement assertion naming the
+ * manifest it is about. The original manifest is left untouched.
+ */
+
+// Name of the endorsement assertion in the label namespace, e.g. com.mikecvet.endorsement
+pub const ENDORSEMENT_NAME: &str = "endorsement";
+
+/* What an endorser concluded about the manifest they reviewed */
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Verdict {
+    // Vouched for, as accurate and fit to publish
+    Endorsed,
+    // Looked over, without vouching for it
+    Reviewed,
+    // Found wanting, e.g. by a fact check
+    Disputed
+}
+
+impl Verdict {
+    pub fn from_name(name: &str) -> Result<Verdict, c2pa::Error> {
+        match name {
+            "endorsed" => Ok(Verdict::Endorsed),
+            "reviewed" => Ok(Verdict::Reviewed),
+            "disputed" => Ok(Verdict::Disputed),
+            _ => Err(c2pa::Error::BadParam(format!("'{}' isn't a verdict; use endorsed, reviewed or disputed", name)))
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Verdict::Endorsed => "endorsed",
+            Verdict::Reviewed => "reviewed",
+            Verdict::Disputed => "disputed"
+        }
+    }
+}
+
+/* Who endorses an asset, and what they have to say about it */
+pub struct Endorser {
+    pub verdict: Verdict,
+    // The person or desk, e.g. "Photo desk"; the signing credentials name the organization
+    pub name: Option<String>,
+    // Their role, e.g. "editor" or "fact-checker"
+    pub role: Option<String>,
+    pub comment: Option<String>,
+    // Ratings of the endorsed version, recorded on the parent ingredient
+    pub reviews: Vec<ReviewRating>,
+    // Instance ID for the parent ingredient in place of the one read from the asset's XMP
+    pub parent_instance_id: Option<String>
+}
+
+/* The endorsement assertion */
+#[derive(Serialize, Deserialize)]
+pub struct Endorsement {
+    pub verdict: Verdict,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub endorser: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub role: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+    // Label of the manifest endorsed, the active manifest of the parent
+    pub manifest: String,
+    // Issuer of the certificate which signed it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signer: Option<String>,
+    pub when: String
+}
+
+/**
+ * Signs a manifest endorsing the active manifest of `asset`, read from
+ * `path`, with `signer`, returning the endorsed asset. Assets without a
+ * manifest, or whose manifest doesn't validate, aren't endorsed.
+ */
+pub fn 
+endorse (path: &Path, format: &str, asset: &[u8], endorser: &Endorser, signer: &dyn Signer) -> Result<Vec<u8>, c2pa::Error> {
+    let no_manifest = || c2pa::Error::BadParam("the asset has no manifest to endorse".to_owned());
+    let manifest_store = match load_manifest_store(format, asset) {
+        Err(c2pa::Error::JumbfNotFound) => return Err(no_manifest()),
+        manifest_store => manifest_store?
+    };
+    let endorsed = manifest_store.active_manifest().ok_or_else(no_manifest)?;
+    let errors = validation_errors(&manifest_store);
+    if !errors.is_empty() {
+        return Err(c2pa::Error::BadParam(format!("the manifest to endorse doesn't validate: {}", errors.join("; "))));
+    }
+    if !signature_validated(&manifest_store) {
+        return Err(c2pa::Error::BadParam("the manifest to endorse has no validated signature".to_owned()));
+    }
+
+    let endorsement = Endorsement {
+        verdict: endorser.verdict,
+        endorser: endorser.name.clone(),
+        role: endorser.role.clone(),
+        comment: endorser.comment.clone(),
+        manifest: manifest_store.active_label().unwrap_or_default().to_owned(),
+        signer: endorsed.issuer(),
+        when: clock::recorded_time()
+    };
+
+    let mut parent = parent_ingredient(path, format, asset)?;
+    review_ingredient(&mut parent, &endorser.reviews);
+    if let Some(instance_id) = &endorser.parent_instance_id {
+        parent.set_instance_id(instance_id);
+    }
+    let opened = Actions::new()
+        .add_action(
+            Action::new(c2pa_action::OPENED)
+                .set_parameter("identifier", parent.instance_id().to_owned())?
+                .set_reason("endorsement")
+                .set_software_agent(software_agent())
+                .set_when(endorsement.when.clone())
+        );
+
+    let mut builder = Builder::new();
+    builder.set_claim_generator_info(software_agent());
+    builder.definition.title = parent.title().map(str::to_owned);
+    parent.set_is_parent();
+    builder.add_ingredient(parent);
+    builder.add_assertion(labels::ACTIONS, &opened)?;
+    builder.add_assertion_json(namespace::label(ENDORSEMENT_NAME), &endorsement)?;
+    clock::apply_instance_id(&mut builder);
+    #[cfg(feature = "thumbnails")]
+    crate::thumbnail::apply_claim_thumbnail(&mut builder, format, asset)?;
+
+    embed(format, asset, &mut builder, signer)
+}
+
+/**
+ * The endorsements recorded in a manifest store, oldest first, each with
+ * the label of the manifest carrying it.
+ */
+pub fn 
+endorsements (manifest_store: &Reader) -> Vec<(String, Endorsement)> {
+    let mut found = Vec::new();
+    for manifest in manifest_store.iter_manifests() {
+        for assertion in manifest.assertions().iter().filter(|assertion| namespace::strip_namespace(assertion.label()) == Some(ENDORSEMENT_NAME)) {
+            if let Ok(endorsement) = assertion.to_assertion::<Endorsement>() {
+                found.push((manifest.label().unwrap_or_default().to_owned(), endorsement));
+            }
+        }
+    }
+    // Times may be recorded with different offsets, so they are compared as instants
+    found.sort_by_key(|(_, endorsement)| DateTime::parse_from_rfc3339(&endorsement.when).ok());
+
+    found
+}
@@ -0,0 +1,99 @@
+use serde_json::{Map, Value};
+
+use crate::query::{assertions_matching, select};
+use crate::timeline::timeline;
+
+/*
+ * A manifest store flattened into the shape `exiftool -json` prints: an
+ * array with one object per file, of tag names and values. Scripts which
+ * already ingest exiftool's output can then take an asset's provenance
+ * without changes. Exif fields captured in `stds.exif` assertions keep
+ * their exiftool tag names (GPSLatitude for `exif:GPSLatitude`). Actions
+ * are flattened the way exiftool flattens structures, into ActionsAction,
+ * ActionsWhen and ActionsSoftwareAgent lists, oldest first. Where manifests
+ * in the chain disagree, the active manifest wins. Like exiftool, a tag
+ * with one value is a scalar and a tag with several is a list. Like `read`,
+ * this has no file-system dependencies.
+ */
+
+/* A tag's value from everything found for it, or None if nothing was */
+fn
+tag_value (mut values: Vec<Value>) -> Option<Value> {
+    match values.len() {
+        0 => None,
+        1 => values.pop(),
+        _ => Some(Value::Array(values))
+    }
+}
+
+fn
+insert (tags: &mut Map<String, Value>, name: &str, values: Vec<Value>) {
+    if let Some(value) = tag_value(values) {
+        tags.insert(name.to_owned(), value);
+    }
+}
+
+/* The labels of a store's manifests, with the active manifest last so its values win */
+fn
+manifest_order (store_json: &Value) -> Vec<&str> {
+    let active = store_json["active_manifest"].as_str();
+    let mut labels: Vec<&str> = store_json["manifests"].as_object()
+        .map(|manifests| manifests.keys().map(String::as_str).filter(|label| Some(*label) != active).collect())
+        .unwrap_or_default();
+    labels.extend(active);
+
+    labels
+}
+
+/* `exiftool -json` output for the file at `source_file`, from its store as `read` prints it */
+pub fn
+exiftool_json (store_json: &Value, source_file: &str) -> Value {
+    let mut tags = Map::new();
+    tags.insert("SourceFile".to_owned(), Value::String(source_file.to_owned()));
+
+    let active = &store_json["manifests"][store_json["active_manifest"].as_str().unwrap_or_default()];
+    for (tag, field) in [("Title", "title"), ("Format", "format"), ("InstanceID", "instance_id"), ("ClaimGenerator", "claim_generator")] {
+        if let Some(value) = active.get(field).filter(|value| !value.is_null()) {
+            tags.insert(tag.to_owned(), value.clone());
+        }
+    }
+    if let Some(issuer) = active["signature_info"]["issuer"].as_str() {
+        tags.insert("SignatureIssuer".to_owned(), Value::String(issuer.to_owned()));
+    }
+
+    let mut authors: Vec<Value> = Vec::new();
+    let mut exif = Map::new();
+    for label in manifest_order(store_json) {
+        for author in assertions_matching(store_json, Some(label), "stds.schema-org.CreativeWork*").iter()
+            .flat_map(|assertion| select(assertion, ".data.author[].name").unwrap_or_default())
+        {
+            if !authors.contains(&author) {
+                authors.push(author);
+            }
+        }
+
+        for assertion in assertions_matching(store_json, Some(label), "stds.exif") {
+            let Some(fields) = assertion["data"].as_object() else {
+                continue;
+            };
+            // exif:, tiff: and exifEX: property names are exiftool's tag names once unprefixed
+            for (name, value) in fields.iter().filter(|(name, _)| !name.starts_with('@')) {
+                let tag = name.split_once(':').map_or(name.as_str(), |(_, tag)| tag);
+                exif.insert(tag.to_owned(), value.clone());
+            }
+        }
+    }
+    insert(&mut tags, "Author", authors);
+
+    let actions = timeline(store_json);
+    insert(&mut tags, "ActionsAction", actions.iter().map(|entry| Value::String(entry.action.clone())).collect());
+    insert(&mut tags, "ActionsWhen", actions.iter().filter_map(|entry| entry.when.clone()).map(Value::String).collect());
+    insert(&mut tags, "ActionsSoftwareAgent", actions.iter().filter_map(|entry| entry.tool.clone()).map(Value::String).collect());
+
+    // Exif comes last, and never replaces a provenance tag of the same name
+    for (tag, value) in exif {
+        tags.entry(tag).or_insert(value);
+    }
+
+    Value::Array(vec![Value::Object(tags)])
+}
@@ -0,0 +1,179 @@
+use std::cell::RefCell;
+use std::ffi::{c_char, c_int, CStr, CString};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+
+use serde_json::json;
+
+use crate::query::manifest_store_json;
+use crate::read::{is_valid, load_manifest_store, validation_errors};
+use crate::sign::{default_manifest_definition, sign_asset, SignerConfig};
+use crate::storage::{format_from_location, read_file, write_file_atomically};
+
+/*
+ * A C ABI over signing, verifying and reading local files, for C and C++
+ * media applications which link the library built with the `ffi` feature
+ * as a cdylib or staticlib; the header is generated into
+ * include/c2pa_walkthrough.h. Strings are NUL-terminated UTF-8. Functions
+ * return 0 or a string on success, and -1 or NULL on failure, leaving the
+ * reason for c2pa_walkthrough_last_error() on the calling thread. Strings
+ * returned are the caller's, freed with c2pa_walkthrough_free_string().
+ */
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+fn
+set_last_error (message: String) {
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+}
+
+/* Runs `f`, keeping any error or panic as the thread's last error rather than letting it cross into C */
+fn
+guarded<T> (f: impl FnOnce() -> Result<T, c2pa::Error>) -> Option<T> {
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(value)) => Some(value),
+        Ok(Err(e)) => {
+            set_last_error(e.to_string());
+            None
+        },
+        Err(_) => {
+            set_last_error("panicked".to_owned());
+            None
+        }
+    }
+}
+
+/* A string argument, or None if it is NULL */
+unsafe fn
+optional_str<'a> (s: *const c_char, name: &str) -> Result<Option<&'a str>, c2pa::Error> {
+    if s.is_null() {
+        return Ok(None);
+    }
+
+    CStr::from_ptr(s).to_str()
+        .map(Some)
+        .map_err(|_| c2pa::Error::BadParam(format!("{} is not UTF-8", name)))
+}
+
+unsafe fn
+required_str<'a> (s: *const c_char, name: &str) -> Result<&'a str, c2pa::Error> {
+    optional_str(s, name)?.ok_or_else(|| c2pa::Error::BadParam(format!("{} is NULL", name)))
+}
+
+fn
+local_format (path: &str) -> Result<String, c2pa::Error> {
+    format_from_location(path).ok_or_else(|| c2pa::Error::BadParam(format!("cannot determine the format of {} from its extension", path)))
+}
+
+fn
+into_c_string (s: String) -> Result<*mut c_char, c2pa::Error> {
+    CString::new(s)
+        .map(CString::into_raw)
+        .map_err(|_| c2pa::Error::BadParam("result contains a NUL byte".to_owned()))
+}
+
+/**
+ * Signs the file at `source` and writes it to `dest`, which may be the same
+ * path, with a manifest built from `manifest_json` (NULL for the default
+ * definition) and signed by the signer profile at `signer_profile`, a JSON
+ * file as `sign --signer-profile` takes.
+ *
+ * # Safety
+ *
+ * Each argument must be NULL or a NUL-terminated string.
+ */
+#[no_mangle]
+pub unsafe extern "C" fn
+c2pa_walkthrough_sign_file (source: *const c_char, dest: *const c_char, manifest_json: *const c_char, signer_profile: *const c_char) -> c_int {
+    let signed = guarded(|| {
+        let source = required_str(source, "source")?;
+        let dest = required_str(dest, "dest")?;
+        let manifest_json = optional_str(manifest_json, "manifest_json")?
+            .map_or_else(default_manifest_definition, str::to_owned);
+        let signer_config = SignerConfig::from_file(required_str(signer_profile, "signer_profile")?)?;
+
+        let asset = read_file(Path::new(source))?;
+        let signed = sign_asset(&local_format(source)?, &asset, &manifest_json, &signer_config)?;
+        write_file_atomically(Path::new(dest), &signed)
+    });
+
+    match signed {
+        Some(()) => 0,
+        None => -1
+    }
+}
+
+/**
+ * Validates the manifests in the file at `path`, returning JSON such as
+ * `{"valid": false, "validation_errors": ["assertion.dataHash.mismatch"],
+ * "active_manifest": "urn:uuid:..."}`, as the daemon's verify op does.
+ * A file without manifests is a failure rather than a verdict.
+ *
+ * # Safety
+ *
+ * `path` must be NULL or a NUL-terminated string.
+ */
+#[no_mangle]
+pub unsafe extern "C" fn
+c2pa_walkthrough_verify_file (path: *const c_char) -> *mut c_char {
+    guarded(|| {
+        let path = required_str(path, "path")?;
+        let manifest_store = load_manifest_store(&local_format(path)?, &read_file(Path::new(path))?)?;
+        let errors = validation_errors(&manifest_store);
+
+        into_c_string(json!({
+            "valid": is_valid(&manifest_store),
+            "validation_errors": errors,
+            "active_manifest": manifest_store.active_label()
+        }).to_string())
+    }).unwrap_or(std::ptr::null_mut())
+}
+
+/**
+ * The manifest store of the file at `path` as JSON, the same JSON `query`
+ * selects from. A store which fails validation is still returned, with its
+ * `validation_status`.
+ *
+ * # Safety
+ *
+ * `path` must be NULL or a NUL-terminated string.
+ */
+#[no_mangle]
+pub unsafe extern "C" fn
+c2pa_walkthrough_read_manifest_json (path: *const c_char) -> *mut c_char {
+    guarded(|| {
+        let path = required_str(path, "path")?;
+        let manifest_store = load_manifest_store(&local_format(path)?, &read_file(Path::new(path))?)?;
+        into_c_string(manifest_store_json(&manifest_store)?.to_string())
+    }).unwrap_or(std::ptr::null_mut())
+}
+
+/**
+ * Why the last call on this thread failed, or NULL if none has. The string
+ * is the caller's to free.
+ */
+#[no_mangle]
+pub extern "C" fn
+c2pa_walkthrough_last_error () -> *mut c_char {
+    LAST_ERROR.with(|last| last.borrow().clone())
+        .and_then(|message| CString::new(message.replace('\0', " ")).ok())
+        .map_or(std::ptr::null_mut(), CString::into_raw)
+}
+
+/**
+ * Frees a string returned by another c2pa_walkthrough function. NULL is
+ * ignored.
+ *
+ * # Safety
+ *
+ * `s` must be NULL or a string returned by this library, not yet freed.
+ */
+#[no_mangle]
+pub unsafe extern "C" fn
+c2pa_walkthrough_free_string (s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
@@ -0,0 +1,267 @@
+use std::io::Cursor;
+use std::path::Path;
+
+use c2pa::{Reader, Signer, SigningAlg};
+use openssl::asn1::Asn1Time;
+use openssl::bn::{BigNum, MsbOption};
+use openssl::ec::{EcGroup, EcKey};
+use openssl::hash::MessageDigest;
+use openssl::nid::Nid;
+use openssl::pkey::{PKey, Private};
+use openssl::x509::extension::{AuthorityKeyIdentifier, BasicConstraints, ExtendedKeyUsage, KeyUsage, SubjectKeyIdentifier};
+use openssl::x509::{X509Builder, X509NameBuilder, X509};
+use serde::Serialize;
+
+use crate::read::validation_errors;
+use crate::sign::{builder_from_json, embed};
+use crate::storage::write_file_atomically;
+
+/*
+ * Tiny signed assets for downstream validators' test suites, one per case
+ * a validator should tell apart. Each is an 8x8 JPEG signed by a leaf of a
+ * test CA made for the run, whose certificate is written beside them as
+ * ca.pem for validators which check trust. The index, fixtures.json, lists
+ * each asset with the validation codes this tool's SDK reports for it, so
+ * suites can compare their validator's verdicts against it.
+ */
+
+// An 8x8 grayscale gradient, the smallest asset every fixture starts from
+const SOURCE_JPEG: [u8; 338] = [
+    0xff, 0xd8, 0xff, 0xe0, 0x00, 0x10, 0x4a, 0x46, 0x49, 0x46, 0x00, 0x01, 0x02, 0x00, 0x00, 0x01,
+    0x00, 0x01, 0x00, 0x00, 0xff, 0xc0, 0x00, 0x0b, 0x08, 0x00, 0x08, 0x00, 0x08, 0x01, 0x01, 0x11,
+    0x00, 0xff, 0xdb, 0x00, 0x43, 0x00, 0x10, 0x0b, 0x0c, 0x0e, 0x0c, 0x0a, 0x10, 0x0e, 0x0d, 0x0e,
+    0x12, 0x11, 0x10, 0x13, 0x18, 0x28, 0x1a, 0x18, 0x16, 0x16, 0x18, 0x31, 0x23, 0x25, 0x1d, 0x28,
+    0x3a, 0x33, 0x3d, 0x3c, 0x39, 0x33, 0x38, 0x37, 0x40, 0x48, 0x5c, 0x4e, 0x40, 0x44, 0x57, 0x45,
+    0x37, 0x38, 0x50, 0x6d, 0x51, 0x57, 0x5f, 0x62, 0x67, 0x68, 0x67, 0x3e, 0x4d, 0x71, 0x79, 0x70,
+    0x64, 0x78, 0x5c, 0x65, 0x67, 0x63, 0xff, 0xc4, 0x00, 0x1f, 0x00, 0x00, 0x01, 0x05, 0x01, 0x01,
+    0x01, 0x01, 0x01, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x02, 0x03, 0x04,
+    0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0xff, 0xc4, 0x00, 0xb5, 0x10, 0x00, 0x02, 0x01, 0x03,
+    0x03, 0x02, 0x04, 0x03, 0x05, 0x05, 0x04, 0x04, 0x00, 0x00, 0x01, 0x7d, 0x01, 0x02, 0x03, 0x00,
+    0x04, 0x11, 0x05, 0x12, 0x21, 0x31, 0x41, 0x06, 0x13, 0x51, 0x61, 0x07, 0x22, 0x71, 0x14, 0x32,
+    0x81, 0x91, 0xa1, 0x08, 0x23, 0x42, 0xb1, 0xc1, 0x15, 0x52, 0xd1, 0xf0, 0x24, 0x33, 0x62, 0x72,
+    0x82, 0x09, 0x0a, 0x16, 0x17, 0x18, 0x19, 0x1a, 0x25, 0x26, 0x27, 0x28, 0x29, 0x2a, 0x34, 0x35,
+    0x36, 0x37, 0x38, 0x39, 0x3a, 0x43, 0x44, 0x45, 0x46, 0x47, 0x48, 0x49, 0x4a, 0x53, 0x54, 0x55,
+    0x56, 0x57, 0x58, 0x59, 0x5a, 0x63, 0x64, 0x65, 0x66, 0x67, 0x68, 0x69, 0x6a, 0x73, 0x74, 0x75,
+    0x76, 0x77, 0x78, 0x79, 0x7a, 0x83, 0x84, 0x85, 0x86, 0x87, 0x88, 0x89, 0x8a, 0x92, 0x93, 0x94,
+    0x95, 0x96, 0x97, 0x98, 0x99, 0x9a, 0xa2, 0xa3, 0xa4, 0xa5, 0xa6, 0xa7, 0xa8, 0xa9, 0xaa, 0xb2,
+    0xb3, 0xb4, 0xb5, 0xb6, 0xb7, 0xb8, 0xb9, 0xba, 0xc2, 0xc3, 0xc4, 0xc5, 0xc6, 0xc7, 0xc8, 0xc9,
+    0xca, 0xd2, 0xd3, 0xd4, 0xd5, 0xd6, 0xd7, 0xd8, 0xd9, 0xda, 0xe1, 0xe2, 0xe3, 0xe4, 0xe5, 0xe6,
+    0xe7, 0xe8, 0xe9, 0xea, 0xf1, 0xf2, 0xf3, 0xf4, 0xf5, 0xf6, 0xf7, 0xf8, 0xf9, 0xfa, 0xff, 0xda,
+    0x00, 0x08, 0x01, 0x01, 0x00, 0x00, 0x3f, 0x00, 0xaf, 0xa2, 0xe8, 0xff, 0x00, 0x77, 0xe5, 0xaf,
+    0xff, 0xd9
+];
+
+const FORMAT: &str = "image/jpeg";
+
+// Days either side of now that certificates are valid for
+const CA_DAYS: i64 = 365 * 20;
+const LEAF_DAYS: i64 = 365 * 10;
+
+// Seconds the expired fixture's certificate is valid for; the SDK won't sign with one which has already expired
+const EXPIRING_SECONDS: i64 = 2;
+
+/* One generated asset and what validating it reports */
+#[derive(Serialize)]
+pub struct Fixture {
+    pub file: String,
+    pub case: &'static str,
+    pub description: &'static str,
+    // The remote manifest store of an asset which doesn't embed one, to be served at `remote_url`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub manifest: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remote_url: Option<String>,
+    pub validation_errors: Vec<String>
+}
+
+/* The fixtures.json index */
+#[derive(Serialize)]
+pub struct FixtureIndex {
+    pub trust_anchor: String,
+    pub fixtures: Vec<Fixture>
+}
+
+fn 
+openssl_error (e: openssl::error::ErrorStack) -> c2pa::Error {
+    c2pa::Error::OtherError(Box::new(e))
+}
+
+fn 
+seconds_from_now (seconds: i64) -> Result<Asn1Time, c2pa::Error> {
+    Asn1Time::from_unix(chrono::Utc::now().timestamp() + seconds).map_err(openssl_error)
+}
+
+/**
+ * A certificate for `key` named `common_name`, valid from `not_before` to
+ * `not_after` seconds from now. With an issuer it is a C2PA signing leaf
+ * (digitalSignature, emailProtection) issued by it; without, a self-signed
+ * CA.
+ */
+fn 
+certificate (common_name: &str, key: &PKey<Private>, issuer: Option<(&X509, &PKey<Private>)>, not_before: i64, not_after: i64) -> Result<X509, c2pa::Error> {
+    let (not_before, not_after) = (seconds_from_now(not_before)?, seconds_from_now(not_after)?);
+    let build = || -> Result<X509, openssl::error::ErrorStack> {
+        let mut name = X509NameBuilder::new()?;
+        name.append_entry_by_nid(Nid::ORGANIZATIONNAME, "c2pa-walkthrough fixtures")?;
+        name.append_entry_by_nid(Nid::COMMONNAME, common_name)?;
+        let name = name.build();
+
+        let mut serial = BigNum::new()?;
+        serial.rand(64, MsbOption::MAYBE_ZERO, false)?;
+        let mut builder = X509Builder::new()?;
+        builder.set_version(2)?;
+        builder.set_serial_number(&*serial.to_asn1_integer()?)?;
+        builder.set_subject_name(&name)?;
+        builder.set_pubkey(key)?;
+        builder.set_not_before(&not_before)?;
+        builder.set_not_after(&not_after)?;
+
+        let (issuer_cert, issuer_key) = match issuer {
+            Some((cert, key)) => (Some(&**cert), key),
+            None => (None, key)
+        };
+        builder.set_issuer_name(issuer_cert.map_or(&name, |cert| cert.subject_name()))?;
+        let subject_key_id = SubjectKeyIdentifier::new().build(&builder.x509v3_context(issuer_cert, None))?;
+        builder.append_extension(subject_key_id)?;
+        match issuer_cert {
+            Some(_) => {
+                builder.append_extension(BasicConstraints::new().critical().build()?)?;
+                builder.append_extension(KeyUsage::new().critical().digital_signature().build()?)?;
+                builder.append_extension(ExtendedKeyUsage::new().email_protection().build()?)?;
+                let authority_key_id = AuthorityKeyIdentifier::new().keyid(true).build(&builder.x509v3_context(issuer_cert, None))?;
+                builder.append_extension(authority_key_id)?;
+            },
+            None => {
+                builder.append_extension(BasicConstraints::new().critical().ca().build()?)?;
+                builder.append_extension(KeyUsage::new().critical().key_cert_sign().crl_sign().build()?)?;
+            }
+        }
+
+        builder.sign(issuer_key, MessageDigest::sha256())?;
+        Ok(builder.build())
+    };
+
+    build().map_err(openssl_error)
+}
+
+fn 
+ec_key () -> Result<PKey<Private>, c2pa::Error> {
+    let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).map_err(openssl_error)?;
+    EcKey::generate(&group).and_then(PKey::from_ec_key).map_err(openssl_error)
+}
+
+/* An es256 signer for a leaf issued by the CA, valid from `not_before` to `not_after` seconds from now */
+fn 
+leaf_signer (common_name: &str, ca: &X509, ca_key: &PKey<Private>, not_before: i64, not_after: i64) -> Result<Box<dyn Signer>, c2pa::Error> {
+    let key = ec_key()?;
+    let leaf = certificate(common_name, &key, Some((ca, ca_key)), not_before, not_after)?;
+
+    let mut chain = leaf.to_pem().map_err(openssl_error)?;
+    chain.extend(ca.to_pem().map_err(openssl_error)?);
+    c2pa::create_signer::from_keys(&chain, &key.private_key_to_pem_pkcs8().map_err(openssl_error)?, SigningAlg::Es256, None)
+}
+
+/* A manifest definition titled `title`, recording `action`, with any further assertions */
+fn 
+definition (title: &str, action: &str, assertions: &[serde_json::Value]) -> String {
+    let mut all = vec![serde_json::json!({"label": "c2pa.actions", "data": {"actions": [{"action": action}]}})];
+    all.extend_from_slice(assertions);
+
+    serde_json::json!({
+        "claim_generator_info": [{"name": "c2pa-walkthrough gen-fixtures", "version": env!("CARGO_PKG_VERSION")}],
+        "title": title,
+        "assertions": all
+    }).to_string()
+}
+
+fn 
+sign (title: &str, asset: &[u8], signer: &dyn Signer) -> Result<Vec<u8>, c2pa::Error> {
+    embed(FORMAT, asset, &mut builder_from_json(&definition(title, "c2pa.created", &[]))?, signer)
+}
+
+/* The asset with one byte of its image data changed, so the data hash no longer matches */
+fn 
+tamper (signed: &[u8]) -> Vec<u8> {
+    let mut tampered = signed.to_vec();
+    // The byte before the end-of-image marker is entropy-coded data in any JPEG
+    let i = tampered.len() - 3;
+    tampered[i] ^= 0x01;
+
+    tampered
+}
+
+fn 
+fixture (file: &str, case: &'static str, description: &'static str, manifest_store: &Reader) -> Fixture {
+    Fixture {
+        file: file.to_owned(),
+        case,
+        description,
+        manifest: None,
+        remote_url: None,
+        validation_errors: validation_errors(manifest_store)
+    }
+}
+
+/**
+ * Writes the fixtures, their CA certificate and the fixtures.json index
+ * to `dir`, creating it if needed. remote.jpg references its manifest store
+ * at `remote_url`, where remote.c2pa is to be served.
+ */
+pub fn 
+generate (dir: &Path, remote_url: &str) -> Result<FixtureIndex, c2pa::Error> {
+    std::fs::create_dir_all(dir)?;
+    let ca_key = ec_key()?;
+    let day = 24 * 60 * 60;
+    let ca = certificate("c2pa-walkthrough fixtures CA", &ca_key, None, -CA_DAYS * day, CA_DAYS * day)?;
+    write_file_atomically(&dir.join("ca.pem"), &ca.to_pem().map_err(openssl_error)?)?;
+
+    let signer = leaf_signer("c2pa-walkthrough fixtures signer", &ca, &ca_key, -day, LEAF_DAYS * day)?;
+    let mut fixtures = Vec::new();
+    let mut write = |file: &str, asset: &[u8], fixture: Fixture| -> Result<(), c2pa::Error> {
+        write_file_atomically(&dir.join(file), asset)?;
+        fixtures.push(fixture);
+        Ok(())
+    };
+    let read = |asset: &[u8]| Reader::from_stream(FORMAT, Cursor::new(asset));
+
+    let valid = sign("valid.jpg", &SOURCE_JPEG, &*signer)?;
+    write("valid.jpg", &valid, fixture("valid.jpg", "valid", "signed by a current certificate, unaltered", &read(&valid)?))?;
+
+    // Signed while its certificate is valid, then read back once it has expired, as if years later
+    let expiring_signer = leaf_signer("c2pa-walkthrough fixtures expiring signer", &ca, &ca_key, -day, EXPIRING_SECONDS)?;
+    let expired = sign("expired.jpg", &SOURCE_JPEG, &*expiring_signer)?;
+    std::thread::sleep(std::time::Duration::from_secs(EXPIRING_SECONDS as u64 + 1));
+    write("expired.jpg", &expired, fixture("expired.jpg", "expired-cert", "signed by a certificate which has since expired, with no timestamp", &read(&expired)?))?;
+
+    let tampered = tamper(&valid);
+    write("tampered.jpg", &tampered, fixture("tampered.jpg", "tampered", "a valid asset with one byte of image data changed after signing", &read(&tampered)?))?;
+
+    // The parent credits an author, whose CreativeWork assertion its child redacts
+    let author = serde_json::json!({
+        "label": "stds.schema-org.CreativeWork",
+        "data": {"@context": "https://schema.org", "@type": "CreativeWork", "author": [{"@type": "Person", "name": "Fixture Author"}]}
+    });
+    let parent = embed(FORMAT, &SOURCE_JPEG, &mut builder_from_json(&definition("parent.jpg", "c2pa.created", &[author]))?, &*signer)?;
+    let mut builder = builder_from_json(&definition("redacted.jpg", "c2pa.edited", &[]))?;
+    builder.add_ingredient_from_stream(r#"{"title": "parent.jpg", "relationship": "parentOf"}"#, FORMAT, &mut Cursor::new(&parent))?;
+    builder.definition.redactions = Some(vec!["stds.schema-org.CreativeWork".to_owned()]);
+    let redacted = embed(FORMAT, &parent, &mut builder, &*signer)?;
+    write("redacted.jpg", &redacted, fixture("redacted.jpg", "redacted-assertion", "edited from a parent whose CreativeWork assertion it redacts; c2pa-rs 0.45 reports the redacted assertion and parent claim missing", &read(&redacted)?))?;
+
+    let mut builder = builder_from_json(&definition("remote.jpg", "c2pa.created", &[]))?;
+    builder.set_remote_url(remote_url).set_no_embed(true);
+    let mut remote = Cursor::new(Vec::new());
+    let manifest_store = builder.sign(&*signer, FORMAT, &mut Cursor::new(&SOURCE_JPEG[..]), &mut remote)?;
+    let remote = remote.into_inner();
+    write_file_atomically(&dir.join("remote.c2pa"), &manifest_store)?;
+    write("remote.jpg", &remote, Fixture {
+        manifest: Some("remote.c2pa".to_owned()),
+        remote_url: Some(remote_url.to_owned()),
+        ..fixture("remote.jpg", "remote-manifest", "embeds no manifest store, referencing remote.c2pa at remote_url in its XMP", &Reader::from_manifest_data_and_stream(&manifest_store, FORMAT, Cursor::new(&remote))?)
+    })?;
+
+    let index = FixtureIndex { trust_anchor: "ca.pem".to_owned(), fixtures };
+    write_file_atomically(&dir.join("fixtures.json"), &serde_json::to_vec_pretty(&index)?)?;
+
+    Ok(index)
+}
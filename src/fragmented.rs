@@ -0,0 +1,128 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use c2pa::{Reader, Signer};
+
+use crate::clock;
+use crate::sign::{apply_claim_version, builder_from_json};
+use crate::transcode::ffmpeg_version;
+
+/*
+ * Fragmented BMFF video, such as the init segment and .m4s fragments of
+ * DASH or CMAF, signed with a Merkle-tree hash: a `c2pa.hash.bmff.v2`
+ * assertion with a `merkle` map, in place of one flat hash over the whole
+ * file. The manifest goes in the init segment, and each fragment is a leaf
+ * of the tree and carries its own proof, so any fragment can be checked
+ * against the init segment without reading the others. How much a leaf
+ * covers is how long a fragment is, so a 50 GB master cut into 2 second
+ * fragments is verified 2 seconds at a time. Masters are cut with ffmpeg,
+ * run as for `transcode`; the SDK picks the number of proofs itself.
+ */
+
+/* An init segment and the fragments which follow it, in playback order */
+#[derive(Clone, Debug)]
+pub struct Fragmented {
+    pub init: PathBuf,
+    pub fragments: Vec<PathBuf>
+}
+
+// File names ffmpeg gives each representation's init segment and fragments
+const INIT_PREFIX: &str = "init-";
+const FRAGMENT_PREFIX: &str = "chunk-";
+
+/**
+ * Cuts the local video `master` into fragments of about `seconds` each
+ * with the `ffmpeg` program, without re-encoding, writing them to `dir`.
+ * Each stream is cut separately, giving an init segment and fragments per
+ * stream. Fragments start on key frames, so are only as short as the
+ * master's key frames are frequent.
+ */
+pub fn 
+fragment (ffmpeg: &str, master: &Path, dir: &Path, seconds: f64) -> Result<Vec<Fragmented>, c2pa::Error> {
+    ffmpeg_version(ffmpeg)?;
+    std::fs::create_dir_all(dir)?;
+
+    let output = Command::new(ffmpeg)
+        .args(["-nostdin", "-hide_banner", "-loglevel", "error", "-y", "-i"])
+        .arg(master)
+        .args(["-map", "0", "-c", "copy", "-f", "dash", "-use_template", "1", "-use_timeline", "0"])
+        .arg("-seg_duration").arg(seconds.to_string())
+        .arg("-init_seg_name").arg(format!("{}$RepresentationID$.mp4", INIT_PREFIX))
+        .arg("-media_seg_name").arg(format!("{}$RepresentationID$-$Number%05d$.m4s", FRAGMENT_PREFIX))
+        .arg(dir.join("master.mpd"))
+        .output()?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(c2pa::Error::BadParam(match stderr.trim() {
+            "" => format!("ffmpeg failed ({})", output.status),
+            stderr => format!("ffmpeg failed: {}", stderr)
+        }));
+    }
+
+    let mut representations = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let init = entry?.path();
+        let name = init.file_name().unwrap_or_default().to_string_lossy().into_owned();
+        let Some(id) = name.strip_prefix(INIT_PREFIX).and_then(|rest| rest.strip_suffix(".mp4")) else {
+            continue;
+        };
+        let prefix = format!("{}{}-", FRAGMENT_PREFIX, id);
+        let mut fragments: Vec<PathBuf> = std::fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.file_name().is_some_and(|name| name.to_string_lossy().starts_with(&prefix)))
+            .collect();
+        // Numbered with leading zeros, so they sort in playback order
+        fragments.sort();
+        representations.push(Fragmented { init, fragments });
+    }
+    representations.sort_by(|a, b| a.init.cmp(&b.init));
+
+    match representations.is_empty() {
+        true => Err(c2pa::Error::BadParam(format!("ffmpeg wrote no fragments of {}", master.display()))),
+        false => Ok(representations)
+    }
+}
+
+/**
+ * Signs an init segment and its fragments with a manifest built from a
+ * manifest-definition JSON string, hashing them as a Merkle tree, and
+ * writes them under the same names to `output_dir`. Returns the manifest
+ * store read back from what was written, validated against every fragment.
+ * The SDK can't add to a manifest they already carry.
+ */
+pub fn 
+sign_fragmented (fragmented: &Fragmented, output_dir: &Path, manifest_json: &str, signer: &dyn Signer) -> Result<Reader, c2pa::Error> {
+    let Some(init_name) = fragmented.init.file_name() else {
+        return Err(c2pa::Error::BadParam(format!("{} isn't a file", fragmented.init.display())));
+    };
+    if fragmented.fragments.is_empty() {
+        return Err(c2pa::Error::BadParam(format!("no fragments follow {}", fragmented.init.display())));
+    }
+
+    let names = fragmented.fragments.iter()
+        .map(|fragment| fragment.file_name())
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(|| c2pa::Error::BadParam("a fragment isn't a file".to_owned()))?;
+
+    // The SDK takes the format from the extension of the directory it writes to, so it writes to one named for the init segment
+    let staging = output_dir.join(format!(".signing-{}", init_name.to_string_lossy()));
+    let mut builder = builder_from_json(manifest_json)?;
+    clock::apply_instance_id(&mut builder);
+    apply_claim_version(&mut builder);
+    // The SDK can't read back a claim with no assertions before it adds the Merkle hash, so one without records the fragments' creation
+    if builder.definition.assertions.is_empty() {
+        builder.add_assertion("c2pa.actions", &serde_json::json!({"actions": [{"action": "c2pa.created"}]}))?;
+    }
+    let signed = builder.sign_fragmented_files(signer, fragmented.init.as_path(), &fragmented.fragments, staging.as_path())
+        .and_then(|_| {
+            for name in names.iter().copied().chain([init_name]) {
+                std::fs::rename(staging.join(name), output_dir.join(name))?;
+            }
+            Ok(())
+        });
+    let _ = std::fs::remove_dir_all(&staging);
+    signed?;
+
+    let signed_fragments = names.iter().map(|name| output_dir.join(name)).collect();
+    Reader::from_fragmented_files(output_dir.join(init_name), &signed_fragments)
+}
@@ -0,0 +1,120 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use tonic::{transport::Server, Request, Response, Status};
+
+use crate::metrics;
+use crate::read::{is_valid, load_manifest_store, validation_errors};
+use crate::sign::{sign_asset_with_signer, SharedSigner};
+use crate::webhook;
+
+pub mod proto {
+    tonic::include_proto!("c2pa_walkthrough");
+}
+
+use proto::provenance_server::{Provenance, ProvenanceServer};
+use proto::{
+    ReadManifestRequest, ReadManifestResponse, SignAssetRequest, SignAssetResponse,
+    VerifyAssetRequest, VerifyAssetResponse
+};
+
+// Assets travel inline, so raise tonic's 4 MiB default message limit to
+// something that fits full-resolution photos and short video clips
+const MAX_MESSAGE_SIZE: usize = 256 * 1024 * 1024;
+
+struct ProvenanceService {
+    signer: Arc<SharedSigner>
+}
+
+/**
+ * Maps SDK errors onto gRPC status codes; an asset without a manifest is
+ * `NOT_FOUND`, problems with the signer are `INTERNAL`, and anything else
+ * is blamed on the request.
+ */
+fn 
+to_status (e: c2pa::Error) -> Status {
+    match e {
+        c2pa::Error::JumbfNotFound | c2pa::Error::ProvenanceMissing => Status::not_found(e.to_string()),
+        c2pa::Error::IoError(_) | c2pa::Error::RawSignerError(_) | c2pa::Error::InvalidSigningKey | c2pa::Error::CoseSignature => Status::internal(e.to_string()),
+        _ => Status::invalid_argument(e.to_string())
+    }
+}
+
+/**
+ * Runs blocking SDK work (hashing, signing, TSA/OCSP requests) off the
+ * async executor threads.
+ */
+async fn 
+blocking<T, F> (f: F) -> Result<T, Status> 
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T, c2pa::Error> + Send + 'static
+{
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(|e| Status::internal(e.to_string()))?
+        .map_err(to_status)
+}
+
+#[tonic::async_trait]
+impl Provenance for ProvenanceService {
+    async fn sign_asset(&self, request: Request<SignAssetRequest>) -> Result<Response<SignAssetResponse>, Status> {
+        let request = request.into_inner();
+        let signer = Arc::clone(&self.signer);
+
+        let asset = blocking(move || {
+            let signed = metrics::timed("sign", || sign_asset_with_signer(&request.format, &request.asset, &request.manifest_json, &*signer))?;
+            webhook::notify_in_background(&request.format, &request.asset, &signed, None);
+            Ok(signed)
+        }).await?;
+
+        Ok(Response::new(SignAssetResponse { asset }))
+    }
+
+    async fn verify_asset(&self, request: Request<VerifyAssetRequest>) -> Result<Response<VerifyAssetResponse>, Status> {
+        let request = request.into_inner();
+        let manifest_store = blocking(move || {
+            metrics::timed("verify", || load_manifest_store(&request.format, &request.asset))
+        }).await?;
+        let errors = validation_errors(&manifest_store);
+        metrics::record_validation_errors(&errors);
+
+        Ok(Response::new(VerifyAssetResponse {
+            valid: is_valid(&manifest_store),
+            validation_errors: errors,
+            active_manifest: manifest_store.active_label().unwrap_or_default().to_owned()
+        }))
+    }
+
+    async fn read_manifest(&self, request: Request<ReadManifestRequest>) -> Result<Response<ReadManifestResponse>, Status> {
+        let request = request.into_inner();
+        let manifest_store = blocking(move || {
+            metrics::timed("read", || load_manifest_store(&request.format, &request.asset))
+        }).await?;
+
+        Ok(Response::new(ReadManifestResponse {
+            manifest_store_json: manifest_store.to_string()
+        }))
+    }
+}
+
+/**
+ * Serves the `Provenance` gRPC API on the given address until the process
+ * is terminated. Every signing request uses `signer`.
+ */
+pub fn 
+serve (addr: SocketAddr, signer: SharedSigner) -> Result<(), c2pa::Error> {
+    let signer = Arc::new(signer);
+    let runtime = tokio::runtime::Runtime::new()?;
+
+    runtime.block_on(async {
+        Server::builder()
+            .add_service(
+                ProvenanceServer::new(ProvenanceService { signer })
+                    .max_decoding_message_size(MAX_MESSAGE_SIZE)
+                    .max_encoding_message_size(MAX_MESSAGE_SIZE)
+            )
+            .serve(addr)
+            .await
+    }).map_err(|e| c2pa::Error::OtherError(Box::new(e)))
+}
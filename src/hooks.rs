@@ -0,0 +1,143 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/*
+ * Commands run around signing: pre-sign hooks, such as a virus scan or a
+ * metadata lint, before each asset is signed, and post-sign hooks, such as
+ * an upload, once it is written. Each command runs in the shell, with the
+ * manifest JSON on its stdin (the definition about to be signed before,
+ * the signed active manifest after) and the rest in its environment:
+ *
+ *     C2PA_HOOK_PHASE           pre-sign or post-sign
+ *     C2PA_HOOK_FORMAT          the asset format, e.g. jpg
+ *     C2PA_HOOK_SOURCE          where the asset was read from
+ *     C2PA_HOOK_OUTPUT          where it is written, once it has been
+ *     C2PA_HOOK_MANIFEST_LABEL  the signed manifest's label, after signing
+ *     C2PA_HOOK_ASSET_PATH      a local copy of the asset, exactly as about to be signed or as written
+ *
+ * A hook fails by exiting nonzero, which aborts signing before it and
+ * fails the asset after it. Anything a hook prints goes to stderr, leaving
+ * stdout to this tool's own output.
+ */
+
+// Distinguishes the asset copies of concurrent hooks within one process
+static COPY_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Phase {
+    PreSign,
+    PostSign
+}
+
+impl Phase {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Phase::PreSign => "pre-sign",
+            Phase::PostSign => "post-sign"
+        }
+    }
+}
+
+/* What a hook is told about the asset it runs for */
+pub struct HookContext<'a> {
+    pub phase: Phase,
+    pub format: &'a str,
+    pub source: &'a str,
+    pub output: Option<&'a str>,
+    pub manifest_label: Option<&'a str>,
+    pub manifest_json: &'a str,
+    pub asset: &'a [u8]
+}
+
+/* A temporary copy of an asset for hooks to read, removed once they have run */
+struct AssetCopy(PathBuf);
+
+impl Drop for AssetCopy {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+fn 
+copy_asset (context: &HookContext) -> Result<AssetCopy, c2pa::Error> {
+    let location = context.output.unwrap_or(context.source);
+    let file_name = location.split('?').next().unwrap_or(location).rsplit(['/', '\\']).next().unwrap_or_default();
+    let path = std::env::temp_dir().join(format!(
+        "c2pa-hook-{}-{}-{}", std::process::id(), COPY_COUNTER.fetch_add(1, Ordering::Relaxed), file_name
+    ));
+    let copy = AssetCopy(path);
+    std::fs::write(&copy.0, context.asset)?;
+
+    Ok(copy)
+}
+
+#[cfg(unix)]
+fn 
+shell (command: &str) -> Command {
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(command);
+    shell
+}
+
+#[cfg(windows)]
+fn 
+shell (command: &str) -> Command {
+    let mut shell = Command::new("cmd");
+    shell.arg("/C").arg(command);
+    shell
+}
+
+fn 
+run_one (command: &str, context: &HookContext, asset_path: &Path) -> Result<(), c2pa::Error> {
+    let mut hook = shell(command);
+    hook.env("C2PA_HOOK_PHASE", context.phase.name())
+        .env("C2PA_HOOK_FORMAT", context.format)
+        .env("C2PA_HOOK_SOURCE", context.source)
+        .env("C2PA_HOOK_ASSET_PATH", asset_path)
+        .stdin(Stdio::piped())
+        .stdout(std::io::stderr());
+    if let Some(output) = context.output {
+        hook.env("C2PA_HOOK_OUTPUT", output);
+    }
+    if let Some(label) = context.manifest_label {
+        hook.env("C2PA_HOOK_MANIFEST_LABEL", label);
+    }
+
+    let mut child = hook.spawn()
+        .map_err(|e| c2pa::Error::BadParam(format!("{} hook `{}` couldn't be started: {}", context.phase.name(), command, e)))?;
+    let written = match child.stdin.take() {
+        Some(mut stdin) => stdin.write_all(context.manifest_json.as_bytes()),
+        None => Ok(())
+    };
+    let status = child.wait()?;
+    // A hook which doesn't read the manifest may exit before taking it all
+    match written {
+        Err(e) if e.kind() != std::io::ErrorKind::BrokenPipe => return Err(e.into()),
+        _ => ()
+    }
+    if !status.success() {
+        return Err(c2pa::Error::BadParam(format!("{} hook `{}` failed ({})", context.phase.name(), command, status)));
+    }
+
+    Ok(())
+}
+
+/**
+ * Runs each of `commands` in turn for the asset `context` describes,
+ * stopping at the first to fail.
+ */
+pub fn 
+run (commands: &[String], context: &HookContext) -> Result<(), c2pa::Error> {
+    if commands.is_empty() {
+        return Ok(());
+    }
+
+    let copy = copy_asset(context)?;
+    for command in commands {
+        run_one(command, context, &copy.0)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,248 @@
+use std::io::Read;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::metadata::jpeg_segments;
+use crate::namespace;
+
+/*
+ * Color integrity: the ICC profile an asset carries, recorded when it is
+ * signed so that verification can say when it has changed. A profile
+ * swapped or stripped by a color-managed export changes how every pixel is
+ * shown while leaving the pixels themselves alone; the hard binding fails
+ * too, but only says that some bytes changed. The assertion records the
+ * profile's SHA-256 hash, size, color space, version and description, or
+ * that there was none, in which case viewers take the asset to be sRGB.
+ * Profiles are read from JPEG APP2 segments, PNG iCCP chunks and WebP ICCP
+ * chunks.
+ */
+
+// Name of the color integrity assertion in the label namespace, e.g. com.mikecvet.color-integrity
+pub const COLOR_INTEGRITY_NAME: &str = "color-integrity";
+
+const JPEG_ICC_HEADER: &[u8] = b"ICC_PROFILE\0";
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+// Size of an ICC profile's header, ahead of its tag table
+const ICC_HEADER_LEN: usize = 128;
+
+/* An embedded ICC profile, as the assertion records it */
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct IccProfile {
+    // The profile's own name for itself, e.g. "sRGB IEC61966-2.1" or "Display P3"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    // e.g. RGB, GRAY or CMYK
+    pub color_space: String,
+    pub version: String,
+    pub alg: String,
+    pub hash: String,
+    pub size: u64
+}
+
+/* The color integrity assertion */
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ColorIntegrity {
+    // None for an asset without an embedded profile
+    pub profile: Option<IccProfile>
+}
+
+fn 
+malformed (format: &str) -> c2pa::Error {
+    c2pa::Error::BadParam(format!("malformed {}", format))
+}
+
+fn 
+be_u32 (bytes: &[u8], offset: usize) -> Option<u32> {
+    bytes.get(offset..offset + 4).map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+/* The profile of a JPEG, reassembled from its APP2 chunks in sequence order */
+fn 
+jpeg_profile (asset: &[u8]) -> Result<Option<Vec<u8>>, c2pa::Error> {
+    let mut chunks: Vec<(u8, &[u8])> = jpeg_segments(asset)?.into_iter()
+        .filter(|(marker, payload)| *marker == 0xe2 && asset[payload.clone()].starts_with(JPEG_ICC_HEADER))
+        .map(|(_, payload)| {
+            let chunk = &asset[payload];
+            // The header is followed by the chunk's sequence number and the chunk count
+            match chunk.get(JPEG_ICC_HEADER.len()..JPEG_ICC_HEADER.len() + 2) {
+                Some(numbers) => Ok((numbers[0], &chunk[JPEG_ICC_HEADER.len() + 2..])),
+                None => Err(malformed("JPEG ICC profile"))
+            }
+        })
+        .collect::<Result<_, _>>()?;
+    chunks.sort_by_key(|(sequence, _)| *sequence);
+
+    Ok((!chunks.is_empty()).then(|| chunks.into_iter().flat_map(|(_, chunk)| chunk.iter().copied()).collect()))
+}
+
+/* The profile of a PNG, inflated from its iCCP chunk, which comes before the image data if at all */
+fn 
+png_profile (asset: &[u8]) -> Result<Option<Vec<u8>>, c2pa::Error> {
+    let mut i = PNG_SIGNATURE.len();
+    while let Some(len) = be_u32(asset, i) {
+        let data = asset.get(i + 8..i + 8 + len as usize).ok_or_else(|| malformed("PNG"))?;
+        match &asset[i + 4..i + 8] {
+            b"iCCP" => {
+                // A profile name of up to 79 bytes, then the compression method, always zlib
+                let name_end = data.iter().position(|b| *b == 0).ok_or_else(|| malformed("PNG iCCP chunk"))?;
+                let compressed = data.get(name_end + 2..).ok_or_else(|| malformed("PNG iCCP chunk"))?;
+                let mut profile = Vec::new();
+                flate2::read::ZlibDecoder::new(compressed).read_to_end(&mut profile)
+                    .map_err(|e| c2pa::Error::BadParam(format!("malformed PNG iCCP chunk: {}", e)))?;
+                return Ok(Some(profile));
+            },
+            b"IDAT" | b"IEND" => break,
+            _ => ()
+        }
+        // Length, type, data and CRC
+        i += 12 + len as usize;
+    }
+
+    Ok(None)
+}
+
+/* The profile of a WebP, from its ICCP chunk */
+fn 
+webp_profile (asset: &[u8]) -> Result<Option<Vec<u8>>, c2pa::Error> {
+    let mut i = 12;
+    while let Some(fourcc) = asset.get(i..i + 4) {
+        let len = asset.get(i + 4..i + 8).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize).ok_or_else(|| malformed("WebP"))?;
+        let data = asset.get(i + 8..i + 8 + len).ok_or_else(|| malformed("WebP"))?;
+        if fourcc == b"ICCP" {
+            return Ok(Some(data.to_vec()));
+        }
+        // Chunks are padded to an even length
+        i += 8 + len + len % 2;
+    }
+
+    Ok(None)
+}
+
+/**
+ * The ICC profile embedded in an asset, if it has one. Errors for formats
+ * profiles aren't read from.
+ */
+pub fn 
+embedded_profile (format: &str, asset: &[u8]) -> Result<Option<Vec<u8>>, c2pa::Error> {
+    match format.to_ascii_lowercase().as_str() {
+        "jpg" | "jpeg" | "image/jpeg" => jpeg_profile(asset),
+        "png" | "image/png" if asset.starts_with(PNG_SIGNATURE) => png_profile(asset),
+        "webp" | "image/webp" if asset.starts_with(b"RIFF") && asset.get(8..12) == Some(b"WEBP") => webp_profile(asset),
+        "png" | "image/png" | "webp" | "image/webp" => Err(malformed(format)),
+        _ => Err(c2pa::Error::BadParam(format!("ICC profiles can only be read from JPEG, PNG and WebP, not {}", format)))
+    }
+}
+
+/**
+ * The text of a profile's `desc` tag: an ASCII textDescriptionType in
+ * version 2 profiles, the first record of a multiLocalizedUnicodeType in
+ * version 4 ones.
+ */
+fn 
+description (profile: &[u8]) -> Option<String> {
+    let count = be_u32(profile, ICC_HEADER_LEN)? as usize;
+    let tag = (0..count.min(1024))
+        .map(|i| ICC_HEADER_LEN + 4 + i * 12)
+        .find(|entry| profile.get(*entry..entry + 4) == Some(b"desc"))?;
+    let offset = be_u32(profile, tag + 4)? as usize;
+    let size = be_u32(profile, tag + 8)? as usize;
+    let data = profile.get(offset..offset.checked_add(size)?)?;
+
+    let text = match data.get(0..4)? {
+        b"desc" => {
+            let len = be_u32(data, 8)? as usize;
+            String::from_utf8_lossy(data.get(12..12 + len)?).to_string()
+        },
+        b"mluc" => {
+            // The first record's length and offset, after its language and country codes
+            let len = be_u32(data, 20)? as usize;
+            let start = be_u32(data, 24)? as usize;
+            let units: Vec<u16> = data.get(start..start.checked_add(len)?)?
+                .chunks_exact(2)
+                .map(|unit| u16::from_be_bytes([unit[0], unit[1]]))
+                .collect();
+            String::from_utf16_lossy(&units)
+        },
+        _ => return None
+    };
+    let text = text.trim_end_matches('\0').trim();
+
+    (!text.is_empty()).then(|| text.to_owned())
+}
+
+/* What the assertion records about a profile */
+pub fn 
+describe (profile: &[u8]) -> Result<IccProfile, c2pa::Error> {
+    if profile.len() < ICC_HEADER_LEN || profile.get(36..40) != Some(b"acsp") {
+        return Err(malformed("ICC profile"));
+    }
+
+    Ok(IccProfile {
+        description: description(profile),
+        color_space: String::from_utf8_lossy(&profile[16..20]).trim().to_owned(),
+        version: format!("{}.{}", profile[8], profile[9] >> 4),
+        alg: "sha256".to_owned(),
+        hash: BASE64.encode(Sha256::digest(profile)),
+        size: profile.len() as u64
+    })
+}
+
+/* The color integrity of an asset as it is now */
+pub fn 
+color_integrity (format: &str, asset: &[u8]) -> Result<ColorIntegrity, c2pa::Error> {
+    Ok(ColorIntegrity { profile: embedded_profile(format, asset)?.as_deref().map(describe).transpose()? })
+}
+
+/* The color integrity assertion for an asset, as a manifest-definition entry */
+pub fn 
+color_integrity_assertion (format: &str, asset: &[u8]) -> Result<serde_json::Value, c2pa::Error> {
+    Ok(serde_json::json!({"label": namespace::label(COLOR_INTEGRITY_NAME), "data": serde_json::to_value(color_integrity(format, asset)?)?}))
+}
+
+/* The color integrity assertion of the active manifest of a store, as `read` prints it, if it has one */
+pub fn 
+signed_color_integrity (store_json: &serde_json::Value) -> Result<Option<ColorIntegrity>, c2pa::Error> {
+    let active = store_json["active_manifest"].as_str().unwrap_or_default();
+    let assertion = store_json["manifests"][active]["assertions"].as_array()
+        .and_then(|assertions| assertions.iter().find(|assertion| assertion["label"].as_str().and_then(namespace::strip_namespace) == Some(COLOR_INTEGRITY_NAME)));
+
+    match assertion {
+        Some(assertion) => serde_json::from_value(assertion["data"].clone())
+            .map(Some)
+            .map_err(|e| c2pa::Error::BadParam(format!("{} isn't a valid color integrity assertion: {}", assertion["label"], e))),
+        None => Ok(None)
+    }
+}
+
+fn 
+name (profile: Option<&IccProfile>) -> String {
+    match profile {
+        Some(IccProfile { description: Some(description), .. }) => format!("'{}'", description),
+        Some(profile) => format!("an unnamed {} profile", profile.color_space),
+        None => "no profile".to_owned()
+    }
+}
+
+/**
+ * How the profile an asset carries now differs from the signed one, e.g.
+ * "signed with 'sRGB IEC61966-2.1', now 'Display P3'", or None if it is
+ * the same. Profiles are told apart by hash, so one edited in place keeps
+ * its name.
+ */
+pub fn 
+profile_change (signed: &ColorIntegrity, live: &ColorIntegrity) -> Option<String> {
+    let hash = |integrity: &ColorIntegrity| integrity.profile.as_ref().map(|profile| profile.hash.clone());
+    if hash(signed) == hash(live) {
+        return None;
+    }
+
+    let (signed, live) = (name(signed.profile.as_ref()), name(live.profile.as_ref()));
+    match signed == live {
+        true => Some(format!("signed with {}, now a different one", signed)),
+        false => Some(format!("signed with {}, now {}", signed, live))
+    }
+}
@@ -0,0 +1,370 @@
+use std::io::{BufRead, Write};
+use std::path::Path;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use openssl::encrypt::{Decrypter, Encrypter};
+use openssl::pkey::{PKey, Public};
+use openssl::rsa::Padding;
+use openssl::symm::{decrypt_aead, encrypt_aead, Cipher};
+use openssl::x509::X509;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use crate::clock;
+use crate::metadata::is_metadata_label;
+
+/*
+ * Signing without naming the author, for contributors whose identity has to
+ * be protected. Person authors are removed from the CreativeWork assertions
+ * of a manifest definition, or replaced by a pseudonym, so the manifest only
+ * names the organization whose certificate signs it. The real details are
+ * appended to a local log, one sealed record per line: each is encrypted
+ * with its own AES-256-GCM key, wrapped with RSA-OAEP for a recipient key,
+ * so a signing host can add to the log but only the holder of the private
+ * key can read it.
+ */
+
+const CREATIVE_WORK_LABEL: &str = "stds.schema-org.CreativeWork";
+
+// Fields of the metadata assertions naming the photographer or camera owner
+const METADATA_AUTHOR_FIELDS: [&str; 5] = ["dc:creator", "tiff:Artist", "exifEX:CameraOwnerName", "photoshop:AuthorsPosition", "photoshop:CaptionWriter"];
+
+/* Who a signed manifest names as its author */
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum Authorship {
+    #[default]
+    Named,
+    // organizations only
+    Anonymous,
+    Pseudonym(String)
+}
+
+/* The real authors of one signed asset, as kept in the identity log */
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IdentityRecord {
+    pub recorded_at: String,
+    pub source: String,
+    pub output: String,
+    // links the record to the signed asset without naming anyone in it
+    pub output_sha256: String,
+    pub authors: Vec<Value>,
+    pub pseudonym: Option<String>
+}
+
+impl IdentityRecord {
+    /* The authors concealed in `written`, the asset signed from `source` and written to `output` */
+    pub fn new(source: &str, output: &str, written: &[u8], authors: Vec<Value>, authorship: &Authorship) -> IdentityRecord {
+        IdentityRecord {
+            recorded_at: clock::now().to_rfc3339(),
+            source: source.to_owned(),
+            output: output.to_owned(),
+            output_sha256: hex::encode(Sha256::digest(written)),
+            authors,
+            pseudonym: match authorship {
+                Authorship::Pseudonym(name) => Some(name.clone()),
+                _ => None
+            }
+        }
+    }
+}
+
+/* One line of the identity log */
+#[derive(Serialize, Deserialize)]
+struct SealedRecord {
+    key: String,
+    iv: String,
+    tag: String,
+    data: String
+}
+
+fn 
+ssl_error (e: openssl::error::ErrorStack) -> c2pa::Error {
+    c2pa::Error::OtherError(Box::new(e))
+}
+
+fn 
+base64_field (field: &str) -> Result<Vec<u8>, c2pa::Error> {
+    BASE64.decode(field).map_err(|e| c2pa::Error::BadParam(format!("malformed identity log record: {}", e)))
+}
+
+/**
+ * Removes the Person authors of every CreativeWork assertion in a manifest
+ * definition, returning them along with any metadata author fields. A
+ * pseudonym takes their place; organizations stay as they are. With a
+ * pseudonym and no authors to replace, a CreativeWork assertion crediting
+ * the pseudonym is added.
+ */
+pub fn 
+conceal_authors (definition: &mut Value, authorship: &Authorship) -> Vec<Value> {
+    let mut concealed = Vec::new();
+    if *authorship == Authorship::Named {
+        return concealed;
+    }
+
+    if let Some(assertions) = definition.get_mut("assertions").and_then(Value::as_array_mut) {
+        for assertion in assertions.iter_mut().filter(|assertion| assertion["label"] == CREATIVE_WORK_LABEL) {
+            let Some(data) = assertion.get_mut("data").and_then(Value::as_object_mut) else {
+                continue;
+            };
+            let authors = match data.remove("author") {
+                Some(Value::Array(authors)) => authors,
+                Some(author) => vec![author],
+                None => continue
+            };
+
+            let (people, mut kept): (Vec<Value>, Vec<Value>) = authors.into_iter().partition(|author| author["@type"] != "Organization");
+            if let (Authorship::Pseudonym(name), false) = (authorship, people.is_empty()) {
+                kept.push(json!({"@type": "Person", "name": name}));
+            }
+            if !kept.is_empty() {
+                data.insert("author".to_owned(), Value::Array(kept));
+            }
+            concealed.extend(people);
+        }
+    }
+
+    let people = concealed.len();
+    concealed.extend(conceal_metadata_authors(definition));
+
+    if let (Authorship::Pseudonym(name), 0) = (authorship, people) {
+        let credit = json!({
+            "label": CREATIVE_WORK_LABEL,
+            "data": {"@context": "https://schema.org", "@type": "CreativeWork", "author": [{"@type": "Person", "name": name}]}
+        });
+        match definition.get_mut("assertions").and_then(Value::as_array_mut) {
+            Some(assertions) => assertions.push(credit),
+            None => definition["assertions"] = json!([credit])
+        }
+    }
+
+    concealed
+}
+
+/**
+ * Removes the author fields, such as dc:creator and tiff:Artist, from the
+ * metadata assertions of a manifest definition, returning each as
+ * `{"field": ..., "value": ...}`.
+ */
+pub fn 
+conceal_metadata_authors (definition: &mut Value) -> Vec<Value> {
+    let mut concealed = Vec::new();
+    let Some(assertions) = definition.get_mut("assertions").and_then(Value::as_array_mut) else {
+        return concealed;
+    };
+
+    for assertion in assertions {
+        if !assertion["label"].as_str().is_some_and(is_metadata_label) {
+            continue;
+        }
+        if let Some(data) = assertion.get_mut("data").and_then(Value::as_object_mut) {
+            for field in METADATA_AUTHOR_FIELDS {
+                if let Some(value) = data.remove(field) {
+                    concealed.push(json!({"field": field, "value": value}));
+                }
+            }
+        }
+    }
+
+    concealed
+}
+
+/* The public key of a PEM certificate or public key, which must be RSA to wrap record keys */
+fn 
+recipient_key (pem: &[u8]) -> Result<PKey<Public>, c2pa::Error> {
+    let key = match X509::from_pem(pem) {
+        Ok(cert) => cert.public_key().map_err(ssl_error)?,
+        Err(_) => PKey::public_key_from_pem(pem).map_err(ssl_error)?
+    };
+    if key.rsa().is_err() {
+        return Err(c2pa::Error::BadParam("the identity log recipient must be an RSA key or certificate".to_owned()));
+    }
+
+    Ok(key)
+}
+
+/* Seals a record for the holder of the private key matching `recipient_pem` and appends it to the log */
+pub fn 
+append_identity (log: &Path, recipient_pem: &[u8], record: &IdentityRecord) -> Result<(), c2pa::Error> {
+    let recipient = recipient_key(recipient_pem)?;
+
+    let mut key = [0u8; 32];
+    let mut iv = [0u8; 12];
+    openssl::rand::rand_bytes(&mut key).map_err(ssl_error)?;
+    openssl::rand::rand_bytes(&mut iv).map_err(ssl_error)?;
+    let mut tag = [0u8; 16];
+    let data = encrypt_aead(Cipher::aes_256_gcm(), &key, Some(&iv), &[], &serde_json::to_vec(record)?, &mut tag).map_err(ssl_error)?;
+
+    let mut encrypter = Encrypter::new(&recipient).map_err(ssl_error)?;
+    encrypter.set_rsa_padding(Padding::PKCS1_OAEP).map_err(ssl_error)?;
+    let mut wrapped = vec![0; encrypter.encrypt_len(&key).map_err(ssl_error)?];
+    let len = encrypter.encrypt(&key, &mut wrapped).map_err(ssl_error)?;
+    wrapped.truncate(len);
+
+    let sealed = SealedRecord {
+        key: BASE64.encode(wrapped),
+        iv: BASE64.encode(iv),
+        tag: BASE64.encode(tag),
+        data: BASE64.encode(data)
+    };
+
+    // Only the owner may read the log, even though its records are sealed
+    let mut options = std::fs::OpenOptions::new();
+    options.create(true).append(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options.open(log)?;
+    writeln!(file, "{}", serde_json::to_string(&sealed)?)?;
+    file.sync_all()?;
+
+    Ok(())
+}
+
+/* Opens every record of an identity log with the recipient's RSA private key */
+pub fn 
+read_identities (log: &Path, private_key_pem: &[u8]) -> Result<Vec<IdentityRecord>, c2pa::Error> {
+    let private_key = PKey::private_key_from_pem(private_key_pem).map_err(ssl_error)?;
+    let file = std::io::BufReader::new(std::fs::File::open(log)?);
+
+    let mut records = Vec::new();
+    for line in file.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let sealed: SealedRecord = serde_json::from_str(&line)?;
+
+        let mut decrypter = Decrypter::new(&private_key).map_err(ssl_error)?;
+        decrypter.set_rsa_padding(Padding::PKCS1_OAEP).map_err(ssl_error)?;
+        let wrapped = base64_field(&sealed.key)?;
+        let mut key = vec![0; decrypter.decrypt_len(&wrapped).map_err(ssl_error)?];
+        let len = decrypter.decrypt(&wrapped, &mut key).map_err(ssl_error)?;
+        key.truncate(len);
+
+        let data = decrypt_aead(
+            Cipher::aes_256_gcm(), &key, Some(&base64_field(&sealed.iv)?), &[],
+            &base64_field(&sealed.data)?, &base64_field(&sealed.tag)?
+        ).map_err(ssl_error)?;
+        records.push(serde_json::from_slice(&data)?);
+    }
+
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use openssl::ec::{EcGroup, EcKey};
+    use openssl::nid::Nid;
+    use openssl::rsa::Rsa;
+
+    use super::*;
+
+    /* An empty directory of the test's own */
+    fn scratch_dir(test: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("c2pa-walkthrough-identity-{}-{}", test, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn definition() -> Value {
+        json!({
+            "assertions": [
+                {"label": CREATIVE_WORK_LABEL, "data": {"author": [
+                    {"@type": "Person", "name": "Jane Doe"},
+                    {"@type": "Organization", "name": "Example News"}
+                ]}},
+                {"label": "stds.iptc.photo-metadata", "data": {"dc:creator": ["Jane Doe"], "dc:title": "Harbour"}}
+            ]
+        })
+    }
+
+    /* A PEM RSA key pair, public then private */
+    fn rsa_keys() -> (Vec<u8>, Vec<u8>) {
+        let rsa = Rsa::generate(2048).unwrap();
+        (rsa.public_key_to_pem().unwrap(), rsa.private_key_to_pem().unwrap())
+    }
+
+    fn record(name: &str) -> IdentityRecord {
+        IdentityRecord::new("in.jpg", "out.jpg", b"signed", vec![json!({"@type": "Person", "name": name})], &Authorship::Anonymous)
+    }
+
+    #[test]
+    fn named_authors_are_left_alone() {
+        let mut named = definition();
+        assert!(conceal_authors(&mut named, &Authorship::Named).is_empty());
+        assert_eq!(named, definition());
+    }
+
+    #[test]
+    fn anonymous_signing_keeps_only_organizations() {
+        let mut anonymous = definition();
+        let concealed = conceal_authors(&mut anonymous, &Authorship::Anonymous);
+
+        assert_eq!(concealed, [json!({"@type": "Person", "name": "Jane Doe"}), json!({"field": "dc:creator", "value": ["Jane Doe"]})]);
+        assert_eq!(anonymous["assertions"][0]["data"]["author"], json!([{"@type": "Organization", "name": "Example News"}]));
+        assert_eq!(anonymous["assertions"][1]["data"], json!({"dc:title": "Harbour"}));
+    }
+
+    #[test]
+    fn pseudonyms_replace_people_or_are_credited() {
+        let pseudonym = Authorship::Pseudonym("Harbour Watcher".to_owned());
+        let mut replaced = definition();
+        assert_eq!(conceal_authors(&mut replaced, &pseudonym).len(), 2);
+        assert_eq!(replaced["assertions"][0]["data"]["author"], json!([
+            {"@type": "Organization", "name": "Example News"},
+            {"@type": "Person", "name": "Harbour Watcher"}
+        ]));
+        assert_eq!(replaced["assertions"].as_array().unwrap().len(), 2);
+
+        let mut credited = json!({"title": "Harbour"});
+        assert!(conceal_authors(&mut credited, &pseudonym).is_empty());
+        assert_eq!(credited["assertions"][0]["label"], CREATIVE_WORK_LABEL);
+        assert_eq!(credited["assertions"][0]["data"]["author"], json!([{"@type": "Person", "name": "Harbour Watcher"}]));
+    }
+
+    #[test]
+    fn sealed_records_open_only_with_the_recipient_key() {
+        let log = scratch_dir("sealed").join("identities.log");
+        let (public_key, private_key) = rsa_keys();
+        append_identity(&log, &public_key, &record("Jane Doe")).unwrap();
+        append_identity(&log, &public_key, &record("John Roe")).unwrap();
+
+        let contents = std::fs::read_to_string(&log).unwrap();
+        assert!(!contents.contains("Doe") && !contents.contains("Roe"));
+        #[cfg(unix)]
+        assert_eq!(std::os::unix::fs::PermissionsExt::mode(&std::fs::metadata(&log).unwrap().permissions()) & 0o777, 0o600);
+
+        let records = read_identities(&log, &private_key).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].authors, [json!({"@type": "Person", "name": "Jane Doe"})]);
+        assert_eq!(records[1].output_sha256, hex::encode(Sha256::digest(b"signed")));
+        assert!(read_identities(&log, &rsa_keys().1).is_err());
+    }
+
+    #[test]
+    fn tampered_records_are_rejected() {
+        let log = scratch_dir("tampered").join("identities.log");
+        let (public_key, private_key) = rsa_keys();
+        append_identity(&log, &public_key, &record("Jane Doe")).unwrap();
+
+        let mut sealed: SealedRecord = serde_json::from_str(std::fs::read_to_string(&log).unwrap().trim()).unwrap();
+        let mut data = base64_field(&sealed.data).unwrap();
+        data[0] ^= 1;
+        sealed.data = BASE64.encode(data);
+        std::fs::write(&log, serde_json::to_string(&sealed).unwrap()).unwrap();
+
+        assert!(read_identities(&log, &private_key).is_err());
+    }
+
+    #[test]
+    fn recipient_must_be_rsa() {
+        let ec = EcKey::generate(&EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap()).unwrap();
+        let pem = PKey::from_ec_key(ec).unwrap().public_key_to_pem().unwrap();
+
+        let log = scratch_dir("ec").join("identities.log");
+        assert!(matches!(append_identity(&log, &pem, &record("Jane Doe")), Err(c2pa::Error::BadParam(_))));
+        assert!(!log.exists());
+    }
+}
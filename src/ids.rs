@@ -0,0 +1,84 @@
+use serde::Serialize;
+use serde_json::Value;
+
+/*
+ * The identifiers of a manifest store, for databases which key provenance
+ * records by them rather than by file: each manifest's label, split into
+ * its vendor and UUID, and its instance ID, and the instance and document
+ * IDs of each ingredient with the label of the manifest it carried. Like
+ * `read`, this has no file-system dependencies.
+ */
+
+/* The identifiers of one ingredient */
+#[derive(Clone, Debug, Serialize)]
+pub struct IngredientIds {
+    pub title: Option<String>,
+    pub relationship: Option<String>,
+    pub instance_id: Option<String>,
+    pub document_id: Option<String>,
+    // Label of the ingredient's own active manifest, if it had one
+    pub manifest: Option<String>
+}
+
+/* The identifiers of one manifest */
+#[derive(Clone, Debug, Serialize)]
+pub struct ManifestIds {
+    pub label: String,
+    // Vendor prefix of the label, e.g. acme in acme:urn:uuid:<uuid>
+    pub vendor: Option<String>,
+    pub uuid: Option<String>,
+    pub instance_id: Option<String>,
+    pub active: bool,
+    pub ingredients: Vec<IngredientIds>
+}
+
+/**
+ * Splits a manifest label into its vendor and UUID. Version 1 labels are
+ * `[vendor:]urn:uuid:<uuid>`; version 2 labels are
+ * `urn:c2pa:<uuid>[:generator[:version]]`, whose trailing parts name the
+ * claim generator rather than a vendor.
+ */
+pub fn 
+split_label (label: &str) -> (Option<&str>, Option<&str>) {
+    if let Some(rest) = label.strip_prefix("urn:c2pa:") {
+        // The SDK writes its version 2 labels as urn:c2pa::<uuid>
+        return (None, rest.trim_start_matches(':').split(':').next().filter(|uuid| !uuid.is_empty()));
+    }
+    match label.split_once("urn:uuid:") {
+        Some(("", uuid)) => (None, Some(uuid)),
+        Some((vendor, uuid)) => (vendor.strip_suffix(':'), Some(uuid)),
+        None => (None, None)
+    }
+}
+
+fn 
+string (value: &Value) -> Option<String> {
+    value.as_str().map(str::to_owned)
+}
+
+/* The identifiers of every manifest in a store, as `read` prints it, in store order */
+pub fn 
+manifest_ids (store_json: &Value) -> Vec<ManifestIds> {
+    let active = store_json["active_manifest"].as_str();
+    store_json["manifests"].as_object().into_iter().flatten()
+        .map(|(label, manifest)| {
+            let (vendor, uuid) = split_label(label);
+            ManifestIds {
+                label: label.clone(),
+                vendor: vendor.map(str::to_owned),
+                uuid: uuid.map(str::to_owned),
+                instance_id: string(&manifest["instance_id"]),
+                active: active == Some(label.as_str()),
+                ingredients: manifest["ingredients"].as_array().into_iter().flatten()
+                    .map(|ingredient| IngredientIds {
+                        title: string(&ingredient["title"]),
+                        relationship: string(&ingredient["relationship"]),
+                        instance_id: string(&ingredient["instance_id"]),
+                        document_id: string(&ingredient["document_id"]),
+                        manifest: string(&ingredient["active_manifest"])
+                    })
+                    .collect()
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,160 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::ids::manifest_ids;
+use crate::query::manifest_store_json;
+use crate::read::load_manifest_store;
+use crate::trust::signer_common_name;
+
+/*
+ * An index of a tree of signed assets, so finding which file holds some
+ * content or manifest doesn't mean validating the whole tree again. Each
+ * asset is recorded by its path within the tree with its content hash,
+ * active manifest, signer and claim generator, and the index maps back to
+ * paths from content hashes and from instance IDs. Every manifest in an
+ * asset's store is indexed by its instance ID, not just the active one, so
+ * the ID of an earlier version finds the files derived from it.
+ */
+
+// Bumped whenever the layout of index.json changes
+pub const INDEX_VERSION: u32 = 1;
+
+/* What the index records of one asset */
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct IndexedAsset {
+    // SHA-256 of the whole file, hex-encoded
+    pub sha256: String,
+    pub manifest: Option<String>,
+    pub instance_id: Option<String>,
+    pub claim_generator: Option<String>,
+    // Issuer of the signing certificate
+    pub signer: Option<String>,
+    pub signer_cn: Option<String>,
+    // Instance IDs of every manifest in the store, the active one's included
+    pub instance_ids: Vec<String>
+}
+
+/* The index of a tree, keyed by each asset's path within it */
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ProvenanceIndex {
+    pub version: u32,
+    // The tree's directory, absolute, so lookups find its files from anywhere
+    pub root: String,
+    pub assets: BTreeMap<String, IndexedAsset>,
+    pub content_hashes: BTreeMap<String, Vec<String>>,
+    pub instance_ids: BTreeMap<String, Vec<String>>
+}
+
+impl ProvenanceIndex {
+    pub fn new(root: &str) -> ProvenanceIndex {
+        ProvenanceIndex {
+            version: INDEX_VERSION,
+            root: root.to_owned(),
+            assets: BTreeMap::new(),
+            content_hashes: BTreeMap::new(),
+            instance_ids: BTreeMap::new()
+        }
+    }
+
+    /* Reads an index written by `to_json`, refusing one in a layout this build doesn't know */
+    pub fn from_json(json: &[u8]) -> Result<ProvenanceIndex, c2pa::Error> {
+        let index: ProvenanceIndex = serde_json::from_slice(json)?;
+        if index.version != INDEX_VERSION {
+            return Err(c2pa::Error::BadParam(format!("index version {} isn't supported; this build reads version {}", index.version, INDEX_VERSION)));
+        }
+
+        Ok(index)
+    }
+
+    pub fn to_json(&self) -> Result<Vec<u8>, c2pa::Error> {
+        Ok(serde_json::to_vec_pretty(self)?)
+    }
+
+    /* Records an asset at `path`, replacing whatever was recorded there before */
+    pub fn add(&mut self, path: &str, asset: IndexedAsset) {
+        self.remove(path);
+        add_path(&mut self.content_hashes, &asset.sha256, path);
+        for instance_id in &asset.instance_ids {
+            add_path(&mut self.instance_ids, instance_id, path);
+        }
+        self.assets.insert(path.to_owned(), asset);
+    }
+
+    fn remove(&mut self, path: &str) {
+        let Some(asset) = self.assets.remove(path) else {
+            return;
+        };
+        remove_path(&mut self.content_hashes, &asset.sha256, path);
+        for instance_id in &asset.instance_ids {
+            remove_path(&mut self.instance_ids, instance_id, path);
+        }
+    }
+
+    /**
+     * The paths of assets matching `key`: a content hash, an instance ID or
+     * a manifest label, tried in that order. Hashes match in either case.
+     */
+    pub fn lookup(&self, key: &str) -> Vec<&str> {
+        let found = self.content_hashes.get(&key.to_ascii_lowercase())
+            .or_else(|| self.instance_ids.get(key));
+        if let Some(paths) = found {
+            return paths.iter().map(String::as_str).collect();
+        }
+
+        self.assets.iter()
+            .filter(|(_, asset)| asset.manifest.as_deref() == Some(key))
+            .map(|(path, _)| path.as_str())
+            .collect()
+    }
+}
+
+fn 
+add_path (map: &mut BTreeMap<String, Vec<String>>, key: &str, path: &str) {
+    let paths = map.entry(key.to_owned()).or_default();
+    if !paths.iter().any(|existing| existing == path) {
+        paths.push(path.to_owned());
+        paths.sort();
+    }
+}
+
+fn 
+remove_path (map: &mut BTreeMap<String, Vec<String>>, key: &str, path: &str) {
+    if let Some(paths) = map.get_mut(key) {
+        paths.retain(|existing| existing != path);
+        if paths.is_empty() {
+            map.remove(key);
+        }
+    }
+}
+
+/* The SHA-256 of an asset's bytes, hex-encoded as the index keys it */
+pub fn 
+content_hash (bytes: &[u8]) -> String {
+    Sha256::digest(bytes).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/**
+ * What the index records of a signed asset. Fails as reading its manifest
+ * store does, so an unsigned asset gives JumbfNotFound; the manifests
+ * aren't validated, only read.
+ */
+pub fn 
+index_asset (format: &str, asset: &[u8]) -> Result<IndexedAsset, c2pa::Error> {
+    let manifest_store = load_manifest_store(format, asset)?;
+    let store_json = manifest_store_json(&manifest_store)?;
+    let ids = manifest_ids(&store_json);
+    let active = ids.iter().find(|manifest| manifest.active);
+    let signature = manifest_store.active_manifest().and_then(|manifest| manifest.signature_info());
+
+    Ok(IndexedAsset {
+        sha256: content_hash(asset),
+        manifest: active.map(|manifest| manifest.label.clone()),
+        instance_id: active.and_then(|manifest| manifest.instance_id.clone()),
+        claim_generator: active.and_then(|manifest| store_json["manifests"][&manifest.label]["claim_generator"].as_str()).map(str::to_owned),
+        signer: signature.and_then(|signature| signature.issuer.clone()),
+        signer_cn: signature.and_then(|signature| signer_common_name(signature.cert_chain().as_bytes())),
+        instance_ids: ids.into_iter().filter_map(|manifest| manifest.instance_id).collect()
+    })
+}
@@ -0,0 +1,154 @@
+use std::io::{BufRead, Write};
+
+use serde_json::{json, Value};
+
+use crate::manifest::{edit_action, software_agent, DigitalSourceType};
+use crate::sign::default_manifest_definition;
+
+/*
+ * Guided authoring of a manifest definition for `sign --interactive`. The
+ * user is asked for a title, the author, how the content was made, what was
+ * done to it and which optional assertions to include, with a default for
+ * every answer; the resulting definition (the JSON `c2patool` accepts) is
+ * shown before anything is signed. Prompts read from and write to any
+ * stream, so the flow can be driven by something other than a terminal.
+ */
+
+/* Asks a question, returning the trimmed answer or `default` if it is left blank */
+fn 
+ask (input: &mut impl BufRead, output: &mut impl Write, question: &str, default: &str) -> Result<String, c2pa::Error> {
+    if default.is_empty() {
+        write!(output, "{}: ", question)?;
+    } else {
+        write!(output, "{} [{}]: ", question, default)?;
+    }
+    output.flush()?;
+
+    let mut answer = String::new();
+    if input.read_line(&mut answer)? == 0 {
+        return Err(c2pa::Error::BadParam("input ended before the manifest was complete".to_owned()));
+    }
+
+    Ok(match answer.trim() {
+        "" => default.to_owned(),
+        answer => answer.to_owned()
+    })
+}
+
+fn 
+ask_yes_no (input: &mut impl BufRead, output: &mut impl Write, question: &str, default: bool) -> Result<bool, c2pa::Error> {
+    let question = format!("{} [{}]", question, if default { "Y/n" } else { "y/N" });
+    loop {
+        match ask(input, output, &question, "")?.to_lowercase().as_str() {
+            "" => return Ok(default),
+            "y" | "yes" => return Ok(true),
+            "n" | "no" => return Ok(false),
+            _ => writeln!(output, "please answer y or n")?
+        }
+    }
+}
+
+/* Asks until the answer passes `parse`, explaining what was wrong with each failed answer */
+fn 
+ask_until<T> (
+    input: &mut impl BufRead,
+    output: &mut impl Write,
+    question: &str,
+    default: &str,
+    parse: impl Fn(&str) -> Result<T, String>
+) -> Result<T, c2pa::Error> {
+    loop {
+        match parse(&ask(input, output, question, default)?) {
+            Ok(value) => return Ok(value),
+            Err(why) => writeln!(output, "{}", why)?
+        }
+    }
+}
+
+/**
+ * Walks the user through authoring a manifest definition for the asset
+ * named `title`, or for several assets if `title` is None, and shows it for
+ * confirmation. Returns the definition as JSON, or None if the user
+ * declines to sign with it.
+ */
+pub fn 
+author_manifest (input: &mut impl BufRead, output: &mut impl Write, title: Option<&str>) -> Result<Option<String>, c2pa::Error> {
+    let title = match title {
+        Some(title) => ask(input, output, "Title", title)?,
+        None => ask(input, output, "Title (blank to use each file's name)", "")?
+    };
+
+    let author = ask(input, output, "Author name (blank for none)", "")?;
+    let author_id = if author.is_empty() {
+        String::new()
+    } else {
+        ask(input, output, "Author identifier, e.g. a username or URL (blank for none)", "")?
+    };
+
+    writeln!(output, "How was the content made?")?;
+    for (i, source_type) in DigitalSourceType::ALL.iter().enumerate() {
+        writeln!(output, "  {:>2}. {}", i + 1, source_type.term())?;
+    }
+    let source_type = ask_until(input, output, "Digital source type (number or name)", DigitalSourceType::DigitalCapture.term(), |answer| {
+        let by_number = answer.parse::<usize>().ok().and_then(|n| n.checked_sub(1)).and_then(|i| DigitalSourceType::ALL.get(i));
+        by_number.or_else(|| DigitalSourceType::ALL.iter().find(|source_type| source_type.term().eq_ignore_ascii_case(answer)))
+            .copied()
+            .ok_or_else(|| format!("'{}' is not one of the listed source types", answer))
+    })?;
+
+    let edits = ask_until(input, output, "Edits made since creation, comma-separated, e.g. cropped, filtered (blank for none)", "", |answer| {
+        answer.split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(|name| edit_action(name).map_err(|e| e.to_string()))
+            .collect::<Result<Vec<_>, _>>()
+    })?;
+
+    let allow_training = ask_yes_no(input, output, "Allow use of the content for AI training and data mining?", true)?;
+
+    let mut definition: Value = serde_json::from_str(&default_manifest_definition())?;
+    if !title.is_empty() {
+        definition["title"] = json!(title);
+    }
+
+    let when = crate::clock::recorded_time();
+    let mut actions = vec![json!({
+        "action": c2pa::assertions::c2pa_action::CREATED,
+        "digitalSourceType": source_type.uri(),
+        "softwareAgent": software_agent(),
+        "when": when
+    })];
+    for edit in edits {
+        actions.push(json!({"action": edit, "softwareAgent": software_agent(), "when": when}));
+    }
+
+    let mut assertions = vec![json!({"label": "c2pa.actions", "data": {"actions": actions}})];
+    if !author.is_empty() {
+        let mut person = json!({"@type": "Person", "name": author});
+        if !author_id.is_empty() {
+            person["identifier"] = json!(author_id);
+        }
+        assertions.push(json!({
+            "label": "stds.schema-org.CreativeWork",
+            "data": {"@context": "https://schema.org", "@type": "CreativeWork", "author": [person]}
+        }));
+    }
+    if !allow_training {
+        let not_allowed = json!({"use": "notAllowed"});
+        assertions.push(json!({
+            "label": "c2pa.training-mining",
+            "data": {"entries": {
+                "c2pa.ai_generative_training": not_allowed,
+                "c2pa.ai_inference": not_allowed,
+                "c2pa.ai_training": not_allowed,
+                "c2pa.data_mining": not_allowed
+            }}
+        }));
+    }
+    definition["assertions"] = Value::Array(assertions);
+
+    let definition = serde_json::to_string_pretty(&definition)?;
+    writeln!(output, "\n{}\n", definition)?;
+
+    Ok(ask_yes_no(input, output, "Sign with this manifest?", true)?.then_some(definition))
+}
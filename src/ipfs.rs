@@ -0,0 +1,335 @@
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use c2pa::Signer;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use crate::sign::{builder_from_json, sign_manifest};
+use crate::storage::write_file_atomically;
+
+/*
+ * Remote manifests published to IPFS. An asset's XMP can only reference a
+ * manifest by a name fixed before signing, while a content CID depends on
+ * the manifest, which itself hashes the XMP. So each asset gets its own
+ * IPNS name (a libp2p-key CID) from the local node's API (`IPFS_API_URL`,
+ * by default kubo's http://127.0.0.1:5001); the asset references
+ * `<gateway>/ipns/<name>`, and once signed the manifest store is added to
+ * IPFS and the name published to point at its CID. The manifest store is
+ * left out of the asset itself. Verification fetches remote manifests
+ * referenced by IPFS URLs through a configurable gateway instead of the one
+ * named in the asset.
+ */
+
+pub const DEFAULT_GATEWAY: &str = "https://ipfs.io";
+
+const DEFAULT_API_URL: &str = "http://127.0.0.1:5001";
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct GeneratedKey {
+    name: String,
+    id: String
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct AddedFile {
+    hash: String
+}
+
+/* Where a signed asset's manifest store was published */
+pub struct Published {
+    pub url: String,
+    pub cid: String
+}
+
+fn 
+ipfs_error (e: ureq::Error) -> c2pa::Error {
+    c2pa::Error::OtherError(Box::new(e))
+}
+
+fn 
+api_url (command: &str) -> String {
+    let api = std::env::var("IPFS_API_URL").unwrap_or_else(|_| DEFAULT_API_URL.to_owned());
+    format!("{}/api/v0/{}", api.trim_end_matches('/'), command)
+}
+
+/* A new IPNS key on the local node, for the one asset whose manifest it will name */
+fn 
+generate_key () -> Result<GeneratedKey, c2pa::Error> {
+    let name = format!("c2pa-{}", chrono::Utc::now().format("%Y%m%dT%H%M%S%.9f"));
+    let response = ureq::post(&api_url("key/gen"))
+        .query("arg", &name)
+        .query("type", "ed25519")
+        .query("ipns-base", "base36")
+        .call()
+        .map_err(ipfs_error)?;
+
+    Ok(serde_json::from_str(&response.into_string()?)?)
+}
+
+/* Adds `bytes` to IPFS as a single file, returning its CID */
+fn 
+add (bytes: &[u8]) -> Result<String, c2pa::Error> {
+    let boundary = "c2pa-walkthrough-manifest-store";
+    let mut body = format!(
+        "--{}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"manifest.c2pa\"\r\nContent-Type: application/c2pa\r\n\r\n",
+        boundary
+    ).into_bytes();
+    body.extend_from_slice(bytes);
+    body.extend_from_slice(format!("\r\n--{}--\r\n", boundary).as_bytes());
+
+    let response = ureq::post(&api_url("add"))
+        .query("cid-version", "1")
+        .set("Content-Type", &format!("multipart/form-data; boundary={}", boundary))
+        .send_bytes(&body)
+        .map_err(ipfs_error)?;
+
+    let added: AddedFile = serde_json::from_str(&response.into_string()?)?;
+    Ok(added.hash)
+}
+
+/* An XMP packet holding just a remote manifest reference */
+fn 
+provenance_xmp (url: &str) -> String {
+    format!(
+        concat!(
+            r#"<?xpacket begin="" id="W5M0MpCehiHzreSzNTczkc9d"?><x:xmpmeta xmlns:x="adobe:ns:meta/">"#,
+            r#"<rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">"#,
+            r#"<rdf:Description rdf:about="" xmlns:dcterms="http://purl.org/dc/terms/" dcterms:provenance="{}"/>"#,
+            r#"</rdf:RDF></x:xmpmeta><?xpacket end="w"?>"#
+        ),
+        url
+    )
+}
+
+/**
+ * Writes `url` into a JPEG's XMP as its remote manifest reference
+ * (`dcterms:provenance`). The SDK only writes these references when
+ * signing files on disk, so in-memory signing needs them written first;
+ * the reference is then covered by the manifest's hash of the asset.
+ */
+fn 
+add_remote_reference (format: &str, asset: &[u8], url: &str) -> Result<Vec<u8>, c2pa::Error> {
+    const XMP_NAMESPACE: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+
+    if !matches!(format, "jpg" | "jpeg" | "image/jpeg") {
+        return Err(c2pa::Error::BadParam(format!("IPFS remote manifests are only supported for JPEG assets, not {}", format)));
+    }
+    if !asset.starts_with(&[0xff, 0xd8]) {
+        return Err(c2pa::Error::BadParam("asset is not a JPEG".to_owned()));
+    }
+
+    // Walk the marker segments ahead of the image data looking for existing XMP
+    let mut pos = 2;
+    let mut insert_at = 2;
+    while pos + 4 <= asset.len() && asset[pos] == 0xff && asset[pos + 1] != 0xda {
+        let len = u16::from_be_bytes([asset[pos + 2], asset[pos + 3]]) as usize;
+        let segment = asset.get(pos + 4..pos + 2 + len).ok_or_else(|| c2pa::Error::BadParam("truncated JPEG".to_owned()))?;
+
+        if asset[pos + 1] == 0xe1 && segment.starts_with(XMP_NAMESPACE) {
+            return Err(c2pa::Error::BadParam("asset already has XMP; remote manifest references can only be added to assets without it".to_owned()));
+        }
+        // Keep a JFIF header first
+        if asset[pos + 1] == 0xe0 && pos == 2 {
+            insert_at = pos + 2 + len;
+        }
+        pos += 2 + len;
+    }
+
+    let mut payload = XMP_NAMESPACE.to_vec();
+    payload.extend(provenance_xmp(url).into_bytes());
+
+    let mut referenced = asset[..insert_at].to_vec();
+    referenced.extend([0xff, 0xe1]);
+    referenced.extend(((payload.len() + 2) as u16).to_be_bytes());
+    referenced.extend(payload);
+    referenced.extend(&asset[insert_at..]);
+    Ok(referenced)
+}
+
+/**
+ * Signs an asset referencing a remote manifest store on IPFS, and
+ * publishes the store. The returned asset still embeds the store, for
+ * anything else to be done with the signed manifest; write out the result
+ * of `strip_manifest_store` on it.
+ */
+pub fn 
+sign_asset_to_ipfs (format: &str, asset: &[u8], manifest_json: &str, signer: &dyn Signer, gateway: &str) -> Result<(Vec<u8>, Published), c2pa::Error> {
+    let key = generate_key()?;
+    let url = format!("{}/ipns/{}", gateway.trim_end_matches('/'), key.id);
+
+    let referenced = add_remote_reference(format, asset, &url)?;
+    let mut builder = builder_from_json(manifest_json)?;
+    let signed = sign_manifest(format, &referenced, &mut builder, signer)?;
+
+    let cid = add(&c2pa::jumbf_io::load_jumbf_from_memory(format, &signed)?)?;
+    ureq::post(&api_url("name/publish"))
+        .query("arg", &format!("/ipfs/{}", cid))
+        .query("key", &key.name)
+        .call()
+        .map_err(ipfs_error)?;
+
+    Ok((signed, Published { url, cid }))
+}
+
+/**
+ * The asset without its embedded manifest store, leaving only the remote
+ * reference: the JPEG with the APP11 segments the store's JUMBF is split
+ * across dropped.
+ */
+pub fn 
+strip_manifest_store (format: &str, signed: &[u8]) -> Result<Vec<u8>, c2pa::Error> {
+    if !matches!(format, "jpg" | "jpeg" | "image/jpeg") || !signed.starts_with(&[0xff, 0xd8]) {
+        return Err(c2pa::Error::UnsupportedType);
+    }
+
+    let mut stripped = signed[..2].to_vec();
+    let mut pos = 2;
+    while pos + 4 <= signed.len() && signed[pos] == 0xff && signed[pos + 1] != 0xda {
+        let len = u16::from_be_bytes([signed[pos + 2], signed[pos + 3]]) as usize;
+        let segment = signed.get(pos..pos + 2 + len).ok_or_else(|| c2pa::Error::BadParam("truncated JPEG".to_owned()))?;
+
+        // JUMBF segments start with the "JP" common identifier
+        if !(signed[pos + 1] == 0xeb && segment[4..].starts_with(b"JP")) {
+            stripped.extend(segment);
+        }
+        pos += 2 + len;
+    }
+    stripped.extend(&signed[pos..]);
+
+    Ok(stripped)
+}
+
+/**
+ * Resolves a remote manifest URL to the URL to fetch: `ipfs://` and
+ * `ipns://` URIs, and gateway URLs with an /ipfs/ or /ipns/ path, are
+ * fetched through `gateway`; anything else is fetched as is.
+ */
+fn 
+fetch_url (url: &str, gateway: &str) -> String {
+    let gateway = gateway.trim_end_matches('/');
+
+    if let Some(cid) = url.strip_prefix("ipfs://") {
+        format!("{}/ipfs/{}", gateway, cid)
+    } else if let Some(name) = url.strip_prefix("ipns://") {
+        format!("{}/ipns/{}", gateway, name)
+    } else if let Some(i) = url.find("/ipfs/").or_else(|| url.find("/ipns/")) {
+        format!("{}{}", gateway, &url[i..])
+    } else {
+        url.to_owned()
+    }
+}
+
+/**
+ * Where the manifest store at a remote manifest URL is cached, if the URL
+ * names immutable content, `ipfs://<cid>` or an /ipfs/ gateway path, rather
+ * than an IPNS name, which may be published again, or any other URL.
+ */
+fn 
+cache_path (url: &str, cache_dir: &Path) -> Option<PathBuf> {
+    let content = url.strip_prefix("ipfs://").or_else(|| url.find("/ipfs/").map(|i| &url[i + 6..]))?;
+    let name = hex::encode(Sha256::digest(content.trim_end_matches('/').as_bytes()));
+
+    Some(cache_dir.join(format!("{}.c2pa", &name[..16])))
+}
+
+/**
+ * Returns the asset with its remote manifest store, if it references one
+ * instead of embedding it, fetched and put back in place so it can be
+ * validated like any other asset. Other assets are returned unchanged.
+ * Manifest stores fetched by IPFS content ID are kept in `cache_dir`, since
+ * their content can't change.
+ */
+pub fn 
+with_remote_manifest (format: &str, asset: Vec<u8>, gateway: &str, cache_dir: &Path) -> Result<Vec<u8>, c2pa::Error> {
+    if c2pa::jumbf_io::load_jumbf_from_memory(format, &asset).is_ok() {
+        return Ok(asset);
+    }
+
+    let url = match crate::read::load_manifest_store(format, &asset) {
+        Err(c2pa::Error::RemoteManifestUrl(url)) => url,
+        _ => return Ok(asset)
+    };
+
+    let cache_path = cache_path(&url, cache_dir);
+    if let Some(cached) = cache_path.as_ref().and_then(|path| std::fs::read(path).ok()) {
+        return c2pa::jumbf_io::save_jumbf_to_memory(format, &asset, &cached);
+    }
+
+    let mut manifest_store = Vec::new();
+    ureq::get(&fetch_url(&url, gateway)).call().map_err(ipfs_error)?.into_reader().read_to_end(&mut manifest_store)?;
+    if let Some(path) = &cache_path {
+        std::fs::create_dir_all(cache_dir)?;
+        write_file_atomically(path, &manifest_store)?;
+    }
+
+    c2pa::jumbf_io::save_jumbf_to_memory(format, &asset, &manifest_store)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::read::{is_valid, load_manifest_store};
+    use crate::sign::{SignerConfig, DEFAULT_MANIFEST_DEFINITION};
+
+    const CID: &str = "bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi";
+
+    /* An empty directory of the test's own */
+    fn scratch_dir(test: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("c2pa-walkthrough-ipfs-{}-{}", test, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn ipfs_urls_are_fetched_through_the_gateway() {
+        assert_eq!(fetch_url(&format!("ipfs://{}", CID), "https://gw.example.com/"), format!("https://gw.example.com/ipfs/{}", CID));
+        assert_eq!(fetch_url("ipns://k51q", "https://gw.example.com"), "https://gw.example.com/ipns/k51q");
+        assert_eq!(fetch_url("https://ipfs.io/ipns/k51q", "https://gw.example.com"), "https://gw.example.com/ipns/k51q");
+        assert_eq!(fetch_url("https://example.com/manifest.c2pa", "https://gw.example.com"), "https://example.com/manifest.c2pa");
+    }
+
+    #[test]
+    fn only_content_ids_are_cached() {
+        let dir = Path::new("cache");
+        let cached = cache_path(&format!("ipfs://{}", CID), dir).unwrap();
+        assert_eq!(cache_path(&format!("https://ipfs.io/ipfs/{}/", CID), dir), Some(cached));
+        assert_eq!(cache_path("https://ipfs.io/ipns/k51q", dir), None);
+        assert_eq!(cache_path("https://example.com/manifest.c2pa", dir), None);
+    }
+
+    #[test]
+    fn remote_references_go_only_into_jpegs_without_xmp() {
+        let asset = std::fs::read("test_file.jpg").unwrap();
+        let referenced = add_remote_reference("jpg", &asset, "ipfs://cid").unwrap();
+
+        assert!(referenced.windows(b"dcterms:provenance=\"ipfs://cid\"".len()).any(|window| window == b"dcterms:provenance=\"ipfs://cid\""));
+        assert!(matches!(add_remote_reference("jpg", &referenced, "ipfs://other"), Err(c2pa::Error::BadParam(message)) if message.contains("already has XMP")));
+        assert!(matches!(add_remote_reference("png", &asset, "ipfs://cid"), Err(c2pa::Error::BadParam(_))));
+        assert!(matches!(add_remote_reference("jpg", b"GIF89a", "ipfs://cid"), Err(c2pa::Error::BadParam(_))));
+    }
+
+    #[test]
+    fn stripped_manifest_store_is_restored_from_the_cache() {
+        let url = format!("https://ipfs.io/ipfs/{}", CID);
+        let referenced = add_remote_reference("jpg", &std::fs::read("test_file.jpg").unwrap(), &url).unwrap();
+        let signer = SignerConfig::default().create_signer().unwrap();
+        let signed = sign_manifest("jpg", &referenced, &mut builder_from_json(DEFAULT_MANIFEST_DEFINITION).unwrap(), &*signer).unwrap();
+
+        let stripped = strip_manifest_store("jpg", &signed).unwrap();
+        assert!(c2pa::jumbf_io::load_jumbf_from_memory("jpg", &stripped).is_err());
+        assert!(matches!(load_manifest_store("jpg", &stripped), Err(c2pa::Error::RemoteManifestUrl(remote)) if remote == url));
+
+        let dir = scratch_dir("cached");
+        std::fs::write(cache_path(&url, &dir).unwrap(), c2pa::jumbf_io::load_jumbf_from_memory("jpg", &signed).unwrap()).unwrap();
+        let restored = with_remote_manifest("jpg", stripped, "http://127.0.0.1:9", &dir).unwrap();
+        assert!(is_valid(&load_manifest_store("jpg", &restored).unwrap()));
+
+        // Assets embedding their manifest store are left as they are
+        assert_eq!(with_remote_manifest("jpg", signed.clone(), "http://127.0.0.1:9", &dir).unwrap(), signed);
+        assert!(matches!(strip_manifest_store("png", &signed), Err(c2pa::Error::UnsupportedType)));
+    }
+}
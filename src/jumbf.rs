@@ -0,0 +1,195 @@
+use serde::Serialize;
+
+/*
+ * JUMBF, the box format manifest stores are written in: boxes laid end to
+ * end, each a 32-bit length, a four-character type and a payload. Superboxes
+ * (`jumb`) hold other boxes, starting with a description box (`jumd`) which
+ * gives the content type UUID of the superbox and usually a label; the
+ * labels of nested superboxes make up JUMBF URIs such as
+ * `self#jumbf=/c2pa/<manifest>/c2pa.assertions/c2pa.hash.data`. Offsets are
+ * from the start of the manifest store, which JPEGs split across APP11
+ * segments and other formats embed whole.
+ */
+
+/* One box of a manifest store */
+pub struct JumbfBox<'a> {
+    pub kind: [u8; 4],
+    // offset of the box from the start of the store
+    pub offset: usize,
+    pub len: usize,
+    // the box with its header
+    pub bytes: &'a [u8],
+    pub payload: &'a [u8]
+}
+
+/* What a superbox's description box says */
+pub struct Description {
+    pub uuid: [u8; 16],
+    pub label: Option<String>
+}
+
+/* One box as `list` gives it, superboxes described by their description box */
+#[derive(Debug, Serialize)]
+pub struct BoxEntry {
+    pub index: usize,
+    // number of superboxes the box is nested in
+    pub depth: usize,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub offset: usize,
+    pub len: usize,
+    // content type UUID of a superbox, or the extended type of a `uuid` box
+    pub uuid: Option<String>,
+    pub label: Option<String>,
+    // JUMBF URI path of a superbox, e.g. /c2pa/<manifest>/c2pa.claim
+    pub path: Option<String>
+}
+
+fn 
+malformed () -> c2pa::Error {
+    c2pa::Error::BadParam("malformed JUMBF manifest store".to_owned())
+}
+
+/* The boxes laid end to end in `data`, which starts `offset` bytes into the store */
+pub fn 
+boxes (mut data: &[u8], mut offset: usize) -> Result<Vec<JumbfBox<'_>>, c2pa::Error> {
+    let mut boxes = Vec::new();
+    while !data.is_empty() {
+        let header = data.get(..8).ok_or_else(malformed)?;
+        let kind = [header[4], header[5], header[6], header[7]];
+        let (len, header_len) = match u32::from_be_bytes([header[0], header[1], header[2], header[3]]) {
+            // The box runs to the end of its container
+            0 => (data.len(), 8),
+            // A 64-bit length follows the type
+            1 => {
+                let extended = data.get(8..16).ok_or_else(malformed)?;
+                let len = u64::from_be_bytes(extended.try_into().map_err(|_| malformed())?);
+                (usize::try_from(len).map_err(|_| malformed())?, 16)
+            },
+            len => (len as usize, 8)
+        };
+        let payload = data.get(header_len..len).ok_or_else(malformed)?;
+        boxes.push(JumbfBox { kind, offset, len, bytes: &data[..len], payload });
+        data = &data[len..];
+        offset += len;
+    }
+
+    Ok(boxes)
+}
+
+/* The description of a superbox and its boxes after it */
+pub fn 
+superbox<'a> (jumbf_box: &JumbfBox<'a>) -> Result<(Description, Vec<JumbfBox<'a>>), c2pa::Error> {
+    if &jumbf_box.kind != b"jumb" {
+        return Err(malformed());
+    }
+    let mut children = boxes(jumbf_box.payload, jumbf_box.offset + jumbf_box.bytes.len() - jumbf_box.payload.len())?;
+    if children.is_empty() || &children[0].kind != b"jumd" {
+        return Err(malformed());
+    }
+    let description = children.remove(0).payload;
+
+    // A content type UUID, then toggles, of which 0x02 says a null-terminated label follows
+    let uuid: [u8; 16] = description.get(..16).and_then(|uuid| uuid.try_into().ok()).ok_or_else(malformed)?;
+    let toggles = *description.get(16).ok_or_else(malformed)?;
+    let label = (toggles & 0x02 != 0).then(|| {
+        let label = &description[17..];
+        String::from_utf8_lossy(&label[..label.iter().position(|b| *b == 0).unwrap_or(label.len())]).into_owned()
+    });
+
+    Ok((Description { uuid, label }, children))
+}
+
+/* The manifest superboxes of a manifest store, in store order, the active manifest last */
+pub fn 
+manifests (jumbf: &[u8]) -> Result<Vec<JumbfBox<'_>>, c2pa::Error> {
+    let store = boxes(jumbf, 0)?;
+    let store = store.first().ok_or_else(|| c2pa::Error::BadParam("empty JUMBF manifest store".to_owned()))?;
+    let (_, manifests) = superbox(store)?;
+
+    Ok(manifests.into_iter().filter(|manifest| &manifest.kind == b"jumb").collect())
+}
+
+/* A UUID in its usual hyphenated form */
+pub fn 
+format_uuid (uuid: &[u8; 16]) -> String {
+    let hex: String = uuid.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("{}-{}-{}-{}-{}", &hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..])
+}
+
+/* A box type as text, escaping bytes which aren't printable ASCII */
+fn 
+kind_text (kind: &[u8; 4]) -> String {
+    kind.iter().map(|b| match b.is_ascii_graphic() || *b == b' ' {
+        true => (*b as char).to_string(),
+        false => format!("\\x{:02x}", b)
+    }).collect()
+}
+
+fn 
+list_into (jumbf_boxes: &[JumbfBox<'_>], depth: usize, parent: &str, entries: &mut Vec<BoxEntry>) -> Result<(), c2pa::Error> {
+    for jumbf_box in jumbf_boxes {
+        let mut entry = BoxEntry {
+            index: entries.len(),
+            depth,
+            kind: kind_text(&jumbf_box.kind),
+            offset: jumbf_box.offset,
+            len: jumbf_box.len,
+            uuid: None,
+            label: None,
+            path: None
+        };
+        match &jumbf_box.kind {
+            b"jumb" => {
+                let (description, children) = superbox(jumbf_box)?;
+                let path = format!("{}/{}", parent, description.label.as_deref().unwrap_or_default());
+                entry.uuid = Some(format_uuid(&description.uuid));
+                entry.label = description.label;
+                entry.path = Some(path.clone());
+                entries.push(entry);
+                list_into(&children, depth + 1, &path, entries)?;
+            },
+            b"uuid" => {
+                entry.uuid = jumbf_box.payload.get(..16).and_then(|uuid| uuid.try_into().ok()).map(format_uuid);
+                entries.push(entry);
+            },
+            _ => entries.push(entry)
+        }
+    }
+
+    Ok(())
+}
+
+/**
+ * Every box of a manifest store, depth first in the order they are laid
+ * out, numbered from 0. Description boxes are left out, since each is
+ * given with its superbox.
+ */
+pub fn 
+list (jumbf: &[u8]) -> Result<Vec<BoxEntry>, c2pa::Error> {
+    let mut entries = Vec::new();
+    list_into(&boxes(jumbf, 0)?, 0, "", &mut entries)?;
+
+    Ok(entries)
+}
+
+/**
+ * The bytes of one box of a manifest store, header included: the box
+ * numbered `selector` by `list`, or the superbox whose JUMBF URI path it
+ * is, as `/c2pa/<manifest>/c2pa.claim` or `self#jumbf=c2pa/<manifest>/c2pa.claim`.
+ */
+pub fn 
+extract<'a> (jumbf: &'a [u8], selector: &str) -> Result<&'a [u8], c2pa::Error> {
+    let entries = list(jumbf)?;
+    let entry = match selector.parse::<usize>() {
+        Ok(index) => entries.get(index),
+        Err(_) => {
+            let path = selector.strip_prefix("self#jumbf=").unwrap_or(selector);
+            let path = format!("/{}", path.trim_start_matches('/'));
+            entries.iter().find(|entry| entry.path.as_deref() == Some(path.as_str()))
+        }
+    };
+    let entry = entry.ok_or_else(|| c2pa::Error::BadParam(format!("no box '{}' in the manifest store", selector)))?;
+
+    Ok(&jumbf[entry.offset..entry.offset + entry.len])
+}
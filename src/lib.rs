@@ -0,0 +1,196 @@
+/*
+ * Library core of the c2pa walkthrough. The manifest-reading, validation and
+ * query code in `canonical`, `exiftool`, `ids`, `jumbf`, `read`, `query`,
+ * `size`, `tamper`, `timeline` and `trusted_time` has no file-system or
+ * signing dependencies and builds for `wasm32-unknown-unknown`; everything which
+ * touches local files or private keys is gated behind the default `file_io`
+ * feature.
+ */
+
+pub mod canonical;
+
+pub mod exiftool;
+
+pub mod ids;
+
+pub mod jumbf;
+
+pub mod query;
+
+pub mod read;
+
+pub mod size;
+
+pub mod tamper;
+
+pub mod timeline;
+
+pub mod trusted_time;
+
+#[cfg(feature = "file_io")]
+pub mod asset_ref;
+
+#[cfg(feature = "audit")]
+pub mod audit;
+
+#[cfg(feature = "file_io")]
+pub mod aux_data;
+
+#[cfg(feature = "file_io")]
+pub mod batch;
+
+#[cfg(feature = "file_io")]
+pub mod c2patool;
+
+#[cfg(feature = "file_io")]
+pub mod cert_check;
+
+#[cfg(feature = "file_io")]
+pub mod clock;
+
+#[cfg(feature = "file_io")]
+pub mod cloud_data;
+
+#[cfg(feature = "file_io")]
+pub mod color;
+
+#[cfg(feature = "file_io")]
+pub mod corpus;
+
+#[cfg(all(feature = "file_io", unix))]
+pub mod daemon;
+
+#[cfg(feature = "file_io")]
+pub mod dotenv;
+
+#[cfg(feature = "visual-diff")]
+pub mod diff;
+
+#[cfg(feature = "file_io")]
+pub mod endorse;
+
+#[cfg(feature = "ffi")]
+pub mod ffi;
+
+#[cfg(feature = "file_io")]
+pub mod fixtures;
+
+#[cfg(feature = "file_io")]
+pub mod fragmented;
+
+#[cfg(feature = "file_io")]
+pub mod hooks;
+
+#[cfg(feature = "file_io")]
+pub mod icc;
+
+#[cfg(feature = "file_io")]
+pub mod identity;
+
+#[cfg(feature = "file_io")]
+pub mod index;
+
+#[cfg(feature = "file_io")]
+pub mod interactive;
+
+#[cfg(feature = "file_io")]
+pub mod ipfs;
+
+#[cfg(feature = "file_io")]
+pub mod manifest;
+
+#[cfg(feature = "file_io")]
+pub mod metadata;
+
+#[cfg(feature = "file_io")]
+pub mod metrics;
+
+#[cfg(feature = "file_io")]
+pub mod namespace;
+
+#[cfg(feature = "node")]
+pub mod node;
+
+#[cfg(feature = "file_io")]
+pub mod ots;
+
+#[cfg(feature = "file_io")]
+pub mod paths;
+
+#[cfg(feature = "file_io")]
+pub mod pinning;
+
+#[cfg(feature = "pixel-integrity")]
+pub mod pixels;
+
+#[cfg(feature = "file_io")]
+pub mod profiles;
+
+#[cfg(feature = "file_io")]
+pub mod providers;
+
+#[cfg(feature = "python")]
+pub mod python;
+
+#[cfg(feature = "file_io")]
+pub mod rekor;
+
+#[cfg(feature = "file_io")]
+pub mod relate;
+
+#[cfg(feature = "renditions")]
+pub mod rendition;
+
+#[cfg(feature = "file_io")]
+pub mod report;
+
+#[cfg(feature = "file_io")]
+pub mod rest;
+
+#[cfg(feature = "file_io")]
+pub mod review;
+
+#[cfg(feature = "file_io")]
+pub mod revocation;
+
+#[cfg(feature = "file_io")]
+pub mod secret;
+
+#[cfg(feature = "file_io")]
+pub mod share;
+
+#[cfg(feature = "file_io")]
+pub mod sign;
+
+#[cfg(feature = "file_io")]
+pub mod storage;
+
+#[cfg(feature = "thumbnails")]
+pub mod thumbnail;
+
+#[cfg(feature = "file_io")]
+pub mod timestamp;
+
+#[cfg(feature = "file_io")]
+pub mod transcode;
+
+#[cfg(feature = "image-edits")]
+pub mod transform;
+
+#[cfg(feature = "file_io")]
+pub mod trust;
+
+#[cfg(feature = "file_io")]
+pub mod trust_list;
+
+#[cfg(feature = "file_io")]
+pub mod two_phase;
+
+#[cfg(feature = "file_io")]
+pub mod webhook;
+
+#[cfg(feature = "file_io")]
+pub mod worker;
+
+#[cfg(feature = "grpc")]
+pub mod grpc;
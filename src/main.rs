@@ -1,273 +1,3499 @@
-use std::path::PathBuf;
-use std::time::{SystemTime, UNIX_EPOCH};
-
-use c2pa::{create_signer, Ingredient, Manifest, ManifestStore, SigningAlg};
-use c2pa::assertions::{c2pa_action, Action, Actions, CreativeWork, Exif, SchemaDotOrgPerson};
-use chrono::prelude::{DateTime, Utc};
-use clap::{arg, Command};
-use regex::Regex;
-use serde::Serialize;
-
-#[derive(Serialize)]
-/* Example struct, used as labeled assertion data */
-struct MediaData {
-    n: usize,
-    m: usize,
-    desc: String,
-    ts: u64
-}
-
-impl MediaData {
-    fn new(n: usize, m: usize, desc: String) -> MediaData {
-        MediaData {
-            n: n,
-            m: m,
-            desc: desc,
-            ts: SystemTime::now().duration_since(UNIX_EPOCH).expect("").as_secs()
-        }
-    }
-}
-
-/**
- * Creates a new `Manifest` for an image file. Represents a set of
- * actions performed when creating a new media file, for example, after
- * a digital image is taken.
- */
-fn 
-create_new_manifest (src_path: &String, dest_path: &String) -> Result<(), c2pa::Error> {
-    let now: DateTime<Utc> = SystemTime::now().into();
-
-    // ISO 8601 date and time format
-    let now_string = now.to_rfc3339();
-
-    // Initialized new Manifest with claim generator user agent string
-    let mut manifest = Manifest::new("mikes-c2pa-test-code/0.1".to_owned());
-
-    // A new `CreativeWork`, defined in schema.org https://schema.org/CreativeWork
-    // This represents the media created by the user, whose details are added to the 
-    // `CreativeWork` as the author.
-    let creative_work = CreativeWork::new()
-        .add_author(
-            SchemaDotOrgPerson::new()
-                .set_name("Mike Cvet")
-                  .expect("set name")
-                .set_identifier("mikecvet")
-                  .expect("set identifier")
-        )?;
-
-    // A new `Action` reflecting the creation of the `CreativeWork`.    
-    let created = Actions::new()
-        .add_action(
-            Action::new(c2pa_action::CREATED)
-                .set_source_type("https://cv.iptc.org/newscodes/digitalsourcetype/digitalCapture".to_owned())
-                .set_software_agent("mikes-c2pa-test-code/0.1")
-                .set_when(now_string.clone())
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::ffi::{OsStr, OsString};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use c2pa::assertions::{c2pa_action, ReviewRating};
+use c2pa_walkthrough::asset_ref;
+use c2pa_walkthrough::aux_data;
+use c2pa_walkthrough::batch::{self, Filters, Skip, WalkOptions};
+use c2pa_walkthrough::c2patool;
+use c2pa_walkthrough::canonical::canonical_json;
+use c2pa_walkthrough::cloud_data;
+use c2pa_walkthrough::cert_check;
+use c2pa_walkthrough::clock;
+use c2pa_walkthrough::color;
+use c2pa_walkthrough::corpus;
+use c2pa_walkthrough::dotenv;
+use c2pa_walkthrough::endorse::{self, Endorser, Verdict};
+use c2pa_walkthrough::exiftool;
+use c2pa_walkthrough::fixtures;
+use c2pa_walkthrough::fragmented::{self, Fragmented};
+use c2pa_walkthrough::hooks::{self, HookContext, Phase};
+use c2pa_walkthrough::icc;
+use c2pa_walkthrough::ids;
+use c2pa_walkthrough::identity::{append_identity, conceal_authors, conceal_metadata_authors, read_identities, Authorship, IdentityRecord};
+use c2pa_walkthrough::index::{content_hash, index_asset, ProvenanceIndex};
+use c2pa_walkthrough::interactive;
+use c2pa_walkthrough::ipfs;
+use c2pa_walkthrough::jumbf;
+use c2pa_walkthrough::manifest::{action_time, create_new_manifest, custom_action, edit_action, edit_media_with_action, new_manifest_asset, EditAction, EditPipeline};
+use c2pa_walkthrough::metadata::check::DivergenceKind;
+use c2pa_walkthrough::metadata::standard::{self, StandardMetadata};
+use c2pa_walkthrough::metadata::{self, merge_assertions, strip_asset_gps, strip_gps_assertions, strip_metadata, MetadataBlock, RedactionPolicy, StripGps};
+use c2pa_walkthrough::metrics;
+use c2pa_walkthrough::namespace;
+use c2pa_walkthrough::read::{cert_chain_pem, claim_versions, hash_bindings, is_valid, load_manifest_store, read_manifest, signer_trusted, thumbnail_extension, thumbnails, validation_errors};
+use c2pa_walkthrough::ots::{self, OtsStatus};
+use c2pa_walkthrough::paths;
+use c2pa_walkthrough::pinning::{location_domain, PinCheck, PinPolicy};
+use c2pa_walkthrough::profiles::{self, ExpiringCert, SignerProfiles};
+use c2pa_walkthrough::query;
+use c2pa_walkthrough::rekor;
+use c2pa_walkthrough::relate::{relate, related_asset};
+use c2pa_walkthrough::report;
+use c2pa_walkthrough::rest::{self, RestConfig};
+use c2pa_walkthrough::review;
+use c2pa_walkthrough::revocation::{self, RevocationStatus};
+use c2pa_walkthrough::secret::SecretBytes;
+use c2pa_walkthrough::share::{self, ShareConfig};
+use c2pa_walkthrough::sign::{self, add_assertions_dir, append_assertions, default_manifest_definition, dry_run, sign_asset_with_signer, SharedSigner, SignerConfig};
+use c2pa_walkthrough::size::manifest_store_size;
+use c2pa_walkthrough::storage::{self, back_up_file, format_from_location, read_asset, read_file, resolve_collision, sidecar_location, write_asset, write_file_atomically, Collision, FileLock, IoOptions};
+use c2pa_walkthrough::tamper;
+use c2pa_walkthrough::timeline;
+use c2pa_walkthrough::timestamp::timestamps;
+use c2pa_walkthrough::transcode::{transcode, Preset};
+use c2pa_walkthrough::trust;
+use c2pa_walkthrough::trust_list;
+use c2pa_walkthrough::trusted_time::manifest_times;
+use c2pa_walkthrough::two_phase::{self, Prepared};
+use c2pa_walkthrough::webhook::{self, Webhook};
+use c2pa_walkthrough::worker::{JobPolicy, QueueConfig};
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{DateTime, FixedOffset};
+use clap::parser::ValueSource;
+use clap::{arg, value_parser, ArgAction, ArgGroup, ArgMatches, Command};
+
+/* Starts the standalone /metrics listener if --metrics-addr was given */
+fn 
+spawn_metrics_server (matches: &ArgMatches) -> Result<()> {
+    if let Some(addr) = matches.get_one::<String>("metrics-addr") {
+        metrics::spawn_server(addr).with_context(|| format!("binding the metrics listener to {}", addr))?;
+        println!("serving metrics on http://{}/metrics", addr);
+    }
+
+    Ok(())
+}
+
+/**
+ * The signer profile in the file given by --signer-profile, else the one
+ * named by --profile in the profiles file, else the signer named by the
+ * C2PA_SIGNCERT_PATH, C2PA_PKEY_PATH and C2PA_ALG variables, else the
+ * profiles file's default profile, else the SDK test certs. C2PA_TSA_URL
+ * replaces whichever signer's TSA.
+ */
+fn 
+chosen_signer (matches: &ArgMatches) -> Result<SignerConfig> {
+    // Either flag overrides the other's environment variable, but the two flags can't be given together
+    let given = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+    if given("signer-profile") && given("profile") {
+        bail!("--signer-profile and --profile can't be used together");
+    }
+    let signer_profile = matches.get_one::<String>("signer-profile").filter(|_| !given("profile"));
+    let profile = matches.get_one::<String>("profile").filter(|_| !given("signer-profile"));
+
+    if let Some(path) = signer_profile {
+        let config = SignerConfig::from_file(path).with_context(|| format!("reading signer profile {}", path))?;
+        return Ok(config.with_env_tsa_url());
+    }
+
+    let path = profiles::profiles_path();
+    let signer_profiles = SignerProfiles::load(&path).with_context(|| format!("reading signer profiles {}", path.display()))?;
+    let config = match (profile, signer_profiles) {
+        (Some(name), Some(signer_profiles)) => signer_profiles.get(name)?.clone(),
+        (Some(_), None) => bail!("--profile needs signer profiles in {}, or the file named by {}", path.display(), profiles::PROFILES_VAR),
+        (None, signer_profiles) => match SignerConfig::from_env()? {
+            Some(config) => config,
+            None => signer_profiles.and_then(|signer_profiles| signer_profiles.default_profile().cloned()).unwrap_or_default()
+        }
+    };
+
+    Ok(config.with_env_tsa_url())
+}
+
+/**
+ * `config` with the private key given by --key, a file or - for stdin, or
+ * by --key-env in place of its own. Commands which never read the key
+ * don't take these flags.
+ */
+fn 
+with_key_flags (config: SignerConfig, matches: &ArgMatches) -> Result<SignerConfig> {
+    let flag = |id: &str| matches.try_get_one::<String>(id).ok().flatten();
+    match (flag("key"), flag("key-env")) {
+        (Some(key), _) if key == "-" => {
+            if flag("files-from").is_some_and(|list| list == "-") {
+                bail!("--key - and --files-from - can't both read stdin");
+            }
+            let pkey = SecretBytes::read_stdin().context("reading the private key from stdin")?;
+            if pkey.is_empty() {
+                bail!("--key -: no private key on stdin");
+            }
+            Ok(config.with_pkey(pkey))
+        },
+        (Some(key), _) => Ok(SignerConfig { pkey_path: key.clone(), pkey: None, ..config }),
+        (None, Some(var)) => {
+            let pkey = SecretBytes::take_env(var).context("reading the private key from the environment")?;
+            if pkey.is_empty() {
+                bail!("--key-env: {} is empty", var);
+            }
+            Ok(config.with_pkey(pkey))
+        },
+        (None, None) => Ok(config)
+    }
+}
+
+/**
+ * Warns about certificates in the signer's chain which expire within
+ * --cert-expiry-window days, or have expired, and with --strict-certs
+ * refuses to sign with them.
+ */
+fn 
+check_cert_expiry (config: &SignerConfig, matches: &ArgMatches) -> Result<()> {
+    let window = *matches.get_one::<u32>("cert-expiry-window").unwrap();
+    let expiring = profiles::expiring_certs(config, window).with_context(|| format!("reading the certificate chain {}", config.signcert_path))?;
+    if expiring.is_empty() {
+        return Ok(());
+    }
+
+    if matches.get_flag("strict-certs") {
+        let problems: Vec<String> = expiring.iter().map(ExpiringCert::describe).collect();
+        bail!("{}; not signing with --strict-certs", problems.join("; "));
+    }
+    for cert in expiring {
+        eprintln!("{}: {}", color::yellow("warning"), cert.describe());
+    }
+
+    Ok(())
+}
+
+/**
+ * Fails, naming each violation, if the signer's certificate chain breaks
+ * the C2PA profile's key usage rules, which validators would reject it for.
+ */
+fn 
+check_cert_profile (config: &SignerConfig) -> Result<()> {
+    let chain = std::fs::read(&config.signcert_path).with_context(|| format!("reading the certificate chain {}", config.signcert_path))?;
+    let violations = cert_check::profile_violations(&chain).with_context(|| format!("reading the certificate chain {}", config.signcert_path))?;
+    if !violations.is_empty() {
+        bail!("{} breaks the C2PA certificate profile, so validators would reject its manifests: {}", config.signcert_path, violations.join("; "));
+    }
+
+    Ok(())
+}
+
+/**
+ * The signer to sign with: the one `chosen_signer` picks, with its key
+ * replaced by --key or --key-env, once its certificates have been checked
+ * for expiry and against the C2PA certificate profile.
+ */
+fn 
+signer_config (matches: &ArgMatches) -> Result<SignerConfig> {
+    let config = with_key_flags(chosen_signer(matches)?, matches)?;
+    check_cert_expiry(&config, matches)?;
+    check_cert_profile(&config)?;
+    Ok(config)
+}
+
+/* The audit log given by --audit-log, or with --audit the one in the data directory */
+fn 
+audit_log_path (matches: &ArgMatches) -> Option<PathBuf> {
+    match matches.get_one::<String>("audit-log") {
+        Some(path) => Some(PathBuf::from(path)),
+        None => matches.get_flag("audit").then(paths::audit_log_path)
+    }
+}
+
+/* Starts recording signing operations if --audit-log or --audit was given */
+#[cfg(feature = "audit")]
+fn 
+open_audit_log (matches: &ArgMatches) -> Result<()> {
+    if let Some(path) = audit_log_path(matches) {
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent).with_context(|| format!("creating {}", parent.display()))?;
+        }
+        c2pa_walkthrough::audit::open(&path).with_context(|| format!("opening audit log {}", path.display()))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(not(feature = "audit"))]
+fn 
+open_audit_log (matches: &ArgMatches) -> Result<()> {
+    if audit_log_path(matches).is_some() {
+        bail!("audit logging is not compiled in; rebuild with --features audit");
+    }
+
+    Ok(())
+}
+
+/* Announces each signed asset to --webhook-url, if given */
+fn 
+configure_webhook (matches: &ArgMatches) {
+    if let Some(url) = matches.get_one::<String>("webhook-url") {
+        webhook::configure(Webhook {
+            url: url.clone(),
+            retries: *matches.get_one::<u32>("webhook-retries").unwrap()
+        });
+    }
+}
+
+/* Sets how claim and ingredient thumbnails are made from --thumbnail-format, --thumbnail-quality and --thumbnail-long-edge */
+#[cfg(feature = "thumbnails")]
+fn 
+configure_thumbnails (matches: &ArgMatches) -> Result<()> {
+    use c2pa_walkthrough::thumbnail::{self, ThumbnailOptions};
+
+    let defaults = ThumbnailOptions::default();
+    thumbnail::configure(ThumbnailOptions {
+        format: matches.get_one::<String>("thumbnail-format").map(|format| format.parse()).transpose()?,
+        quality: matches.get_one::<u8>("thumbnail-quality").copied().unwrap_or(defaults.quality),
+        long_edge: matches.get_one::<u32>("thumbnail-long-edge").copied().unwrap_or(defaults.long_edge)
+    });
+
+    Ok(())
+}
+
+#[cfg(not(feature = "thumbnails"))]
+fn 
+configure_thumbnails (matches: &ArgMatches) -> Result<()> {
+    if ["thumbnail-format", "thumbnail-quality", "thumbnail-long-edge"].iter().any(|arg| matches.contains_id(arg)) {
+        bail!("thumbnails are not compiled in; rebuild with --features thumbnails");
+    }
+
+    Ok(())
+}
+
+/* Sets how assets are read and written from --io-chunk-size and --read-ahead */
+fn 
+configure_io (matches: &ArgMatches) -> Result<()> {
+    let defaults = IoOptions::default();
+    let chunk_size = matches.get_one::<u64>("io-chunk-size").copied().unwrap_or(defaults.chunk_size as u64);
+    if chunk_size == 0 {
+        bail!("--io-chunk-size must be at least 1 byte");
+    }
+    storage::configure_io(IoOptions {
+        chunk_size: usize::try_from(chunk_size).context("--io-chunk-size")?,
+        read_ahead: matches.get_one::<u64>("read-ahead").copied().unwrap_or(defaults.read_ahead)
+    });
+
+    Ok(())
+}
+
+#[cfg(feature = "grpc")]
+fn 
+serve_grpc (addr: &str, signer: SharedSigner) -> Result<()> {
+    let addr = addr.parse().with_context(|| format!("--addr {} should be a socket address, e.g. 127.0.0.1:50051", addr))?;
+    println!("serving gRPC on {}", addr);
+
+    c2pa_walkthrough::grpc::serve(addr, signer).context("gRPC server failed")
+}
+
+#[cfg(not(feature = "grpc"))]
+fn 
+serve_grpc (_addr: &str, _signer: SharedSigner) -> Result<()> {
+    bail!("gRPC support is not compiled in; rebuild with --features grpc");
+}
+
+fn 
+serve (matches: &ArgMatches) -> Result<()> {
+    open_audit_log(matches)?;
+    configure_webhook(matches);
+    // One signer serves every request, rather than each re-reading the key
+    let signer = signer_config(matches)?.create_shared_signer().context("loading the signer")?;
+
+    if matches.get_flag("grpc") {
+        spawn_metrics_server(matches)?;
+        let addr = matches.get_one::<String>("addr").map(String::as_str).unwrap_or("127.0.0.1:50051");
+        serve_grpc(addr, signer)
+    } else {
+        let addr = matches.get_one::<String>("addr").map(String::as_str).unwrap_or("127.0.0.1:8080");
+        let config = RestConfig {
+            signer,
+            templates_dir: matches.get_one::<String>("templates").map(PathBuf::from)
+        };
+
+        println!("serving REST API on http://{}", addr);
+        rest::serve(addr, config).context("REST server failed")
+    }
+}
+
+/* The last path segment of a location, without any query string */
+fn 
+location_file_name (location: &str) -> &str {
+    let path = location.split('?').next().unwrap_or(location);
+    path.rsplit(['/', '\\']).next().unwrap_or(path)
+}
+
+/* Where --share-to uploads assets for Verify links, if anywhere */
+fn 
+share_config (matches: &ArgMatches) -> Result<Option<ShareConfig>, c2pa::Error> {
+    matches.get_one::<String>("share-to")
+        .map(|upload_to| ShareConfig::new(upload_to, matches.get_one::<String>("share-url").map(String::as_str)))
+        .transpose()
+}
+
+/* Uploads an asset with --share-to, returning the Verify link for it */
+fn 
+share_asset (matches: &ArgMatches, location: &str, asset: &[u8]) -> Result<Option<String>, c2pa::Error> {
+    let Some(config) = share_config(matches)? else {
+        return Ok(None);
+    };
+
+    Ok(Some(share::verify_link(&config.upload(location_file_name(location), asset)?)))
+}
+
+/* Prints a Verify link beneath an asset, and with --qr its QR code */
+fn 
+print_verify_link (matches: &ArgMatches, link: &str) {
+    println!("    verify: {}", link);
+    if matches.get_flag("qr") {
+        match share::qr_code(link) {
+            Ok(code) => println!("{}", code),
+            Err(e) => println!("    {}: {}", color::yellow("warning"), e)
+        }
+    }
+}
+
+/* What --overwrite and --skip ask for when an output already exists; numbered names otherwise */
+fn 
+collision (matches: &ArgMatches) -> Collision {
+    if matches.get_flag("overwrite") {
+        Collision::Overwrite
+    } else if matches.get_flag("skip") {
+        Collision::Skip
+    } else {
+        Collision::Number
+    }
+}
+
+/* Where --strip-gps and --strip-asset-gps ask for GPS data to be removed from */
+fn 
+strip_gps (matches: &ArgMatches) -> StripGps {
+    if matches.get_flag("strip-asset-gps") {
+        StripGps::AssertionsAndAsset
+    } else if matches.get_flag("strip-gps") {
+        StripGps::Assertions
+    } else {
+        StripGps::Keep
+    }
+}
+
+/* Who --anonymous or --pseudonym ask for signed manifests to name as their author */
+fn 
+authorship (matches: &ArgMatches) -> Authorship {
+    match matches.get_one::<String>("pseudonym") {
+        Some(name) => Authorship::Pseudonym(name.clone()),
+        None if matches.get_flag("anonymous") => Authorship::Anonymous,
+        None => Authorship::Named
+    }
+}
+
+/* The --include, --exclude, --min-size and --max-size filters for files found in directories */
+fn 
+filters (matches: &ArgMatches) -> Result<Filters> {
+    let patterns = |arg: &str| matches.get_many::<String>(arg).unwrap_or_default().cloned().collect::<Vec<_>>();
+    let size = |arg: &str| matches.get_one::<String>(arg).map(|size| batch::parse_size(size)).transpose();
+
+    Ok(Filters::new(&patterns("include"), &patterns("exclude"), size("min-size")?, size("max-size")?)?)
+}
+
+/* How --follow-symlinks and --max-depth have directories walked */
+fn 
+walk_options (matches: &ArgMatches) -> WalkOptions {
+    WalkOptions {
+        follow_symlinks: matches.get_flag("follow-symlinks"),
+        max_depth: matches.get_one::<usize>("max-depth").copied()
+    }
+}
+
+/**
+ * Locations named by the `arg` positionals and any --files-from list, one
+ * per line with blank lines and # comments skipped, or stdin for `-`, each
+ * with the name its output takes in a directory. Local paths holding *, ?
+ * or [ are expanded as globs, and local directories are walked for the
+ * files their filters keep, named by their path within the directory.
+ */
+fn 
+locations (matches: &ArgMatches, arg: &str) -> Result<Vec<(String, String)>> {
+    let mut listed: Vec<String> = matches.get_many::<String>(arg).unwrap_or_default().cloned().collect();
+    if let Some(list) = matches.get_one::<String>("files-from") {
+        let contents = if list == "-" {
+            std::io::read_to_string(std::io::stdin()).context("reading the file list from stdin")?
+        } else {
+            std::fs::read_to_string(list).with_context(|| format!("reading file list {}", list))?
+        };
+        listed.extend(contents.lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(str::to_owned));
+    }
+
+    // Only verify has --dir, which takes directories alone
+    if let Ok(Some(dirs)) = matches.try_get_many::<String>("dir") {
+        for dir in dirs {
+            if !Path::new(dir).is_dir() {
+                bail!("--dir {} is not a directory", dir);
+            }
+            listed.push(dir.clone());
+        }
+    }
+
+    let filters = filters(matches)?;
+    let options = walk_options(matches);
+    let mut locations = Vec::new();
+    for location in listed {
+        if !location.contains("://") && Path::new(&location).is_dir() {
+            let root = Path::new(&location);
+            let walked = batch::walk(root, &filters, options).with_context(|| format!("reading directory {}", location))?;
+
+            // Report what was passed over, except for files the filters were asked to leave out
+            let filtered = walked.skipped.iter().filter(|(_, reason)| matches!(reason, Skip::Filtered)).count();
+            for (path, reason) in walked.skipped.iter().filter(|(_, reason)| !matches!(reason, Skip::Filtered)) {
+                eprintln!("{}: {}: {}", root.join(path).display(), color::yellow("skipped"), reason);
+            }
+            if filtered > 0 {
+                eprintln!("{}: {} file{} excluded by the filters", location, filtered, if filtered == 1 { "" } else { "s" });
+            }
+
+            if walked.files.is_empty() {
+                bail!("no files in {} pass the filters", location);
+            }
+            for file in walked.files {
+                locations.push((root.join(&file).to_string_lossy().into_owned(), batch::slash_path(&file)));
+            }
+            continue;
+        }
+        if location.contains("://") || !location.contains(['*', '?', '[']) {
+            let name = location_file_name(&location).to_owned();
+            locations.push((location, name));
+            continue;
+        }
+
+        let before = locations.len();
+        for path in glob::glob(&location).with_context(|| format!("bad glob {}", location))? {
+            let path = path.with_context(|| format!("expanding {}", location))?;
+            if path.is_file() {
+                let name = location_file_name(&path.to_string_lossy()).to_owned();
+                locations.push((path.to_string_lossy().into_owned(), name));
+            }
+        }
+        if locations.len() == before {
+            bail!("no files match {}", location);
+        }
+    }
+
+    if locations.is_empty() {
+        bail!("no assets given; name them as arguments or with --files-from");
+    }
+    Ok(locations)
+}
+
+/**
+ * Signs one or more assets with a shared signer and manifest definition;
+ * sources and output may be local paths, http(s) URLs or object store URIs.
+ * With more than one source, or a directory, the output is a directory (or
+ * object store prefix) each signed asset is written to under its own file
+ * name, or its path within the source directory. Every asset is attempted;
+ * the run fails if any of them did.
+ */
+fn 
+sign (matches: &ArgMatches) -> Result<()> {
+    if cfg!(not(feature = "renditions")) && matches.get_many::<String>("also-emit").is_some() {
+        bail!("renditions are not compiled in; rebuild with --features renditions");
+    }
+    if cfg!(not(feature = "pixel-integrity")) && matches.get_flag("verify-pixels") {
+        bail!("pixel verification is not compiled in; rebuild with --features pixel-integrity");
+    }
+    share_config(matches).context("--share-to")?;
+    let sources = locations(matches, "sources")?;
+    if let Some(id) = matches.get_one::<uuid::Uuid>("instance-id") {
+        // The SDK only takes random UUIDs as manifest labels
+        if id.get_version() != Some(uuid::Version::Random) {
+            bail!("--instance-id {} isn't a random (version 4) UUID, which manifest labels must be", id);
+        }
+        // The SDK drops the vendor from labels given in full
+        if matches.contains_id("vendor") {
+            bail!("--vendor can't be combined with --instance-id, which pins the whole label");
+        }
+        clock::pin_instance_id(*id);
+    }
+
+    let manifest_json = match matches.get_one::<String>("manifest") {
+        Some(path) => std::fs::read_to_string(path).with_context(|| format!("reading manifest definition {}", path))?,
+        None if matches.get_flag("interactive") => {
+            let title = match sources.as_slice() {
+                [(source, _)] => Some(location_file_name(source)),
+                _ => None
+            };
+            let authored = interactive::author_manifest(&mut std::io::stdin().lock(), &mut std::io::stdout(), title)
+                .context("authoring the manifest")?;
+            match authored {
+                Some(manifest_json) => manifest_json,
+                None => {
+                    println!("not signed");
+                    return Ok(());
+                }
+            }
+        },
+        None => default_manifest_definition()
+    };
+    let mut definition: serde_json::Value = serde_json::from_str(&manifest_json).context("parsing the manifest definition")?;
+    if let Some(dir) = matches.get_one::<PathBuf>("assertions-dir") {
+        add_assertions_dir(&mut definition, dir).with_context(|| format!("reading assertions from {}", dir.display()))?;
+    }
+    add_aux(matches, &mut definition)?;
+    add_review(matches, &mut definition)?;
+    add_standard_metadata(matches, &mut definition)?;
+    upload_cloud_data(matches, &mut definition)?;
+    if strip_gps(matches) != StripGps::Keep {
+        let removed = strip_gps_assertions(&mut definition);
+        if removed > 0 {
+            eprintln!("removed {} GPS field{} from the Exif assertions", removed, if removed == 1 { "" } else { "s" });
+        }
+    }
+    let policy = match matches.get_one::<String>("redaction-policy") {
+        Some(path) => Some(RedactionPolicy::from_file(path).with_context(|| format!("reading redaction policy {}", path))?),
+        None => None
+    };
+    if let Some(policy) = &policy {
+        let redacted = policy.redact(&mut definition);
+        eprintln!("redacted the metadata assertions: {} field{} dropped, {} hashed", redacted.dropped, if redacted.dropped == 1 { "" } else { "s" }, redacted.hashed);
+    }
+    let authorship = authorship(matches);
+    let concealed = conceal_authors(&mut definition, &authorship);
+    if authorship != Authorship::Named {
+        eprintln!("concealed {} author{} of the manifest definition", concealed.len(), if concealed.len() == 1 { "" } else { "s" });
+    }
+    if !concealed.is_empty() && !matches.get_flag("dry-run") && matches.get_one::<String>("identity-log").is_none() {
+        bail!("the concealed authors must be recorded; pass --identity-log and --identity-recipient");
+    }
+
+    let signer = signer_config(matches)?.create_signer().context("loading the signer")?;
+    if !matches.get_flag("dry-run") {
+        open_audit_log(matches)?;
+        configure_webhook(matches);
+    }
+
+    // --in-place and --backup conflict with a given --output, but override C2PA_OUTPUT_DIR
+    let in_place = matches.get_flag("in-place");
+    let output_from_env = matches.value_source("output") == Some(ValueSource::EnvVariable);
+    if !output_from_env && matches.contains_id("output") {
+        if let Some(flag) = [("in-place", in_place), ("backup", matches.get_flag("backup"))].iter().find_map(|(flag, given)| given.then_some(flag)) {
+            bail!("--{} can't be used with --output", flag);
+        }
+    }
+
+    // Several sources, a source directory, an output ending in / or naming a directory, or C2PA_OUTPUT_DIR, write into a directory
+    let output = matches.get_one::<String>("output").map(String::as_str).filter(|_| !(in_place && output_from_env));
+    if let Some((remote, _)) = sources.iter().find(|(source, _)| in_place && source.contains("://")) {
+        bail!("--in-place only signs local files, not {}", remote);
+    }
+    let into_directory = !in_place && sources.len() > 1
+        || matches.get_many::<String>("sources").unwrap_or_default().any(|source| !source.contains("://") && Path::new(source).is_dir())
+        || output.is_some_and(|output| output.ends_with('/') || Path::new(output).is_dir())
+        || output.is_some() && output_from_env;
+    if into_directory {
+        let mut names = std::collections::HashSet::new();
+        if let Some((_, duplicate)) = sources.iter().find(|(_, name)| !names.insert(name)) {
+            bail!("more than one source is named {}; sign them into different directories", duplicate);
+        }
+        if let Some(output) = output.filter(|output| !output.contains("://")) {
+            std::fs::create_dir_all(output).with_context(|| format!("creating {}", output))?;
+        }
+    }
+
+    // Existing outputs are only checked for when writing new local files
+    let collision = collision(matches);
+    let dry_run = matches.get_flag("dry-run");
+    let (mut signed, mut existing, mut failed) = (0, 0, 0);
+    for (source, name) in &sources {
+        let output = match output {
+            Some(output) if into_directory => Some(format!("{}/{}", output.trim_end_matches('/'), name)),
+            Some(output) => Some(output.to_owned()),
+            None if in_place => Some(source.clone()),
+            None => None
+        };
+        let output = match output {
+            Some(output) if !in_place && !dry_run && !output.contains("://") => {
+                let planned = Path::new(&output);
+                if planned.symlink_metadata().is_ok() {
+                    existing += 1;
+                }
+                match resolve_collision(planned, collision) {
+                    Some(resolved) => Some(resolved.to_string_lossy().into_owned()),
+                    None => {
+                        println!("{}: {}: {} already exists", source, color::yellow("skipped"), output);
+                        continue;
+                    }
+                }
+            },
+            output => output
+        };
+        if let Some(parent) = output.as_deref().filter(|output| into_directory && !output.contains("://")).and_then(|output| Path::new(output).parent()) {
+            std::fs::create_dir_all(parent).with_context(|| format!("creating {}", parent.display()))?;
+        }
+
+        let local = |location: &str| !location.contains("://");
+        let Some(_locks) = lock_asset(
+            Some(Path::new(source.as_str())).filter(|_| local(source)),
+            output.as_deref().filter(|output| !dry_run && local(output)).map(Path::new)
+        )? else {
+            continue;
+        };
+
+        // Assets are titled with their own file name unless the definition gives one
+        let mut definition = definition.clone();
+        if definition.get("title").is_none() {
+            definition["title"] = serde_json::json!(location_file_name(source));
+        }
+
+        match sign_one(matches, source, output.as_deref(), &definition, &*signer, &concealed, policy.as_ref()) {
+            Ok(()) => signed += 1,
+            Err(e) if sources.len() == 1 => return Err(e),
+            Err(e) => {
+                println!("{}: {}: {:#}", source, color::red("error"), e);
+                failed += 1;
+            }
+        }
+    }
+
+    if sources.len() > 1 && !dry_run && !matches.get_flag("quiet") {
+        let handled = match collision {
+            Collision::Overwrite => "overwritten",
+            Collision::Skip => "skipped",
+            Collision::Number => "given numbered names"
+        };
+        match existing {
+            0 => println!("signed {} of {} assets", signed, sources.len()),
+            1 => println!("signed {} of {} assets; 1 output already existed and was {}", signed, sources.len(), handled),
+            _ => println!("signed {} of {} assets; {} outputs already existed and were {}", signed, sources.len(), existing, handled)
+        }
+    }
+    if failed > 0 {
+        bail!("{} of {} assets failed to sign", failed, sources.len());
+    }
+    Ok(())
+}
+
+/**
+ * Signs one asset for `sign`, writing it to `output`, or with --dry-run
+ * printing its manifest. Metadata blocks removed with --strip-metadata are
+ * captured into the definition, scrubbed as it was with the GPS options,
+ * `policy` and the authorship. Any `concealed` authors are recorded to the
+ * identity log before the asset is written.
+ */
+fn 
+sign_one (matches: &ArgMatches, source: &str, output: Option<&str>, definition: &serde_json::Value, signer: &dyn c2pa::Signer, concealed: &[serde_json::Value], policy: Option<&RedactionPolicy>) -> Result<()> {
+    let format = matches.get_one::<String>("format").cloned()
+        .or_else(|| format_from_location(source))
+        .with_context(|| format!("cannot determine the format of {}; pass --format", source))?;
+    let mut asset = read_asset(source).with_context(|| format!("reading {}", source))?;
+    warn_about_time("--when", clock::pinned_time().as_ref(), Path::new(source));
+    if strip_gps(matches) == StripGps::AssertionsAndAsset {
+        let (stripped, found) = strip_asset_gps(&format, &asset).with_context(|| format!("removing the GPS location from {}", source))?;
+        if found {
+            eprintln!("removed the GPS location from the Exif of {}", source);
+        }
+        asset = stripped;
+    }
+
+    let mut definition = definition.clone();
+    let mut concealed = concealed.to_vec();
+    if let Some(blocks) = matches.get_many::<String>("strip-metadata") {
+        let blocks = blocks.map(|block| block.parse()).collect::<Result<Vec<MetadataBlock>, _>>()?;
+        let (stripped, assertions) = strip_metadata(&format, &asset, &blocks).with_context(|| format!("removing metadata from {}", source))?;
+        asset = stripped;
+
+        let mut captured = serde_json::json!({"assertions": assertions});
+        if strip_gps(matches) != StripGps::Keep {
+            strip_gps_assertions(&mut captured);
+        }
+        if let Some(policy) = policy {
+            policy.redact(&mut captured);
+        }
+        if authorship(matches) != Authorship::Named {
+            concealed.extend(conceal_metadata_authors(&mut captured));
+        }
+        if let serde_json::Value::Array(assertions) = captured["assertions"].take() {
+            eprintln!("captured {} metadata block{} of {} into assertions", assertions.len(), if assertions.len() == 1 { "" } else { "s" }, source);
+            merge_assertions(&mut definition, assertions);
+        }
+    }
+    if matches.get_flag("color-integrity") {
+        let assertion = icc::color_integrity_assertion(&format, &asset).with_context(|| format!("reading the color profile of {}", source))?;
+        append_assertions(&mut definition, vec![assertion])?;
+    }
+    add_published_urls(matches, output.unwrap_or(source), &mut definition)?;
+    let manifest_json = &definition.to_string();
+    let hook_commands = |arg: &str| -> Vec<String> { matches.get_many::<String>(arg).unwrap_or_default().cloned().collect() };
+    hooks::run(&hook_commands("pre-sign-hook"), &HookContext {
+        phase: Phase::PreSign,
+        format: &format,
+        source,
+        output: None,
+        manifest_label: None,
+        manifest_json,
+        asset: &asset
+    }).with_context(|| format!("not signing {}", source))?;
+
+    // Print what would be embedded, to stdout on its own so it can be piped
+    if matches.get_flag("dry-run") {
+        let (manifest_store, signed) = dry_run(&format, &asset, manifest_json, signer).with_context(|| format!("signing {}", source))?;
+        let store_json = query::manifest_store_json(&manifest_store)?;
+        let mut active = manifest_store.active_label().map_or(serde_json::Value::Null, |label| store_json["manifests"][label].clone());
+        if matches.get_flag("canonical-json") {
+            active = canonical_json(&active);
+        }
+        println!("{}", color::highlight_labels(&serde_json::to_string_pretty(&active)?));
+
+        let errors = validation_errors(&manifest_store);
+        if !errors.is_empty() {
+            bail!("{} would be signed with a manifest that fails validation: {}", source, errors.join(", "));
+        }
+        check_manifest_size(matches, source, &format, &signed)?;
+        check_pixels(matches, source, &format, &asset, &signed)?;
+        eprintln!("dry run: nothing written");
+        return Ok(());
+    }
+
+    let output = output.context("--output is required unless --dry-run or --in-place is given")?;
+    if matches.get_flag("in-place") {
+        back_up(matches, Path::new(output))?;
+    }
+    let (signed, published) = if matches.get_flag("ipfs") {
+        let gateway = matches.get_one::<String>("ipfs-gateway").unwrap();
+        let (signed, published) = ipfs::sign_asset_to_ipfs(&format, &asset, manifest_json, signer, gateway)
+            .with_context(|| format!("signing {} to IPFS", source))?;
+        (signed, Some(published))
+    } else {
+        (sign_asset_with_signer(&format, &asset, manifest_json, signer).with_context(|| format!("signing {}", source))?, None)
+    };
+    check_manifest_size(matches, source, &format, &signed)?;
+    check_pixels(matches, source, &format, &asset, &signed)?;
+
+    // Log the manifest before writing anything, so every written asset is logged
+    if matches.get_flag("rekor") {
+        let url = matches.get_one::<String>("rekor-url").unwrap();
+        let record = rekor::submit(url, &format, &signed, signer).with_context(|| format!("logging {} to {}", source, url))?;
+        let record_location = sidecar_location(output, ".rekor.json");
+        write_asset(&record_location, &serde_json::to_vec_pretty(&record)?)
+            .with_context(|| format!("writing {}", record_location))?;
+        println!("logged to {} as {}", url, record.uuid);
+    }
+
+    if matches.get_flag("ots") {
+        let calendars: Vec<String> = matches.get_many::<String>("ots-calendar").unwrap().cloned().collect();
+        let proof = ots::stamp(&calendars, &format, &signed).with_context(|| format!("timestamping {}", source))?;
+        let proof_location = sidecar_location(output, ".ots");
+        write_asset(&proof_location, &proof).with_context(|| format!("writing {}", proof_location))?;
+    }
+
+    let written = match &published {
+        Some(_) => Cow::Owned(ipfs::strip_manifest_store(&format, &signed).context("removing the embedded manifest store")?),
+        None => Cow::Borrowed(&signed)
+    };
+    if !concealed.is_empty() {
+        // Authors found in the metadata of this asset are only known now
+        let (Some(log), Some(recipient)) = (matches.get_one::<String>("identity-log"), matches.get_one::<String>("identity-recipient")) else {
+            bail!("the concealed authors of {} must be recorded; pass --identity-log and --identity-recipient", source);
+        };
+        let recipient_pem = std::fs::read(recipient).with_context(|| format!("reading {}", recipient))?;
+        let record = IdentityRecord::new(source, output, &written, concealed, &authorship(matches));
+        append_identity(Path::new(log), &recipient_pem, &record).with_context(|| format!("recording the authors of {} to {}", source, log))?;
+    }
+
+    write_asset(output, &written).with_context(|| format!("writing {}", output))?;
+    webhook::notify(&format, &asset, &written, Some(source), Some(output));
+    let post_hooks = hook_commands("post-sign-hook");
+    if !post_hooks.is_empty() {
+        let manifest_store = load_manifest_store(&format, &signed).with_context(|| format!("reading back {}", output))?;
+        let store_json = query::manifest_store_json(&manifest_store)?;
+        let active = manifest_store.active_label().map_or(&serde_json::Value::Null, |label| &store_json["manifests"][label]);
+        hooks::run(&post_hooks, &HookContext {
+            phase: Phase::PostSign,
+            format: &format,
+            source,
+            output: Some(output),
+            manifest_label: manifest_store.active_label(),
+            manifest_json: &active.to_string(),
+            asset: &written
+        }).with_context(|| format!("after signing {} -> {}", source, output))?;
+    }
+    if let Some(published) = published {
+        println!("published manifest store as {} ({})", published.cid, published.url);
+    }
+
+    if !matches.get_flag("quiet") {
+        println!("signed {} -> {}", source, output);
+    }
+    if let Some(link) = share_asset(matches, output, &written).with_context(|| format!("sharing {}", output))? {
+        print_verify_link(matches, &link);
+    }
+
+    #[cfg(feature = "renditions")]
+    emit_renditions(matches, source, &format, output, &signed, signer)?;
+    Ok(())
+}
+
+/**
+ * Prints the size of the manifest store an asset was signed with, part by
+ * part, to stderr with --size-report or --dry-run, and holds it to
+ * --max-manifest-size: a store over budget fails the asset before anything
+ * is written, or only warns with --warn-manifest-size.
+ */
+fn 
+check_manifest_size (matches: &ArgMatches, source: &str, format: &str, signed: &[u8]) -> Result<()> {
+    let report = matches.get_flag("size-report") || matches.get_flag("dry-run");
+    let budget = matches.get_one::<u64>("max-manifest-size");
+    if !report && budget.is_none() {
+        return Ok(());
+    }
+    let size = manifest_store_size(format, signed).with_context(|| format!("measuring the manifest store of {}", source))?;
+
+    if report {
+        eprintln!("manifest store of {}: {} bytes in {} manifest{}", source, size.bytes, size.manifests.len(), if size.manifests.len() == 1 { "" } else { "s" });
+        // The active manifest is broken down; those of ingredients are only totalled
+        for (i, manifest) in size.manifests.iter().enumerate().rev() {
+            if i + 1 < size.manifests.len() {
+                eprintln!("  {}: {} bytes (ingredient)", manifest.label, manifest.bytes);
+                continue;
+            }
+            eprintln!("  {}: {} bytes", manifest.label, manifest.bytes);
+            let width = manifest.parts.iter().map(|part| part.label.len()).max().unwrap_or_default();
+            for part in &manifest.parts {
+                eprintln!("    {:width$}  {:>8}", part.label, part.bytes, width = width);
+            }
+        }
+    }
+
+    if let Some(budget) = budget.filter(|budget| size.bytes as u64 > **budget) {
+        let message = format!("the manifest store of {} is {} bytes, over the --max-manifest-size budget of {}", source, size.bytes, budget);
+        if !matches.get_flag("warn-manifest-size") {
+            bail!(message);
+        }
+        eprintln!("{}: {}", color::yellow("warning"), message);
+    }
+
+    Ok(())
+}
+
+/**
+ * With --verify-pixels, decodes an asset before and after its manifest was
+ * embedded and fails it, before anything is written, if its pixels changed.
+ */
+#[cfg(feature = "pixel-integrity")]
+fn 
+check_pixels (matches: &ArgMatches, source: &str, format: &str, asset: &[u8], signed: &[u8]) -> Result<()> {
+    if !matches.get_flag("verify-pixels") {
+        return Ok(());
+    }
+    let comparison = c2pa_walkthrough::pixels::compare_pixels(format, asset, format, signed)
+        .with_context(|| format!("comparing the pixels of {} before and after signing", source))?;
+    if !comparison.identical() {
+        bail!("embedding the manifest changed the pixels of {}: {}", source, comparison.summary());
+    }
+
+    Ok(())
+}
+
+#[cfg(not(feature = "pixel-integrity"))]
+fn 
+check_pixels (_matches: &ArgMatches, _source: &str, _format: &str, _asset: &[u8], _signed: &[u8]) -> Result<()> {
+    Ok(())
+}
+
+/**
+ * Writes each --also-emit rendition of a signed asset beside its output,
+ * e.g. photo.webp for photo.jpg, transcoded from the signed asset and
+ * signed with a manifest taking it as the parent.
+ */
+#[cfg(feature = "renditions")]
+fn 
+emit_renditions (matches: &ArgMatches, source: &str, format: &str, output: &str, signed: &[u8], signer: &dyn c2pa::Signer) -> Result<()> {
+    use c2pa_walkthrough::rendition::{render, RenditionFormat};
+    use c2pa_walkthrough::storage::with_extension;
+
+    for name in matches.get_many::<String>("also-emit").unwrap_or_default() {
+        let rendition: RenditionFormat = name.parse()?;
+        let planned = with_extension(output, rendition.extension());
+        if planned == output {
+            bail!("{} is already a {}; --also-emit takes other formats", output, rendition.extension());
+        }
+        let location = match planned.contains("://") {
+            true => Some(planned.clone()),
+            false => resolve_collision(Path::new(&planned), collision(matches)).map(|path| path.to_string_lossy().into_owned())
+        };
+        let Some(location) = location else {
+            println!("{}: {}: {} already exists", source, color::yellow("skipped"), planned);
+            continue;
+        };
+
+        let rendered = render(signed, format, output, rendition, location_file_name(&location), signer)
+            .with_context(|| format!("rendering {} as {}", source, rendition.extension()))?;
+        write_asset(&location, &rendered).with_context(|| format!("writing {}", location))?;
+        println!("rendered {} -> {}", source, location);
+    }
+
+    Ok(())
+}
+
+/* Failed checks of the Rekor record kept alongside the asset at `location`, against the log's key if pinned */
+fn 
+rekor_errors (location: &str, format: &str, asset: &[u8], log_key_pem: Option<&[u8]>) -> Result<Vec<String>, c2pa::Error> {
+    let record = match read_asset(&sidecar_location(location, ".rekor.json")) {
+        Ok(record) => record,
+        Err(_) => return Ok(vec!["rekor.recordMissing".to_owned()])
+    };
+
+    rekor::verify(format, asset, &serde_json::from_slice(&record)?, log_key_pem)
+}
+
+/* The state of the OpenTimestamps proof kept alongside the asset at `location` */
+fn 
+ots_status (location: &str, format: &str, asset: &[u8]) -> Result<OtsStatus, c2pa::Error> {
+    match read_asset(&sidecar_location(location, ".ots")) {
+        Ok(proof) => ots::verify(format, asset, &proof),
+        Err(_) => Ok(OtsStatus::Invalid("ots.proofMissing".to_owned()))
+    }
+}
+
+/**
+ * Writes a heatmap of where a tampered image differs from its manifest's
+ * thumbnail to `<location>.diff.png`, returning a note on what was found.
+ */
+#[cfg(feature = "visual-diff")]
+fn 
+visual_diff (location: &str, asset: &[u8], manifest_store: &c2pa::Reader) -> Result<String, c2pa::Error> {
+    let Some(diff) = c2pa_walkthrough::diff::visual_diff(asset, manifest_store)? else {
+        return Ok("no thumbnail to diff against".to_owned());
+    };
+
+    let diff_location = sidecar_location(location, ".diff.png");
+    write_asset(&diff_location, &diff.png)?;
+    Ok(match diff.region {
+        Some((x, y, width, height)) => format!(
+            "{:.0}% differs from the {}, within {}x{} at ({}, {}); see {}",
+            diff.changed * 100.0, diff.reference, width, height, x, y, diff_location
+        ),
+        None => format!("no visible difference from the {}; see {}", diff.reference, diff_location)
+    })
+}
+
+#[cfg(not(feature = "visual-diff"))]
+fn 
+visual_diff (_location: &str, _asset: &[u8], _manifest_store: &c2pa::Reader) -> Result<String, c2pa::Error> {
+    Err(c2pa::Error::BadParam("visual diffs need the visual-diff feature; rebuild with --features visual-diff".to_owned()))
+}
+
+/* What verifying one asset found */
+struct AssetCheck {
+    notes: Vec<String>,
+    errors: Vec<String>,
+    // Nothing failed, but something couldn't be confirmed, such as whether a certificate was revoked
+    warnings: Vec<String>,
+    details: Vec<String>,
+    // With --share-to, the Verify link for the uploaded asset
+    verify_link: Option<String>,
+    // Label, claim generator, signing certificate issuer and common name of the active manifest
+    manifest: Option<String>,
+    claim_generator: Option<String>,
+    signer: Option<String>,
+    signer_cn: Option<String>
+}
+
+/* How an asset fared, as the summary of a collection counts it */
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Outcome {
+    Valid,
+    Warnings,
+    Errors,
+    Unsigned
+}
+
+impl Outcome {
+    const ALL: [Outcome; 4] = [Outcome::Valid, Outcome::Warnings, Outcome::Errors, Outcome::Unsigned];
+
+    fn of(result: &Result<AssetCheck, c2pa::Error>) -> Outcome {
+        match result {
+            Ok(check) if !check.errors.is_empty() => Outcome::Errors,
+            Ok(check) if !check.warnings.is_empty() => Outcome::Warnings,
+            Ok(_) => Outcome::Valid,
+            Err(c2pa::Error::JumbfNotFound | c2pa::Error::ProvenanceMissing) => Outcome::Unsigned,
+            Err(_) => Outcome::Errors
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Outcome::Valid => "valid",
+            Outcome::Warnings => "warnings",
+            Outcome::Errors => "errors",
+            Outcome::Unsigned => "unsigned"
+        }
+    }
+}
+
+/* What --summary rolls up across every asset verified, each tally by name */
+#[derive(Default)]
+struct VerifySummary {
+    signed: usize,
+    total: usize,
+    claim_generators: BTreeMap<String, usize>,
+    signers: BTreeMap<String, usize>,
+    // Failure codes, from the SDK's validation and verify's own checks
+    status_codes: BTreeMap<String, usize>
+}
+
+impl VerifySummary {
+    fn add(&mut self, result: &Result<AssetCheck, c2pa::Error>) {
+        self.total += 1;
+        let check = match result {
+            Ok(check) => check,
+            Err(c2pa::Error::JumbfNotFound | c2pa::Error::ProvenanceMissing) => return,
+            Err(_) => {
+                // Whatever an unreadable asset carries can't be told, so it is only counted as such
+                *self.status_codes.entry("unreadable".to_owned()).or_default() += 1;
+                return;
+            }
+        };
+        self.signed += 1;
+        *self.claim_generators.entry(check.claim_generator.clone().unwrap_or_else(|| "unknown".to_owned())).or_default() += 1;
+        *self.signers.entry(check.signer.clone().unwrap_or_else(|| "unknown".to_owned())).or_default() += 1;
+        for code in &check.errors {
+            *self.status_codes.entry(code.clone()).or_default() += 1;
+        }
+    }
+
+    /* Prints each tally, most common first */
+    fn print(&self) {
+        println!();
+        println!("{} of {} assets signed", self.signed, self.total);
+        for (heading, tally) in [("claim generators", &self.claim_generators), ("signers", &self.signers), ("status codes", &self.status_codes)] {
+            if tally.is_empty() {
+                continue;
+            }
+            let mut entries: Vec<(&String, &usize)> = tally.iter().collect();
+            entries.sort_by(|(a_name, a_count), (b_name, b_count)| b_count.cmp(a_count).then(a_name.cmp(b_name)));
+            let width = entries.iter().map(|(name, _)| name.len()).max().unwrap_or_default();
+            println!("{}:", heading);
+            for (name, count) in entries {
+                println!("    {:<width$} {:>8}", name, count, width = width);
+            }
+        }
+    }
+}
+
+/* Validates the manifest store of the asset at `location`, with whatever else the flags ask to check */
+fn 
+verify_asset (matches: &ArgMatches, location: &str, pin_policy: Option<&PinPolicy>) -> Result<AssetCheck, c2pa::Error> {
+    let format = matches.get_one::<String>("format").cloned()
+        .or_else(|| format_from_location(location))
+        .unwrap_or_default();
+
+    let asset = read_asset(location)?;
+    let asset = ipfs::with_remote_manifest(&format, asset, matches.get_one::<String>("ipfs-gateway").unwrap(), &paths::manifest_cache_dir())?;
+    let manifest_store = load_manifest_store(&format, &asset)?;
+    let mut errors = validation_errors(&manifest_store);
+    // The SDK reports nothing wrong with a signature it never checked, such as an unfinalized prepare's placeholder
+    if errors.is_empty() && !is_valid(&manifest_store) {
+        errors.push("claimSignature.notValidated".to_owned());
+    }
+    let mut notes = vec![manifest_store.active_label().unwrap_or("no active manifest").to_owned()];
+    if signer_trusted(&manifest_store) {
+        notes.push("signer on the trust list".to_owned());
+    }
+    let mut warnings = Vec::new();
+    let mut chain_failure = None;
+    // Says which certificate the SDK's signingCredential.invalid is about, and why
+    let signature = manifest_store.active_manifest().and_then(|manifest| manifest.signature_info());
+    let profile_violations = match signature {
+        Some(signature) => cert_check::profile_violations(signature.cert_chain().as_bytes())?,
+        None => Vec::new()
+    };
+    if !profile_violations.is_empty() {
+        errors.push("signingCredential.profileViolation".to_owned());
+    }
+    if matches.get_flag("rekor") {
+        let log_key_pem = matches.get_one::<String>("rekor-public-key").map(std::fs::read).transpose()?;
+        errors.extend(rekor_errors(location, &format, &asset, log_key_pem.as_deref())?);
+    }
+    if let Some(cafile) = matches.get_one::<String>("cafile") {
+        let chain = cert_chain_pem(&format, &asset)?;
+        match trust::verify_chain(chain.as_bytes(), &std::fs::read(cafile)?)? {
+            None => notes.push(format!("chain verified against {}", cafile)),
+            Some(failure) => {
+                errors.push("cafile.chainInvalid".to_owned());
+                chain_failure = Some(format!(
+                    "certificate chain fails at depth {} ({}): {}",
+                    failure.depth, failure.subject, failure.reason
+                ));
+            }
+        }
+    }
+    if matches.get_flag("ots") {
+        match ots_status(location, &format, &asset)? {
+            OtsStatus::Anchored(height) => notes.push(format!("anchored in Bitcoin block {}", height)),
+            OtsStatus::Pending(calendars) => warnings.push(format!("timestamp pending at {}", calendars.join(", "))),
+            OtsStatus::Invalid(code) => errors.push(code)
+        }
+    }
+
+    let mut details: Vec<String> = chain_failure.into_iter().chain(profile_violations).collect();
+    if let (Some(policy), Some(manifest)) = (pin_policy, manifest_store.active_manifest()) {
+        let domain = matches.get_one::<String>("source-domain").cloned().or_else(|| location_domain(location));
+        match policy.check(domain.as_deref(), manifest)? {
+            PinCheck::Unpinned => (),
+            PinCheck::Matched => notes.push("signer pinned".to_owned()),
+            PinCheck::Mismatch { spki_sha256 } => {
+                errors.push("pinning.mismatch".to_owned());
+                details.push(format!("signing key (SPKI sha256 {}) is not one pinned for this asset", spki_sha256));
+            }
+        }
+    }
+    if matches.get_flag("crl") {
+        for manifest in revocation::check_store(&manifest_store, &paths::crl_cache_dir())? {
+            match manifest.status {
+                RevocationStatus::Good => details.push(format!("{}: not revoked", manifest.manifest_label)),
+                RevocationStatus::Revoked { subject, date } => {
+                    errors.push("signingCredential.revoked".to_owned());
+                    details.push(format!("{}: {} revoked on {}", manifest.manifest_label, subject, date));
+                },
+                RevocationStatus::Unknown(reason) => warnings.push(format!("{}: revocation unknown; {}", manifest.manifest_label, reason))
+            }
+        }
+    }
+    if matches.get_flag("details") {
+        for finding in tamper::findings(&manifest_store) {
+            match finding.explanation {
+                Some(explanation) => details.push(format!("{}: {} ({})", finding.code, finding.location, explanation)),
+                None => details.push(format!("{}: {}", finding.code, finding.location))
+            }
+        }
+    }
+    for (label, endorsement) in endorse::endorsements(&manifest_store) {
+        let by = [endorsement.endorser, endorsement.role].into_iter().flatten().collect::<Vec<String>>().join(", ");
+        let by = match by.is_empty() {
+            true => String::new(),
+            false => format!(" by {}", by)
+        };
+        let comment = endorsement.comment.map(|comment| format!(": {}", comment)).unwrap_or_default();
+        details.push(format!("{}: {}{} on {}{}", label, endorsement.verdict.name(), by, endorsement.when, comment));
+    }
+    for found in review::reviews(&manifest_store) {
+        match found.ingredient {
+            Some(title) => details.push(format!("{}: review of {}: {}", found.manifest, title, review::describe(&found.rating))),
+            None => details.push(format!("{}: review: {}", found.manifest, review::describe(&found.rating)))
+        }
+    }
+    let store_json = query::manifest_store_json(&manifest_store)?;
+    for url in asset_ref::published_urls(&store_json) {
+        if !matches.get_flag("check-published") {
+            details.push(format!("published at {}", url));
+            continue;
+        }
+        match asset_ref::matches_published(&url, &asset) {
+            Ok(true) => details.push(format!("published at {}, identical to this copy", url)),
+            Ok(false) => warnings.push(format!("differs from the copy published at {}", url)),
+            Err(e) => warnings.push(format!("published copy not checked: {}", e))
+        }
+    }
+    // A profile swapped since signing is named, where the hard binding only says bytes changed
+    if let Some(signed) = icc::signed_color_integrity(&store_json)? {
+        match icc::color_integrity(&format, &asset) {
+            Ok(live) => match icc::profile_change(&signed, &live) {
+                Some(change) => {
+                    errors.push("colorProfile.mismatch".to_owned());
+                    details.push(format!("ICC profile changed: {}", change));
+                },
+                None => notes.push("color profile intact".to_owned())
+            },
+            Err(e) => warnings.push(format!("color profile not checked: {}", e))
+        }
+    }
+    if matches.get_flag("visual-diff") && !errors.is_empty() {
+        details.push(format!("visual diff: {}", visual_diff(location, &asset, &manifest_store)?));
+    }
+
+    let verify_link = share_asset(matches, location, &asset)?;
+    let active = store_json["active_manifest"].as_str().unwrap_or_default();
+    let claim_generator = store_json["manifests"][active]["claim_generator"].as_str().map(str::to_owned);
+    let signer = signature.and_then(|signature| signature.issuer.clone());
+    let signer_cn = signature.and_then(|signature| trust::signer_common_name(signature.cert_chain().as_bytes()));
+
+    Ok(AssetCheck {
+        notes,
+        errors,
+        warnings,
+        details,
+        verify_link,
+        manifest: manifest_store.active_label().map(str::to_owned),
+        claim_generator,
+        signer,
+        signer_cn
+    })
+}
+
+/* Prints an asset's verdict, then any warnings and details beneath it */
+fn 
+print_asset_check (location: &str, result: &Result<AssetCheck, c2pa::Error>) {
+    match result {
+        Ok(check) => {
+            if check.errors.is_empty() {
+                println!("{}: {} ({})", location, color::green("valid"), check.notes.join("; "));
+            } else {
+                println!("{}: {}: {}", location, color::red("invalid"), check.errors.join(", "));
+            }
+            for warning in &check.warnings {
+                println!("    {}: {}", color::yellow("warning"), warning);
+            }
+            for detail in &check.details {
+                println!("    {}", detail);
+            }
+        },
+        Err(e) => println!("{}: {}: {}", location, color::yellow("error"), e)
+    }
+}
+
+/* An asset's verdict as one line of --jsonl */
+fn 
+asset_check_json (location: &str, result: &Result<AssetCheck, c2pa::Error>) -> serde_json::Value {
+    let outcome = Outcome::of(result).name();
+    match result {
+        Ok(check) => serde_json::json!({
+            "location": location,
+            "outcome": outcome,
+            "notes": check.notes,
+            "errors": check.errors,
+            "warnings": check.warnings,
+            "details": check.details,
+            "verify_link": check.verify_link
+        }),
+        Err(e) => serde_json::json!({"location": location, "outcome": outcome, "error": e.to_string()})
+    }
+}
+
+// Columns of --csv
+const CSV_HEADER: &str = "path,signed,signer_cn,claim_generator,active_manifest,verdict";
+
+/* A CSV field, quoted if it holds a comma, quote or line break */
+fn 
+csv_field (field: &str) -> Cow<'_, str> {
+    match field.contains([',', '"', '\n', '\r']) {
+        true => Cow::Owned(format!("\"{}\"", field.replace('"', "\"\""))),
+        false => Cow::Borrowed(field)
+    }
+}
+
+/* An asset's verdict as one row of --csv; `signed` is left empty when an unreadable asset can't say */
+fn 
+asset_check_csv (location: &str, result: &Result<AssetCheck, c2pa::Error>) -> String {
+    let outcome = Outcome::of(result);
+    let (signed, check) = match result {
+        Ok(check) => ("true", Some(check)),
+        Err(_) if outcome == Outcome::Unsigned => ("false", None),
+        Err(_) => ("", None)
+    };
+    let field = |value: Option<&String>| value.map(|value| csv_field(value).into_owned()).unwrap_or_default();
+
+    [
+        csv_field(location).into_owned(),
+        signed.to_owned(),
+        field(check.and_then(|check| check.signer_cn.as_ref())),
+        field(check.and_then(|check| check.claim_generator.as_ref())),
+        field(check.and_then(|check| check.manifest.as_ref())),
+        outcome.name().to_owned()
+    ].join(",")
+}
+
+/**
+ * Validates the manifest store of each asset, on up to --jobs threads,
+ * printing a verdict per asset in the order given and, for more than one,
+ * a count of each outcome. With --summary, then tallies the claim
+ * generators, signers and failure codes across them all. Exits nonzero if
+ * any asset is unsigned or fails validation.
+ */
+fn 
+verify (matches: &ArgMatches) -> Result<()> {
+    if !matches.get_flag("no-trust-list") {
+        let ttl = std::time::Duration::from_secs(matches.get_one::<u64>("trust-list-ttl").unwrap() * 60 * 60);
+        let anchor_urls: Vec<String> = match matches.get_many::<String>("trust-list-url") {
+            Some(urls) => urls.cloned().collect(),
+            None => trust_list::TRUST_ANCHOR_URLS.iter().map(|url| url.to_string()).collect()
+        };
+        let trust_list = trust_list::load(&paths::trust_list_cache_dir(), ttl, &anchor_urls)
+            .context("loading the C2PA trust list; pass --no-trust-list to verify without it")?;
+        for (url, e) in &trust_list.stale {
+            eprintln!("couldn't refresh {} ({}); using the cached copy", url, e);
+        }
+        trust_list::apply(&trust_list)?;
+    }
+    share_config(matches).context("--share-to")?;
+    let pin_policy = match matches.get_one::<String>("pin-policy") {
+        Some(path) => Some(PinPolicy::from_file(path).with_context(|| format!("reading pin policy {}", path))?),
+        None => None
+    };
+    let mut jsonl = match matches.get_one::<PathBuf>("jsonl") {
+        Some(path) => Some((std::io::BufWriter::new(std::fs::File::create(path).with_context(|| format!("creating {}", path.display()))?), path)),
+        None => None
+    };
+    let mut csv = match matches.get_one::<PathBuf>("csv") {
+        Some(path) => {
+            let mut out = std::io::BufWriter::new(std::fs::File::create(path).with_context(|| format!("creating {}", path.display()))?);
+            writeln!(out, "{}", CSV_HEADER).with_context(|| format!("writing {}", path.display()))?;
+            Some((out, path))
+        },
+        None => None
+    };
+
+    let locations = locations(matches, "paths")?;
+    let jobs = *matches.get_one::<u32>("jobs").unwrap() as usize;
+    let mut counts = [0; Outcome::ALL.len()];
+    let mut summary = VerifySummary::default();
+    let mut written = Ok(());
+    let mut csv_written = Ok(());
+    batch::in_parallel(&locations, jobs, |(location, _)| verify_asset(matches, location, pin_policy.as_ref()), |(location, _), result| {
+        print_asset_check(location, &result);
+        if let Ok(AssetCheck { verify_link: Some(link), .. }) = &result {
+            print_verify_link(matches, link);
+        }
+        counts[Outcome::of(&result) as usize] += 1;
+        summary.add(&result);
+        if let (Some((out, _)), Ok(())) = (&mut jsonl, &written) {
+            written = writeln!(out, "{}", asset_check_json(location, &result));
+        }
+        if let (Some((out, _)), Ok(())) = (&mut csv, &csv_written) {
+            csv_written = writeln!(out, "{}", asset_check_csv(location, &result));
+        }
+    });
+    if let Some((mut out, path)) = jsonl {
+        written.and_then(|_| out.flush()).with_context(|| format!("writing {}", path.display()))?;
+    }
+    if let Some((mut out, path)) = csv {
+        csv_written.and_then(|_| out.flush()).with_context(|| format!("writing {}", path.display()))?;
+    }
+
+    if locations.len() > 1 {
+        println!();
+        for outcome in Outcome::ALL {
+            println!("{:<10} {:>8}", outcome.name(), counts[outcome as usize]);
+        }
+        println!("{:<10} {:>8}", "total", locations.len());
+    }
+    if matches.get_flag("summary") {
+        summary.print();
+    }
+
+    if counts[Outcome::Errors as usize] + counts[Outcome::Unsigned as usize] > 0 {
+        return Err(Failed.into());
+    }
+
+    Ok(())
+}
+
+/**
+ * Indexes every signed asset under a directory, on up to --jobs threads,
+ * writing the index to --output, or index.json in the directory. Unsigned
+ * files are left out; unreadable ones are reported and left out too.
+ */
+fn 
+index (matches: &ArgMatches) -> Result<()> {
+    let dir = matches.get_one::<PathBuf>("dir").unwrap();
+    let root = std::fs::canonicalize(dir).with_context(|| format!("reading directory {}", dir.display()))?;
+    let output = matches.get_one::<PathBuf>("output").cloned().unwrap_or_else(|| dir.join("index.json"));
+    let options = walk_options(matches);
+    let walked = batch::walk(dir, &filters(matches)?, options).with_context(|| format!("reading directory {}", dir.display()))?;
+    for (path, reason) in walked.skipped.iter().filter(|(_, reason)| !matches!(reason, Skip::Filtered)) {
+        eprintln!("{}: {}: {}", dir.join(path).display(), color::yellow("skipped"), reason);
+    }
+
+    let jobs = *matches.get_one::<u32>("jobs").unwrap() as usize;
+    let mut index = ProvenanceIndex::new(&root.to_string_lossy());
+    let mut unsigned = 0;
+    batch::in_parallel(&walked.files, jobs, |file| {
+        let location = dir.join(file).to_string_lossy().into_owned();
+        let format = format_from_location(&location).unwrap_or_default();
+        read_file(dir.join(file)).and_then(|asset| index_asset(&format, &asset))
+    }, |file, indexed| match indexed {
+        Ok(asset) => index.add(&batch::slash_path(file), asset),
+        Err(c2pa::Error::JumbfNotFound | c2pa::Error::ProvenanceMissing | c2pa::Error::UnsupportedType) => unsigned += 1,
+        Err(e) => eprintln!("{}: {}: {}", dir.join(file).display(), color::yellow("error"), e)
+    });
+
+    write_file_atomically(&output, &index.to_json()?).with_context(|| format!("writing {}", output.display()))?;
+    println!(
+        "indexed {} signed asset{} of {} files in {} ({} unsigned)",
+        index.assets.len(), if index.assets.len() == 1 { "" } else { "s" }, walked.files.len(), output.display(), unsigned
+    );
+
+    Ok(())
+}
+
+/**
+ * Finds assets in an index without walking its tree: by a file, whose
+ * content hash is looked up, or by a content hash, instance ID or manifest
+ * label. Fails if nothing matches.
+ */
+fn 
+lookup (matches: &ArgMatches) -> Result<()> {
+    let index_path = matches.get_one::<PathBuf>("index").unwrap();
+    let index = ProvenanceIndex::from_json(&read_file(index_path)?).with_context(|| format!("reading index {}", index_path.display()))?;
+    let key = matches.get_one::<String>("key").unwrap();
+    let key = match Path::new(key).is_file() {
+        true => content_hash(&read_file(key).with_context(|| format!("reading {}", key))?),
+        false => key.clone()
+    };
+
+    let paths = index.lookup(&key);
+    if paths.is_empty() {
+        bail!("nothing in {} matches {}", index_path.display(), key);
+    }
+    let root = Path::new(&index.root);
+    if matches.get_flag("json") {
+        let found: Vec<serde_json::Value> = paths.iter()
+            .map(|path| {
+                let mut asset = serde_json::to_value(&index.assets[*path])?;
+                asset["path"] = serde_json::Value::String(root.join(path).to_string_lossy().into_owned());
+                Ok(asset)
+            })
+            .collect::<Result<_>>()?;
+        println!("{}", serde_json::to_string_pretty(&found)?);
+        return Ok(());
+    }
+
+    for path in paths {
+        let asset = &index.assets[path];
+        println!("{}", root.join(path).display());
+        println!("    manifest:        {}", asset.manifest.as_deref().unwrap_or("none"));
+        println!("    signer:          {}", asset.signer_cn.as_deref().or(asset.signer.as_deref()).unwrap_or("unknown"));
+        println!("    claim generator: {}", asset.claim_generator.as_deref().unwrap_or("unknown"));
+    }
+
+    Ok(())
+}
+
+/**
+ * Finds the duplicates and derivatives among assets, on up to --jobs
+ * threads: files which are byte-identical, which share content under
+ * different manifests, and which name others as ingredients. Unreadable
+ * assets are reported and left out.
+ */
+fn 
+relate_assets (matches: &ArgMatches) -> Result<()> {
+    let locations = locations(matches, "paths")?;
+    let jobs = *matches.get_one::<u32>("jobs").unwrap() as usize;
+    let mut assets = Vec::new();
+    batch::in_parallel(&locations, jobs, |(location, _)| {
+        let format = matches.get_one::<String>("format").cloned()
+            .or_else(|| format_from_location(location))
+            .unwrap_or_default();
+        read_asset(location).and_then(|asset| related_asset(location, &format, &asset))
+    }, |(location, _), related| match related {
+        Ok(related) => assets.push(related),
+        Err(e) => eprintln!("{}: {}: {}", location, color::yellow("error"), e)
+    });
+    let relations = relate(&assets);
+
+    if matches.get_flag("json") {
+        println!("{}", serde_json::to_string_pretty(&relations)?);
+        return Ok(());
+    }
+    if relations.is_empty() {
+        println!("no duplicates or derivatives among {} assets", assets.len());
+        return Ok(());
+    }
+
+    let sections = [("identical", &relations.identical), ("same content, different manifests", &relations.same_content)];
+    for (heading, groups) in sections.into_iter().filter(|(_, groups)| !groups.is_empty()) {
+        println!("{}:", heading);
+        for group in groups {
+            println!("    {}", group.join("\n    = "));
+        }
+    }
+    if !relations.ingredients.is_empty() {
+        println!("ingredients:");
+        for link in &relations.ingredients {
+            println!("    {} -> {} ({})", link.ingredient, link.asset, link.relationship);
+        }
+    }
+
+    Ok(())
+}
+
+/**
+ * The manifest store as `read` prints it: the SDK's JSON, with each
+ * manifest's hash algorithm, claim version and decoded timestamp added,
+ * and its claimed action times set against its trusted ones.
+ */
+fn 
+detailed_store_json (format: &str, asset: &[u8], manifest_store: &c2pa::Reader, location: &str) -> Result<serde_json::Value> {
+    let mut store_json = query::manifest_store_json(manifest_store)?;
+
+    // Hash assertions aren't in the manifest JSON, so what the hard bindings were hashed with is added to it
+    for binding in hash_bindings(format, asset).with_context(|| format!("reading the hash bindings of {}", location))? {
+        if let Some(manifest) = store_json["manifests"].get_mut(&binding.manifest_label) {
+            manifest["hash_alg"] = serde_json::Value::String(binding.alg);
+        }
+    }
+    // Nor does it say which generation of the spec each claim was written to
+    for claim in claim_versions(format, asset).with_context(|| format!("reading the claim versions of {}", location))? {
+        if let Some(manifest) = store_json["manifests"].get_mut(&claim.manifest_label) {
+            manifest["claim_version"] = serde_json::Value::from(claim.version);
+        }
+    }
+    // And it gives a timestamp's time, but not its TSA or whether it outlasts the signing certificate
+    for timestamp in timestamps(format, asset).with_context(|| format!("reading the timestamps of {}", location))? {
+        if let Some(manifest) = store_json["manifests"].get_mut(&timestamp.manifest_label) {
+            manifest["timestamp"] = serde_json::to_value(&timestamp)?;
+        }
+    }
+    // Action times are only claimed; a verified timestamp bounds when they can have been
+    for times in manifest_times(&store_json) {
+        if let Some(manifest) = store_json["manifests"].get_mut(&times.manifest_label) {
+            manifest["times"] = serde_json::to_value(&times)?;
+        }
+    }
+
+    Ok(store_json)
+}
+
+/**
+ * Prints an asset's manifest store as JSON, or with --label and --select
+ * just the matching assertions or fields, one JSON value per line. With
+ * --output-format exiftool, prints it flattened as exiftool -json would,
+ * with --output-format ids, just the manifest and ingredient identifiers,
+ * and with --canonical-json, normalized for comparison against a golden
+ * file.
+ */
+fn 
+read (matches: &ArgMatches) -> Result<()> {
+    let location = matches.get_one::<String>("path").unwrap();
+    let format = matches.get_one::<String>("format").cloned()
+        .or_else(|| format_from_location(location))
+        .unwrap_or_default();
+
+    let asset = read_asset(location).with_context(|| format!("reading {}", location))?;
+    let asset = ipfs::with_remote_manifest(&format, asset, matches.get_one::<String>("ipfs-gateway").unwrap(), &paths::manifest_cache_dir())
+        .with_context(|| format!("fetching the remote manifest of {}", location))?;
+    let manifest_store = load_manifest_store(&format, &asset).with_context(|| format!("reading the manifest store of {}", location))?;
+    // The Reader's Debug output is the SDK's detailed report, with claims, signatures and assertion stores as it parses them
+    let mut store_json = match matches.get_flag("detailed") {
+        true => serde_json::from_str(&format!("{:?}", manifest_store))?,
+        false => detailed_store_json(&format, &asset, &manifest_store, location)?
+    };
+    if !matches.get_flag("detailed") && !matches.get_flag("no-cloud-data") {
+        let mismatched = cloud_data::verify_payloads(&mut store_json);
+        if mismatched > 0 {
+            let (payloads, verb) = if mismatched == 1 { ("payload", "doesn't") } else { ("payloads", "don't") };
+            eprintln!("{}: {} cloud-data {} of {} {} match what was signed", color::red("warning"), mismatched, payloads, location, verb);
+        }
+    }
+    if !matches.get_flag("detailed") {
+        for times in manifest_times(&store_json).iter().filter(|times| !times.outside_window.is_empty()) {
+            eprintln!("{}: {} claims actions at {}, outside the window its timestamps allow", color::yellow("warning"), times.manifest_label, times.outside_window.join(", "));
+        }
+    }
+
+    let mut values = match matches.get_one::<String>("label") {
+        Some(label_glob) => query::assertions_matching(&store_json, None, label_glob),
+        None if matches.get_one::<String>("output-format").unwrap() == "exiftool" => vec![exiftool::exiftool_json(&store_json, location)],
+        None if matches.get_one::<String>("output-format").unwrap() == "ids" => vec![serde_json::to_value(ids::manifest_ids(&store_json))?],
+        None => vec![store_json]
+    };
+    if let Some(selector) = matches.get_one::<String>("select") {
+        values = values.iter()
+            .map(|value| query::select(value, selector))
+            .collect::<Result<Vec<_>, _>>()?
+            .concat();
+    }
+    if matches.get_flag("canonical-json") {
+        values = values.iter().map(canonical_json).collect();
+    }
+
+    for value in values {
+        if matches.get_flag("compact") {
+            println!("{}", color::highlight_labels(&value.to_string()));
+        } else {
+            println!("{}", color::highlight_labels(&serde_json::to_string_pretty(&value)?));
+        }
+    }
+
+    Ok(())
+}
+
+/**
+ * Writes the decoded content of one assertion to a file (or stdout) as
+ * JSON; CBOR assertions are converted.
+ */
+fn 
+extract_assertion (matches: &ArgMatches) -> Result<()> {
+    let location = matches.get_one::<String>("path").unwrap();
+    let format = matches.get_one::<String>("format").cloned()
+        .or_else(|| format_from_location(location))
+        .unwrap_or_default();
+    let label = matches.get_one::<String>("label").unwrap();
+
+    let asset = read_asset(location).with_context(|| format!("reading {}", location))?;
+    let manifest_store = load_manifest_store(&format, &asset).with_context(|| format!("reading the manifest store of {}", location))?;
+    let store_json = query::manifest_store_json(&manifest_store)?;
+
+    // Without --manifest, look in the active manifest first and then in all of them
+    let assertions = match matches.get_one::<String>("manifest") {
+        Some(manifest_label) => query::assertions_matching(&store_json, Some(manifest_label), label),
+        None => {
+            let active = query::assertions_matching(&store_json, manifest_store.active_label(), label);
+            if active.is_empty() { query::assertions_matching(&store_json, None, label) } else { active }
+        }
+    };
+
+    let assertion = match assertions.as_slice() {
+        [assertion] => assertion,
+        [] => bail!("{} has no assertion labelled {}", location, label),
+        _ => bail!("{} assertions match {}; pick a manifest with --manifest", assertions.len(), label)
+    };
+    let content = serde_json::to_string_pretty(&assertion["data"])?;
+
+    match matches.get_one::<String>("output") {
+        Some(output) => write_asset(output, content.as_bytes()).with_context(|| format!("writing {}", output))?,
+        None => println!("{}", content)
+    }
+
+    Ok(())
+}
+
+/**
+ * Lists every box of an asset's manifest store with its type, offset,
+ * length, UUID and label, or with --extract writes the raw bytes of one of
+ * them to a file (or stdout).
+ */
+fn 
+boxes (matches: &ArgMatches) -> Result<()> {
+    let location = matches.get_one::<String>("path").unwrap();
+    let format = matches.get_one::<String>("format").cloned()
+        .or_else(|| format_from_location(location))
+        .unwrap_or_default();
+
+    let asset = read_asset(location).with_context(|| format!("reading {}", location))?;
+    let store = c2pa::jumbf_io::load_jumbf_from_memory(&format, &asset)
+        .with_context(|| format!("reading the manifest store of {}", location))?;
+
+    if let Some(selector) = matches.get_one::<String>("extract") {
+        let jumbf_box = jumbf::extract(&store, selector).with_context(|| format!("extracting from the manifest store of {}", location))?;
+        match matches.get_one::<String>("output") {
+            Some(output) => write_asset(output, jumbf_box).with_context(|| format!("writing {}", output))?,
+            None => std::io::stdout().write_all(jumbf_box).context("writing the box to stdout")?
+        }
+        return Ok(());
+    }
+
+    let entries = jumbf::list(&store).with_context(|| format!("parsing the manifest store of {}", location))?;
+    if matches.get_flag("json") {
+        println!("{}", serde_json::to_string_pretty(&entries)?);
+        return Ok(());
+    }
+
+    println!("{:>5}  {:>8}  {:>8}  {:<12}  {:<36}  label", "index", "offset", "length", "type", "uuid");
+    for entry in &entries {
+        let line = format!(
+            "{:>5}  {:>8}  {:>8}  {:<12}  {:<36}  {}",
+            entry.index, entry.offset, entry.len,
+            format!("{}{}", "  ".repeat(entry.depth), entry.kind),
+            entry.uuid.as_deref().unwrap_or("-"),
+            entry.label.as_deref().unwrap_or("")
         );
+        println!("{}", line.trim_end());
+    }
+
+    Ok(())
+}
+
+/* Writes the certificate chain the active manifest was signed with as PEM */
+fn 
+export_certs (matches: &ArgMatches) -> Result<()> {
+    let location = matches.get_one::<String>("path").unwrap();
+    let format = matches.get_one::<String>("format").cloned()
+        .or_else(|| format_from_location(location))
+        .unwrap_or_default();
+
+    let asset = read_asset(location).with_context(|| format!("reading {}", location))?;
+    let asset = ipfs::with_remote_manifest(&format, asset, matches.get_one::<String>("ipfs-gateway").unwrap(), &paths::manifest_cache_dir())
+        .with_context(|| format!("fetching the remote manifest of {}", location))?;
+    let chain = cert_chain_pem(&format, &asset).with_context(|| format!("reading the signing certificates of {}", location))?;
+
+    match matches.get_one::<String>("output") {
+        Some(output) => write_asset(output, chain.as_bytes()).with_context(|| format!("writing {}", output))?,
+        None => print!("{}", chain)
+    }
+
+    Ok(())
+}
+
+/* Adds the auxiliary data given with --aux to a manifest definition, embedded or with --link-aux linked */
+fn 
+add_aux (matches: &ArgMatches, definition: &mut serde_json::Value) -> Result<()> {
+    let aux: Vec<(String, String)> = matches.get_many::<(String, String)>("aux").into_iter().flatten().cloned().collect();
+    aux_data::add_aux_data(definition, &aux, matches.get_flag("link-aux")).context("--aux")?;
+
+    Ok(())
+}
+
+/**
+ * Adds an asset reference assertion recording the --published-url
+ * locations, if any, of the asset to be written to `output`, to a manifest
+ * definition.
+ */
+fn 
+add_published_urls (matches: &ArgMatches, output: &str, definition: &mut serde_json::Value) -> Result<()> {
+    let urls: Vec<String> = matches.get_many::<String>("published-url").unwrap_or_default()
+        .map(|template| asset_ref::published_url(template, location_file_name(output)))
+        .collect();
+    if !urls.is_empty() {
+        append_assertions(definition, vec![asset_ref::asset_ref_assertion(&urls)?]).context("--published-url")?;
+    }
+
+    Ok(())
+}
+
+// Flags setting fields of the standard metadata assertion
+const STANDARD_METADATA_ARGS: [&str; 6] = ["standard-metadata", "description", "credit", "rights", "usage-terms", "date-created"];
+
+/**
+ * Sets the fields of the standard metadata assertion given with
+ * --standard-metadata and the flags for each field, which override the
+ * file's, in a manifest definition.
+ */
+fn 
+add_standard_metadata (matches: &ArgMatches, definition: &mut serde_json::Value) -> Result<()> {
+    let from_file = match matches.get_one::<String>("standard-metadata") {
+        Some(path) => {
+            let json = std::fs::read_to_string(path).with_context(|| format!("reading {}", path))?;
+            StandardMetadata::from_json(&json).with_context(|| format!("reading {}", path))?
+        },
+        None => StandardMetadata::default()
+    };
+    let flag = |id: &str| matches.get_one::<String>(id).cloned();
+    let metadata = from_file.overridden_by(StandardMetadata {
+        description: flag("description"),
+        credit: flag("credit"),
+        rights: flag("rights"),
+        usage_terms: flag("usage-terms"),
+        date_created: flag("date-created")
+    });
+    metadata.apply(definition);
+
+    Ok(())
+}
+
+/* The --review ratings given */
+fn 
+reviews (matches: &ArgMatches) -> Vec<ReviewRating> {
+    matches.get_many::<ReviewRating>("review").into_iter().flatten().cloned().collect()
+}
+
+/* Adds a review assertion rating the asset as a whole with the --review ratings, if any, to a manifest definition */
+fn 
+add_review (matches: &ArgMatches, definition: &mut serde_json::Value) -> Result<()> {
+    let reviews = reviews(matches);
+    if !reviews.is_empty() {
+        append_assertions(definition, vec![review::review_assertion(&reviews)?]).context("--review")?;
+    }
+
+    Ok(())
+}
+
+/**
+ * Uploads the payloads given with --cloud-data to --cloud-data-to, adding a
+ * cloud-data assertion for each to a manifest definition.
+ */
+fn 
+upload_cloud_data (matches: &ArgMatches, definition: &mut serde_json::Value) -> Result<()> {
+    let payloads: Vec<(String, String)> = matches.get_many::<(String, String)>("cloud-data").into_iter().flatten().cloned().collect();
+    if payloads.is_empty() {
+        return Ok(());
+    }
+    let share = ShareConfig::new(matches.get_one::<String>("cloud-data-to").unwrap(), matches.get_one::<String>("cloud-data-url").map(String::as_str))
+        .context("--cloud-data-to")?;
+    cloud_data::add_cloud_data(definition, &payloads, &share).context("--cloud-data")?;
+
+    Ok(())
+}
+
+/**
+ * Lists the auxiliary data in an asset's active manifest, checking each
+ * piece against the size and hash its claim records, and with --extract
+ * writes the pieces to a directory as <kind>.<ext>. Fails if any piece is
+ * missing or has been altered.
+ */
+fn 
+aux (matches: &ArgMatches) -> Result<()> {
+    let location = matches.get_one::<String>("path").unwrap();
+    let format = matches.get_one::<String>("format").cloned()
+        .or_else(|| format_from_location(location))
+        .unwrap_or_default();
+
+    let asset = read_asset(location).with_context(|| format!("reading {}", location))?;
+    let manifest_store = load_manifest_store(&format, &asset).with_context(|| format!("reading the manifest store of {}", location))?;
+    let found = aux_data::aux_data(&query::manifest_store_json(&manifest_store)?).with_context(|| format!("reading the auxiliary data of {}", location))?;
+    if found.is_empty() {
+        println!("{}: no auxiliary data", location);
+        return Ok(());
+    }
+
+    let output = matches.get_one::<String>("extract").map(|output| output.trim_end_matches('/'));
+    if let Some(output) = output.filter(|output| !output.contains("://")) {
+        std::fs::create_dir_all(output).with_context(|| format!("creating {}", output))?;
+    }
+
+    let mut failed = 0;
+    for piece in &found {
+        let stored = match &piece.aux.url {
+            Some(url) => format!("linked to {}", url),
+            None => "embedded".to_owned()
+        };
+        let data = match aux_data::load(&piece.aux, location) {
+            Ok(data) => data,
+            Err(e) => {
+                failed += 1;
+                println!("{}: {}, {}: {}", piece.label, stored, color::red("failed"), e);
+                continue;
+            }
+        };
+        println!("{}: {} {} bytes, {}, {}", piece.label, piece.aux.format, data.len(), stored, color::green("matches its hash"));
+
+        if let Some(output) = output {
+            // Labels are unique within a manifest, where kinds may repeat
+            let name = aux_data::aux_name(&piece.label).unwrap_or(&piece.label);
+            let piece_location = format!("{}/{}.{}", output, name, thumbnail_extension(&piece.aux.format));
+            write_asset(&piece_location, &data).with_context(|| format!("writing {}", piece_location))?;
+        }
+    }
+
+    if failed > 0 {
+        bail!("{} of {} pieces of auxiliary data in {} don't match what the claim records", failed, found.len(), location);
+    }
+
+    Ok(())
+}
+
+/**
+ * Writes every claim and ingredient thumbnail in an asset's manifest store
+ * to a directory, with a thumbnails.json index describing each file.
+ */
+fn 
+extract_thumbnails (matches: &ArgMatches) -> Result<()> {
+    let location = matches.get_one::<String>("path").unwrap();
+    let output = matches.get_one::<String>("output").unwrap().trim_end_matches('/');
+    let format = matches.get_one::<String>("format").cloned()
+        .or_else(|| format_from_location(location))
+        .unwrap_or_default();
+
+    let asset = read_asset(location).with_context(|| format!("reading {}", location))?;
+    let manifest_store = load_manifest_store(&format, &asset).with_context(|| format!("reading the manifest store of {}", location))?;
+
+    if !output.contains("://") {
+        std::fs::create_dir_all(output).with_context(|| format!("creating {}", output))?;
+    }
+
+    let mut index = Vec::new();
+    for thumbnail in thumbnails(&manifest_store) {
+        let prefix = thumbnail.manifest_label.replace([':', '/'], "_");
+        let extension = thumbnail_extension(&thumbnail.format);
+        let file_name = match &thumbnail.ingredient {
+            None => format!("{}.claim.{}", prefix, extension),
+            Some((i, _)) => format!("{}.ingredient-{}.{}", prefix, i, extension)
+        };
+
+        let thumbnail_location = format!("{}/{}", output, file_name);
+        write_asset(&thumbnail_location, &thumbnail.bytes).with_context(|| format!("writing {}", thumbnail_location))?;
+        index.push(serde_json::json!({
+            "manifest": thumbnail.manifest_label,
+            "ingredient": thumbnail.ingredient.as_ref().map(|(i, title)| serde_json::json!({"index": i, "title": title})),
+            "format": thumbnail.format,
+            "file": file_name
+        }));
+        println!("{}", file_name);
+    }
+
+    let index_location = format!("{}/thumbnails.json", output);
+    write_asset(&index_location, &serde_json::to_vec_pretty(&index)?).with_context(|| format!("writing {}", index_location))?;
+
+    Ok(())
+}
+
+/**
+ * Format of a local media file: --format if given, else the file's
+ * extension. Files without an extension need --format.
+ */
+fn 
+local_format (matches: &ArgMatches, path: &Path) -> Result<String> {
+    matches.get_one::<String>("format").cloned()
+        .or_else(|| path.extension().and_then(OsStr::to_str).filter(|ext| !ext.is_empty()).map(str::to_ascii_lowercase))
+        .with_context(|| format!("cannot determine the format of {}; pass --format", path.display()))
+}
+
+/* Where `--add` writes a media file with a new manifest: test_file.jpg becomes test_file_c2pa.jpg */
+fn 
+with_manifest_suffix (path: &Path) -> PathBuf {
+    let mut file_name = path.file_stem().unwrap_or_else(|| OsStr::new("untitled")).to_owned();
+    file_name.push("_c2pa");
+    if let Some(extension) = path.extension() {
+        file_name.push(".");
+        file_name.push(extension);
+    }
+
+    path.with_file_name(file_name)
+}
+
+/**
+ * Where an edit of a local file is written: -o if given, numbered, replaced
+ * or skipped (None) if it exists, else the file itself with --in-place,
+ * first copied to <file>.bak with --backup. Either way the output is staged
+ * and renamed into place, never written over, and the file and its output
+ * stay locked for as long as the returned locks are held.
+ */
+fn 
+edit_output (matches: &ArgMatches, source: &Path) -> Result<Option<(PathBuf, Vec<FileLock>)>> {
+    let in_place = matches.get_one::<PathBuf>("output").is_none();
+    let output = match matches.get_one::<PathBuf>("output") {
+        Some(output) => resolve_output(matches, output),
+        None => Some(source.to_owned())
+    };
+    let Some(output) = output else {
+        return Ok(None);
+    };
+    let Some(locks) = lock_asset(Some(source), Some(&output))? else {
+        return Ok(None);
+    };
+    if in_place {
+        back_up(matches, source)?;
+    }
+
+    Ok(Some((output, locks)))
+}
+
+/**
+ * Advisory locks on a local source and output, held while an asset is
+ * signed or edited so another run can't replace it meanwhile, or None,
+ * reporting the asset skipped, when another run holds either. A source
+ * written in place is only locked as the output.
+ */
+fn 
+lock_asset (source: Option<&Path>, output: Option<&Path>) -> Result<Option<Vec<FileLock>>> {
+    let mut locks = Vec::new();
+    if let Some(output) = output {
+        match storage::lock_output(output).with_context(|| format!("locking {}", output.display()))? {
+            Some(lock) => locks.push(lock),
+            None => {
+                println!("{}: {}: locked by another run", output.display(), color::yellow("skipped"));
+                return Ok(None);
+            }
+        }
+    }
+
+    let in_place = |source: &Path| output.is_some_and(|output| {
+        let canonical = std::fs::canonicalize(source).ok();
+        canonical.is_some() && canonical == std::fs::canonicalize(output).ok()
+    });
+    if let Some(source) = source.filter(|source| !in_place(source)) {
+        match storage::lock_input(source).with_context(|| format!("locking {}", source.display()))? {
+            Some(lock) => locks.push(lock),
+            None => {
+                println!("{}: {}: locked by another run", source.display(), color::yellow("skipped"));
+                return Ok(None);
+            }
+        }
+    }
+
+    Ok(Some(locks))
+}
+
+/* A local output resolved by --overwrite or --skip, reporting when it is skipped */
+fn 
+resolve_output (matches: &ArgMatches, path: &Path) -> Option<PathBuf> {
+    let resolved = resolve_collision(path, collision(matches));
+    if resolved.is_none() {
+        println!("{}: {}: already exists", path.display(), color::yellow("skipped"));
+    }
+
+    resolved
+}
+
+/* With --backup, keeps a copy of a file about to be replaced in place */
+fn 
+back_up (matches: &ArgMatches, path: &Path) -> Result<()> {
+    if matches.get_flag("backup") {
+        let backup = back_up_file(path).with_context(|| format!("backing up {}", path.display()))?;
+        println!("kept the original {} as {}", path.display(), backup.display());
+    }
+
+    Ok(())
+}
+
+/* A custom action parameter given as key=value; values are JSON where they parse as JSON, else strings */
+fn 
+action_parameter (param: &str) -> Result<(String, serde_json::Value)> {
+    let (key, value) = param.split_once('=').ok_or_else(|| anyhow!("parameter '{}' should be key=value", param))?;
+    let value = serde_json::from_str(value).unwrap_or_else(|_| serde_json::Value::String(value.to_owned()));
+
+    Ok((key.to_owned(), value))
+}
+
+/**
+ * Records edits to a local media file: one derived manifest per action, in
+ * the order given, or a single manifest holding all of them with --combine.
+ * Standard and custom actions may be mixed; custom actions carry the
+ * description and parameters given.
+ */
+fn 
+edit (matches: &ArgMatches) -> Result<()> {
+    let source = matches.get_one::<PathBuf>("path").unwrap();
+    let format = local_format(matches, source)?;
+    let description = matches.get_one::<String>("description");
+    let parameters: Vec<(String, serde_json::Value)> = matches.get_many::<String>("param").unwrap_or_default()
+        .map(|param| action_parameter(param))
+        .collect::<Result<_>>()?;
+    let Some((output, _locks)) = edit_output(matches, source)? else {
+        return Ok(());
+    };
+    let output = &output;
+
+    // Keep the order actions were given in across both flags
+    let mut actions: Vec<(usize, EditAction)> = Vec::new();
+    if let (Some(indices), Some(names)) = (matches.indices_of("action"), matches.get_many::<String>("action")) {
+        for (i, arg) in indices.zip(names) {
+            let (name, when) = action_time(arg).with_context(|| format!("--action {}", arg))?;
+            let mut action = EditAction::new(edit_action(name)?);
+            action.when = when.map(|when| when.to_rfc3339());
+            actions.push((i, action));
+        }
+    }
+    if let (Some(indices), Some(labels)) = (matches.indices_of("custom-action"), matches.get_many::<String>("custom-action")) {
+        for (i, arg) in indices.zip(labels) {
+            let (label, when) = action_time(arg).with_context(|| format!("--custom-action {}", arg))?;
+            let mut action = EditAction::new(&custom_action(label)?);
+            action.description = description.cloned();
+            action.parameters = parameters.clone();
+            action.when = when.map(|when| when.to_rfc3339());
+            actions.push((i, action));
+        }
+    }
+    actions.sort_by_key(|(i, _)| *i);
+    let actions: Vec<EditAction> = actions.into_iter().map(|(_, action)| action).collect();
+    warn_about_time("--when", clock::pinned_time().as_ref(), source);
+    for action in &actions {
+        let when = action.when.as_deref().and_then(|when| clock::parse_time(when).ok());
+        warn_about_time(&action.label, when.as_ref(), source);
+    }
 
-    // Example Exif data to be embedded into the `Manifest`    
-    let exif = Exif::from_json_str(
-        r#"{
-        "@context" : {
-          "exif": "http://ns.adobe.com/exif/1.0/"
-        },
-        "exif:GPSLatitude": "48,15.7068N",
-        "exif:GPSLongitude": "16,15.9996W",
-        "exif:GPSTimeStamp": "2023-08-23T19:12:45Z"
-        }"#,
-    ).expect("exif");
-
-    // This is a verified credential string; see https://www.w3.org/TR/vc-data-model
-    let vc = r#"{
-        "@context": [
-            "https://www.w3.org/2018/credentials/v1",
-            "http://schema.org"
-        ],
-        "type": [
-            "VerifiableCredential",
-            "NPPACredential"
-        ],
-        "issuer": "https://nppa.org/",
-        "credentialSubject": {
-            "id": "did:nppa:eb1bb9934d9896a374c384521410c7f14",
-            "name": "Bob Ross",
-            "memberOf": "https://nppa.org/"
+    let signer = signer_config(matches)?.create_signer().context("loading the signer")?;
+    // Each manifest is made on the asset in memory, and only the last version written
+    let mut pipeline = EditPipeline::open(source, output, &format).with_context(|| format!("reading {}", source.display()))?;
+    pipeline.review(&reviews(matches));
+    if let Some(instance_id) = matches.get_one::<String>("parent-instance-id") {
+        pipeline.set_parent_instance_id(instance_id);
+    }
+    if matches.get_flag("combine") {
+        pipeline.record(&actions, &*signer).with_context(|| format!("recording edits to {}", source.display()))?;
+    } else {
+        for action in actions.iter() {
+            pipeline.record(std::slice::from_ref(action), &*signer)
+                .with_context(|| format!("recording {} on {}", action.label, source.display()))?;
+        }
+    }
+    pipeline.finish(output).with_context(|| format!("writing {}", output.display()))?;
+
+    let labels: Vec<&str> = actions.iter().map(|action| action.label.as_str()).collect();
+    println!("{}: {}", output.display(), labels.join(", "));
+    Ok(())
+}
+
+/**
+ * Warns about a time given for an action on `source` which looks wrong:
+ * one in the future, or before the file was last modified. Sources which
+ * aren't local files are only checked for future times.
+ */
+fn 
+warn_about_time (what: &str, when: Option<&DateTime<FixedOffset>>, source: &Path) {
+    let Some(when) = when else {
+        return;
+    };
+    let modified = std::fs::metadata(source).and_then(|metadata| metadata.modified()).ok();
+    for warning in clock::time_warnings(when, modified) {
+        eprintln!("{}: {} time {}", color::yellow("warning"), what, warning);
+    }
+}
+
+/**
+ * Endorses a local asset someone else signed, in a manifest signed with
+ * the endorser's own credentials which takes the asset as its parent.
+ */
+fn 
+endorse_asset (matches: &ArgMatches) -> Result<()> {
+    let source = matches.get_one::<PathBuf>("path").unwrap();
+    let format = local_format(matches, source)?;
+    let endorser = Endorser {
+        verdict: Verdict::from_name(matches.get_one::<String>("verdict").unwrap())?,
+        name: matches.get_one::<String>("by").cloned(),
+        role: matches.get_one::<String>("role").cloned(),
+        comment: matches.get_one::<String>("comment").cloned(),
+        reviews: reviews(matches),
+        parent_instance_id: matches.get_one::<String>("parent-instance-id").cloned()
+    };
+    let Some((output, _locks)) = edit_output(matches, source)? else {
+        return Ok(());
+    };
+    warn_about_time("--when", clock::pinned_time().as_ref(), source);
+
+    let signer = signer_config(matches)?.create_signer().context("loading the signer")?;
+    let asset = read_file(source).with_context(|| format!("reading {}", source.display()))?;
+    let endorsed = endorse::endorse(source, &format, &asset, &endorser, &*signer)
+        .with_context(|| format!("endorsing {}", source.display()))?;
+    write_file_atomically(&output, &endorsed).with_context(|| format!("writing {}", output.display()))?;
+
+    println!("{}: {}", output.display(), endorser.verdict.name());
+    Ok(())
+}
+
+/**
+ * Carries out transforms on a local image, in the order given, and signs
+ * the result with a manifest recording them against the original.
+ */
+#[cfg(feature = "image-edits")]
+fn 
+transform_image (matches: &ArgMatches, transforms: &[&str]) -> Result<()> {
+    use c2pa_walkthrough::transform::{apply_transforms, Transform};
+
+    let source = matches.get_one::<PathBuf>("path").unwrap();
+    let format = local_format(matches, source)?;
+    let transforms: Vec<Transform> = transforms.iter()
+        .map(|op| op.parse())
+        .collect::<Result<_, _>>()?;
+    let Some((output, _locks)) = edit_output(matches, source)? else {
+        return Ok(());
+    };
+    let output = &output;
+
+    let signer = signer_config(matches)?.create_signer().context("loading the signer")?;
+    let actions = apply_transforms(source, output, &format, &transforms, &*signer)
+        .with_context(|| format!("editing {}", source.display()))?;
+
+    let labels: Vec<&str> = actions.iter().map(|action| action.label.as_str()).collect();
+    println!("{}: {}", output.display(), labels.join(", "));
+    Ok(())
+}
+
+#[cfg(not(feature = "image-edits"))]
+fn 
+transform_image (_matches: &ArgMatches, _transforms: &[&str]) -> Result<()> {
+    bail!("image edits need the image-edits feature; rebuild with --features image-edits");
+}
+
+fn 
+apply (matches: &ArgMatches) -> Result<()> {
+    let ops: Vec<&str> = matches.get_many::<String>("op").unwrap().map(String::as_str).collect();
+    transform_image(matches, &ops)
+}
+
+fn 
+crop (matches: &ArgMatches) -> Result<()> {
+    transform_image(matches, &[&format!("crop={}", matches.get_one::<String>("rect").unwrap())])
+}
+
+fn 
+rotate (matches: &ArgMatches) -> Result<()> {
+    transform_image(matches, &[&format!("rotate={}", matches.get_one::<String>("degrees").unwrap())])
+}
+
+fn 
+resize (matches: &ArgMatches) -> Result<()> {
+    let width = matches.get_one::<String>("width").map_or("", String::as_str);
+    let height = matches.get_one::<String>("height").map_or("", String::as_str);
+    transform_image(matches, &[&format!("resize={}x{}", width, height)])
+}
+
+/**
+ * Re-encodes a local video with ffmpeg and signs it with a manifest
+ * recording the transcode, and the codecs used, against the original.
+ */
+fn 
+transcode_video (matches: &ArgMatches) -> Result<()> {
+    let source = matches.get_one::<PathBuf>("path").unwrap();
+    let format = local_format(matches, source)?;
+    let preset: Preset = matches.get_one::<String>("preset").unwrap().parse()?;
+    let ffmpeg = matches.get_one::<String>("ffmpeg").unwrap();
+    let output = matches.get_one::<PathBuf>("output").unwrap();
+    if output.extension().and_then(OsStr::to_str).is_some_and(|ext| !ext.eq_ignore_ascii_case(preset.format())) {
+        bail!("the {} preset writes {}; give {} a .{} extension", preset.name(), preset.format(), output.display(), preset.format());
+    }
+    let Some(output) = resolve_output(matches, output) else {
+        return Ok(());
+    };
+
+    let signer = signer_config(matches)?.create_signer().context("loading the signer")?;
+    let action = transcode(ffmpeg, source, &output, &format, preset, &*signer)
+        .with_context(|| format!("transcoding {}", source.display()))?;
+
+    println!("{}: {} ({} preset)", output.display(), action.label, preset.name());
+    Ok(())
+}
+
+/**
+ * Signs fragmented video with a Merkle-tree hash: an init segment and the
+ * fragments given, or each stream of --master once ffmpeg has cut it into
+ * fragments of --fragment-duration. Each init segment is signed with the
+ * fragments which follow it, written under their own names to --output,
+ * and read back to check every fragment validates.
+ */
+fn 
+sign_fragmented_video (matches: &ArgMatches) -> Result<()> {
+    let output = matches.get_one::<PathBuf>("output").unwrap();
+    let manifest_json = match matches.get_one::<String>("manifest") {
+        Some(path) => std::fs::read_to_string(path).with_context(|| format!("reading manifest definition {}", path))?,
+        None => default_manifest_definition()
+    };
+
+    // ffmpeg's fragments are kept beside the master's signed ones only while signing
+    let cut_dir = std::env::temp_dir().join(format!("c2pa-walkthrough-fragments-{}", std::process::id()));
+    let representations = match matches.get_one::<PathBuf>("master") {
+        Some(master) => {
+            let seconds = *matches.get_one::<f64>("fragment-duration").unwrap();
+            let ffmpeg = matches.get_one::<String>("ffmpeg").unwrap();
+            let cut = fragmented::fragment(ffmpeg, master, &cut_dir, seconds);
+            if cut.is_err() {
+                let _ = std::fs::remove_dir_all(&cut_dir);
+            }
+            cut.with_context(|| format!("cutting {} into fragments", master.display()))?
         },
-        "proof": {
-            "type": "RsaSignature2018",
-            "created": "2021-06-18T21:19:10Z",
-            "proofPurpose": "assertionMethod",
-            "verificationMethod":
-            "did:nppa:eb1bb9934d9896a374c384521410c7f14#_Qq0UL2Fq651Q0Fjd6TvnYE-faHiOpRlPVQcY_-tA4A",
-            "jws": "eyJhbGciOiJQUzI1NiIsImI2NCI6ZmFsc2UsImNyaXQiOlsiYjY0Il19DJBMvvFAIC00nSGB6Tn0XKbbF9XrsaJZREWvR2aONYTQQxnyXirtXnlewJMBBn2h9hfcGZrvnC1b6PgWmukzFJ1IiH1dWgnDIS81BH-IxXnPkbuYDeySorc4QU9MJxdVkY5EL4HYbcIfwKj6X4LBQ2_ZHZIu1jdqLcRZqHcsDF5KKylKc1THn5VRWy5WhYg_gBnyWny8E6Qkrze53MR7OuAmmNJ1m1nN8SxDrG6a08L78J0-Fbas5OjAQz3c17GY8mVuDPOBIOVjMEghBlgl3nOi1ysxbRGhHLEK4s0KKbeRogZdgt1DkQxDFxxn41QWDw_mmMCjs9qxg0zcZzqEJw"
+        None => {
+            let init = matches.get_one::<PathBuf>("init").context("give an init segment and its fragments, or --master")?;
+            let fragments: Vec<PathBuf> = matches.get_many::<PathBuf>("fragments").unwrap_or_default().cloned().collect();
+            vec![Fragmented { init: init.clone(), fragments }]
         }
-    }"#;
+    };
 
-    // Sets some basics of the manifest
-    manifest.set_title("title");
-    manifest.set_format("image/jpeg");
+    let signer = signer_config(matches)?.create_signer().context("loading the signer");
+    let signed = signer.and_then(|signer| {
+        for representation in &representations {
+            let manifest_store = fragmented::sign_fragmented(representation, output, &manifest_json, &*signer)
+                .with_context(|| format!("signing {} and its fragments", representation.init.display()))?;
+            let errors = validation_errors(&manifest_store);
+            if !errors.is_empty() {
+                bail!("{} was signed but doesn't validate: {}", representation.init.display(), errors.join(", "));
+            }
+            println!(
+                "signed {} and {} fragment{} -> {}",
+                representation.init.display(), representation.fragments.len(), if representation.fragments.len() == 1 { "" } else { "s" }, output.display()
+            );
+        }
+        Ok(())
+    });
+    if matches.contains_id("master") {
+        let _ = std::fs::remove_dir_all(&cut_dir);
+    }
 
-    // Adds assertions about the content to the manifest
-    manifest.add_assertion(&creative_work)?;
-    manifest.add_assertion(&created)?;
-    manifest.add_assertion(&exif)?;
+    signed
+}
 
-    // Add custom data until this label
-    manifest.add_labeled_assertion("org.contentauth.test", &MediaData::new(128, 256, "descriptive string".to_string()))?;
+/* Opens an identity log with the recipient's private key and prints the authors it records */
+fn 
+identities (matches: &ArgMatches) -> Result<()> {
+    let log = matches.get_one::<String>("log").unwrap();
+    let key = matches.get_one::<String>("key").unwrap();
+    let key_pem = match key.as_str() {
+        "-" => SecretBytes::read_stdin().context("reading the private key from stdin")?,
+        key => SecretBytes::read(key).with_context(|| format!("reading {}", key))?
+    };
 
-    // For some reason, this causes manifest embedding to fail. AFAICT this is a valid formatting for verified credentials, pulled
-    // from SDK test code. 
-    // manifest.add_verifiable_credential(&vc.to_string())?;
+    let records = read_identities(Path::new(log), &key_pem).with_context(|| format!("opening identity log {}", log))?;
+    println!("{}", serde_json::to_string_pretty(&records)?);
+    Ok(())
+}
 
-    let source = PathBuf::from(src_path);
-    let dest = PathBuf::from(dest_path);
+/**
+ * Compares the metadata assertions of an asset's manifest with the Exif,
+ * IPTC and XMP it carries now, printing each field that diverges. Changed
+ * and removed fields fail the check; fields only the asset has fail it with
+ * --strict.
+ */
+fn 
+check_metadata (matches: &ArgMatches) -> Result<()> {
+    let location = matches.get_one::<String>("path").unwrap();
+    let format = matches.get_one::<String>("format").cloned()
+        .or_else(|| format_from_location(location))
+        .unwrap_or_default();
 
-    // Create a ps256 signer using certs and key files
-    let signcert_path = "../c2pa-rs/sdk/tests/fixtures/certs/ps256.pub";
-    let pkey_path = "../c2pa-rs/sdk/tests/fixtures/certs/ps256.pem";
-    let signer = create_signer::from_files(signcert_path, pkey_path, SigningAlg::Ps256, None);
+    let asset = read_asset(location).with_context(|| format!("reading {}", location))?;
+    let manifest_store = load_manifest_store(&format, &asset).with_context(|| format!("reading the manifest store of {}", location))?;
+    let store_json = query::manifest_store_json(&manifest_store)?;
+    let manifest_label = match matches.get_one::<String>("manifest") {
+        Some(label) => label.as_str(),
+        None => manifest_store.active_label().with_context(|| format!("{} has no active manifest", location))?
+    };
+    let manifest = store_json["manifests"].get(manifest_label).with_context(|| format!("{} has no manifest {}", location, manifest_label))?;
+    let check = metadata::check::check_metadata(manifest, &format, &asset).with_context(|| format!("reading the metadata of {}", location))?;
 
-    // Signs and embeds the manifest into the destination file
-    manifest.embed(&source, &dest, &*signer.unwrap())?;
+    let strict = matches.get_flag("strict");
+    let failed = check.divergences.iter().filter(|divergence| strict || divergence.kind != DivergenceKind::Unsigned).count();
+    if matches.get_flag("json") {
+        println!("{}", serde_json::to_string_pretty(&check)?);
+    } else {
+        let show = |value: &Option<serde_json::Value>| value.as_ref().map(serde_json::Value::to_string).unwrap_or_default();
+        for divergence in &check.divergences {
+            let (assertion, field) = (&divergence.assertion, &divergence.field);
+            match divergence.kind {
+                DivergenceKind::Changed => println!("{} {}: {}: signed {}, now {}", assertion, field, color::red("changed"), show(&divergence.signed), show(&divergence.live)),
+                DivergenceKind::Removed => println!("{} {}: {}: signed {}", assertion, field, color::red("removed"), show(&divergence.signed)),
+                DivergenceKind::Unsigned if strict => println!("{} {}: {}: now {}", assertion, field, color::yellow("unsigned"), show(&divergence.live)),
+                DivergenceKind::Unsigned => ()
+            }
+        }
+        // Fields only the asset has are usually just fields nobody chose to sign, so they're only counted
+        for label in check.compared.iter().filter(|_| !strict) {
+            let unsigned = check.divergences.iter().filter(|divergence| divergence.kind == DivergenceKind::Unsigned && divergence.assertion == *label).count();
+            if unsigned > 0 {
+                println!("{}: {} field{} of the asset {} unsigned; --strict lists them", label, unsigned, if unsigned == 1 { "" } else { "s" }, if unsigned == 1 { "is" } else { "are" });
+            }
+        }
+        for label in &check.uncompared {
+            println!("{}: {}: the asset carries no metadata block to compare it with", label, color::yellow("not compared"));
+        }
+    }
 
+    if failed > 0 {
+        bail!("{} metadata field{} of {} diverge from manifest {}", failed, if failed == 1 { "" } else { "s" }, location, manifest_label);
+    }
+    if !matches.get_flag("json") {
+        match check.compared.len() {
+            0 => println!("{}: no signed metadata to compare", location),
+            n => println!("{}: {} ({} assertion{} compared)", location, color::green("metadata consistent"), n, if n == 1 { "" } else { "s" })
+        }
+    }
     Ok(())
 }
 
+/* Compares the decoded pixels of two images, failing if they differ */
+#[cfg(feature = "pixel-integrity")]
 fn 
-edit_media_with_action (src_path: &String, dest_path: &String, action: &str) -> Result<(), c2pa::Error> {
-    // Manifests cannot be edited. To modify the contents of the manifest store, pull in earlier versions of the content
-    // and its manifest as an ingredient.
-    let parent = Ingredient::from_file(src_path)?;
+pixel_compare (matches: &ArgMatches) -> Result<()> {
+    let locations = [matches.get_one::<String>("a").unwrap(), matches.get_one::<String>("b").unwrap()];
+    let mut decoded = Vec::with_capacity(2);
+    for location in locations {
+        let format = matches.get_one::<String>("format").cloned()
+            .or_else(|| format_from_location(location))
+            .with_context(|| format!("cannot determine the format of {}; pass --format", location))?;
+        let asset = read_asset(location).with_context(|| format!("reading {}", location))?;
+        decoded.push(c2pa_walkthrough::pixels::decode(&format, &asset).with_context(|| format!("decoding {}", location))?);
+    }
+    let comparison = c2pa_walkthrough::pixels::compare(&decoded[0], &decoded[1]);
 
-    let mut manifest = Manifest::new("mikes-c2pa-test-code/0.1".to_owned());
+    if matches.get_flag("json") {
+        println!("{}", serde_json::to_string_pretty(&comparison)?);
+    }
+    if !comparison.identical() {
+        bail!("the pixels of {} and {} differ: {}", locations[0], locations[1], comparison.summary());
+    }
+    if !matches.get_flag("json") {
+        println!("{} and {}: {} ({})", locations[0], locations[1], color::green("pixels identical"), comparison.summary());
+    }
+    Ok(())
+}
 
-    let now: DateTime<Utc> = SystemTime::now().into();
-    let now_string = now.to_rfc3339();
+#[cfg(not(feature = "pixel-integrity"))]
+fn 
+pixel_compare (_matches: &ArgMatches) -> Result<()> {
+    bail!("pixel comparison is not compiled in; rebuild with --features pixel-integrity");
+}
 
-    // also add an action that we opened the file
-    let actions = Actions::new()
-        .add_action(
-            Action::new(c2pa_action::OPENED)
-                .set_parameter("identifier", parent.instance_id().to_owned())
-                .expect("set identifier")
-                .set_reason("editing")
-                .set_software_agent("mikes-c2pa-test-code/0.1")
-                .set_when(now_string.clone())
-        )
-        .add_action(
-            Action::new(action)
-                .set_parameter("identifier", parent.instance_id().to_owned())
-                .expect("set identifier")
-                .set_reason("editing")
-                .set_source_type("https://cv.iptc.org/newscodes/digitalsourcetype/minorHumanEdits".to_owned())
-                .set_software_agent("mikes-c2pa-test-code/0.1")
-                .set_when(now_string.clone())
-        );
+/**
+ * Renders a provenance report on an asset's manifest store from a
+ * Handlebars template, or as plain text without one, to a file or stdout.
+ */
+fn 
+report (matches: &ArgMatches) -> Result<()> {
+    let location = matches.get_one::<String>("path").unwrap();
+    let format = matches.get_one::<String>("format").cloned()
+        .or_else(|| format_from_location(location))
+        .unwrap_or_default();
+    let (template, html) = match matches.get_one::<PathBuf>("template") {
+        Some(path) => (
+            std::fs::read_to_string(path).with_context(|| format!("reading report template {}", path.display()))?,
+            report::escapes_html(path)
+        ),
+        None => (report::DEFAULT_TEMPLATE.to_owned(), false)
+    };
+
+    let asset = read_asset(location).with_context(|| format!("reading {}", location))?;
+    let asset = ipfs::with_remote_manifest(&format, asset, matches.get_one::<String>("ipfs-gateway").unwrap(), &paths::manifest_cache_dir())
+        .with_context(|| format!("fetching the remote manifest of {}", location))?;
+    let manifest_store = load_manifest_store(&format, &asset).with_context(|| format!("reading the manifest store of {}", location))?;
+    let store_json = detailed_store_json(&format, &asset, &manifest_store, location)?;
+    let context = report::report_context(location, &format, &manifest_store, store_json);
+    let rendered = report::render(&template, html, &context).with_context(|| format!("reporting on {}", location))?;
+
+    match matches.get_one::<String>("output") {
+        Some(output) => write_file_atomically(Path::new(output), rendered.as_bytes()).with_context(|| format!("writing {}", output))?,
+        None => print!("{}", rendered)
+    }
+
+    Ok(())
+}
+
+/* Prints every action in an asset's manifest store as one chronological history */
+fn 
+timeline (matches: &ArgMatches) -> Result<()> {
+    let location = matches.get_one::<String>("path").unwrap();
+    let format = matches.get_one::<String>("format").cloned()
+        .or_else(|| format_from_location(location))
+        .unwrap_or_default();
 
-    manifest.set_parent(parent)?;
-    manifest.add_assertion(&actions)?;
+    let asset = read_asset(location).with_context(|| format!("reading {}", location))?;
+    let asset = ipfs::with_remote_manifest(&format, asset, matches.get_one::<String>("ipfs-gateway").unwrap(), &paths::manifest_cache_dir())
+        .with_context(|| format!("fetching the remote manifest of {}", location))?;
+    let manifest_store = load_manifest_store(&format, &asset).with_context(|| format!("reading the manifest store of {}", location))?;
+    let store_json = query::manifest_store_json(&manifest_store)?;
+    let entries = timeline::timeline(&store_json);
 
-    // Create a ps256 signer using certs and key files
-    let signcert_path = "../c2pa-rs/sdk/tests/fixtures/certs/ps256.pub";
-    let pkey_path = "../c2pa-rs/sdk/tests/fixtures/certs/ps256.pem";
-    let signer = create_signer::from_files(signcert_path, pkey_path, SigningAlg::Ps256, None);
+    if matches.get_flag("json") {
+        println!("{}", serde_json::to_string_pretty(&entries)?);
+        return Ok(());
+    }
 
-    manifest.embed(&src_path, &dest_path, &*signer.unwrap())?;
+    for entry in entries {
+        println!(
+            "{}  {}  by {}  with {}  ({})",
+            entry.when.as_deref().unwrap_or("(no time)"),
+            entry.action,
+            entry.who.as_deref().unwrap_or("unknown"),
+            entry.tool.as_deref().unwrap_or("unknown"),
+            entry.manifest
+        );
+    }
 
     Ok(())
 }
 
 fn 
-read_manifest (path: &String) -> Result<(), c2pa::Error> {
+worker (matches: &ArgMatches) -> Result<()> {
+    open_audit_log(matches)?;
+    configure_webhook(matches);
+    spawn_metrics_server(matches)?;
+    let signer_config = signer_config(matches)?;
+    let queue = QueueConfig::from_url(
+        matches.get_one::<String>("queue").unwrap(),
+        matches.get_one::<String>("topic").unwrap(),
+        matches.get_one::<String>("group").unwrap()
+    ).context("--queue should be an amqp://, amqps:// or kafka:// URL")?;
+
+    // Jobs without their own manifest definition are signed with this one
+    let default_manifest = match matches.get_one::<String>("manifest") {
+        Some(path) => std::fs::read_to_string(path).with_context(|| format!("reading manifest definition {}", path))?,
+        None => default_manifest_definition()
+    };
+
+    let local_dirs: Vec<PathBuf> = matches.get_many::<PathBuf>("allow-local-dir").unwrap_or_default().cloned().collect();
+    let policy = JobPolicy::new(&local_dirs, *matches.get_one::<u64>("max-asset-size").unwrap()).context("--allow-local-dir")?;
+
+    c2pa_walkthrough::worker::run(&queue, &default_manifest, &signer_config, &policy).context("worker failed")
+}
+
+/* Lists the signing operations recorded in an audit log, or shows one in full */
+#[cfg(feature = "audit")]
+fn 
+log (matches: &ArgMatches) -> Result<()> {
+    use c2pa_walkthrough::audit;
 
-    let manifest_store = ManifestStore::from_file(path)?;
+    let path = matches.get_one::<String>("audit-log").map(PathBuf::from).unwrap_or_else(paths::audit_log_path);
 
-    match manifest_store.validation_status() {
-        Some(statuses) if !statuses.is_empty() => {
-            println!("Loading manifest resulted in validation errors:");
-            for status in statuses {
-                println!("Validation status code: {}", status.code());
+    match matches.subcommand() {
+        Some(("show", show_matches)) => {
+            let id = *show_matches.get_one::<i64>("id").unwrap();
+            match audit::show(&path, id).with_context(|| format!("reading audit log {}", path.display()))? {
+                Some(entry) => println!("{}", serde_json::to_string_pretty(&entry)?),
+                None => bail!("no audit log entry {}", id)
+            }
+        },
+        _ => {
+            for entry in audit::list(&path).with_context(|| format!("reading audit log {}", path.display()))? {
+                println!("{}\t{}\t{}\t{}\t{}", entry.id, entry.signed_at, entry.operator,
+                    entry.manifest_label.as_deref().unwrap_or("-"), entry.output_sha256);
             }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(not(feature = "audit"))]
+fn 
+log (_matches: &ArgMatches) -> Result<()> {
+    bail!("audit logging is not compiled in; rebuild with --features audit");
+}
+
+/* Prints where the tool keeps its configuration, caches and records, and whether each exists yet */
+fn 
+show_paths (matches: &ArgMatches) -> Result<()> {
+    let locations = paths::locations();
+
+    if matches.get_flag("json") {
+        let json: serde_json::Map<String, serde_json::Value> = locations.iter()
+            .map(|location| (location.name.to_owned(), serde_json::json!(location.path)))
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&json)?);
+        return Ok(());
+    }
 
-            panic!("data validation errors");
+    let width = locations.iter().map(|location| location.name.len()).max().unwrap_or_default();
+    for location in &locations {
+        let missing = match location.path.exists() {
+            true => "",
+            false => " (not created yet)"
+        };
+        println!("{:width$}  {}{}", location.name, location.path.display(), missing, width = width);
+    }
+
+    Ok(())
+}
+
+/* Lists the signer profiles, or points the default profile at another */
+fn 
+signer_profiles (matches: &ArgMatches) -> Result<()> {
+    let path = profiles::profiles_path();
+
+    match matches.subcommand() {
+        Some(("rotate", rotate_matches)) => {
+            let name = rotate_matches.get_one::<String>("name").unwrap();
+            // A profile whose key or certificate doesn't load is never made the default
+            let signer_profiles = SignerProfiles::from_file(&path).with_context(|| format!("reading signer profiles {}", path.display()))?;
+            signer_profiles.get(name)?.create_signer().with_context(|| format!("loading the signer of profile {}", name))?;
+
+            let previous = profiles::rotate(&path, name).with_context(|| format!("updating signer profiles {}", path.display()))?;
+            println!("default signer profile: {} -> {}", previous.as_deref().unwrap_or("none"), name);
         },
-        _ => ()
+        _ => {
+            let signer_profiles = SignerProfiles::from_file(&path).with_context(|| format!("reading signer profiles {}", path.display()))?;
+            for (name, config) in &signer_profiles.profiles {
+                let marker = match signer_profiles.default.as_deref() == Some(name) {
+                    true => "*",
+                    false => " "
+                };
+                let expiry = profiles::not_after(config).map_or_else(|e| format!("unreadable certificate: {}", e), |date| format!("expires {}", date));
+                println!("{} {}\t{}\t{}\t{}", marker, name, config.alg, expiry, config.signcert_path);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/**
+ * Writes malformed variants of each seed's manifest store to a directory,
+ * with a corpus.json index, and reads each back as `read_all` does; fails
+ * if reading any of them panicked.
+ */
+fn 
+gen_corpus (matches: &ArgMatches) -> Result<()> {
+    let dir = matches.get_one::<PathBuf>("output").unwrap();
+    std::fs::create_dir_all(dir).with_context(|| format!("creating {}", dir.display()))?;
+
+    let mut index = Vec::new();
+    let mut panicked = 0;
+    for seed in matches.get_many::<String>("seeds").unwrap() {
+        let format = format_from_location(seed).with_context(|| format!("cannot determine the format of {}", seed))?;
+        let asset = read_asset(seed).with_context(|| format!("reading {}", seed))?;
+        let mutants = corpus::mutate(&format, &asset).with_context(|| format!("reading the manifest store of {}", seed))?;
+
+        let name = location_file_name(seed);
+        let (stem, extension) = name.rsplit_once('.').unwrap_or((name, &format));
+        for mutant in mutants {
+            let file = format!("{}-{}-{}.{}", stem, mutant.mutation, mutant.index, extension);
+            write_file_atomically(&dir.join(&file), &mutant.asset).with_context(|| format!("writing {}", file))?;
+
+            // Panics are counted rather than ending the run, so every mutant is tried
+            let read = std::panic::catch_unwind(|| corpus::read_all(&format, &mutant.asset));
+            if read.is_err() {
+                println!("{}: {} reading it ({} of box {})", file, color::red("panicked"), mutant.mutation, mutant.index);
+                panicked += 1;
+            }
+            index.push(serde_json::json!({
+                "file": file,
+                "seed": seed,
+                "mutation": mutant.mutation,
+                "index": mutant.index,
+                "path": mutant.path,
+                "panicked": read.is_err()
+            }));
+        }
+    }
+    write_file_atomically(&dir.join("corpus.json"), &serde_json::to_vec_pretty(&index)?).context("writing corpus.json")?;
+
+    println!("wrote {} malformed assets and corpus.json to {}", index.len(), dir.display());
+    if panicked > 0 {
+        bail!("reading {} of {} malformed assets panicked", panicked, index.len());
+    }
+
+    Ok(())
+}
+
+/* Writes the test fixtures to a directory, printing what validating each reports */
+fn 
+gen_fixtures (matches: &ArgMatches) -> Result<()> {
+    let dir = matches.get_one::<PathBuf>("output").unwrap();
+    let index = fixtures::generate(dir, matches.get_one::<String>("remote-url").unwrap())
+        .with_context(|| format!("generating fixtures in {}", dir.display()))?;
+
+    for fixture in &index.fixtures {
+        match fixture.validation_errors.is_empty() {
+            true => println!("{}: {} ({})", fixture.file, color::green("valid"), fixture.case),
+            false => println!("{}: {}: {} ({})", fixture.file, color::red("invalid"), fixture.validation_errors.join(", "), fixture.case)
+        }
+    }
+    println!("wrote {} fixtures, ca.pem and fixtures.json to {}", index.fixtures.len(), dir.display());
+
+    Ok(())
+}
+
+/**
+ * Checks a signer's certificate chain against the C2PA certificate profile
+ * and for expiry, and its key against its certificate, printing each check.
+ * Exits nonzero if any fails, or with --strict if any warns.
+ */
+fn 
+cert_check (matches: &ArgMatches) -> Result<()> {
+    let config = with_key_flags(chosen_signer(matches)?, matches)?;
+    let window = *matches.get_one::<u32>("cert-expiry-window").unwrap();
+    let report = cert_check::check_signer(&config, window).with_context(|| format!("checking the signer {}", config.signcert_path))?;
+
+    if matches.get_flag("json") {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        println!("{} ({})", report.signcert_path, report.alg);
+        let mut certificate = None;
+        for finding in &report.findings {
+            if finding.certificate != certificate {
+                certificate = finding.certificate.clone();
+                println!("{}", certificate.as_deref().unwrap_or("signer"));
+            }
+            println!("  {}  {:<26}{}", paint_status(finding.status), finding.check, finding.detail);
+        }
+        println!("overall: {}", paint_status(report.status).trim_end());
+    }
+
+    let failed = match matches.get_flag("strict") {
+        true => report.status != cert_check::Status::Ok,
+        false => report.status == cert_check::Status::Fail
+    };
+    if failed {
+        return Err(Failed.into());
     }
 
-    println!("manifest store: {}", manifest_store);
+    Ok(())
+}
 
-    // active manifest is the most recently added manifest in the store.
-    let manifest = manifest_store.get_active().unwrap();
-    println!("active manifest: {}", manifest);
+/* A check's status, padded to line up and colored like verify verdicts */
+fn 
+paint_status (status: cert_check::Status) -> String {
+    let name = format!("{:<4}", status.name());
+    match status {
+        cert_check::Status::Ok => color::green(&name),
+        cert_check::Status::Warn => color::yellow(&name),
+        cert_check::Status::Fail => color::red(&name)
+    }
+}
 
-    println!("all manifests:\n----------------------");
-    for iter in manifest_store.manifests().iter() {
-        println!("manifest {},{}", iter.0, iter.1);
+/**
+ * Embeds a manifest whose signature is left as a placeholder, writing the
+ * prepared asset, the payload to sign beside it as <DEST>.tbs and what
+ * `finalize` needs as <DEST>.prepared.json.
+ */
+fn 
+prepare (matches: &ArgMatches) -> Result<()> {
+    let source = matches.get_one::<String>("source").unwrap();
+    let output = matches.get_one::<String>("output").unwrap();
+    let format = match matches.get_one::<String>("format") {
+        Some(format) => format.clone(),
+        None => format_from_location(source).with_context(|| format!("cannot determine the format of {}; pass --format", source))?
+    };
+    let mut manifest_json = match matches.get_one::<String>("manifest") {
+        Some(path) => std::fs::read_to_string(path).with_context(|| format!("reading manifest definition {}", path))?,
+        None => default_manifest_definition()
+    };
+    if ["assertions-dir", "aux", "review", "cloud-data", "published-url"].iter().chain(&STANDARD_METADATA_ARGS).any(|id| matches.contains_id(id)) {
+        let mut definition: serde_json::Value = serde_json::from_str(&manifest_json).context("parsing the manifest definition")?;
+        if let Some(dir) = matches.get_one::<PathBuf>("assertions-dir") {
+            add_assertions_dir(&mut definition, dir).with_context(|| format!("reading assertions from {}", dir.display()))?;
+        }
+        add_aux(matches, &mut definition)?;
+        add_review(matches, &mut definition)?;
+        add_standard_metadata(matches, &mut definition)?;
+        upload_cloud_data(matches, &mut definition)?;
+        add_published_urls(matches, output, &mut definition)?;
+        manifest_json = definition.to_string();
     }
 
+    let asset = read_asset(source).with_context(|| format!("reading {}", source))?;
+    let (prepared_asset, prepared) = two_phase::prepare(&format, &asset, &manifest_json, &signer_config(matches)?)
+        .with_context(|| format!("preparing {}", source))?;
+
+    let payload_location = sidecar_location(output, ".tbs");
+    let state_location = sidecar_location(output, ".prepared.json");
+    write_asset(output, &prepared_asset).with_context(|| format!("writing {}", output))?;
+    write_asset(&payload_location, &prepared.payload()?).with_context(|| format!("writing {}", payload_location))?;
+    write_asset(&state_location, serde_json::to_string_pretty(&prepared)?.as_bytes()).with_context(|| format!("writing {}", state_location))?;
+
+    println!("{}: prepared; sign {} with the {} key", output, payload_location, prepared.alg);
+    Ok(())
+}
+
+/* Writes the signature the key holders produced into a prepared asset */
+fn 
+finalize (matches: &ArgMatches) -> Result<()> {
+    let source = matches.get_one::<String>("prepared").unwrap();
+    let output = matches.get_one::<String>("output").unwrap();
+    let signature_path = matches.get_one::<PathBuf>("signature").unwrap();
+    let state_location = matches.get_one::<String>("state").cloned().unwrap_or_else(|| sidecar_location(source, ".prepared.json"));
+
+    let prepared: Prepared = serde_json::from_slice(&read_asset(&state_location).with_context(|| format!("reading {}", state_location))?)
+        .with_context(|| format!("parsing {}", state_location))?;
+    let signature = std::fs::read(signature_path).with_context(|| format!("reading signature {}", signature_path.display()))?;
+    let prepared_asset = read_asset(source).with_context(|| format!("reading {}", source))?;
+
+    let finished = two_phase::finalize(&prepared_asset, &prepared, &signature).with_context(|| format!("finalizing {}", source))?;
+    write_asset(output, &finished).with_context(|| format!("writing {}", output))?;
+
+    println!("{}: signed", output);
     Ok(())
 }
 
+#[cfg(unix)]
+fn 
+daemon (matches: &ArgMatches) -> Result<()> {
+    open_audit_log(matches)?;
+    configure_webhook(matches);
+    spawn_metrics_server(matches)?;
+    let signer_config = signer_config(matches)?;
+    let socket_path = matches.get_one::<PathBuf>("socket").cloned().unwrap_or_else(paths::daemon_socket_path);
+
+    println!("listening on {}", socket_path.display());
+    c2pa_walkthrough::daemon::run(&socket_path, &signer_config).context("daemon failed")
+}
+
+#[cfg(not(unix))]
+fn 
+daemon (_matches: &ArgMatches) -> Result<()> {
+    bail!("daemon mode requires Unix domain sockets");
+}
+
 fn 
-main() {
+run () -> Result<()> {
 
     // By default, just run with --add test_file.jpg
     //   This adds a manifest to an output file test_file_c2pa.jpg
     // Read the contents of a file with a c2pa manifest via --read filename_c2pa.jpg
 
+    // Settings from a .env file are loaded first, so flags backed by environment variables see them
+    if let Some(path) = dotenv::env_file() {
+        dotenv::load(&path).with_context(|| format!("loading {}", path.display()))?;
+    }
+
+    let args: Vec<OsString> = std::env::args_os().collect();
+    let Some(c2patool_args) = c2patool::invocation(&args) else {
+        return run_command(args);
+    };
+
+    // A c2patool command line becomes one or more of this tool's own
+    let translation = c2patool::translate(&c2patool::command().get_matches_from(c2patool_args)).context("c2patool compatibility mode")?;
+    for (var, value) in &translation.env {
+        std::env::set_var(var, value);
+    }
+    if let Some(trust) = &translation.trust {
+        trust_list::apply_bundles(trust.trust_anchors.as_deref(), trust.allowed_list.as_deref(), trust.trust_config.as_deref())
+            .context("loading the c2patool trust settings")?;
+    }
+    for command in &translation.commands {
+        run_command(command.clone())?;
+    }
+
+    Ok(())
+}
+
+/* The arguments choosing which files in directories a command walks */
+fn 
+walk_args (cmd: Command) -> Command {
+    cmd
+    .arg(arg!(--include <GLOB>).required(false).action(ArgAction::Append).help("in directories, only processes files matching, e.g. '*.jpg'; may be repeated"))
+    .arg(arg!(--exclude <GLOB>).required(false).action(ArgAction::Append).help("in directories, skips files matching, e.g. 'thumbs/**'; may be repeated"))
+    .arg(arg!(--"min-size" <SIZE>).required(false).help("in directories, skips files smaller than this, e.g. 4096, 500k or 20M"))
+    .arg(arg!(--"max-size" <SIZE>).required(false).help("in directories, skips files larger than this, e.g. 4096, 500k or 20M"))
+    .arg(arg!(--"follow-symlinks").help("in directories, follows symbolic links to files and directories, walking each directory once"))
+    .arg(arg!(--"max-depth" <N>).required(false).value_parser(value_parser!(usize)).help("in directories, descends at most N levels of subdirectories; 0 keeps to the directory itself"))
+}
+
+/* The arguments choosing the signer's profile and checking its certificates, for commands which never read its key */
+fn 
+signer_profile_args (cmd: Command) -> Command {
+    cmd
+    .arg(arg!(--"signer-profile" <FILE>).required(false).env("C2PA_SIGNER_PROFILE").help("JSON signer profile with signcert_path, pkey_path, alg and optional tsa_url"))
+    .arg(arg!(--profile <NAME>).required(false).env("C2PA_PROFILE").help("named signer profile from the profiles file; defaults to its default profile"))
+    .arg(arg!(--"cert-expiry-window" <DAYS>).required(false).default_value("30").value_parser(value_parser!(u32))
+        .help("warns when the signing certificate or a CA certificate in its chain expires within DAYS days"))
+    .arg(arg!(--"strict-certs").help("refuses to sign with a certificate chain --cert-expiry-window warns about"))
+}
+
+/* The arguments choosing the signer, shared by every command that signs */
+fn 
+signer_args (cmd: Command) -> Command {
+    signer_profile_args(cmd)
+    .arg(arg!(--key <FILE>).required(false).help("private key PEM to sign with in place of the signer's own; - reads it from stdin"))
+    .arg(arg!(--"key-env" <VAR>).required(false).conflicts_with("key").help("reads the private key PEM to sign with from environment variable VAR in place of the signer's own"))
+}
+
+/**
+ * The arguments choosing where a command deriving a new version of one
+ * local file writes it, and what happens when that output exists: the
+ * `result` it writes, e.g. "edited image", the word numbered outputs are
+ * named for, e.g. "edited", and what it does, e.g. "editing".
+ */
+fn 
+output_args (cmd: Command, result: &str, past: &str, doing: &str) -> Command {
+    cmd
+    .arg(arg!(-o --output <DEST>).required(false).value_parser(value_parser!(PathBuf)).help(format!("where to write the {}", result)))
+    .arg(arg!(--"in-place").help(format!("replaces the original, renaming the {} over it once fully written", result)))
+    .arg(arg!(--backup).conflicts_with("output").help("with --in-place, first keeps a copy of the original as <file>.bak"))
+    .arg(arg!(--overwrite).conflicts_with("in-place").help(format!("replaces the output if it already exists; by default a numbered name such as {}-2.jpg is used", past)))
+    .arg(arg!(--skip).conflicts_with_all(["in-place", "overwrite"]).help(format!("leaves the output alone if it already exists, {} nothing", doing)))
+    .group(ArgGroup::new("destination").args(["output", "in-place"]).required(true))
+}
+
+/* Parses and runs one command line of this tool's own, program name first */
+fn 
+run_command (args: Vec<OsString>) -> Result<()> {
     let matches = Command::new("c2pa-walkthrough")
     .version("0.1")
     .about("learning the c2pa-rs SDK")
-    .arg(arg!(--add <VALUE>).required(false).help("adds a c2pa manifest to a media file, displays the contents afterwards"))
-    .arg(arg!(--read <VALUE>).required(false).help("prints the c2pa manifest contents of a media file; fails if no manifest is present"))
-    .get_matches();
-
-    let add_path = matches.get_one::<String>("add");
-    let read_path = matches.get_one::<String>("read");
-
-    match (add_path, read_path) {
-        (Some(file_path), read_path_opt) => {
-            let file_path_regex = Regex::new(r"(.+)\.([a-zA-Z]+)").unwrap();
-            let captures = file_path_regex.captures(&file_path).unwrap();
+    .arg(arg!(--add <VALUE>).required(false).value_parser(value_parser!(PathBuf)).help("adds a c2pa manifest to a media file, displays the contents afterwards"))
+    .arg(arg!(--read <VALUE>).required(false).value_parser(value_parser!(PathBuf)).help("prints the c2pa manifest contents of a media file; fails if no manifest is present"))
+    .arg(arg!(--format <FORMAT>).required(false).help("media format for --add and --read, e.g. jpg; needed for files without an extension"))
+    .arg(arg!(--overwrite).help("with --add, replaces an existing <name>_c2pa file; by default a numbered name such as test_file_c2pa-2.jpg is used"))
+    .arg(arg!(--skip).conflicts_with("overwrite").help("with --add, leaves an existing <name>_c2pa file alone and adds nothing"))
+    .arg(arg!(--"strip-gps").help("with --add, leaves the GPS location out of the Exif assertion"))
+    .arg(arg!(--"strip-asset-gps").help("with --add, also removes the GPS location from the image's own Exif; implies --strip-gps"))
+    .arg(arg!(--pipeline).help("with --add, makes the manifest and its three edits in memory and writes the file once, rather than rewriting it for each"))
+    .arg(arg!(--when <RFC3339>).required(false).global(true).help("pins the time recorded in actions and assertions, e.g. 2024-01-01T00:00:00Z or 2024-01-01T09:00:00+09:00, keeping its offset; overrides C2PA_FAKE_TIME"))
+    .arg(arg!(--"local-time").global(true).help("records times in the local timezone with its offset, e.g. 2026-10-15T17:30:00+02:00, rather than in UTC"))
+    .arg(arg!(--"thumbnail-format" <FORMAT>).required(false).global(true).value_parser(["jpeg", "png", "webp"])
+        .help("writes claim and ingredient thumbnails in this format; by default PNG for images with transparency, else JPEG (needs the thumbnails feature)"))
+    .arg(arg!(--"thumbnail-quality" <QUALITY>).required(false).global(true).value_parser(value_parser!(u8).range(1..=100))
+        .help("JPEG thumbnail quality, 1 to 100; defaults to 80"))
+    .arg(arg!(--"thumbnail-long-edge" <PIXELS>).required(false).global(true).value_parser(value_parser!(u32).range(1..))
+        .help("scales thumbnails down to at most this many pixels along the long edge; defaults to 1024"))
+    .arg(arg!(--"spec-version" <VERSION>).required(false).global(true).value_parser(["1.3", "2.0"])
+        .help("C2PA spec version the claims of generated manifests follow: 1.3 writes version 1 claims, 2.0 version 2 claims; defaults to 1.3"))
+    .arg(arg!(--vendor <NAME>).required(false).global(true).value_parser(|arg: &str| clock::parse_vendor(arg))
+        .help("puts a vendor name in front of the labels of generated manifests, e.g. com.example:urn:uuid:<uuid>"))
+    .arg(arg!(--"label-namespace" <NAMESPACE>).required(false).global(true).env("C2PA_LABEL_NAMESPACE").value_parser(|arg: &str| namespace::parse_namespace(arg))
+        .help("reverse-domain name your organization owns, e.g. com.mycompany, for the labels of the custom assertions this tool writes; defaults to com.mikecvet"))
+    .arg(arg!(--"io-chunk-size" <SIZE>).required(false).global(true).value_parser(|size: &str| batch::parse_size(size).map_err(|e| e.to_string()))
+        .help("reads and writes assets this much at a time, e.g. 8M for network filesystems and large video masters; defaults to 1M"))
+    .arg(arg!(--"read-ahead" <SIZE>).required(false).global(true).value_parser(|size: &str| batch::parse_size(size).map_err(|e| e.to_string()))
+        .help("asks the kernel to fetch this much of a local asset ahead of each read, e.g. 64M; by default the kernel decides (Linux only)"))
+    .arg(arg!(--"no-color").global(true).help("never colors output; by default it is colored when stdout is a terminal and NO_COLOR is unset"))
+    .subcommand(signer_args(walk_args(
+        Command::new("sign")
+        .about("signs an asset with a manifest definition; locations may be paths, http(s) URLs or s3://bucket/key")
+        .arg(arg!([sources] ... "assets to sign; local paths may be directories or globs such as 'photos/*.jpg'"))
+        .arg(arg!(--"files-from" <FILE>).required(false).help("also signs each location listed in FILE, one per line; - reads the list from stdin"))
+        .arg(arg!(-o --output <DEST>).required_unless_present_any(["dry-run", "in-place"]).env("C2PA_OUTPUT_DIR")
+            .help("where to write the signed asset; a directory when signing more than one, or when given by C2PA_OUTPUT_DIR"))
+        .arg(arg!(--"in-place").help("replaces each local source with its signed version, renamed over it once fully written"))
+        .arg(arg!(--backup).requires("in-place").help("with --in-place, first keeps a copy of each source as <file>.bak"))
+        .arg(arg!(--overwrite).conflicts_with("in-place").help("replaces outputs which already exist; by default a numbered name such as photo-2.jpg is used"))
+        .arg(arg!(--skip).conflicts_with_all(["in-place", "overwrite"]).help("leaves outputs which already exist alone, skipping their sources"))
+        .arg(arg!(--manifest <FILE>).required(false).help("manifest definition JSON, as accepted by c2patool"))
+        .arg(arg!(--interactive).conflicts_with("manifest").help("prompts for the author, actions, source type and assertions, and previews the manifest before signing"))
+        .arg(arg!(--"assertions-dir" <DIR>).required(false).value_parser(value_parser!(PathBuf))
+            .help("also attaches each <label>.json file in DIR as an assertion with that label, e.g. licensing terms or contact details"))
+        .arg(arg!(--aux <"KIND=PATH">).required(false).action(ArgAction::Append).value_parser(|arg: &str| aux_data::parse_aux_arg(arg))
+            .help("also records the file at PATH as auxiliary data of KIND, e.g. depth-map=depth.png or segmentation-mask=mask.png, covered by the claim; repeatable"))
+        .arg(arg!(--"link-aux").requires("aux").help("records --aux files by name and hash, to be kept beside the signed asset, instead of embedding them"))
+        .arg(arg!(--review <"VALUE[:CODE]=EXPLANATION">).required(false).action(ArgAction::Append).value_parser(|arg: &str| review::parse_review(arg))
+            .help("rates the asset as a whole from 1 (worst) to 5 (best) in a review assertion, e.g. 4=checked against the wire original; repeatable"))
+        .arg(arg!(--"published-url" <URL>).required(false).action(ArgAction::Append).value_parser(|arg: &str| asset_ref::parse_published_url(arg))
+            .help("records where the signed asset is published, in an asset reference assertion; {name} stands for its file name, e.g. https://cdn.example.com/photos/{name}; repeatable"))
+        .arg(arg!(--"standard-metadata" <FILE>).required(false)
+            .help("JSON object of standard metadata (c2pa.metadata) fields to set: description, credit, rights, usage_terms and date_created"))
+        .arg(arg!(--description <TEXT>).required(false).help("caption for the standard metadata assertion (dc:description)"))
+        .arg(arg!(--credit <TEXT>).required(false).help("credit line for the standard metadata assertion (photoshop:Credit)"))
+        .arg(arg!(--rights <TEXT>).required(false).help("copyright notice for the standard metadata assertion (dc:rights)"))
+        .arg(arg!(--"usage-terms" <TEXT>).required(false).help("usage terms for the standard metadata assertion (xmpRights:UsageTerms)"))
+        .arg(arg!(--"date-created" <DATE>).required(false).value_parser(|arg: &str| standard::parse_date_created(arg))
+            .help("when the content was created, as YYYY-MM-DD or an RFC 3339 date-time, for the standard metadata assertion (photoshop:DateCreated)"))
+        .arg(arg!(--"cloud-data" <"LABEL=PATH">).required(false).action(ArgAction::Append).requires("cloud-data-to").value_parser(|arg: &str| cloud_data::parse_cloud_data_arg(arg))
+            .help("uploads the file at PATH, e.g. raw sensor data or an edit history, and records its URL and hash in a cloud-data assertion standing for LABEL; repeatable"))
+        .arg(arg!(--"cloud-data-to" <LOCATION>).required(false).help("directory, http(s) prefix or object store prefix to upload --cloud-data payloads to"))
+        .arg(arg!(--"cloud-data-url" <URL>).required(false).help("public URL --cloud-data-to is served from; defaults to --cloud-data-to if it is an http(s) prefix"))
+        .arg(arg!(--"instance-id" <UUID>).required(false).value_parser(value_parser!(uuid::Uuid)).help("pins the manifest label to urn:uuid:<UUID>, a random (version 4) UUID, instead of generating one"))
+        .arg(arg!(--"strip-gps").help("removes GPS fields from the Exif assertions of the manifest definition before signing"))
+        .arg(arg!(--"verify-pixels").help("decodes each image before and after embedding and fails it if its pixels changed, e.g. if embedding recompressed it (needs the pixel-integrity feature)"))
+        .arg(arg!(--"color-integrity").help("records each asset's embedded ICC profile, its hash and description, so verify can flag color-management changes"))
+        .arg(arg!(--"strip-asset-gps").help("also removes the GPS location from each JPEG's own Exif before signing; implies --strip-gps"))
+        .arg(arg!(--"redaction-policy" <FILE>).required(false).help("JSON policy of Exif/IPTC/XMP fields to drop or hash in the metadata assertions before signing"))
+        .arg(arg!(--"strip-metadata" <BLOCKS>).required(false).value_delimiter(',').value_parser(["exif", "xmp", "iptc"])
+            .help("removes these metadata blocks from JPEGs, e.g. exif,xmp, after capturing their fields into the metadata assertions"))
+        .arg(arg!(--"size-report").help("prints the size of each manifest store and of the parts of its active manifest to stderr; always on with --dry-run"))
+        .arg(arg!(--"max-manifest-size" <SIZE>).required(false).value_parser(|size: &str| batch::parse_size(size).map_err(|e| e.to_string()))
+            .help("fails an asset, before writing it, whose manifest store is larger than this, e.g. 32k"))
+        .arg(arg!(--"warn-manifest-size").requires("max-manifest-size").help("with --max-manifest-size, only warns about manifest stores over budget"))
+        .arg(arg!(--"also-emit" <FORMATS>).required(false).value_delimiter(',').value_parser(["webp", "avif", "png", "jpg"])
+            .help("also writes each signed image in these formats beside its output, e.g. webp,avif, each signed with the original as its parent (needs the renditions feature)"))
+        .arg(arg!(--anonymous).help("removes people from the CreativeWork authors, so the manifest names only the signing organization"))
+        .arg(arg!(--pseudonym <NAME>).required(false).conflicts_with("anonymous").help("credits NAME as the author in place of the people in the CreativeWork authors"))
+        .arg(arg!(--"identity-log" <FILE>).required(false).requires("identity-recipient").help("with --anonymous or --pseudonym, appends the real authors of each asset to this encrypted log"))
+        .arg(arg!(--"identity-recipient" <PEM>).required(false).requires("identity-log").help("RSA certificate or public key the identity log is encrypted for"))
+        .arg(arg!(--"pre-sign-hook" <CMD>).required(false).action(ArgAction::Append).env("C2PA_PRE_SIGN_HOOK")
+            .help("shell command run before each asset is signed, e.g. a virus scan; failing it aborts signing that asset; may be repeated"))
+        .arg(arg!(--"post-sign-hook" <CMD>).required(false).action(ArgAction::Append).env("C2PA_POST_SIGN_HOOK")
+            .help("shell command run once each signed asset is written, e.g. an upload; failing it fails the asset; may be repeated"))
+        .arg(arg!(-q --quiet).help("doesn't print each signed asset or the batch summary"))
+        .arg(arg!(--"share-to" <LOCATION>).required(false).env("C2PA_SHARE_TO")
+            .help("uploads each signed asset here, e.g. s3://bucket/shared or a directory a web server publishes, and prints a Content Credentials Verify link to it"))
+        .arg(arg!(--"share-url" <URL>).required(false).requires("share-to").env("C2PA_SHARE_URL").help("public URL --share-to is served from; defaults to --share-to if that is an http(s) URL"))
+        .arg(arg!(--qr).requires("share-to").help("with --share-to, also prints each Verify link as a QR code"))
+        .arg(arg!(--"dry-run").conflicts_with_all(["rekor", "ots", "ipfs", "share-to", "cloud-data"]).help("signs in memory and prints the manifest that would be embedded, writing and publishing nothing"))
+        .arg(arg!(--"canonical-json").requires("dry-run").help("prints the --dry-run manifest with UUIDs numbered in order of appearance, times and hashes blanked and keys sorted, for diffing against golden files"))
+        .arg(arg!(--format <FORMAT>).required(false).help("asset format, e.g. jpg or image/jpeg; defaults to the source extension"))
+        .arg(arg!(--"audit-log" <FILE>).required(false).help("records every signing operation to this SQLite database (--features audit)"))
+        .arg(arg!(--audit).conflicts_with("audit-log").help("records every signing operation to the audit log in the data directory, as `paths` shows (--features audit)"))
+        .arg(arg!(--"webhook-url" <URL>).required(false).env("C2PA_WEBHOOK_URL").help("POSTs a JSON event announcing each signed asset to this URL, e.g. for an asset management system"))
+        .arg(arg!(--"webhook-retries" <N>).required(false).default_value("3").value_parser(value_parser!(u32))
+            .help("times to retry an event the webhook didn't take, waiting 1s, then 2s, 4s and so on"))
+        .arg(arg!(--rekor).help("logs the signed manifest to a Rekor transparency log, keeping the entry in <DEST>.rekor.json; needs an ECDSA signer"))
+        .arg(arg!(--"rekor-url" <URL>).required(false).default_value(rekor::DEFAULT_REKOR_URL).help("Rekor instance to log to"))
+        .arg(arg!(--ots).help("timestamps the signed manifest with OpenTimestamps, keeping the proof in <DEST>.ots"))
+        .arg(arg!(--ipfs).help("publishes the manifest store to IPFS under a new IPNS name, referenced from the asset instead of embedded"))
+        .arg(arg!(--"ipfs-gateway" <URL>).required(false).default_value(ipfs::DEFAULT_GATEWAY).help("IPFS gateway named in the asset's remote manifest URL"))
+        .arg(arg!(--"ots-calendar" <URL>).required(false).action(ArgAction::Append).default_values(ots::DEFAULT_CALENDARS).help("OpenTimestamps calendar to submit to; may be repeated"))
+    )))
+    .subcommand(signer_profile_args(
+        Command::new("prepare")
+        .about("embeds a manifest awaiting its signature, for keys held under dual control; finish with finalize")
+        .arg(arg!(<source> "asset to prepare; a path, http(s) URL or s3://bucket/key"))
+        .arg(arg!(-o --output <DEST>).required(true).help("where to write the prepared asset; the payload to sign goes to <DEST>.tbs and the signing state to <DEST>.prepared.json"))
+        .arg(arg!(--manifest <FILE>).required(false).help("manifest definition JSON, as accepted by c2patool"))
+        .arg(arg!(--"assertions-dir" <DIR>).required(false).value_parser(value_parser!(PathBuf))
+            .help("also attaches each <label>.json file in DIR as an assertion with that label, e.g. licensing terms or contact details"))
+        .arg(arg!(--aux <"KIND=PATH">).required(false).action(ArgAction::Append).value_parser(|arg: &str| aux_data::parse_aux_arg(arg))
+            .help("also records the file at PATH as auxiliary data of KIND, e.g. depth-map=depth.png or segmentation-mask=mask.png, covered by the claim; repeatable"))
+        .arg(arg!(--"link-aux").requires("aux").help("records --aux files by name and hash, to be kept beside the signed asset, instead of embedding them"))
+        .arg(arg!(--review <"VALUE[:CODE]=EXPLANATION">).required(false).action(ArgAction::Append).value_parser(|arg: &str| review::parse_review(arg))
+            .help("rates the asset as a whole from 1 (worst) to 5 (best) in a review assertion, e.g. 4=checked against the wire original; repeatable"))
+        .arg(arg!(--"published-url" <URL>).required(false).action(ArgAction::Append).value_parser(|arg: &str| asset_ref::parse_published_url(arg))
+            .help("records where the signed asset is published, in an asset reference assertion; {name} stands for its file name, e.g. https://cdn.example.com/photos/{name}; repeatable"))
+        .arg(arg!(--"standard-metadata" <FILE>).required(false)
+            .help("JSON object of standard metadata (c2pa.metadata) fields to set: description, credit, rights, usage_terms and date_created"))
+        .arg(arg!(--description <TEXT>).required(false).help("caption for the standard metadata assertion (dc:description)"))
+        .arg(arg!(--credit <TEXT>).required(false).help("credit line for the standard metadata assertion (photoshop:Credit)"))
+        .arg(arg!(--rights <TEXT>).required(false).help("copyright notice for the standard metadata assertion (dc:rights)"))
+        .arg(arg!(--"usage-terms" <TEXT>).required(false).help("usage terms for the standard metadata assertion (xmpRights:UsageTerms)"))
+        .arg(arg!(--"date-created" <DATE>).required(false).value_parser(|arg: &str| standard::parse_date_created(arg))
+            .help("when the content was created, as YYYY-MM-DD or an RFC 3339 date-time, for the standard metadata assertion (photoshop:DateCreated)"))
+        .arg(arg!(--"cloud-data" <"LABEL=PATH">).required(false).action(ArgAction::Append).requires("cloud-data-to").value_parser(|arg: &str| cloud_data::parse_cloud_data_arg(arg))
+            .help("uploads the file at PATH, e.g. raw sensor data or an edit history, and records its URL and hash in a cloud-data assertion standing for LABEL; repeatable"))
+        .arg(arg!(--"cloud-data-to" <LOCATION>).required(false).help("directory, http(s) prefix or object store prefix to upload --cloud-data payloads to"))
+        .arg(arg!(--"cloud-data-url" <URL>).required(false).help("public URL --cloud-data-to is served from; defaults to --cloud-data-to if it is an http(s) prefix"))
+        .arg(arg!(--format <FORMAT>).required(false).help("asset format, e.g. jpg or image/jpeg; defaults to the source extension"))
+    ).mut_arg("signer-profile", |arg| arg.help("JSON signer profile whose certificate chain, alg and tsa_url are used; its key is never read")))
+    .subcommand(
+        Command::new("finalize")
+        .about("writes the signature over a prepared asset's payload into it, checking it verifies first")
+        .arg(arg!(<prepared> "asset written by prepare"))
+        .arg(arg!(--signature <FILE>).required(true).value_parser(value_parser!(PathBuf))
+            .help("signature over <prepared>.tbs: raw, or DER for ECDSA as `openssl dgst -sign` writes"))
+        .arg(arg!(--state <FILE>).required(false).help("signing state written by prepare; defaults to <prepared>.prepared.json"))
+        .arg(arg!(-o --output <DEST>).required(true).help("where to write the signed asset"))
+    )
+    .subcommand(signer_args(output_args(
+        Command::new("edit")
+        .about("records edits to a local media file, as derived manifests taking it as their parent")
+        .arg(arg!(<path> "media file to edit").value_parser(value_parser!(PathBuf)))
+        .arg(arg!(--action <ACTION>).required(false).action(ArgAction::Append)
+            .help("standard edit action, e.g. cropped or c2pa.filtered, optionally with the RFC 3339 time it was done at, e.g. cropped@2026-10-15T09:30:00+02:00; may be repeated"))
+        .arg(arg!(--"custom-action" <LABEL>).required(false).action(ArgAction::Append)
+            .help("vendor action with a reverse-domain label, e.g. com.example.stylize, or a bare name put in the label namespace, optionally with @TIME as --action takes; may be repeated"))
+        .arg(arg!(--description <TEXT>).required(false).requires("custom-action").help("what the custom actions did"))
+        .arg(arg!(--param <KEY_VALUE>).required(false).action(ArgAction::Append).requires("custom-action").help("custom action parameter as key=value, the value parsed as JSON if it can be; may be repeated"))
+        .group(ArgGroup::new("actions").args(["action", "custom-action"]).required(true).multiple(true))
+        .arg(arg!(--combine).help("records every action in one derived manifest instead of one manifest per action"))
+        .arg(arg!(--review <"VALUE[:CODE]=EXPLANATION">).required(false).action(ArgAction::Append).value_parser(|arg: &str| review::parse_review(arg))
+            .help("rates the original, recorded on its ingredient, e.g. 3:actions.possiblyMissing=levels look adjusted; repeatable"))
+        .arg(arg!(--"parent-instance-id" <ID>).required(false).help("instance ID to record for the source file in place of the one in its XMP, e.g. the ID an asset database keys it by"))
+        .arg(arg!(--format <FORMAT>).required(false).help("media format, e.g. jpg; needed for files without an extension")),
+        "edited file", "edited", "editing"
+    )))
+    .subcommand(signer_args(output_args(
+        Command::new("endorse")
+        .about("endorses or disputes a local asset someone else signed, in a new manifest signed with your own credentials")
+        .arg(arg!(<path> "signed asset to endorse").value_parser(value_parser!(PathBuf)))
+        .arg(arg!(--verdict <VERDICT>).required(false).default_value("endorsed").value_parser(["endorsed", "reviewed", "disputed"])
+            .help("endorsed vouches for the asset, reviewed records looking it over, disputed records finding it wanting"))
+        .arg(arg!(--by <NAME>).required(false).help("person or desk endorsing, e.g. 'Photo desk'; the signing certificate names the organization"))
+        .arg(arg!(--role <ROLE>).required(false).help("the endorser's role, e.g. editor or fact-checker"))
+        .arg(arg!(--comment <TEXT>).required(false).help("what the endorser has to say about the asset"))
+        .arg(arg!(--review <"VALUE[:CODE]=EXPLANATION">).required(false).action(ArgAction::Append).value_parser(|arg: &str| review::parse_review(arg))
+            .help("rates the endorsed version, recorded on its ingredient, e.g. 2:ingredient.possiblyModified=crop not disclosed; repeatable"))
+        .arg(arg!(--"parent-instance-id" <ID>).required(false).help("instance ID to record for the endorsed version in place of the one in its XMP, e.g. the ID an asset database keys it by"))
+        .arg(arg!(--format <FORMAT>).required(false).help("media format, e.g. jpg; needed for files without an extension")),
+        "endorsed asset", "endorsed", "endorsing"
+    )))
+    .subcommand(signer_args(output_args(
+        Command::new("apply")
+        .about("crops, resizes, rotates or grayscales a local image and records each edit with its parameters (--features image-edits)")
+        .arg(arg!(<path> "image to edit").value_parser(value_parser!(PathBuf)))
+        .arg(arg!(--op <OP>).required(true).action(ArgAction::Append).help("crop=x,y,w,h, resize=W, resize=xH, resize=WxH, rotate=90|180|270 or grayscale; may be repeated, applied in order"))
+        .arg(arg!(--format <FORMAT>).required(false).help("media format, e.g. jpg; needed for files without an extension")),
+        "edited image", "edited", "editing"
+    )))
+    .subcommand(signer_args(output_args(
+        Command::new("crop")
+        .about("crops a local image, recording c2pa.cropped with the region against the original (--features image-edits)")
+        .arg(arg!(<path> "image to crop").value_parser(value_parser!(PathBuf)))
+        .arg(arg!(--rect <RECT>).required(true).help("region to keep, as x,y,width,height in pixels"))
+        .arg(arg!(--format <FORMAT>).required(false).help("media format, e.g. jpg; needed for files without an extension")),
+        "cropped image", "edited", "editing"
+    )))
+    .subcommand(signer_args(output_args(
+        Command::new("rotate")
+        .about("rotates a local image clockwise, recording c2pa.orientation against the original (--features image-edits)")
+        .arg(arg!(<path> "image to rotate").value_parser(value_parser!(PathBuf)))
+        .arg(arg!(--degrees <DEGREES>).required(true).value_parser(["90", "180", "270"]).help("clockwise rotation"))
+        .arg(arg!(--format <FORMAT>).required(false).help("media format, e.g. jpg; needed for files without an extension")),
+        "rotated image", "edited", "editing"
+    )))
+    .subcommand(signer_args(output_args(
+        Command::new("resize")
+        .about("resizes a local image, recording c2pa.resized with the old and new sizes against the original (--features image-edits)")
+        .arg(arg!(<path> "image to resize").value_parser(value_parser!(PathBuf)))
+        .arg(arg!(--width <PIXELS>).required(false).help("new width; keeps the aspect ratio if --height isn't given"))
+        .arg(arg!(--height <PIXELS>).required(false).help("new height; keeps the aspect ratio if --width isn't given"))
+        .group(ArgGroup::new("size").args(["width", "height"]).required(true).multiple(true))
+        .arg(arg!(--format <FORMAT>).required(false).help("media format, e.g. jpg; needed for files without an extension")),
+        "resized image", "edited", "editing"
+    )))
+    .subcommand(signer_args(
+        Command::new("transcode")
+        .about("re-encodes a local video with ffmpeg, recording c2pa.transcoded and its codecs against the original")
+        .arg(arg!(<path> "video to transcode").value_parser(value_parser!(PathBuf)))
+        .arg(arg!(--preset <PRESET>).required(true).value_parser(["web"]).help("encoding settings; web is H.264 and AAC in a fast-start MP4 at most 1080 lines high"))
+        .arg(arg!(-o --output <DEST>).required(true).value_parser(value_parser!(PathBuf)).help("where to write the transcoded video"))
+        .arg(arg!(--overwrite).help("replaces the output if it already exists; by default a numbered name such as web-2.mp4 is used"))
+        .arg(arg!(--skip).conflicts_with("overwrite").help("leaves the output alone if it already exists, transcoding nothing"))
+        .arg(arg!(--ffmpeg <PROGRAM>).required(false).default_value("ffmpeg").help("the ffmpeg program to run"))
+        .arg(arg!(--format <FORMAT>).required(false).help("media format, e.g. mov; needed for files without an extension"))
+    ))
+    .subcommand(signer_args(
+        Command::new("sign-fragmented")
+        .about("signs fragmented video, such as DASH or CMAF segments, with a Merkle-tree hash so each fragment can be verified on its own")
+        .arg(arg!([init] "init segment to sign, e.g. init.mp4; its manifest covers the fragments").value_parser(value_parser!(PathBuf)).required_unless_present("master"))
+        .arg(arg!([fragments] ... "fragments following the init segment, in playback order, e.g. 'chunk-*.m4s'").value_parser(value_parser!(PathBuf)).requires("init"))
+        .arg(arg!(--master <FILE>).required(false).value_parser(value_parser!(PathBuf)).conflicts_with_all(["init", "fragments"])
+            .help("cuts this local video into fragments with ffmpeg, without re-encoding, and signs those, one init segment per stream"))
+        .arg(arg!(--"fragment-duration" <SECONDS>).required(false).default_value("2").value_parser(value_parser!(f64)).requires("master")
+            .help("with --master, cuts fragments of about this many seconds, each a leaf of the Merkle tree; fragments start on key frames"))
+        .arg(arg!(--ffmpeg <PROGRAM>).required(false).default_value("ffmpeg").help("the ffmpeg program to run for --master"))
+        .arg(arg!(-o --output <DIR>).required(true).value_parser(value_parser!(PathBuf)).help("directory to write the signed init segment and fragments to, under their own names"))
+        .arg(arg!(--manifest <FILE>).required(false).help("manifest definition JSON, as accepted by c2patool"))
+    ))
+    .subcommand(walk_args(
+        Command::new("verify")
+        .about("validates the c2pa manifests of assets; locations may be paths, http(s) URLs or s3://bucket/key")
+        .arg(arg!([paths] ... "assets to verify; local paths may be directories or globs such as 'photos/*.jpg'"))
+        .arg(arg!(--"files-from" <FILE>).required(false).help("also verifies each location listed in FILE, one per line; - reads the list from stdin"))
+        .arg(arg!(--format <FORMAT>).required(false).help("asset format; defaults to each location's extension"))
+        .arg(arg!(--rekor).help("also checks each asset's <path>.rekor.json transparency log entry, its inclusion proof and the log's signatures over both"))
+        .arg(arg!(--"rekor-public-key" <FILE>).required(false).requires("rekor").help("PEM public key of the Rekor log, pinned; by default the key the log serves is fetched from it"))
+        .arg(arg!(--"ipfs-gateway" <URL>).required(false).default_value(ipfs::DEFAULT_GATEWAY).help("IPFS gateway to fetch remote manifests on IPFS through"))
+        .arg(arg!(--ots).help("also checks each asset's <path>.ots OpenTimestamps proof, reporting the Bitcoin block it is anchored in"))
+        .arg(arg!(--cafile <FILE>).required(false).help("also builds and validates the signing certificate chain against the roots in this PEM bundle"))
+        .arg(arg!(--"pin-policy" <FILE>).required(false).help("JSON policy pinning the signer certificates or SPKI hashes expected per source domain or claim generator"))
+        .arg(arg!(--"source-domain" <DOMAIN>).required(false).requires("pin-policy").help("with --pin-policy, the domain local files came from; URLs use their host"))
+        .arg(arg!(--crl).help("also checks each manifest's certificates against the CRLs they name, caching them until their next update"))
+        .arg(arg!(--"no-trust-list").help("skips checking signers against the C2PA trust list, e.g. for assets signed with test certificates"))
+        .arg(arg!(--"trust-list-ttl" <HOURS>).required(false).default_value("24").value_parser(value_parser!(u64)).help("fetches the cached trust list again once older than this"))
+        .arg(arg!(--"trust-list-url" <URL>).required(false).action(ArgAction::Append).conflicts_with("no-trust-list").help("takes trust anchors from this PEM bundle instead of the C2PA and CAI lists; may be a path and may be repeated"))
+        .arg(arg!(--details).help("describes what part of the asset each failed check implicates"))
+        .arg(arg!(--summary).help("after the verdicts, counts the assets signed and tallies their claim generators, signers and failure status codes"))
+        .arg(arg!(--"check-published").help("fetches the published copy each asset's manifest names and warns if it differs from the asset"))
+        .arg(arg!(--"visual-diff").help("for invalid images, writes a <path>.diff.png heatmap of where they differ from their manifest's thumbnail (needs the visual-diff feature)"))
+        .arg(arg!(--dir <PATH>).required(false).action(ArgAction::Append).help("also verifies every file in this directory, walked as directories among the paths are; may be repeated"))
+        .arg(arg!(--jobs <N>).required(false).default_value("1").value_parser(value_parser!(u32).range(1..)).help("verifies up to N assets at once; verdicts are still printed in order"))
+        .arg(arg!(--jsonl <FILE>).required(false).value_parser(value_parser!(PathBuf)).help("also writes each asset's verdict to FILE as a line of JSON, e.g. for auditing an archive"))
+        .arg(arg!(--csv <FILE>).required(false).value_parser(value_parser!(PathBuf)).help("also writes each asset's path, whether it is signed, signer CN, claim generator, active manifest and verdict to FILE as a CSV row, e.g. for review in a spreadsheet"))
+        .arg(arg!(--"share-to" <LOCATION>).required(false).env("C2PA_SHARE_TO")
+            .help("uploads each verified asset here, e.g. s3://bucket/shared or a directory a web server publishes, and prints a Content Credentials Verify link to it"))
+        .arg(arg!(--"share-url" <URL>).required(false).requires("share-to").env("C2PA_SHARE_URL").help("public URL --share-to is served from; defaults to --share-to if that is an http(s) URL"))
+        .arg(arg!(--qr).requires("share-to").help("with --share-to, also prints each Verify link as a QR code"))
+    ))
+    .subcommand(walk_args(
+        Command::new("index")
+        .about("indexes the signed assets under a directory by content hash and instance ID, for lookups that don't walk it again")
+        .arg(arg!(<dir> "directory of assets to index").value_parser(value_parser!(PathBuf)))
+        .arg(arg!(-o --output <FILE>).required(false).value_parser(value_parser!(PathBuf)).help("where to write the index; defaults to index.json in the directory"))
+        .arg(arg!(--jobs <N>).required(false).default_value("1").value_parser(value_parser!(u32).range(1..)).help("reads up to N assets at once"))
+    ))
+    .subcommand(
+        Command::new("lookup")
+        .about("finds assets in an index written by `index`, by file, content hash, instance ID or manifest label")
+        .arg(arg!(<key> "a file, whose SHA-256 is looked up, or a SHA-256, instance ID or manifest label"))
+        .arg(arg!(--index <FILE>).required(false).default_value("index.json").value_parser(value_parser!(PathBuf)).help("index to search"))
+        .arg(arg!(--json "prints each matching asset's index entry as JSON"))
+    )
+    .subcommand(walk_args(
+        Command::new("relate")
+        .about("finds assets which are byte-identical, share content under different manifests, or name each other as ingredients")
+        .arg(arg!([paths] ... "assets to relate; local paths may be directories or globs such as 'photos/*.jpg'"))
+        .arg(arg!(--"files-from" <FILE>).required(false).help("also relates each location listed in FILE, one per line; - reads the list from stdin"))
+        .arg(arg!(--format <FORMAT>).required(false).help("asset format; defaults to each location's extension"))
+        .arg(arg!(--jobs <N>).required(false).default_value("1").value_parser(value_parser!(u32).range(1..)).help("reads up to N assets at once"))
+        .arg(arg!(--json "prints the groups and ingredient links as JSON"))
+    ))
+    .subcommand(
+        Command::new("read")
+        .about("prints the manifest store of an asset as JSON, or selected parts of it")
+        .arg(arg!(<path> "asset to read; a path, http(s) URL or object store URI"))
+        .arg(arg!(--format <FORMAT>).required(false).help("asset format; defaults to the location's extension"))
+        .arg(arg!(--label <GLOB>).required(false).help("prints only assertions whose label matches, e.g. stds.exif or c2pa.actions*"))
+        .arg(arg!(--select <SELECTOR>).required(false).help("jq-style path to print, e.g. .active_manifest or .manifests[].assertions[].label; applies to each assertion with --label"))
+        .arg(arg!(--"output-format" <FORMAT>).required(false).default_value("json").value_parser(["json", "exiftool", "ids"]).conflicts_with_all(["label", "select"])
+            .help("json prints the manifest store; exiftool flattens its title, authors, actions and Exif fields into the tags exiftool -json prints; ids lists each manifest's label, vendor and instance ID and its ingredients' instance and document IDs"))
+        .arg(arg!(--detailed).conflicts_with_all(["label", "output-format"]).help("prints the SDK's detailed report of the manifest store, as c2patool --detailed does"))
+        .arg(arg!(--compact).help("prints each value on a single line"))
+        .arg(arg!(--"canonical-json").help("numbers UUIDs in order of appearance, blanks times and hashes and sorts keys, for diffing against golden files"))
+        .arg(arg!(--"no-cloud-data").help("doesn't fetch cloud-data payloads to check them against their hashes"))
+        .arg(arg!(--"ipfs-gateway" <URL>).required(false).default_value(ipfs::DEFAULT_GATEWAY).help("IPFS gateway to fetch remote manifests on IPFS through"))
+    )
+    .subcommand(
+        Command::new("extract-assertion")
+        .about("writes the decoded content of an assertion as JSON, converting CBOR assertions")
+        .arg(arg!(<path> "asset to read; a path, http(s) URL or object store URI"))
+        .arg(arg!(--label <LABEL>).required(true).help("assertion label, e.g. stds.exif; may be a glob matching one assertion"))
+        .arg(arg!(--manifest <LABEL>).required(false).help("manifest to take the assertion from; defaults to the active manifest, then any manifest with a single match"))
+        .arg(arg!(-o --output <DEST>).required(false).help("where to write the assertion; defaults to stdout"))
+        .arg(arg!(--format <FORMAT>).required(false).help("asset format; defaults to the location's extension"))
+    )
+    .subcommand(
+        Command::new("boxes")
+        .about("lists every JUMBF box of a manifest store with its offset, length, UUID and label, or extracts one")
+        .arg(arg!(<path> "asset to read; a path, http(s) URL or object store URI"))
+        .arg(arg!(--extract <BOX>).required(false).help("writes the raw bytes of one box, header included: its index in the listing, or a superbox's JUMBF path, e.g. /c2pa/<manifest>/c2pa.claim"))
+        .arg(arg!(-o --output <DEST>).required(false).requires("extract").help("where to write the extracted box; defaults to stdout"))
+        .arg(arg!(--json).conflicts_with("extract").help("lists the boxes as JSON"))
+        .arg(arg!(--format <FORMAT>).required(false).help("asset format; defaults to the location's extension"))
+    )
+    .subcommand(
+        Command::new("export-certs")
+        .about("writes the certificate chain the active manifest was signed with as PEM, signing certificate first")
+        .arg(arg!(<path> "asset to read; a path, http(s) URL or object store URI"))
+        .arg(arg!(-o --output <DEST>).required(false).help("where to write the chain; defaults to stdout"))
+        .arg(arg!(--format <FORMAT>).required(false).help("asset format; defaults to the location's extension"))
+        .arg(arg!(--"ipfs-gateway" <URL>).required(false).default_value(ipfs::DEFAULT_GATEWAY).help("IPFS gateway to fetch remote manifests on IPFS through"))
+    )
+    .subcommand(
+        Command::new("aux")
+        .about("lists the auxiliary data, such as depth maps, in an asset's manifest, checking each piece against its hash")
+        .arg(arg!(<path> "asset to read; a path, http(s) URL or object store URI; linked data is read from beside it"))
+        .arg(arg!(--extract <DIR>).required(false).help("also writes each piece to DIR (or an object store prefix), named for its label"))
+        .arg(arg!(--format <FORMAT>).required(false).help("asset format; defaults to the location's extension"))
+    )
+    .subcommand(
+        Command::new("extract-thumbnails")
+        .about("writes the claim and ingredient thumbnails of every manifest to a directory, with a thumbnails.json index")
+        .arg(arg!(<path> "asset to read; a path, http(s) URL or object store URI"))
+        .arg(arg!(-o --output <DIR>).required(true).help("directory (or object store prefix) to write the thumbnails to"))
+        .arg(arg!(--format <FORMAT>).required(false).help("asset format; defaults to the location's extension"))
+    )
+    .subcommand(
+        Command::new("identities")
+        .about("decrypts an identity log kept with sign --anonymous or --pseudonym and prints the real authors")
+        .arg(arg!(<log> "identity log to open"))
+        .arg(arg!(--key <PEM>).required(true).help("RSA private key matching the log's --identity-recipient; - reads it from stdin"))
+    )
+    .subcommand(
+        Command::new("check-metadata")
+        .about("compares the signed Exif, IPTC, XMP and CreativeWork assertions with the metadata a JPEG carries now")
+        .arg(arg!(<path> "asset to check; a path, http(s) URL or object store URI"))
+        .arg(arg!(--manifest <LABEL>).required(false).help("manifest whose assertions to compare; defaults to the active manifest"))
+        .arg(arg!(--format <FORMAT>).required(false).help("asset format; defaults to the location's extension"))
+        .arg(arg!(--strict "also fails on fields the asset has but the signed assertions lack"))
+        .arg(arg!(--json "prints the comparison as JSON"))
+    )
+    .subcommand(
+        Command::new("pixel-compare")
+        .about("decodes two images and compares their pixels, failing if any differ (needs the pixel-integrity feature)")
+        .arg(arg!(<a> "first image; a path, http(s) URL or object store URI"))
+        .arg(arg!(<b> "second image"))
+        .arg(arg!(--format <FORMAT>).required(false).help("format of both images; defaults to each location's extension"))
+        .arg(arg!(--json "prints the comparison as JSON"))
+    )
+    .subcommand(
+        Command::new("report")
+        .about("renders a provenance report on an asset's manifest store, from a Handlebars template or as plain text")
+        .arg(arg!(<path> "asset to report on; a path, http(s) URL or object store URI"))
+        .arg(arg!(--template <FILE>).required(false).value_parser(value_parser!(PathBuf))
+            .help("Handlebars template given the manifest store as context; values are HTML-escaped when FILE ends in .html or .html.hbs"))
+        .arg(arg!(-o --output <FILE>).required(false).help("where to write the report; defaults to stdout"))
+        .arg(arg!(--format <FORMAT>).required(false).help("asset format; defaults to the location's extension"))
+        .arg(arg!(--"ipfs-gateway" <URL>).required(false).default_value(ipfs::DEFAULT_GATEWAY).help("IPFS gateway to fetch remote manifests on IPFS through"))
+    )
+    .subcommand(
+        Command::new("timeline")
+        .about("prints the actions from every manifest in an asset's store as one chronological history")
+        .arg(arg!(<path> "asset to read; a path, http(s) URL or object store URI"))
+        .arg(arg!(--format <FORMAT>).required(false).help("asset format; defaults to the location's extension"))
+        .arg(arg!(--json "prints the history as JSON"))
+        .arg(arg!(--"ipfs-gateway" <URL>).required(false).default_value(ipfs::DEFAULT_GATEWAY).help("IPFS gateway to fetch remote manifests on IPFS through"))
+    )
+    .subcommand(signer_args(
+        Command::new("serve")
+        .about("serves signing and verification APIs over REST (POST /sign, POST /verify) or gRPC")
+        .arg(arg!(--grpc).help("serves the gRPC API (SignAsset, VerifyAsset, ReadManifest) instead of REST"))
+        .arg(arg!(--addr <ADDR>).required(false).help("address to listen on; defaults to 127.0.0.1:8080 (REST) or 127.0.0.1:50051 (gRPC)"))
+        .arg(arg!(--"metrics-addr" <ADDR>).required(false).help("serves Prometheus metrics at http://<ADDR>/metrics in gRPC mode; REST serves /metrics on --addr"))
+        .arg(arg!(--templates <DIR>).required(false).help("directory of <name>.json manifest templates selectable via the `template` field of POST /sign"))
+        .arg(arg!(--"audit-log" <FILE>).required(false).help("records every signing operation to this SQLite database (--features audit)"))
+        .arg(arg!(--audit).conflicts_with("audit-log").help("records every signing operation to the audit log in the data directory, as `paths` shows (--features audit)"))
+        .arg(arg!(--"webhook-url" <URL>).required(false).env("C2PA_WEBHOOK_URL").help("POSTs a JSON event announcing each signed asset to this URL, e.g. for an asset management system"))
+        .arg(arg!(--"webhook-retries" <N>).required(false).default_value("3").value_parser(value_parser!(u32))
+            .help("times to retry an event the webhook didn't take, waiting 1s, then 2s, 4s and so on"))
+    ))
+    .subcommand(signer_args(
+        Command::new("daemon")
+        .about("serves sign/verify requests over a Unix socket using length-prefixed frames, loading the signer once")
+        .arg(arg!(--"metrics-addr" <ADDR>).required(false).help("serves Prometheus metrics at http://<ADDR>/metrics"))
+        .arg(arg!(--socket <PATH>).required(false).value_parser(value_parser!(PathBuf))
+            .help("path of the Unix socket to listen on; defaults to daemon.sock in the per-user runtime directory, as `paths` shows"))
+        .arg(arg!(--"audit-log" <FILE>).required(false).help("records every signing operation to this SQLite database (--features audit)"))
+        .arg(arg!(--audit).conflicts_with("audit-log").help("records every signing operation to the audit log in the data directory, as `paths` shows (--features audit)"))
+        .arg(arg!(--"webhook-url" <URL>).required(false).env("C2PA_WEBHOOK_URL").help("POSTs a JSON event announcing each signed asset to this URL, e.g. for an asset management system"))
+        .arg(arg!(--"webhook-retries" <N>).required(false).default_value("3").value_parser(value_parser!(u32))
+            .help("times to retry an event the webhook didn't take, waiting 1s, then 2s, 4s and so on"))
+    ))
+    .subcommand(signer_args(
+        Command::new("worker")
+        .about("consumes signing jobs from RabbitMQ (--features amqp) or Kafka (--features kafka)")
+        .arg(arg!(--queue <URL>).required(true).help("amqp[s]://user:pass@host:port/vhost or kafka://host:port[,host:port]"))
+        .arg(arg!(--topic <NAME>).required(false).default_value("c2pa-signing-jobs").help("queue (AMQP) or topic (Kafka) to consume"))
+        .arg(arg!(--group <NAME>).required(false).default_value("c2pa-walkthrough").help("Kafka consumer group"))
+        .arg(arg!(--manifest <FILE>).required(false).help("manifest definition for jobs which don't carry their own"))
+        .arg(arg!(--"allow-local-dir" <DIR>).required(false).action(ArgAction::Append).value_parser(value_parser!(PathBuf))
+            .help("lets jobs read sources from and write destinations to local paths under DIR; by default they may only name remote locations; may be repeated"))
+        .arg(arg!(--"max-asset-size" <SIZE>).required(false).default_value("512M").value_parser(|size: &str| batch::parse_size(size).map_err(|e| e.to_string()))
+            .help("fails jobs whose source asset is larger than this, e.g. 2G, without downloading more of it"))
+        .arg(arg!(--"metrics-addr" <ADDR>).required(false).help("serves Prometheus metrics at http://<ADDR>/metrics"))
+        .arg(arg!(--"audit-log" <FILE>).required(false).help("records every signing operation to this SQLite database (--features audit)"))
+        .arg(arg!(--audit).conflicts_with("audit-log").help("records every signing operation to the audit log in the data directory, as `paths` shows (--features audit)"))
+        .arg(arg!(--"webhook-url" <URL>).required(false).env("C2PA_WEBHOOK_URL").help("POSTs a JSON event announcing each signed asset to this URL, e.g. for an asset management system"))
+        .arg(arg!(--"webhook-retries" <N>).required(false).default_value("3").value_parser(value_parser!(u32))
+            .help("times to retry an event the webhook didn't take, waiting 1s, then 2s, 4s and so on"))
+    ))
+    .subcommand(
+        Command::new("paths")
+        .about("prints where the config, signer profiles, caches and audit log live on this platform")
+        .arg(arg!(--json).help("prints the locations as a JSON object"))
+    )
+    .subcommand(
+        Command::new("profiles")
+        .about("lists the named signer profiles, or rotates the default to another; kept in $C2PA_SIGNER_PROFILES or the config directory, as `paths` shows")
+        .subcommand(Command::new("list").about("lists each profile with its algorithm, certificate expiry and chain, marking the default with *"))
+        .subcommand(
+            Command::new("rotate")
+            .about("points the default profile at another, once its signer loads, for commands given no --profile")
+            .arg(arg!(<name> "profile to make the default"))
+        )
+    )
+    .subcommand(
+        Command::new("cert-check")
+        .about("checks a signer's certificate chain against the C2PA certificate profile and for expiry, and its key against its certificate")
+        .arg(arg!(--"signer-profile" <FILE>).required(false).env("C2PA_SIGNER_PROFILE").help("JSON signer profile with signcert_path, pkey_path, alg and optional tsa_url"))
+        .arg(arg!(--profile <NAME>).required(false).env("C2PA_PROFILE").help("named signer profile from the profiles file; defaults to its default profile"))
+        .arg(arg!(--key <FILE>).required(false).help("private key PEM to check in place of the signer's own; - reads it from stdin"))
+        .arg(arg!(--"key-env" <VAR>).required(false).conflicts_with("key").help("reads the private key PEM to check from environment variable VAR in place of the signer's own"))
+        .arg(arg!(--"cert-expiry-window" <DAYS>).required(false).default_value("30").value_parser(value_parser!(u32))
+            .help("warns when the signing certificate or a CA certificate in its chain expires within DAYS days"))
+        .arg(arg!(--strict).help("exits nonzero on warnings too, not just failures"))
+        .arg(arg!(--json "prints the checks as JSON"))
+    )
+    .subcommand(
+        Command::new("gen-fixtures")
+        .about("writes tiny signed JPEGs for validators' test suites: valid, expired certificate, tampered, redacted assertion and remote manifest")
+        .arg(arg!(-o --output <DIR>).required(true).value_parser(value_parser!(PathBuf)).help("directory to write the fixtures, their CA certificate and the fixtures.json index to"))
+        .arg(arg!(--"remote-url" <URL>).required(false).default_value("http://127.0.0.1:8000/remote.c2pa").help("URL remote.jpg references its manifest store at, where remote.c2pa is to be served"))
+    )
+    .subcommand(
+        Command::new("gen-corpus")
+        .about("writes malformed variants of signed assets' manifest stores, with truncated boxes, bad lengths, bad CBOR and oversized assertions, as a fuzzing corpus")
+        .arg(arg!(<seeds> ... "signed assets to mutate; a path, http(s) URL or object store URI"))
+        .arg(arg!(-o --output <DIR>).required(true).value_parser(value_parser!(PathBuf)).help("directory to write the malformed assets and the corpus.json index to"))
+    )
+    .subcommand(
+        Command::new("log")
+        .about("lists or shows the signing operations recorded with --audit-log (--features audit)")
+        .arg(arg!(--"audit-log" <FILE>).required(false).help("SQLite audit log to read; defaults to the one --audit records to"))
+        .subcommand(Command::new("list").about("lists every recorded signing operation"))
+        .subcommand(
+            Command::new("show")
+            .about("shows one recorded signing operation in full")
+            .arg(arg!(<id> "entry id, as printed by `log list`").value_parser(clap::value_parser!(i64)))
+        )
+    )
+    .subcommand(
+        // Handled before parsing, in run(); listed here for --help
+        Command::new("c2patool")
+        .about("runs a c2patool command line, e.g. `c2patool photo.jpg -m manifest.json -o signed.jpg -f`; also the mode when invoked as c2patool")
+        .arg(arg!([args] ... "c2patool arguments").trailing_var_arg(true).allow_hyphen_values(true))
+    )
+    .get_matches_from(args);
 
-            // filename prefix; output media files (with added manifests) to to a new file with a suffix added.
-            // For exmaple, destination file would be "test_file_c2pa.jpg" given an input of "test_file.jpg"
-            let mut file_with_manifest = captures.get(1).unwrap().as_str().to_owned();
+    color::init(matches.get_flag("no-color"));
+    match matches.get_one::<String>("when") {
+        Some(when) => clock::pin_time(clock::parse_time(when).context("--when")?),
+        None => {
+            clock::fake_time().context(clock::FAKE_TIME_VAR)?;
+        }
+    }
+    if matches.get_flag("local-time") {
+        clock::capture_local_time();
+    }
+    configure_thumbnails(&matches)?;
+    configure_io(&matches)?;
+    match matches.get_one::<String>("spec-version").map(String::as_str) {
+        Some("2.0") => sign::set_claim_version(2),
+        Some(_) => sign::set_claim_version(1),
+        None => ()
+    }
+    if let Some(vendor) = matches.get_one::<String>("vendor") {
+        clock::pin_vendor(vendor);
+    }
+    if let Some(label_namespace) = matches.get_one::<String>("label-namespace") {
+        namespace::set_namespace(label_namespace);
+    }
 
-            // suffix for output file
-            file_with_manifest.push_str("_c2pa");
+    match matches.subcommand() {
+        Some(("sign", sign_matches)) => return sign(sign_matches),
+        Some(("edit", edit_matches)) => return edit(edit_matches),
+        Some(("endorse", endorse_matches)) => return endorse_asset(endorse_matches),
+        Some(("apply", apply_matches)) => return apply(apply_matches),
+        Some(("crop", crop_matches)) => return crop(crop_matches),
+        Some(("rotate", rotate_matches)) => return rotate(rotate_matches),
+        Some(("resize", resize_matches)) => return resize(resize_matches),
+        Some(("transcode", transcode_matches)) => return transcode_video(transcode_matches),
+        Some(("sign-fragmented", fragmented_matches)) => return sign_fragmented_video(fragmented_matches),
+        Some(("verify", verify_matches)) => return verify(verify_matches),
+        Some(("index", index_matches)) => return index(index_matches),
+        Some(("lookup", lookup_matches)) => return lookup(lookup_matches),
+        Some(("relate", relate_matches)) => return relate_assets(relate_matches),
+        Some(("read", read_matches)) => return read(read_matches),
+        Some(("extract-assertion", extract_matches)) => return extract_assertion(extract_matches),
+        Some(("boxes", boxes_matches)) => return boxes(boxes_matches),
+        Some(("export-certs", export_matches)) => return export_certs(export_matches),
+        Some(("aux", aux_matches)) => return aux(aux_matches),
+        Some(("extract-thumbnails", extract_matches)) => return extract_thumbnails(extract_matches),
+        Some(("identities", identities_matches)) => return identities(identities_matches),
+        Some(("report", report_matches)) => return report(report_matches),
+        Some(("timeline", timeline_matches)) => return timeline(timeline_matches),
+        Some(("check-metadata", check_matches)) => return check_metadata(check_matches),
+        Some(("pixel-compare", compare_matches)) => return pixel_compare(compare_matches),
+        Some(("serve", serve_matches)) => return serve(serve_matches),
+        Some(("daemon", daemon_matches)) => return daemon(daemon_matches),
+        Some(("worker", worker_matches)) => return worker(worker_matches),
+        Some(("paths", paths_matches)) => return show_paths(paths_matches),
+        Some(("profiles", profiles_matches)) => return signer_profiles(profiles_matches),
+        Some(("cert-check", check_matches)) => return cert_check(check_matches),
+        Some(("gen-fixtures", fixtures_matches)) => return gen_fixtures(fixtures_matches),
+        Some(("gen-corpus", corpus_matches)) => return gen_corpus(corpus_matches),
+        Some(("prepare", prepare_matches)) => return prepare(prepare_matches),
+        Some(("finalize", finalize_matches)) => return finalize(finalize_matches),
+        Some(("log", log_matches)) => return log(log_matches),
+        _ => ()
+    }
 
-            // filename extension
-            file_with_manifest.push_str(".");
-            file_with_manifest.push_str(captures.get(2).unwrap().as_str());
+    let add_path = matches.get_one::<PathBuf>("add");
+    let read_path = matches.get_one::<PathBuf>("read");
 
-            match create_new_manifest(file_path, &file_with_manifest) {
-                Ok(_) => (),
-                Err(e) => panic!("error creating manifest: {}", e)
+    match (add_path, read_path) {
+        (Some(file_path), read_path_opt) => {
+            // output media files (with added manifests) to a new file with a suffix added to the file name.
+            // For example, destination file would be "test_file_c2pa.jpg" given an input of "test_file.jpg"
+            let format = local_format(&matches, file_path)?;
+            if let Some(file_with_manifest) = resolve_output(&matches, &with_manifest_suffix(file_path)) {
+                let edits = [
+                    (c2pa_action::CROPPED, "cropping"),
+                    (c2pa_action::FILTERED, "filtering"),
+                    (c2pa_action::COLOR_ADJUSTMENTS, "color adjustment")
+                ];
+                // The ps256 test signer, built once for the new manifest and all three edits
+                let signer = SignerConfig::default().create_signer().context("loading the signer")?;
+                if matches.get_flag("pipeline") {
+                    // The same manifests, made on the asset in memory and written once
+                    let signed = new_manifest_asset(file_path, &file_with_manifest, &format, strip_gps(&matches), &*signer)
+                        .with_context(|| format!("creating a manifest for {}", file_path.display()))?;
+                    let mut pipeline = EditPipeline::new(&file_with_manifest, &file_with_manifest, &format, signed);
+                    for (action, phase) in edits {
+                        pipeline.record(&[EditAction::new(action)], &*signer)
+                            .with_context(|| format!("{} edit of {} failed", phase, file_with_manifest.display()))?;
+                    }
+                    pipeline.finish(&file_with_manifest).with_context(|| format!("writing {}", file_with_manifest.display()))?;
+                } else {
+                    create_new_manifest(file_path, &file_with_manifest, &format, strip_gps(&matches), &*signer)
+                        .with_context(|| format!("creating a manifest for {}", file_path.display()))?;
+                    for (action, phase) in edits {
+                        edit_media_with_action(&file_with_manifest, &file_with_manifest, &format, action, &*signer)
+                            .with_context(|| format!("{} edit of {} failed", phase, file_with_manifest.display()))?;
+                    }
+                }
+                read_manifest(&file_with_manifest, &format)
+                    .with_context(|| format!("reading the manifest of {}", file_with_manifest.display()))?;
             }
 
-            match (
-                edit_media_with_action(&file_with_manifest, &file_with_manifest, c2pa_action::CROPPED), 
-                edit_media_with_action(&file_with_manifest, &file_with_manifest, c2pa_action::FILTERED), 
-                edit_media_with_action(&file_with_manifest, &file_with_manifest, c2pa_action::COLOR_ADJUSTMENTS)
-            ) {
-                (Ok(()), Ok(()), Ok(())) => {
-                    read_manifest(&file_with_manifest).expect("manifest should be printed to stdout");
-                },
-                (Err(e), _, _) => panic!("cropping edit failed with {}", e),
-                (_, Err(e), _) => panic!("filtering edit failed with {}", e),
-                (_, _, Err(e)) => panic!("color adjustment edit failed with {}", e),
-            };
-
-            match read_path_opt {
-                Some(read_path) => read_manifest(&read_path).expect("manifest should be printed to stdout"),
-                _ => ()
+            if let Some(read_path) = read_path_opt {
+                read_manifest(read_path, &local_format(&matches, read_path)?)
+                    .with_context(|| format!("reading the manifest of {}", read_path.display()))?;
             }
         }
         (None, Some(file_path)) => {
-            read_manifest(&file_path).expect("manifest should be printed to stdout; perhaps no c2pa manifest is present?");
+            read_manifest(file_path, &local_format(&matches, file_path)?)
+                .with_context(|| format!("reading the manifest of {}", file_path.display()))?;
         }
         (None, None) => {
             println!("provide a path to a media file via --add <path> or --read <path>");
         }
     }
+
+    Ok(())
+}
+
+/* A command which failed after printing why, such as verify finding invalid assets */
+#[derive(Debug)]
+struct Failed;
+
+impl std::fmt::Display for Failed {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "failed")
+    }
+}
+
+impl std::error::Error for Failed {}
+
+/* Prints failures as one line of context down to the underlying error, e.g. "error: reading a.jpg: No such file or directory" */
+fn 
+main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) if e.is::<Failed>() => ExitCode::FAILURE,
+        Err(e) => {
+            eprintln!("error: {:#}", e);
+            ExitCode::FAILURE
+        }
+    }
 }
@@ -0,0 +1,519 @@
+use std::path::Path;
+
+use c2pa::{Builder, ClaimGeneratorInfo, Ingredient, Signer};
+use c2pa::assertions::{c2pa_action, labels, Action, Actions, CreativeWork, Exif, ReviewRating, SchemaDotOrgPerson};
+use chrono::{DateTime, FixedOffset};
+use serde::Serialize;
+
+use crate::clock;
+use crate::metadata::{strip_asset_gps, strip_gps_fields, StripGps};
+use crate::namespace;
+use crate::providers::apply_providers;
+use crate::review::review_ingredient;
+use crate::sign::embed;
+use crate::storage::{read_file, write_file_atomically};
+
+#[derive(Serialize)]
+/* Example struct, used as labeled assertion data */
+struct MediaData {
+    n: usize,
+    m: usize,
+    desc: String,
+    ts: u64
+}
+
+impl MediaData {
+    fn new(n: usize, m: usize, desc: String) -> MediaData {
+        MediaData {
+            n,
+            m,
+            desc,
+            ts: clock::now().timestamp() as u64
+        }
+    }
+}
+
+/* The file name of a path, which the SDK uses as the title of manifests and ingredients */
+fn 
+file_title (path: &Path) -> String {
+    path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default()
+}
+
+/**
+ * How an asset's content came to be, from the IPTC digital source type
+ * vocabulary at http://cv.iptc.org/newscodes/digitalsourcetype/.
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DigitalSourceType {
+    DigitalCapture,
+    ComputationalCapture,
+    NegativeFilm,
+    PositiveFilm,
+    Print,
+    HumanEdits,
+    MinorHumanEdits,
+    CompositeCapture,
+    AlgorithmicallyEnhanced,
+    DigitalArt,
+    VirtualRecording,
+    ScreenCapture,
+    Composite,
+    CompositeSynthetic,
+    TrainedAlgorithmicMedia,
+    AlgorithmicMedia,
+    DataDrivenMedia
+}
+
+impl DigitalSourceType {
+    pub const ALL: [DigitalSourceType; 17] = [
+        DigitalSourceType::DigitalCapture,
+        DigitalSourceType::ComputationalCapture,
+        DigitalSourceType::NegativeFilm,
+        DigitalSourceType::PositiveFilm,
+        DigitalSourceType::Print,
+        DigitalSourceType::HumanEdits,
+        DigitalSourceType::MinorHumanEdits,
+        DigitalSourceType::CompositeCapture,
+        DigitalSourceType::AlgorithmicallyEnhanced,
+        DigitalSourceType::DigitalArt,
+        DigitalSourceType::VirtualRecording,
+        DigitalSourceType::ScreenCapture,
+        DigitalSourceType::Composite,
+        DigitalSourceType::CompositeSynthetic,
+        DigitalSourceType::TrainedAlgorithmicMedia,
+        DigitalSourceType::AlgorithmicMedia,
+        DigitalSourceType::DataDrivenMedia
+    ];
+
+    /* The term's name in the vocabulary, e.g. digitalCapture */
+    pub fn term(&self) -> &'static str {
+        match self {
+            DigitalSourceType::DigitalCapture => "digitalCapture",
+            DigitalSourceType::ComputationalCapture => "computationalCapture",
+            DigitalSourceType::NegativeFilm => "negativeFilm",
+            DigitalSourceType::PositiveFilm => "positiveFilm",
+            DigitalSourceType::Print => "print",
+            DigitalSourceType::HumanEdits => "humanEdits",
+            DigitalSourceType::MinorHumanEdits => "minorHumanEdits",
+            DigitalSourceType::CompositeCapture => "compositeCapture",
+            DigitalSourceType::AlgorithmicallyEnhanced => "algorithmicallyEnhanced",
+            DigitalSourceType::DigitalArt => "digitalArt",
+            DigitalSourceType::VirtualRecording => "virtualRecording",
+            DigitalSourceType::ScreenCapture => "screenCapture",
+            DigitalSourceType::Composite => "composite",
+            DigitalSourceType::CompositeSynthetic => "compositeSynthetic",
+            DigitalSourceType::TrainedAlgorithmicMedia => "trainedAlgorithmicMedia",
+            DigitalSourceType::AlgorithmicMedia => "algorithmicMedia",
+            DigitalSourceType::DataDrivenMedia => "dataDrivenMedia"
+        }
+    }
+
+    /* The term's URI, as recorded in an action's `digitalSourceType` */
+    pub fn uri(&self) -> String {
+        format!("http://cv.iptc.org/newscodes/digitalsourcetype/{}", self.term())
+    }
+}
+
+/**
+ * This tool as the structured software agent of the actions it records.
+ * A structured agent, rather than a plain string, makes the SDK write the
+ * v2 actions assertion (`c2pa.actions.v2`).
+ */
+pub fn 
+software_agent () -> ClaimGeneratorInfo {
+    let mut agent = ClaimGeneratorInfo::new("mikes-c2pa-test-code");
+    agent.set_version("0.1");
+    agent
+}
+
+/**
+ * Creates a new manifest for an image file. Represents a set of
+ * actions performed when creating a new media file, for example, after
+ * a digital image is taken. `strip_gps` leaves the GPS location out of the
+ * example Exif assertion, and with `AssertionsAndAsset` out of the image's
+ * own Exif too.
+ */
+pub fn 
+create_new_manifest (src_path: &Path, dest_path: &Path, format: &str, strip_gps: StripGps, signer: &dyn Signer) -> Result<(), c2pa::Error> {
+    let signed = new_manifest_asset(src_path, dest_path, format, strip_gps, signer)?;
+    write_file_atomically(dest_path, &signed)?;
+
+    Ok(())
+}
+
+/* As `create_new_manifest`, but returning the signed asset rather than writing it */
+pub fn 
+new_manifest_asset (src_path: &Path, dest_path: &Path, format: &str, strip_gps: StripGps, signer: &dyn Signer) -> Result<Vec<u8>, c2pa::Error> {
+    // ISO 8601 date and time format
+    let now_string = clock::recorded_time();
+
+    // Initialized new manifest with this tool as its claim generator
+    let mut builder = Builder::new();
+    builder.set_claim_generator_info(software_agent());
+
+    // A new `CreativeWork`, defined in schema.org https://schema.org/CreativeWork
+    // This represents the media created by the user, whose details are added to the 
+    // `CreativeWork` as the author.
+    let creative_work = CreativeWork::new()
+        .add_author(
+            SchemaDotOrgPerson::new()
+                .set_name("Mike Cvet")
+                  .expect("set name")
+                .set_identifier("mikecvet")
+                  .expect("set identifier")
+        )?;
+
+    // A new `Action` reflecting the creation of the `CreativeWork`.    
+    let created = Actions::new()
+        .add_action(
+            Action::new(c2pa_action::CREATED)
+                .set_source_type(DigitalSourceType::DigitalCapture.uri())
+                .set_software_agent(software_agent())
+                .set_when(now_string.clone())
+        );
+
+    // Example Exif data to be embedded into the `Manifest`    
+    let mut exif_json: serde_json::Value = serde_json::from_str(
+        r#"{
+        "@context" : {
+          "exif": "http://ns.adobe.com/exif/1.0/"
+        },
+        "exif:GPSLatitude": "48,15.7068N",
+        "exif:GPSLongitude": "16,15.9996W",
+        "exif:GPSTimeStamp": "2023-08-23T19:12:45Z"
+        }"#,
+    )?;
+    if strip_gps != StripGps::Keep {
+        strip_gps_fields(&mut exif_json);
+    }
+    let exif = Exif::from_json_str(&exif_json.to_string()).expect("exif");
+
+    // This is a verified credential string; see https://www.w3.org/TR/vc-data-model
+    let _vc = r#"{
+        "@context": [
+            "https://www.w3.org/2018/credentials/v1",
+            "http://schema.org"
+        ],
+        "type": [
+            "VerifiableCredential",
+            "NPPACredential"
+        ],
+        "issuer": "https://nppa.org/",
+        "credentialSubject": {
+            "id": "did:nppa:eb1bb9934d9896a374c384521410c7f14",
+            "name": "Bob Ross",
+            "memberOf": "https://nppa.org/"
+        },
+        "proof": {
+            "type": "RsaSignature2018",
+            "created": "2021-06-18T21:19:10Z",
+            "proofPurpose": "assertionMethod",
+            "verificationMethod":
+            "did:nppa:eb1bb9934d9896a374c384521410c7f14#_Qq0UL2Fq651Q0Fjd6TvnYE-faHiOpRlPVQcY_-tA4A",
+            "jws": "eyJhbGciOiJQUzI1NiIsImI2NCI6ZmFsc2UsImNyaXQiOlsiYjY0Il19DJBMvvFAIC00nSGB6Tn0XKbbF9XrsaJZREWvR2aONYTQQxnyXirtXnlewJMBBn2h9hfcGZrvnC1b6PgWmukzFJ1IiH1dWgnDIS81BH-IxXnPkbuYDeySorc4QU9MJxdVkY5EL4HYbcIfwKj6X4LBQ2_ZHZIu1jdqLcRZqHcsDF5KKylKc1THn5VRWy5WhYg_gBnyWny8E6Qkrze53MR7OuAmmNJ1m1nN8SxDrG6a08L78J0-Fbas5OjAQz3c17GY8mVuDPOBIOVjMEghBlgl3nOi1ysxbRGhHLEK4s0KKbeRogZdgt1DkQxDFxxn41QWDw_mmMCjs9qxg0zcZzqEJw"
+        }
+    }"#;
+
+    // Sets some basics of the manifest; the format is set from the asset when signing
+    builder.definition.title = Some(file_title(dest_path));
+
+    // Adds assertions about the content to the manifest
+    builder.add_assertion(labels::CREATIVE_WORK, &creative_work)?;
+    builder.add_assertion(labels::ACTIONS, &created)?;
+    builder.add_assertion(labels::EXIF, &exif)?;
+
+    // Add custom data under this label, in the label namespace
+    builder.add_assertion(namespace::label("test"), &MediaData::new(128, 256, "descriptive string".to_string()))?;
+
+    // For some reason, this causes manifest embedding to fail. AFAICT this is a valid formatting for verified credentials, pulled
+    // from SDK test code. 
+    // builder.add_verifiable_credential(&vc.to_string())?;
+
+    // Signs and embeds the manifest into the destination file. The format is given rather than taken from the
+    // file's extension, so files without one can be signed too.
+    let mut asset = read_file(src_path)?;
+    if strip_gps == StripGps::AssertionsAndAsset {
+        asset = strip_asset_gps(format, &asset)?.0;
+    }
+    apply_providers(&mut builder, format, &asset)?;
+    #[cfg(feature = "thumbnails")]
+    crate::thumbnail::apply_claim_thumbnail(&mut builder, format, &asset)?;
+    embed(format, &asset, &mut builder, signer)
+}
+
+// The standard `c2pa_action` vocabulary for edits. `c2pa.opened` is recorded with every
+// edit, and `c2pa.created` only describes new assets, so neither can be requested.
+pub const EDIT_ACTIONS: [&str; 13] = [
+    c2pa_action::COLOR_ADJUSTMENTS,
+    c2pa_action::CONVERTED,
+    c2pa_action::CROPPED,
+    c2pa_action::DRAWING,
+    c2pa_action::EDITED,
+    c2pa_action::FILTERED,
+    c2pa_action::ORIENTATION,
+    c2pa_action::PLACED,
+    c2pa_action::PUBLISHED,
+    c2pa_action::REPACKAGED,
+    c2pa_action::RESIZED,
+    c2pa_action::TRANSCODED,
+    c2pa_action::UNKNOWN
+];
+
+/**
+ * Resolves an edit action name, with or without its `c2pa.` prefix
+ * (`cropped` or `c2pa.cropped`), to its standard label.
+ */
+pub fn 
+edit_action (name: &str) -> Result<&'static str, c2pa::Error> {
+    let label = format!("c2pa.{}", name.strip_prefix("c2pa.").unwrap_or(name));
+
+    EDIT_ACTIONS.iter().copied().find(|action| *action == label).ok_or_else(|| {
+        let names: Vec<&str> = EDIT_ACTIONS.iter().map(|action| action.trim_start_matches("c2pa.")).collect();
+        c2pa::Error::BadParam(format!("unknown edit action '{}'; expected one of {}", name, names.join(", ")))
+    })
+}
+
+/**
+ * Splits an action argument into the action and the time it was done at,
+ * if one is given after an `@`, e.g. `cropped@2026-10-15T09:30:00+02:00`.
+ */
+pub fn 
+action_time (arg: &str) -> Result<(&str, Option<DateTime<FixedOffset>>), c2pa::Error> {
+    match arg.split_once('@') {
+        Some((action, when)) => Ok((action, Some(clock::parse_time(when)?))),
+        None => Ok((arg, None))
+    }
+}
+
+/**
+ * Checks a non-standard action label: vendor actions are namespaced by a
+ * reverse domain name the vendor controls, such as `com.example.stylize`,
+ * and the `c2pa.` namespace is reserved for the standard vocabulary. A
+ * bare name such as `stylize` is put in the label namespace.
+ */
+pub fn 
+custom_action (label: &str) -> Result<String, c2pa::Error> {
+    let label = &match label.contains('.') {
+        true => label.to_owned(),
+        false => namespace::label(label)
+    };
+    let components: Vec<&str> = label.split('.').collect();
+    let well_formed = components.len() >= 3 && components.iter().all(|component| {
+        !component.is_empty() && component.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    });
+
+    if label.starts_with("c2pa.") {
+        Err(c2pa::Error::BadParam(format!("custom action '{}' uses the reserved c2pa. namespace", label)))
+    } else if !well_formed {
+        Err(c2pa::Error::BadParam(format!("custom action '{}' should be a vendor-prefixed label such as com.example.stylize", label)))
+    } else {
+        Ok(label.clone())
+    }
+}
+
+/* An edit to record: a standard or vendor action label, and anything said about it */
+pub struct EditAction {
+    pub label: String,
+    pub description: Option<String>,
+    pub parameters: Vec<(String, serde_json::Value)>,
+    // When the edit was made, as RFC 3339, in place of the time it is recorded
+    pub when: Option<String>
+}
+
+impl EditAction {
+    pub fn new(label: &str) -> EditAction {
+        EditAction {
+            label: label.to_owned(),
+            description: None,
+            parameters: Vec::new(),
+            when: None
+        }
+    }
+
+    pub fn with_parameter<T: Serialize>(mut self, key: &str, value: T) -> Result<EditAction, c2pa::Error> {
+        self.parameters.push((key.to_owned(), serde_json::to_value(value)?));
+        Ok(self)
+    }
+}
+
+pub fn 
+edit_media_with_action (src_path: &Path, dest_path: &Path, format: &str, action: &str, signer: &dyn Signer) -> Result<(), c2pa::Error> {
+    edit_media_with_actions(src_path, dest_path, format, &[EditAction::new(action)], signer)
+}
+
+/**
+ * Records a set of edits to a media file in one derived manifest, which
+ * takes the file and its manifest as its parent ingredient.
+ */
+pub fn 
+edit_media_with_actions (src_path: &Path, dest_path: &Path, format: &str, actions: &[EditAction], signer: &dyn Signer) -> Result<(), c2pa::Error> {
+    // Manifests cannot be edited. To modify the contents of the manifest store, pull in earlier versions of the content
+    // and its manifest as an ingredient.
+    let asset = read_file(src_path)?;
+    let parent = parent_ingredient(src_path, format, &asset)?;
+
+    record_edits(parent, &asset, dest_path, format, actions, signer)
+}
+
+/* The version of a media file edits are made to, as the parent ingredient of the manifest recording them */
+pub fn 
+parent_ingredient (path: &Path, format: &str, asset: &[u8]) -> Result<Ingredient, c2pa::Error> {
+    titled_parent(&file_title(path), format, asset)
+}
+
+fn 
+titled_parent (title: &str, format: &str, asset: &[u8]) -> Result<Ingredient, c2pa::Error> {
+    let mut parent = Ingredient::from_memory(format, asset)?;
+    parent.set_title(title);
+    #[cfg(feature = "thumbnails")]
+    crate::thumbnail::apply_ingredient_thumbnail(&mut parent, format, asset)?;
+    Ok(parent)
+}
+
+/**
+ * Embeds a derived manifest recording `actions` into `asset`, writing it
+ * to `dest_path`. The parent ingredient is the version the edits were made
+ * to, which may differ from `asset` when the edits were actually carried
+ * out on its content.
+ */
+pub fn 
+record_edits (parent: Ingredient, asset: &[u8], dest_path: &Path, format: &str, actions: &[EditAction], signer: &dyn Signer) -> Result<(), c2pa::Error> {
+    let signed = embed_edits(parent, asset, &file_title(dest_path), format, actions, signer)?;
+    write_file_atomically(dest_path, &signed)?;
+
+    Ok(())
+}
+
+/* As `record_edits`, but returning the signed asset, titled `title`, rather than writing it */
+pub fn 
+embed_edits (mut parent: Ingredient, asset: &[u8], title: &str, format: &str, actions: &[EditAction], signer: &dyn Signer) -> Result<Vec<u8>, c2pa::Error> {
+    let mut builder = Builder::new();
+    builder.set_claim_generator_info(software_agent());
+    builder.definition.title = Some(title.to_owned());
+
+    let now_string = clock::recorded_time();
+
+    // also add an action that we opened the file
+    let mut assertion = Actions::new()
+        .add_action(
+            Action::new(c2pa_action::OPENED)
+                .set_parameter("identifier", parent.instance_id().to_owned())
+                .expect("set identifier")
+                .set_reason("editing")
+                .set_software_agent(software_agent())
+                .set_when(now_string.clone())
+        );
+
+    let mut templates = Vec::new();
+    for edit in actions {
+        let mut action = Action::new(&edit.label)
+            .set_parameter("identifier", parent.instance_id().to_owned())?;
+        for (key, value) in &edit.parameters {
+            action = action.set_parameter(key, value)?;
+        }
+
+        // Actions describe themselves through the template for their label
+        if let Some(description) = &edit.description {
+            templates.push(serde_json::json!({
+                "action": edit.label,
+                "description": description,
+                "softwareAgent": software_agent(),
+                "digitalSourceType": DigitalSourceType::MinorHumanEdits.uri()
+            }));
+        }
+
+        assertion = assertion.add_action(
+            action
+                .set_reason("editing")
+                .set_source_type(DigitalSourceType::MinorHumanEdits.uri())
+                .set_software_agent(software_agent())
+                .set_when(edit.when.clone().unwrap_or_else(|| now_string.clone()))
+        );
+    }
+    // The SDK doesn't export its template type, so templates go in through JSON
+    if !templates.is_empty() {
+        let mut json = serde_json::to_value(&assertion)?;
+        json["templates"] = serde_json::Value::Array(templates);
+        assertion = Actions::from_json_value(&json)?;
+    }
+
+    parent.set_is_parent();
+    builder.add_ingredient(parent);
+    builder.add_assertion(labels::ACTIONS, &assertion)?;
+    apply_providers(&mut builder, format, asset)?;
+    #[cfg(feature = "thumbnails")]
+    crate::thumbnail::apply_claim_thumbnail(&mut builder, format, asset)?;
+
+    embed(format, asset, &mut builder, signer)
+}
+
+/**
+ * A chain of derived manifests recorded on an asset held in memory, each
+ * taking the version before it as its parent ingredient. Only the final
+ * version is written, so a large asset isn't re-read and rewritten once per
+ * edit, and a chain which fails part way leaves the destination untouched.
+ */
+pub struct EditPipeline {
+    format: String,
+    // Titles the manifests take, from the file the result is written to
+    title: String,
+    // Title of the version the next edits are made to, which starts as the source file's
+    parent_title: String,
+    // Ratings of the version the next edits are made to, recorded on its ingredient
+    reviews: Vec<ReviewRating>,
+    // Instance ID given to the source file's ingredient in place of the one read from its XMP
+    parent_instance_id: Option<String>,
+    asset: Vec<u8>
+}
+
+impl EditPipeline {
+    /* Starts a chain on `asset`, read from `src_path`, to be written to `dest_path` */
+    pub fn new(src_path: &Path, dest_path: &Path, format: &str, asset: Vec<u8>) -> EditPipeline {
+        EditPipeline {
+            format: format.to_owned(),
+            title: file_title(dest_path),
+            parent_title: file_title(src_path),
+            reviews: Vec::new(),
+            parent_instance_id: None,
+            asset
+        }
+    }
+
+    /* Starts a chain on the file at `src_path` */
+    pub fn open(src_path: &Path, dest_path: &Path, format: &str) -> Result<EditPipeline, c2pa::Error> {
+        Ok(EditPipeline::new(src_path, dest_path, format, read_file(src_path)?))
+    }
+
+    /* Rates the current version, on the parent ingredient of the next manifest recorded */
+    pub fn review(&mut self, reviews: &[ReviewRating]) {
+        self.reviews.extend_from_slice(reviews);
+    }
+
+    /**
+     * Keys the source file's ingredient by `instance_id`, e.g. the ID an
+     * asset database holds it under; the versions after it keep theirs.
+     */
+    pub fn set_parent_instance_id(&mut self, instance_id: &str) {
+        self.parent_instance_id = Some(instance_id.to_owned());
+    }
+
+    /* Records `actions` in one derived manifest on the current version */
+    pub fn record(&mut self, actions: &[EditAction], signer: &dyn Signer) -> Result<(), c2pa::Error> {
+        let mut parent = titled_parent(&self.parent_title, &self.format, &self.asset)?;
+        if let Some(instance_id) = self.parent_instance_id.take() {
+            parent.set_instance_id(instance_id);
+        }
+        review_ingredient(&mut parent, &std::mem::take(&mut self.reviews));
+        self.asset = embed_edits(parent, &self.asset, &self.title, &self.format, actions, signer)?;
+        self.parent_title = self.title.clone();
+
+        Ok(())
+    }
+
+    /* Writes the final version to `dest_path` */
+    pub fn finish(self, dest_path: &Path) -> Result<(), c2pa::Error> {
+        write_file_atomically(dest_path, &self.asset)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,216 @@
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+
+use super::{has_label, live_metadata, MetadataBlock};
+
+/*
+ * Comparing the metadata signed into a manifest with the Exif, IPTC and XMP
+ * an asset carries now. A failed hash says that bytes changed, not what
+ * they were; Exif rewritten after signing, or assertions that never matched
+ * the asset, show up here field by field. Values are compared loosely, so
+ * that the forms Exif and XMP give the same value in agree: rationals such
+ * as "28/10" and 2.8, "2023:07:23 09:01:54" and "2023-07-23T09:01:54",
+ * GPS coordinates in degrees and minutes or seconds, and one-item lists and
+ * their item.
+ */
+
+const CREATIVE_WORK_LABEL: &str = "stds.schema-org.CreativeWork";
+
+// Fields of the live metadata naming who made the asset
+const CREATOR_FIELDS: [&str; 2] = ["dc:creator", "tiff:Artist"];
+
+/* How a live field differs from the signed one */
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DivergenceKind {
+    Changed,
+    // signed, but no longer in the asset
+    Removed,
+    // in the asset, but not in the signed assertion for its block
+    Unsigned
+}
+
+/* One field whose live value differs from the signed one */
+#[derive(Debug, Serialize)]
+pub struct Divergence {
+    pub assertion: String,
+    pub field: String,
+    pub kind: DivergenceKind,
+    pub signed: Option<Value>,
+    pub live: Option<Value>
+}
+
+/* The outcome of `check_metadata` */
+#[derive(Debug, Default, Serialize)]
+pub struct MetadataCheck {
+    // labels of the signed assertions compared with the asset's metadata
+    pub compared: Vec<String>,
+    // labels of signed metadata assertions whose block the asset doesn't carry
+    pub uncompared: Vec<String>,
+    pub divergences: Vec<Divergence>
+}
+
+/* A coordinate such as "39,21.102N" or "39,21,6.12N" in signed decimal degrees */
+fn 
+coordinate (text: &str) -> Option<f64> {
+    let sign = match text.chars().last()? {
+        'N' | 'E' => 1.0,
+        'S' | 'W' => -1.0,
+        _ => return None
+    };
+    let parts = text[..text.len() - 1].split(',').map(|part| part.trim().parse::<f64>().ok()).collect::<Option<Vec<_>>>()?;
+    let degrees = match parts.as_slice() {
+        [degrees, minutes] => degrees + minutes / 60.0,
+        [degrees, minutes, seconds] => degrees + minutes / 60.0 + seconds / 3600.0,
+        _ => return None
+    };
+
+    Some(sign * degrees)
+}
+
+/* A number given as text, plainly or as a rational such as "1/200" */
+fn 
+number (text: &str) -> Option<f64> {
+    let number = match text.split_once('/') {
+        Some((numerator, denominator)) => numerator.trim().parse::<f64>().ok()? / denominator.trim().parse::<f64>().ok()?,
+        None => text.parse::<f64>().ok()?
+    };
+
+    number.is_finite().then_some(number)
+}
+
+/* A value in one form whichever of Exif or XMP it came from */
+fn 
+normalize (value: &Value) -> Value {
+    match value {
+        Value::Array(items) if items.len() == 1 => normalize(&items[0]),
+        Value::Array(items) => Value::Array(items.iter().map(normalize).collect()),
+        Value::String(text) => {
+            let text = text.trim();
+            if let Some(number) = number(text).or_else(|| coordinate(text)) {
+                return json!(number);
+            }
+            // Exif dates are "2023:07:23 09:01:54"
+            match text.as_bytes() {
+                [_, _, _, _, b':', _, _, b':', _, _, b' ', ..] => Value::String(format!("{}-{}-{}T{}", &text[..4], &text[5..7], &text[8..10], &text[11..])),
+                _ => Value::String(text.to_owned())
+            }
+        },
+        value => value.clone()
+    }
+}
+
+/* Whether a signed and a live value agree, once normalized */
+fn 
+same (signed: &Value, live: &Value) -> bool {
+    match (normalize(signed), normalize(live)) {
+        (Value::Number(signed), Value::Number(live)) => {
+            let (signed, live) = (signed.as_f64().unwrap_or_default(), live.as_f64().unwrap_or_default());
+            (signed - live).abs() <= 1e-6 * signed.abs().max(live.abs()).max(1.0)
+        },
+        (Value::Array(signed), Value::Array(live)) => signed.len() == live.len() && signed.iter().zip(&live).all(|(signed, live)| same(signed, live)),
+        // Exif times have no zone, so only the date and time are compared
+        (Value::String(signed), Value::String(live)) if signed.len().min(live.len()) >= 19 && signed.as_bytes()[10] == b'T' && live.as_bytes()[10] == b'T' =>
+            signed.as_bytes()[..19] == live.as_bytes()[..19],
+        (signed, live) => signed == live
+    }
+}
+
+/* Compares the fields of one signed assertion with the live block it carries */
+fn 
+compare (label: &str, block: MetadataBlock, signed: &Map<String, Value>, live: &Map<String, Value>, divergences: &mut Vec<Divergence>) {
+    let divergence = |field: &str, kind, signed: Option<&Value>, live: Option<&Value>| Divergence {
+        assertion: label.to_owned(),
+        field: field.to_owned(),
+        kind,
+        signed: signed.cloned(),
+        live: live.cloned()
+    };
+
+    // JSON-LD keywords such as @context aren't metadata
+    for (field, value) in signed.iter().filter(|(field, _)| !field.starts_with('@')) {
+        match live.get(field) {
+            Some(now) if !same(value, now) => divergences.push(divergence(field, DivergenceKind::Changed, Some(value), Some(now))),
+            None if block.captures(field) => divergences.push(divergence(field, DivergenceKind::Removed, Some(value), None)),
+            _ => ()
+        }
+    }
+    for (field, now) in live.iter().filter(|(field, _)| !field.starts_with('@') && !signed.contains_key(*field)) {
+        divergences.push(divergence(field, DivergenceKind::Unsigned, None, Some(now)));
+    }
+}
+
+/* The names of a CreativeWork's authors, given as objects with a name or as plain strings */
+fn 
+author_names (creative_work: &Value) -> Vec<String> {
+    let authors = match &creative_work["author"] {
+        Value::Array(authors) => authors.iter().collect(),
+        Value::Null => Vec::new(),
+        author => vec![author]
+    };
+
+    authors.into_iter()
+        .filter_map(|author| author.as_str().or_else(|| author["name"].as_str()))
+        .map(str::to_owned)
+        .collect()
+}
+
+/**
+ * Compares the metadata assertions of a manifest (as `read` prints it)
+ * with the Exif, IPTC and XMP of a JPEG: the fields of `stds.exif`,
+ * `stds.iptc` and `c2pa.metadata` or `stds.xmp` with the block each came
+ * from, and the CreativeWork authors with the creators the metadata names.
+ */
+pub fn 
+check_metadata (manifest: &Value, format: &str, asset: &[u8]) -> Result<MetadataCheck, c2pa::Error> {
+    let live = live_metadata(format, asset)?;
+    let mut check = MetadataCheck::default();
+    let assertions = manifest["assertions"].as_array().map(Vec::as_slice).unwrap_or_default();
+
+    for assertion in assertions {
+        let label = assertion["label"].as_str().unwrap_or_default();
+        let (Some(block), Some(signed)) = (MetadataBlock::of_label(label), assertion["data"].as_object()) else {
+            continue;
+        };
+        match live.iter().find(|captured| captured.block == block) {
+            Some(captured) => {
+                compare(label, block, signed, &captured.fields, &mut check.divergences);
+                check.compared.push(label.to_owned());
+            },
+            None => check.uncompared.push(label.to_owned())
+        }
+    }
+
+    // Every creator named in the metadata should be a signed author
+    let mut creators: Vec<String> = Vec::new();
+    for value in live.iter().flat_map(|captured| CREATOR_FIELDS.iter().filter_map(|field| captured.fields.get(*field))) {
+        let names = match value {
+            Value::Array(names) => names.iter().filter_map(Value::as_str).collect(),
+            value => value.as_str().into_iter().collect::<Vec<_>>()
+        };
+        for name in names {
+            if !creators.iter().any(|creator| creator == name) {
+                creators.push(name.to_owned());
+            }
+        }
+    }
+    for assertion in assertions.iter().filter(|assertion| assertion["label"].as_str().is_some_and(|label| has_label(label, CREATIVE_WORK_LABEL))) {
+        let authors = author_names(&assertion["data"]);
+        if authors.is_empty() || creators.is_empty() {
+            continue;
+        }
+        let label = assertion["label"].as_str().unwrap_or_default().to_owned();
+        if creators.iter().any(|creator| !authors.contains(creator)) {
+            check.divergences.push(Divergence {
+                assertion: label.clone(),
+                field: "author".to_owned(),
+                kind: DivergenceKind::Changed,
+                signed: Some(json!(authors)),
+                live: Some(json!(creators))
+            });
+        }
+        check.compared.push(label);
+    }
+
+    Ok(check)
+}
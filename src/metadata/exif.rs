@@ -0,0 +1,307 @@
+use serde_json::{json, Map, Value};
+
+use super::malformed;
+
+/*
+ * Exif, a TIFF structure of tagged fields grouped into IFDs: IFD0 with the
+ * basic image tags, and the Exif and GPS IFDs it points to. Fields with an
+ * XMP name are captured under it, as the `stds.exif` assertion expects.
+ */
+
+// Tags of IFD0's pointers to the Exif and GPS IFDs
+const EXIF_IFD_TAG: u16 = 0x8769;
+pub(super) const GPS_IFD_TAG: u16 = 0x8825;
+
+const IFD0_TAGS: [(u16, &str); 13] = [
+    (0x0100, "tiff:ImageWidth"),
+    (0x0101, "tiff:ImageLength"),
+    (0x010e, "tiff:ImageDescription"),
+    (0x010f, "tiff:Make"),
+    (0x0110, "tiff:Model"),
+    (0x0112, "tiff:Orientation"),
+    (0x011a, "tiff:XResolution"),
+    (0x011b, "tiff:YResolution"),
+    (0x0128, "tiff:ResolutionUnit"),
+    (0x0131, "tiff:Software"),
+    (0x0132, "tiff:DateTime"),
+    (0x013b, "tiff:Artist"),
+    (0x8298, "tiff:Copyright")
+];
+
+const EXIF_TAGS: [(u16, &str); 30] = [
+    (0x829a, "exif:ExposureTime"),
+    (0x829d, "exif:FNumber"),
+    (0x8822, "exif:ExposureProgram"),
+    (0x8827, "exif:ISOSpeedRatings"),
+    (0x9000, "exif:ExifVersion"),
+    (0x9003, "exif:DateTimeOriginal"),
+    (0x9004, "exif:DateTimeDigitized"),
+    (0x9010, "exifEX:OffsetTime"),
+    (0x9011, "exifEX:OffsetTimeOriginal"),
+    (0x9201, "exif:ShutterSpeedValue"),
+    (0x9202, "exif:ApertureValue"),
+    (0x9204, "exif:ExposureBiasValue"),
+    (0x9205, "exif:MaxApertureValue"),
+    (0x9207, "exif:MeteringMode"),
+    (0x9208, "exif:LightSource"),
+    (0x9209, "exif:Flash"),
+    (0x920a, "exif:FocalLength"),
+    (0xa001, "exif:ColorSpace"),
+    (0xa002, "exif:PixelXDimension"),
+    (0xa003, "exif:PixelYDimension"),
+    (0xa402, "exif:ExposureMode"),
+    (0xa403, "exif:WhiteBalance"),
+    (0xa405, "exif:FocalLengthIn35mmFilm"),
+    (0xa406, "exif:SceneCaptureType"),
+    (0xa420, "exif:ImageUniqueID"),
+    (0xa430, "exifEX:CameraOwnerName"),
+    (0xa431, "exifEX:BodySerialNumber"),
+    (0xa433, "exifEX:LensMake"),
+    (0xa434, "exifEX:LensModel"),
+    (0xa435, "exifEX:LensSerialNumber")
+];
+
+const GPS_TAGS: [(u16, &str); 11] = [
+    (0x0000, "exif:GPSVersionID"),
+    (0x0001, "exif:GPSLatitudeRef"),
+    (0x0002, "exif:GPSLatitude"),
+    (0x0003, "exif:GPSLongitudeRef"),
+    (0x0004, "exif:GPSLongitude"),
+    (0x0005, "exif:GPSAltitudeRef"),
+    (0x0006, "exif:GPSAltitude"),
+    (0x0007, "exif:GPSTimeStamp"),
+    (0x0010, "exif:GPSImgDirectionRef"),
+    (0x0011, "exif:GPSImgDirection"),
+    (0x001d, "exif:GPSDateStamp")
+];
+
+/* A TIFF structure, as Exif is stored, in either byte order */
+struct Tiff<B> {
+    bytes: B,
+    little_endian: bool
+}
+
+impl<B: AsRef<[u8]>> Tiff<B> {
+    fn new(bytes: B) -> Result<Tiff<B>, c2pa::Error> {
+        let little_endian = match bytes.as_ref().get(..2) {
+            Some(b"II") => true,
+            Some(b"MM") => false,
+            _ => return Err(malformed("Exif"))
+        };
+        Ok(Tiff { bytes, little_endian })
+    }
+
+    fn get(&self, at: usize, len: usize) -> Result<&[u8], c2pa::Error> {
+        self.bytes.as_ref().get(at..at.checked_add(len).ok_or_else(|| malformed("Exif"))?).ok_or_else(|| malformed("Exif"))
+    }
+
+    fn u16(&self, at: usize) -> Result<u16, c2pa::Error> {
+        let b = self.get(at, 2)?;
+        Ok(if self.little_endian { u16::from_le_bytes([b[0], b[1]]) } else { u16::from_be_bytes([b[0], b[1]]) })
+    }
+
+    fn u32(&self, at: usize) -> Result<usize, c2pa::Error> {
+        let b = self.get(at, 4)?;
+        let bytes = [b[0], b[1], b[2], b[3]];
+        Ok(if self.little_endian { u32::from_le_bytes(bytes) } else { u32::from_be_bytes(bytes) } as usize)
+    }
+
+    fn f64(&self, at: usize) -> Result<f64, c2pa::Error> {
+        let b = self.get(at, 8)?;
+        let bytes = [b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]];
+        Ok(if self.little_endian { f64::from_le_bytes(bytes) } else { f64::from_be_bytes(bytes) })
+    }
+
+    /* The value of the IFD entry at `entry` as JSON: text, a number or an array of numbers, if it has a sensible form */
+    fn value(&self, entry: usize) -> Result<Option<Value>, c2pa::Error> {
+        let field_type = self.u16(entry + 2)?;
+        let count = self.u32(entry + 4)?;
+        let size = type_size(field_type).checked_mul(count).ok_or_else(|| malformed("Exif"))?;
+        // Values of more than four bytes are stored outside the IFD, at an offset
+        let at = if size > 4 { self.u32(entry + 8)? } else { entry + 8 };
+        let bytes = self.get(at, size)?;
+
+        let mut numbers = Vec::with_capacity(count);
+        for i in 0..count {
+            let number = match field_type {
+                1 => json!(bytes[i]),
+                6 => json!(bytes[i] as i8),
+                3 => json!(self.u16(at + 2 * i)?),
+                8 => json!(self.u16(at + 2 * i)? as i16),
+                4 => json!(self.u32(at + 4 * i)?),
+                9 => json!(self.u32(at + 4 * i)? as u32 as i32),
+                5 | 10 => {
+                    let (numerator, denominator) = (self.u32(at + 8 * i)? as u32, self.u32(at + 8 * i + 4)? as u32);
+                    if denominator == 0 {
+                        return Ok(None);
+                    }
+                    match field_type {
+                        5 => json!(numerator as f64 / denominator as f64),
+                        _ => json!(numerator as i32 as f64 / denominator as i32 as f64)
+                    }
+                },
+                11 => json!(f32::from_bits(self.u32(at + 4 * i)? as u32)),
+                12 => json!(self.f64(at + 8 * i)?),
+                // ASCII, and the few undefined-type tags captured, such as ExifVersion, are text
+                2 | 7 => {
+                    let text = String::from_utf8_lossy(bytes.split(|&b| b == 0).next().unwrap_or_default()).trim().to_owned();
+                    return Ok((!text.is_empty()).then_some(Value::String(text)));
+                },
+                _ => return Ok(None)
+            };
+            numbers.push(number);
+        }
+
+        Ok(match numbers.len() {
+            0 => None,
+            1 => numbers.pop(),
+            _ => Some(Value::Array(numbers))
+        })
+    }
+
+    /* Captures the fields of the IFD at `ifd` named in `tags`, returning its entries */
+    fn capture_ifd(&self, ifd: usize, tags: &[(u16, &str)], fields: &mut Map<String, Value>) -> Result<Vec<(u16, usize)>, c2pa::Error> {
+        let count = self.u16(ifd)? as usize;
+        let mut entries = Vec::with_capacity(count);
+        for i in 0..count {
+            let entry = ifd + 2 + 12 * i;
+            let tag = self.u16(entry)?;
+            if let Some((_, name)) = tags.iter().find(|(known, _)| *known == tag) {
+                if let Some(value) = self.value(entry)? {
+                    fields.insert((*name).to_owned(), value);
+                }
+            }
+            entries.push((tag, entry));
+        }
+
+        Ok(entries)
+    }
+}
+
+impl<B: AsRef<[u8]> + AsMut<[u8]>> Tiff<B> {
+    fn set_u16(&mut self, at: usize, value: u16) {
+        let bytes = if self.little_endian { value.to_le_bytes() } else { value.to_be_bytes() };
+        self.bytes.as_mut()[at..at + 2].copy_from_slice(&bytes);
+    }
+
+    fn zero(&mut self, at: usize, len: usize) -> Result<(), c2pa::Error> {
+        self.get(at, len)?;
+        self.bytes.as_mut()[at..at + len].fill(0);
+        Ok(())
+    }
+}
+
+/* Size in bytes of one value of a TIFF field type; unknown types are taken as bytes */
+fn 
+type_size (field_type: u16) -> usize {
+    match field_type {
+        3 | 8 => 2,
+        4 | 9 | 11 | 13 => 4,
+        5 | 10 | 12 => 8,
+        _ => 1
+    }
+}
+
+/**
+ * Zeroes the GPS IFD of a TIFF structure and the values it points to, and
+ * removes IFD0's pointer to it, moving the entries after it up. Returns
+ * whether there was a GPS IFD.
+ */
+pub(super) fn 
+strip_gps_ifd (tiff: &mut [u8]) -> Result<bool, c2pa::Error> {
+    let mut tiff = Tiff::new(tiff)?;
+
+    let ifd0 = tiff.u32(4)?;
+    let count = tiff.u16(ifd0)? as usize;
+    let entry = |i: usize| ifd0 + 2 + 12 * i;
+    let mut pointer = None;
+    for i in 0..count {
+        if tiff.u16(entry(i))? == GPS_IFD_TAG {
+            pointer = Some(i);
+            break;
+        }
+    }
+    let Some(pointer) = pointer else {
+        return Ok(false);
+    };
+
+    let gps = tiff.u32(entry(pointer) + 8)?;
+    let gps_count = tiff.u16(gps)? as usize;
+    for i in 0..gps_count {
+        let field = gps + 2 + 12 * i;
+        let size = type_size(tiff.u16(field + 2)?).checked_mul(tiff.u32(field + 4)?).ok_or_else(|| malformed("Exif"))?;
+        if size > 4 {
+            let offset = tiff.u32(field + 8)?;
+            tiff.zero(offset, size)?;
+        }
+    }
+    tiff.zero(gps, 2 + 12 * gps_count + 4)?;
+
+    // Entries after the pointer, and the offset of the next IFD, move up over it
+    let end = entry(count) + 4;
+    tiff.get(ifd0, end - ifd0)?;
+    tiff.bytes.copy_within(entry(pointer + 1)..end, entry(pointer));
+    tiff.bytes[end - 12..end].fill(0);
+    tiff.set_u16(ifd0, count as u16 - 1);
+
+    Ok(true)
+}
+
+/**
+ * The fields of a TIFF structure with an XMP name, keyed by it as in a
+ * `stds.exif` assertion. Rationals become decimals, and GPS coordinates
+ * take the XMP form, e.g. "39,21.102N".
+ */
+pub(super) fn 
+capture (tiff: &[u8]) -> Result<Map<String, Value>, c2pa::Error> {
+    let tiff = Tiff::new(tiff)?;
+    let mut fields = Map::new();
+
+    // Only IFD0's pointers are followed, so a malformed file can't loop
+    for (tag, entry) in tiff.capture_ifd(tiff.u32(4)?, &IFD0_TAGS, &mut fields)? {
+        match tag {
+            EXIF_IFD_TAG => { tiff.capture_ifd(tiff.u32(entry + 8)?, &EXIF_TAGS, &mut fields)?; },
+            GPS_IFD_TAG => { tiff.capture_ifd(tiff.u32(entry + 8)?, &GPS_TAGS, &mut fields)?; },
+            _ => ()
+        }
+    }
+
+    for axis in ["Latitude", "Longitude"] {
+        let coordinate = format!("exif:GPS{}", axis);
+        let reference = format!("exif:GPS{}Ref", axis);
+        let xmp = match (fields.get(&coordinate), fields.get(&reference)) {
+            (Some(Value::Array(parts)), Some(Value::String(hemisphere))) => {
+                let part = |i: usize| parts.get(i).and_then(Value::as_f64);
+                match (part(0), part(1), part(2)) {
+                    (Some(degrees), Some(minutes), Some(seconds)) => Some(format!("{},{:.4}{}", degrees, minutes + seconds / 60.0, hemisphere)),
+                    _ => None
+                }
+            },
+            _ => None
+        };
+        if let Some(xmp) = xmp {
+            fields.insert(coordinate, Value::String(xmp));
+            fields.remove(&reference);
+        }
+    }
+
+    if !fields.is_empty() {
+        fields.insert("@context".to_owned(), json!({
+            "exif": "http://ns.adobe.com/exif/1.0/",
+            "exifEX": "http://cipa.jp/exif/1.0/",
+            "tiff": "http://ns.adobe.com/tiff/1.0/"
+        }));
+    }
+
+    Ok(fields)
+}
+
+/**
+ * Whether `capture` reads a field, so its absence from the Exif means it
+ * isn't there. The latitude and longitude references are folded into the
+ * coordinates, so they count as unread.
+ */
+pub(super) fn 
+is_captured (name: &str) -> bool {
+    !name.ends_with("itudeRef") && IFD0_TAGS.iter().chain(&EXIF_TAGS).chain(&GPS_TAGS).any(|(_, known)| *known == name)
+}
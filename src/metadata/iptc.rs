@@ -0,0 +1,127 @@
+use serde_json::{json, Map, Value};
+
+use super::malformed;
+
+/*
+ * IPTC IIM, as stored in the Photoshop image resources of a JPEG's APP13
+ * segment: a sequence of datasets, of which the application record
+ * (record 2) holds the descriptive fields. Those with an IPTC Core XMP name
+ * are captured under it, as the `stds.iptc` assertion expects.
+ */
+
+// Photoshop image resource holding IIM
+const IIM_RESOURCE: u16 = 0x0404;
+
+const DATASETS: [(u8, &str); 18] = [
+    (5, "dc:title"),
+    (25, "dc:subject"),
+    (40, "photoshop:Instructions"),
+    (55, "photoshop:DateCreated"),
+    (80, "dc:creator"),
+    (85, "photoshop:AuthorsPosition"),
+    (90, "photoshop:City"),
+    (92, "Iptc4xmpCore:Location"),
+    (95, "photoshop:State"),
+    (100, "Iptc4xmpCore:CountryCode"),
+    (101, "photoshop:Country"),
+    (103, "photoshop:TransmissionReference"),
+    (105, "photoshop:Headline"),
+    (110, "photoshop:Credit"),
+    (115, "photoshop:Source"),
+    (116, "dc:rights"),
+    (120, "dc:description"),
+    (122, "photoshop:CaptionWriter")
+];
+
+// Datasets which may repeat, captured as lists: keywords and by-lines
+const REPEATABLE: [u8; 2] = [25, 80];
+
+/* The IIM of Photoshop image resources, each `8BIM`, an ID, a padded name and a padded length of data */
+fn 
+iim_resource (resources: &[u8]) -> Result<Option<&[u8]>, c2pa::Error> {
+    let mut i = 0;
+    while i < resources.len() {
+        let header = resources.get(i..i + 6).ok_or_else(|| malformed("IPTC"))?;
+        if &header[..4] != b"8BIM" {
+            return Err(malformed("IPTC"));
+        }
+        let id = u16::from_be_bytes([header[4], header[5]]);
+
+        // A Pascal string, padded to an even length along with its length byte
+        let name_len = *resources.get(i + 6).ok_or_else(|| malformed("IPTC"))? as usize;
+        let at = i + 6 + (name_len + 1).next_multiple_of(2);
+        let len = resources.get(at..at + 4).map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]) as usize).ok_or_else(|| malformed("IPTC"))?;
+        let data = resources.get(at + 4..at + 4 + len).ok_or_else(|| malformed("IPTC"))?;
+
+        if id == IIM_RESOURCE {
+            return Ok(Some(data));
+        }
+        i = at + 4 + len.next_multiple_of(2);
+    }
+
+    Ok(None)
+}
+
+/**
+ * The application record datasets of the Photoshop image resources of an
+ * APP13 segment (after its `Photoshop 3.0` header) which have an XMP name,
+ * keyed by it as in a `stds.iptc` assertion.
+ */
+pub(super) fn 
+capture (resources: &[u8]) -> Result<Map<String, Value>, c2pa::Error> {
+    let mut fields = Map::new();
+    let Some(iim) = iim_resource(resources)? else {
+        return Ok(fields);
+    };
+
+    let mut i = 0;
+    // Each dataset is a 0x1c tag marker, the record and dataset numbers, and a length
+    while let Some(&[0x1c, record, dataset, high, low]) = iim.get(i..i + 5) {
+        let mut len = u16::from_be_bytes([high, low]) as usize;
+        i += 5;
+        // Lengths over 32767 are given in the number of bytes that follow
+        if len & 0x8000 != 0 {
+            let bytes = iim.get(i..i + (len & 0x7fff)).filter(|b| b.len() <= 4).ok_or_else(|| malformed("IPTC"))?;
+            i += bytes.len();
+            len = bytes.iter().fold(0, |len, &b| len << 8 | b as usize);
+        }
+        let value = iim.get(i..i + len).ok_or_else(|| malformed("IPTC"))?;
+        i += len;
+
+        let Some((_, name)) = DATASETS.iter().find(|(known, _)| record == 2 && *known == dataset) else {
+            continue;
+        };
+        let mut text = String::from_utf8_lossy(value).trim_end_matches('\0').trim().to_owned();
+        // Dates are CCYYMMDD
+        if dataset == 55 && text.len() == 8 && text.bytes().all(|b| b.is_ascii_digit()) {
+            text = format!("{}-{}-{}", &text[..4], &text[4..6], &text[6..]);
+        }
+        if text.is_empty() {
+            continue;
+        }
+
+        if REPEATABLE.contains(&dataset) {
+            if let Value::Array(items) = fields.entry(*name).or_insert_with(|| json!([])) {
+                items.push(Value::String(text));
+            }
+        } else {
+            fields.insert((*name).to_owned(), Value::String(text));
+        }
+    }
+
+    if !fields.is_empty() {
+        fields.insert("@context".to_owned(), json!({
+            "dc": "http://purl.org/dc/elements/1.1/",
+            "photoshop": "http://ns.adobe.com/photoshop/1.0/",
+            "Iptc4xmpCore": "http://iptc.org/std/Iptc4xmpCore/1.0/xmlns/"
+        }));
+    }
+
+    Ok(fields)
+}
+
+/* Whether `capture` reads a field, so its absence from the IIM means it isn't there */
+pub(super) fn 
+is_captured (name: &str) -> bool {
+    DATASETS.iter().any(|(_, known)| *known == name)
+}
@@ -0,0 +1,452 @@
+use std::ops::Range;
+use std::str::FromStr;
+
+use glob::Pattern;
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use sha2::{Digest, Sha256};
+
+/*
+ * Scrubbing privacy-sensitive metadata before it is signed. Once signed, a
+ * field can't be removed without invalidating the manifest, so precise
+ * locations are best dropped beforehand: GPS fields are removed from the
+ * Exif assertions of a manifest definition and, for JPEGs, from the Exif
+ * block of the asset itself, leaving the rest of the Exif as it was. A
+ * `RedactionPolicy` drops or hashes any other fields of the metadata
+ * assertions, such as serial numbers and operator names. Whole Exif, XMP
+ * and IPTC blocks can also be removed from a JPEG once their content has
+ * been captured into assertions, so it is only carried, and signed, once.
+ */
+
+pub mod check;
+mod exif;
+mod iptc;
+pub mod standard;
+mod xmp;
+
+const EXIF_LABEL: &str = "stds.exif";
+
+// Labels of the assertions carrying Exif, IPTC and XMP metadata
+const METADATA_LABELS: [&str; 5] = [EXIF_LABEL, "stds.iptc", "stds.xmp", "c2pa.metadata", "cawg.metadata"];
+
+// Headers identifying the APP1 and APP13 segments of a JPEG which hold each kind of metadata
+const EXIF_HEADER: &[u8] = b"Exif\0\0";
+const XMP_HEADER: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+const EXTENDED_XMP_HEADER: &[u8] = b"http://ns.adobe.com/xmp/extension/\0";
+const IPTC_HEADER: &[u8] = b"Photoshop 3.0\0";
+
+/* Where GPS data is removed from before signing */
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum StripGps {
+    #[default]
+    Keep,
+    Assertions,
+    // the asset's own Exif as well as the assertions
+    AssertionsAndAsset
+}
+
+fn 
+malformed (what: &str) -> c2pa::Error {
+    c2pa::Error::BadParam(format!("malformed {}", what))
+}
+
+/* Whether an assertion label is one of the metadata assertions */
+pub fn 
+is_metadata_label (label: &str) -> bool {
+    METADATA_LABELS.iter().any(|base| has_label(label, base))
+}
+
+/* Whether `label` is `base`, a more specific label under it (base.photo-metadata), or another instance of it (base__2) */
+fn 
+has_label (label: &str, base: &str) -> bool {
+    label.strip_prefix(base).is_some_and(|rest| rest.is_empty() || rest.starts_with('.') || rest.starts_with("__"))
+}
+
+/* Whether an Exif assertion field, e.g. exif:GPSLatitude, is part of the location */
+fn 
+is_gps_field (key: &str) -> bool {
+    key.rsplit(':').next().unwrap_or(key).starts_with("GPS")
+}
+
+/* Removes the GPS fields from the data of one Exif assertion, returning how many there were */
+pub fn 
+strip_gps_fields (exif: &mut Value) -> usize {
+    match exif.as_object_mut() {
+        Some(fields) => {
+            let before = fields.len();
+            fields.retain(|key, _| !is_gps_field(key));
+            before - fields.len()
+        },
+        None => 0
+    }
+}
+
+/**
+ * Removes the GPS fields from every Exif assertion of a manifest definition
+ * (the JSON `c2patool` accepts), returning how many there were.
+ */
+pub fn 
+strip_gps_assertions (definition: &mut Value) -> usize {
+    let Some(assertions) = definition.get_mut("assertions").and_then(Value::as_array_mut) else {
+        return 0;
+    };
+
+    assertions.iter_mut()
+        .filter(|assertion| assertion["label"].as_str().is_some_and(|label| has_label(label, EXIF_LABEL)))
+        .filter_map(|assertion| assertion.get_mut("data"))
+        .map(strip_gps_fields)
+        .sum()
+}
+
+/**
+ * Fields to drop from the metadata assertions of a manifest definition, or
+ * to replace with a SHA-256 hash of their value, loaded from JSON such as
+ * `{"drop": ["exif:BodySerialNumber", "exif:GPS*"], "hash": ["dc:creator"], "salt": "..."}`.
+ * Patterns are globs matched against prefixed field names at any depth;
+ * fields matching both lists are dropped. Hashes are salted with `salt`, if
+ * given, so short values such as names can't simply be looked up.
+ */
+#[derive(Debug, Default)]
+pub struct RedactionPolicy {
+    pub drop: Vec<Pattern>,
+    pub hash: Vec<Pattern>,
+    pub salt: String
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PolicyFile {
+    #[serde(default)]
+    drop: Vec<String>,
+    #[serde(default)]
+    hash: Vec<String>,
+    #[serde(default)]
+    salt: String
+}
+
+/* How many fields a `RedactionPolicy` dropped and hashed */
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Redacted {
+    pub dropped: usize,
+    pub hashed: usize
+}
+
+impl RedactionPolicy {
+    pub fn from_json(json: &str) -> Result<RedactionPolicy, c2pa::Error> {
+        let file: PolicyFile = serde_json::from_str(json)?;
+        let compile = |patterns: Vec<String>| patterns.iter()
+            .map(|pattern| Pattern::new(pattern).map_err(|e| c2pa::Error::BadParam(format!("bad field pattern '{}': {}", pattern, e))))
+            .collect::<Result<Vec<_>, _>>();
+
+        Ok(RedactionPolicy {
+            drop: compile(file.drop)?,
+            hash: compile(file.hash)?,
+            salt: file.salt
+        })
+    }
+
+    pub fn from_file(path: &str) -> Result<RedactionPolicy, c2pa::Error> {
+        RedactionPolicy::from_json(&std::fs::read_to_string(path)?)
+    }
+
+    /* Applies the policy to every metadata assertion of a manifest definition */
+    pub fn redact(&self, definition: &mut Value) -> Redacted {
+        let mut redacted = Redacted::default();
+        let Some(assertions) = definition.get_mut("assertions").and_then(Value::as_array_mut) else {
+            return redacted;
+        };
+
+        for assertion in assertions {
+            if assertion["label"].as_str().is_some_and(is_metadata_label) {
+                if let Some(data) = assertion.get_mut("data") {
+                    self.redact_value(data, &mut redacted);
+                }
+            }
+        }
+
+        redacted
+    }
+
+    fn redact_value(&self, value: &mut Value, redacted: &mut Redacted) {
+        let matching = |patterns: &[Pattern], key: &str| patterns.iter().any(|pattern| pattern.matches(key));
+
+        match value {
+            Value::Object(fields) => {
+                // JSON-LD keywords such as @context are never redacted
+                fields.retain(|key, _| {
+                    let drop = !key.starts_with('@') && matching(&self.drop, key);
+                    redacted.dropped += drop as usize;
+                    !drop
+                });
+                for (key, field) in fields.iter_mut() {
+                    if !key.starts_with('@') && matching(&self.hash, key) {
+                        *field = Value::String(self.hash_value(field));
+                        redacted.hashed += 1;
+                    } else {
+                        self.redact_value(field, redacted);
+                    }
+                }
+            },
+            Value::Array(items) => {
+                for item in items {
+                    self.redact_value(item, redacted);
+                }
+            },
+            _ => ()
+        }
+    }
+
+    /* `sha256:<hex>` of the salt followed by a string's text, or any other value's JSON */
+    fn hash_value(&self, value: &Value) -> String {
+        let text = match value {
+            Value::String(text) => text.clone(),
+            value => value.to_string()
+        };
+        let digest = Sha256::digest(format!("{}{}", self.salt, text));
+
+        format!("sha256:{}", hex::encode(digest))
+    }
+}
+
+/**
+ * Removes the GPS IFD from the Exif of a JPEG, returning the asset with its
+ * fields and their values zeroed and IFD0's pointer to it dropped, and
+ * whether there was one. Every other byte stays where it was.
+ */
+pub fn 
+strip_asset_gps (format: &str, asset: &[u8]) -> Result<(Vec<u8>, bool), c2pa::Error> {
+    if !is_jpeg(format) {
+        return Err(c2pa::Error::BadParam(format!("GPS can only be removed from the Exif of JPEGs, not {}", format)));
+    }
+
+    let mut stripped = asset.to_vec();
+    let mut found = false;
+    for (marker, payload) in jpeg_segments(asset)? {
+        if marker == 0xe1 && asset[payload.clone()].starts_with(EXIF_HEADER) {
+            found |= exif::strip_gps_ifd(&mut stripped[payload.start + EXIF_HEADER.len()..payload.end])?;
+        }
+    }
+
+    Ok((stripped, found))
+}
+
+fn 
+is_jpeg (format: &str) -> bool {
+    matches!(format.to_ascii_lowercase().as_str(), "jpg" | "jpeg" | "image/jpeg")
+}
+
+/* The marker and payload of each segment of a JPEG ahead of its image data */
+pub(crate) fn 
+jpeg_segments (jpeg: &[u8]) -> Result<Vec<(u8, Range<usize>)>, c2pa::Error> {
+    if !jpeg.starts_with(&[0xff, 0xd8]) {
+        return Err(malformed("JPEG"));
+    }
+
+    let mut segments = Vec::new();
+    let mut i = 2;
+    loop {
+        // Markers may be preceded by any number of 0xff fill bytes
+        while jpeg.get(i) == Some(&0xff) && jpeg.get(i + 1) == Some(&0xff) {
+            i += 1;
+        }
+        let (Some(0xff), Some(&marker)) = (jpeg.get(i), jpeg.get(i + 1)) else {
+            return Err(malformed("JPEG"));
+        };
+
+        match marker {
+            // start of scan or end of image
+            0xda | 0xd9 => return Ok(segments),
+            // markers without a payload
+            0x01 | 0xd0..=0xd7 => i += 2,
+            _ => {
+                let length = jpeg.get(i + 2..i + 4).map(|b| u16::from_be_bytes([b[0], b[1]]) as usize).ok_or_else(|| malformed("JPEG"))?;
+                if length < 2 || i + 2 + length > jpeg.len() {
+                    return Err(malformed("JPEG"));
+                }
+                segments.push((marker, i + 4..i + 2 + length));
+                i += 2 + length;
+            }
+        }
+    }
+}
+
+/* A legacy metadata block of a JPEG, which `strip_metadata` can remove */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MetadataBlock {
+    Exif,
+    Xmp,
+    Iptc
+}
+
+impl FromStr for MetadataBlock {
+    type Err = c2pa::Error;
+
+    fn from_str(s: &str) -> Result<MetadataBlock, c2pa::Error> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "exif" => Ok(MetadataBlock::Exif),
+            "xmp" => Ok(MetadataBlock::Xmp),
+            "iptc" => Ok(MetadataBlock::Iptc),
+            _ => Err(c2pa::Error::BadParam(format!("unknown metadata block '{}'; expected exif, xmp or iptc", s)))
+        }
+    }
+}
+
+impl MetadataBlock {
+    /* Label of the assertion the block's content is captured in */
+    pub fn label(&self) -> &'static str {
+        match self {
+            MetadataBlock::Exif => EXIF_LABEL,
+            MetadataBlock::Xmp => "c2pa.metadata",
+            MetadataBlock::Iptc => "stds.iptc"
+        }
+    }
+
+    /* The block whose content an assertion label carries */
+    pub fn of_label(label: &str) -> Option<MetadataBlock> {
+        match label {
+            label if has_label(label, EXIF_LABEL) => Some(MetadataBlock::Exif),
+            label if has_label(label, "stds.iptc") => Some(MetadataBlock::Iptc),
+            label if has_label(label, "c2pa.metadata") || has_label(label, "stds.xmp") => Some(MetadataBlock::Xmp),
+            _ => None
+        }
+    }
+
+    /* Whether a field the block lacks is known to be absent, rather than unreadable here */
+    fn captures(&self, field: &str) -> bool {
+        match self {
+            MetadataBlock::Exif => exif::is_captured(field),
+            MetadataBlock::Iptc => iptc::is_captured(field),
+            MetadataBlock::Xmp => true
+        }
+    }
+
+    /* The block a JPEG segment holds, if any, and the length of the header ahead of its content */
+    fn of_segment(marker: u8, payload: &[u8]) -> Option<(MetadataBlock, usize)> {
+        let headers = [
+            (0xe1, EXIF_HEADER, MetadataBlock::Exif),
+            (0xe1, XMP_HEADER, MetadataBlock::Xmp),
+            (0xe1, EXTENDED_XMP_HEADER, MetadataBlock::Xmp),
+            (0xed, IPTC_HEADER, MetadataBlock::Iptc)
+        ];
+        headers.into_iter()
+            .find(|(block_marker, header, _)| *block_marker == marker && payload.starts_with(header))
+            .map(|(_, header, block)| (block, header.len()))
+    }
+}
+
+/* A metadata block of a JPEG and the fields captured from it, keyed as in its assertion */
+#[derive(Debug)]
+pub struct CapturedBlock {
+    pub block: MetadataBlock,
+    pub fields: Map<String, Value>
+}
+
+/**
+ * The selected metadata blocks of a JPEG, each with the fields captured
+ * from its segments, in order of appearance, along with the spans of those
+ * segments from their markers.
+ */
+fn 
+capture_blocks (format: &str, asset: &[u8], blocks: &[MetadataBlock]) -> Result<(Vec<CapturedBlock>, Vec<Range<usize>>), c2pa::Error> {
+    if !is_jpeg(format) {
+        return Err(c2pa::Error::BadParam(format!("metadata blocks can only be read from JPEGs, not {}", format)));
+    }
+
+    let mut captured: Vec<CapturedBlock> = Vec::new();
+    let mut spans = Vec::new();
+    for (marker, payload) in jpeg_segments(asset)? {
+        let Some((block, header)) = MetadataBlock::of_segment(marker, &asset[payload.clone()]).filter(|(block, _)| blocks.contains(block)) else {
+            continue;
+        };
+
+        let content = &asset[payload.start + header..payload.end];
+        let fields = match block {
+            MetadataBlock::Exif => exif::capture(content)?,
+            MetadataBlock::Xmp if header == XMP_HEADER.len() => xmp::capture(&String::from_utf8_lossy(content)),
+            MetadataBlock::Xmp => Map::new(),
+            MetadataBlock::Iptc => iptc::capture(content)?
+        };
+        match captured.iter_mut().find(|captured| captured.block == block) {
+            Some(existing) => existing.fields.extend(fields),
+            None => captured.push(CapturedBlock { block, fields })
+        }
+
+        // The segment goes from its marker, ahead of the length and payload
+        spans.push(payload.start - 4..payload.end);
+    }
+
+    Ok((captured, spans))
+}
+
+/**
+ * Removes the selected metadata blocks from a JPEG, returning the asset
+ * without them and assertions capturing what they held: Exif tags in
+ * `stds.exif`, IPTC datasets in `stds.iptc` and XMP properties in
+ * `c2pa.metadata`. Only fields with an XMP name, and simple XMP properties
+ * and lists, are captured; maker notes, thumbnails, XMP structures and the
+ * chunks of extended XMP are dropped with the block.
+ */
+pub fn 
+strip_metadata (format: &str, asset: &[u8], blocks: &[MetadataBlock]) -> Result<(Vec<u8>, Vec<Value>), c2pa::Error> {
+    let (captured, spans) = capture_blocks(format, asset, blocks)?;
+
+    let mut stripped = Vec::with_capacity(asset.len());
+    let mut copied = 0;
+    for span in spans {
+        stripped.extend_from_slice(&asset[copied..span.start]);
+        copied = span.end;
+    }
+    stripped.extend_from_slice(&asset[copied..]);
+
+    let assertions = captured.into_iter()
+        .filter(|captured| !captured.fields.is_empty())
+        .map(|captured| json!({"label": captured.block.label(), "data": captured.fields}))
+        .collect();
+
+    Ok((stripped, assertions))
+}
+
+/* The Exif, XMP and IPTC blocks a JPEG carries, each with the fields it holds */
+pub fn 
+live_metadata (format: &str, asset: &[u8]) -> Result<Vec<CapturedBlock>, c2pa::Error> {
+    Ok(capture_blocks(format, asset, &[MetadataBlock::Exif, MetadataBlock::Xmp, MetadataBlock::Iptc])?.0)
+}
+
+/**
+ * Adds captured metadata assertions to a manifest definition. Where the
+ * definition already has an assertion with the same label, only the fields
+ * (and `@context` prefixes) it doesn't set are added to it, so its own
+ * values win.
+ */
+pub fn 
+merge_assertions (definition: &mut Value, captured: Vec<Value>) {
+    if !definition["assertions"].is_array() {
+        definition["assertions"] = json!([]);
+    }
+    let Some(assertions) = definition["assertions"].as_array_mut() else {
+        return;
+    };
+
+    for assertion in captured {
+        let existing = assertions.iter_mut()
+            .find(|existing| existing["label"] == assertion["label"])
+            .and_then(|existing| existing.get_mut("data"))
+            .and_then(Value::as_object_mut);
+        let (Some(existing), Some(fields)) = (existing, assertion["data"].as_object()) else {
+            assertions.push(assertion);
+            continue;
+        };
+
+        for (key, value) in fields {
+            match (existing.get_mut(key), value) {
+                (Some(Value::Object(context)), Value::Object(prefixes)) if key == "@context" => {
+                    for (prefix, namespace) in prefixes {
+                        context.entry(prefix.clone()).or_insert_with(|| namespace.clone());
+                    }
+                },
+                (Some(_), _) => (),
+                (None, value) => { existing.insert(key.clone(), value.clone()); }
+            }
+        }
+    }
+}
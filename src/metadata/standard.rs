@@ -0,0 +1,117 @@
+use chrono::{DateTime, NaiveDate};
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+
+use super::merge_assertions;
+
+/*
+ * The standard metadata assertion, `c2pa.metadata`, filled in from flags
+ * or a JSON file rather than captured from a JPEG's XMP. Some validators
+ * read rights and credit from it and not from a schema.org CreativeWork.
+ * Fields are written under their XMP names, as JSON-LD with an `@context`
+ * of the namespaces used, the same form `--strip-metadata` captures XMP in,
+ * so `check-metadata` compares them with the asset's own XMP.
+ */
+
+const LABEL: &str = "c2pa.metadata";
+
+const DC: (&str, &str) = ("dc", "http://purl.org/dc/elements/1.1/");
+const PHOTOSHOP: (&str, &str) = ("photoshop", "http://ns.adobe.com/photoshop/1.0/");
+const XMP_RIGHTS: (&str, &str) = ("xmpRights", "http://ns.adobe.com/xap/1.0/rights/");
+
+/* The fields of the standard metadata assertion which can be set */
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StandardMetadata {
+    // dc:description, a caption
+    pub description: Option<String>,
+    // photoshop:Credit, the credit line to publish with the asset
+    pub credit: Option<String>,
+    // dc:rights, the copyright notice
+    pub rights: Option<String>,
+    // xmpRights:UsageTerms, how the asset may be used
+    pub usage_terms: Option<String>,
+    // photoshop:DateCreated, when the content was created, as YYYY-MM-DD or an RFC 3339 date-time
+    pub date_created: Option<String>
+}
+
+/* Checks a creation date, which XMP takes as a date or a date-time */
+pub fn 
+parse_date_created (date: &str) -> Result<String, c2pa::Error> {
+    match NaiveDate::parse_from_str(date, "%Y-%m-%d").is_ok() || DateTime::parse_from_rfc3339(date).is_ok() {
+        true => Ok(date.to_owned()),
+        false => Err(c2pa::Error::BadParam(format!("'{}' isn't a date, such as 2026-10-15, or an RFC 3339 date-time", date)))
+    }
+}
+
+impl StandardMetadata {
+    /* Reads the fields from a JSON object, e.g. {"credit": "Jane Doe / Example News", "date_created": "2026-10-15"} */
+    pub fn 
+    from_json (json: &str) -> Result<StandardMetadata, c2pa::Error> {
+        let metadata: StandardMetadata = serde_json::from_str(json)
+            .map_err(|e| c2pa::Error::BadParam(format!("not standard metadata: {}", e)))?;
+        if let Some(date) = &metadata.date_created {
+            parse_date_created(date)?;
+        }
+
+        Ok(metadata)
+    }
+
+    /* These fields, overridden by those `other` sets */
+    pub fn 
+    overridden_by (self, other: StandardMetadata) -> StandardMetadata {
+        StandardMetadata {
+            description: other.description.or(self.description),
+            credit: other.credit.or(self.credit),
+            rights: other.rights.or(self.rights),
+            usage_terms: other.usage_terms.or(self.usage_terms),
+            date_created: other.date_created.or(self.date_created)
+        }
+    }
+
+    /* Each field set, with its XMP name and namespace */
+    fn 
+    fields (&self) -> Vec<(&'static str, (&'static str, &'static str), &str)> {
+        [
+            ("dc:description", DC, &self.description),
+            ("photoshop:Credit", PHOTOSHOP, &self.credit),
+            ("dc:rights", DC, &self.rights),
+            ("xmpRights:UsageTerms", XMP_RIGHTS, &self.usage_terms),
+            ("photoshop:DateCreated", PHOTOSHOP, &self.date_created)
+        ]
+        .into_iter()
+        .filter_map(|(name, namespace, value)| value.as_deref().map(|value| (name, namespace, value)))
+        .collect()
+    }
+
+    /**
+     * Sets these fields in the `c2pa.metadata` assertion of a manifest
+     * definition, adding one if it has none. Unlike metadata captured from
+     * the asset, fields given explicitly replace those the definition sets.
+     */
+    pub fn 
+    apply (&self, definition: &mut Value) {
+        let fields = self.fields();
+        if fields.is_empty() {
+            return;
+        }
+
+        let existing = definition["assertions"].as_array_mut().into_iter().flatten()
+            .filter(|assertion| assertion["label"] == LABEL)
+            .filter_map(|assertion| assertion["data"].as_object_mut());
+        for data in existing {
+            for (name, _, _) in &fields {
+                data.remove(*name);
+            }
+        }
+
+        let mut context = Map::new();
+        let mut data = Map::new();
+        for (name, (prefix, namespace), value) in fields {
+            context.insert(prefix.to_owned(), Value::from(namespace));
+            data.insert(name.to_owned(), Value::from(value));
+        }
+        data.insert("@context".to_owned(), Value::Object(context));
+        merge_assertions(definition, vec![json!({"label": LABEL, "data": data})]);
+    }
+}
@@ -0,0 +1,259 @@
+use serde_json::{Map, Value};
+
+/*
+ * XMP packets, RDF/XML read just far enough to capture their simple
+ * properties: those given as attributes or text elements of an
+ * rdf:Description, and lists of text (rdf:Seq and rdf:Bag as arrays,
+ * rdf:Alt by its first, default, item). Structured properties are skipped.
+ */
+
+enum Token<'a> {
+    Start { name: &'a str, attributes: Vec<(&'a str, String)>, empty: bool },
+    End,
+    Text(String)
+}
+
+/* Replaces XML's predefined entities and character references */
+fn 
+unescape (text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(at) = rest.find('&') {
+        unescaped.push_str(&rest[..at]);
+        rest = &rest[at..];
+        let Some(end) = rest.find(';') else {
+            break;
+        };
+        let character = match &rest[1..end] {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            reference => reference.strip_prefix("#x").map(|hex| u32::from_str_radix(hex, 16))
+                .or_else(|| reference.strip_prefix('#').map(str::parse))
+                .and_then(Result::ok)
+                .and_then(char::from_u32)
+        };
+        match character {
+            Some(character) => {
+                unescaped.push(character);
+                rest = &rest[end + 1..];
+            },
+            None => {
+                unescaped.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    unescaped.push_str(rest);
+
+    unescaped
+}
+
+/* The `name="value"` attributes of a start tag, after its name */
+fn 
+attributes (mut tag: &str) -> Vec<(&str, String)> {
+    let mut attributes = Vec::new();
+    while let Some((name, rest)) = tag.split_once('=') {
+        let rest = rest.trim_start();
+        let Some(quote) = rest.chars().next().filter(|c| *c == '"' || *c == '\'') else {
+            break;
+        };
+        let Some((value, after)) = rest[1..].split_once(quote) else {
+            break;
+        };
+        attributes.push((name.trim(), unescape(value)));
+        tag = after;
+    }
+
+    attributes
+}
+
+/* Where a tag starting at `<` ends, at the `>` outside of any quoted attribute value */
+fn 
+tag_end (xml: &str) -> Option<usize> {
+    let mut quote = None;
+    for (i, c) in xml.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(open), _) if c == open => quote = None,
+            (None, '>') => return Some(i),
+            _ => ()
+        }
+    }
+
+    None
+}
+
+/* The elements and text of an XML document, without comments, processing instructions or declarations */
+fn 
+tokens (xml: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut rest = xml;
+    while !rest.is_empty() {
+        let Some(markup) = rest.strip_prefix('<') else {
+            let end = rest.find('<').unwrap_or(rest.len());
+            if !rest[..end].trim().is_empty() {
+                tokens.push(Token::Text(unescape(rest[..end].trim())));
+            }
+            rest = &rest[end..];
+            continue;
+        };
+
+        let skip_past = |terminator: &str| markup.find(terminator).map(|at| &markup[at + terminator.len()..]);
+        let next = if let Some(cdata) = markup.strip_prefix("![CDATA[") {
+            cdata.split_once("]]>").map(|(text, after)| {
+                tokens.push(Token::Text(text.to_owned()));
+                after
+            })
+        } else if markup.starts_with("!--") {
+            skip_past("-->")
+        } else if markup.starts_with('?') {
+            skip_past("?>")
+        } else if markup.starts_with('!') || markup.starts_with('/') {
+            if markup.starts_with('/') {
+                tokens.push(Token::End);
+            }
+            skip_past(">")
+        } else {
+            tag_end(markup).map(|end| {
+                let tag = markup[..end].trim_end();
+                let (tag, empty) = match tag.strip_suffix('/') {
+                    Some(tag) => (tag, true),
+                    None => (tag, false)
+                };
+                let (name, rest) = tag.split_once(char::is_whitespace).unwrap_or((tag, ""));
+                tokens.push(Token::Start { name, attributes: attributes(rest), empty });
+                &markup[end + 1..]
+            })
+        };
+        // An unterminated construct ends the document
+        let Some(next) = next else {
+            break;
+        };
+        rest = next;
+    }
+
+    tokens
+}
+
+/* Whether an attribute or element name is a metadata property rather than RDF or XML syntax */
+fn 
+is_property (name: &str) -> bool {
+    name.contains(':') && !["xmlns:", "rdf:", "xml:", "x:"].iter().any(|prefix| name.starts_with(prefix))
+}
+
+/* The index after the end of the element whose content starts at `tokens[i]` */
+fn 
+skip_element (tokens: &[Token], mut i: usize) -> usize {
+    let mut depth = 1;
+    while let Some(token) = tokens.get(i) {
+        i += 1;
+        match token {
+            Token::Start { empty: false, .. } => depth += 1,
+            Token::End => {
+                depth -= 1;
+                if depth == 0 {
+                    break;
+                }
+            },
+            _ => ()
+        }
+    }
+
+    i
+}
+
+/**
+ * The value of a property element whose content starts at `tokens[i]`,
+ * if it is text or a list of text, and the index after the element.
+ */
+fn 
+property_value (tokens: &[Token], i: usize) -> (Option<Value>, usize) {
+    match (tokens.get(i), tokens.get(i + 1)) {
+        (Some(Token::Text(text)), Some(Token::End)) => (Some(Value::String(text.clone())), i + 2),
+        (Some(Token::Start { name: list @ ("rdf:Seq" | "rdf:Bag" | "rdf:Alt"), empty: false, .. }), _) => {
+            let mut items = Vec::new();
+            let mut j = i + 1;
+            while let Some(Token::Start { name, empty, .. }) = tokens.get(j) {
+                match (name, empty, tokens.get(j + 1), tokens.get(j + 2)) {
+                    (&"rdf:li", false, Some(Token::Text(text)), Some(Token::End)) => {
+                        items.push(Value::String(text.clone()));
+                        j += 3;
+                    },
+                    (_, true, _, _) => j += 1,
+                    _ => j = skip_element(tokens, j + 1)
+                }
+            }
+            // The rest of the list, then the property's end tag
+            let end = skip_element(tokens, skip_element(tokens, j));
+            let value = match *list {
+                "rdf:Alt" => items.into_iter().next(),
+                _ => Some(Value::Array(items))
+            };
+            (value, end)
+        },
+        _ => (None, skip_element(tokens, i))
+    }
+}
+
+/**
+ * The simple properties of the rdf:Descriptions of an XMP packet, keyed
+ * by their prefixed names, with an `@context` of the namespaces declared.
+ */
+pub(super) fn 
+capture (packet: &str) -> Map<String, Value> {
+    let tokens = tokens(packet);
+    let mut fields = Map::new();
+    let mut context = Map::new();
+
+    let mut i = 0;
+    while let Some(token) = tokens.get(i) {
+        i += 1;
+        let Token::Start { name, attributes, empty } = token else {
+            continue;
+        };
+        for (attribute, value) in attributes {
+            match attribute.strip_prefix("xmlns:") {
+                Some(prefix) if !["rdf", "x", "xml"].contains(&prefix) => { context.insert(prefix.to_owned(), Value::String(value.clone())); },
+                Some(_) => (),
+                None if *name == "rdf:Description" && is_property(attribute) => { fields.insert((*attribute).to_owned(), Value::String(value.clone())); },
+                None => ()
+            }
+        }
+        if *name != "rdf:Description" || *empty {
+            continue;
+        }
+
+        // The property elements of the description, up to its end tag
+        while let Some(token) = tokens.get(i) {
+            match token {
+                Token::Start { name, attributes, empty: true } => {
+                    if let (true, Some((_, resource))) = (is_property(name), attributes.iter().find(|(attribute, _)| *attribute == "rdf:resource")) {
+                        fields.insert((*name).to_owned(), Value::String(resource.clone()));
+                    }
+                    i += 1;
+                },
+                Token::Start { name, empty: false, .. } => {
+                    let (value, next) = property_value(&tokens, i + 1);
+                    if let (true, Some(value)) = (is_property(name), value) {
+                        fields.insert((*name).to_owned(), value);
+                    }
+                    i = next;
+                },
+                Token::End => {
+                    i += 1;
+                    break;
+                },
+                Token::Text(_) => i += 1
+            }
+        }
+    }
+
+    if !fields.is_empty() {
+        fields.insert("@context".to_owned(), Value::Object(context));
+    }
+
+    fields
+}
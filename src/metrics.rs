@@ -0,0 +1,165 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Instant;
+
+use tiny_http::{Header, Response, Server};
+
+// Upper bounds, in seconds, of the operation latency histogram buckets
+const LATENCY_BUCKETS: [f64; 10] = [0.01, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0];
+
+/* Cumulative latency histogram for one operation */
+struct Histogram {
+    buckets: [u64; LATENCY_BUCKETS.len()],
+    count: u64,
+    sum: f64
+}
+
+/* Every metric the server and worker modes report, keyed by label values */
+struct Registry {
+    // (operation, result) -> count
+    operations: BTreeMap<(&'static str, &'static str), u64>,
+    latencies: BTreeMap<&'static str, Histogram>,
+    // validation status code -> count
+    validation_failures: BTreeMap<String, u64>,
+    // signer error kind -> count
+    signer_errors: BTreeMap<&'static str, u64>
+}
+
+static METRICS: Mutex<Registry> = Mutex::new(Registry {
+    operations: BTreeMap::new(),
+    latencies: BTreeMap::new(),
+    validation_failures: BTreeMap::new(),
+    signer_errors: BTreeMap::new()
+});
+
+/**
+ * Classifies errors which come from the signing backend (key handling,
+ * signature generation, time stamping) rather than from the request.
+ */
+fn 
+signer_error_kind (e: &c2pa::Error) -> Option<&'static str> {
+    match e {
+        c2pa::Error::RawSignerError(_) | c2pa::Error::InvalidSigningKey => Some("openssl"),
+        c2pa::Error::CoseSignature | c2pa::Error::CoseSigboxTooSmall => Some("signature"),
+        c2pa::Error::CoseTimeStampGeneration | c2pa::Error::CoseTimeStampAuthority | c2pa::Error::TimeStampError(_) => Some("timestamp"),
+        _ => None
+    }
+}
+
+/**
+ * Runs `f` as the named operation ("sign", "verify", "read"), recording its
+ * latency, its outcome and, for failed signing, the signer error kind.
+ */
+pub fn 
+timed<T, F: FnOnce() -> Result<T, c2pa::Error>> (operation: &'static str, f: F) -> Result<T, c2pa::Error> {
+    let start = Instant::now();
+    let result = f();
+    let elapsed = start.elapsed().as_secs_f64();
+
+    let mut metrics = METRICS.lock().unwrap();
+    let outcome = if result.is_ok() { "ok" } else { "error" };
+    *metrics.operations.entry((operation, outcome)).or_insert(0) += 1;
+
+    let histogram = metrics.latencies.entry(operation).or_insert(Histogram {
+        buckets: [0; LATENCY_BUCKETS.len()],
+        count: 0,
+        sum: 0.0
+    });
+    for (bucket, bound) in histogram.buckets.iter_mut().zip(LATENCY_BUCKETS) {
+        if elapsed <= bound {
+            *bucket += 1;
+        }
+    }
+    histogram.count += 1;
+    histogram.sum += elapsed;
+
+    if let Some(kind) = result.as_ref().err().and_then(signer_error_kind) {
+        *metrics.signer_errors.entry(kind).or_insert(0) += 1;
+    }
+
+    result
+}
+
+/* Records a failure to construct the signer itself, e.g. unreadable keys */
+pub fn 
+record_signer_init_error () {
+    *METRICS.lock().unwrap().signer_errors.entry("init").or_insert(0) += 1;
+}
+
+/* Counts the validation status codes reported for a verified asset */
+pub fn 
+record_validation_errors (codes: &[String]) {
+    let mut metrics = METRICS.lock().unwrap();
+    for code in codes {
+        *metrics.validation_failures.entry(code.clone()).or_insert(0) += 1;
+    }
+}
+
+/* Renders all metrics in the Prometheus text exposition format */
+pub fn 
+render () -> String {
+    let metrics = METRICS.lock().unwrap();
+    let mut out = String::new();
+
+    out.push_str("# HELP c2pa_operations_total Sign, verify and read operations by result.\n");
+    out.push_str("# TYPE c2pa_operations_total counter\n");
+    for ((operation, outcome), count) in &metrics.operations {
+        let _ = writeln!(out, "c2pa_operations_total{{operation=\"{}\",result=\"{}\"}} {}", operation, outcome, count);
+    }
+
+    out.push_str("# HELP c2pa_operation_duration_seconds Latency of sign, verify and read operations.\n");
+    out.push_str("# TYPE c2pa_operation_duration_seconds histogram\n");
+    for (operation, histogram) in &metrics.latencies {
+        for (count, bound) in histogram.buckets.iter().zip(LATENCY_BUCKETS) {
+            let _ = writeln!(out, "c2pa_operation_duration_seconds_bucket{{operation=\"{}\",le=\"{}\"}} {}", operation, bound, count);
+        }
+        let _ = writeln!(out, "c2pa_operation_duration_seconds_bucket{{operation=\"{}\",le=\"+Inf\"}} {}", operation, histogram.count);
+        let _ = writeln!(out, "c2pa_operation_duration_seconds_sum{{operation=\"{}\"}} {}", operation, histogram.sum);
+        let _ = writeln!(out, "c2pa_operation_duration_seconds_count{{operation=\"{}\"}} {}", operation, histogram.count);
+    }
+
+    out.push_str("# HELP c2pa_validation_failures_total Validation status codes reported for verified assets.\n");
+    out.push_str("# TYPE c2pa_validation_failures_total counter\n");
+    for (code, count) in &metrics.validation_failures {
+        let _ = writeln!(out, "c2pa_validation_failures_total{{code=\"{}\"}} {}", code.replace('"', "\\\""), count);
+    }
+
+    out.push_str("# HELP c2pa_signer_errors_total Signing backend failures by kind.\n");
+    out.push_str("# TYPE c2pa_signer_errors_total counter\n");
+    for (kind, count) in &metrics.signer_errors {
+        let _ = writeln!(out, "c2pa_signer_errors_total{{kind=\"{}\"}} {}", kind, count);
+    }
+
+    out
+}
+
+pub fn 
+metrics_response () -> Response<std::io::Cursor<Vec<u8>>> {
+    Response::from_data(render().into_bytes())
+        .with_header(Header::from_bytes("Content-Type", "text/plain; version=0.0.4").unwrap())
+}
+
+/**
+ * Serves `GET /metrics` on its own address from a background thread, for
+ * modes which don't already run an HTTP server (gRPC, daemon, worker).
+ */
+pub fn 
+spawn_server (addr: &str) -> Result<(), c2pa::Error> {
+    let server = Server::http(addr).map_err(c2pa::Error::OtherError)?;
+
+    thread::spawn(move || {
+        for request in server.incoming_requests() {
+            let response = if request.url() == "/metrics" {
+                metrics_response()
+            } else {
+                Response::from_string("not found").with_status_code(404)
+            };
+
+            let _ = request.respond(response);
+        }
+    });
+
+    Ok(())
+}
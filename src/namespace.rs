@@ -0,0 +1,65 @@
+use std::sync::Mutex;
+
+/*
+ * The namespace of the custom assertion labels this tool writes, such as
+ * `<namespace>.endorsement` and `<namespace>.aux.depth-map`. Custom labels
+ * take a reverse-domain name their owner controls, so an organization
+ * signing with this tool sets its own, e.g. com.mycompany, with
+ * `set_namespace`, rather than shipping assertions under ours. Labels are
+ * recognized under the configured namespace and under the default one, so
+ * assets signed before an organization configured its own still read back.
+ */
+
+// This tool's own namespace, used until another is configured
+pub const DEFAULT_NAMESPACE: &str = "com.mikecvet";
+
+// First parts of labels the spec reserves for its own assertions
+const RESERVED: [&str; 2] = ["c2pa", "stds"];
+
+static NAMESPACE: Mutex<Option<String>> = Mutex::new(None);
+
+/**
+ * Checks a namespace for custom labels: a reverse-domain name of at least
+ * two dot-separated parts, each of lowercase letters, digits and hyphens,
+ * and not one of the prefixes the spec reserves.
+ */
+pub fn 
+parse_namespace (namespace: &str) -> Result<String, c2pa::Error> {
+    let parts: Vec<&str> = namespace.split('.').collect();
+    let legal = parts.len() >= 2 && parts.iter().all(|part| {
+        !part.is_empty() && !part.starts_with('-') && !part.ends_with('-')
+            && part.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+    });
+    if !legal {
+        return Err(c2pa::Error::BadParam(format!("'{}' isn't a reverse-domain name such as com.mycompany", namespace)));
+    }
+    if RESERVED.contains(&parts[0]) {
+        return Err(c2pa::Error::BadParam(format!("labels starting {}. are reserved for the C2PA spec's own assertions", parts[0])));
+    }
+
+    Ok(namespace.to_owned())
+}
+
+pub fn 
+set_namespace (namespace: &str) {
+    *NAMESPACE.lock().unwrap() = Some(namespace.to_owned());
+}
+
+/* The configured namespace, else the default */
+pub fn 
+namespace () -> String {
+    NAMESPACE.lock().unwrap().clone().unwrap_or_else(|| DEFAULT_NAMESPACE.to_owned())
+}
+
+/* The label for `name` in the configured namespace, e.g. com.mycompany.endorsement */
+pub fn 
+label (name: &str) -> String {
+    format!("{}.{}", namespace(), name)
+}
+
+/* The name a label gives under the configured or the default namespace, or None if it is under neither */
+pub fn 
+strip_namespace (label: &str) -> Option<&str> {
+    let strip = |namespace: &str| label.strip_prefix(namespace).and_then(|rest| rest.strip_prefix('.'));
+    strip(&namespace()).or_else(|| strip(DEFAULT_NAMESPACE))
+}
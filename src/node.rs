@@ -0,0 +1,162 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use napi::bindgen_prelude::{AsyncTask, Env};
+use napi::{JsUnknown, Task};
+use napi_derive::napi;
+use serde_json::json;
+
+use crate::read::{is_valid, load_manifest_store, validation_errors};
+use crate::sign::{default_manifest_definition, sign_asset_with_signer, SharedSigner, SignerConfig};
+use crate::storage::{format_from_location, read_file, write_file_atomically};
+
+/*
+ * The Node.js addon, for signing, verifying and reading local files from
+ * Node or Electron without spawning the command line tool:
+ *
+ *     const { Signer, verifyFile } = require("./c2pa-walkthrough.node");
+ *     const signer = new Signer("signer.json");
+ *     const store = await signer.signFile("photo.jpg", "signed/photo.jpg", { title: "photo.jpg" });
+ *     (await verifyFile("signed/photo.jpg")).valid
+ *
+ * Manifest definitions and results are plain objects, in the same shape as
+ * their JSON elsewhere. Every call returns a promise and does its work on
+ * libuv's thread pool, off the event loop, so an editor's UI stays
+ * responsive while large assets are signed.
+ */
+
+fn
+error (e: c2pa::Error) -> napi::Error {
+    napi::Error::from_reason(e.to_string())
+}
+
+fn
+local_format (path: &Path) -> Result<String, c2pa::Error> {
+    format_from_location(&path.to_string_lossy())
+        .ok_or_else(|| c2pa::Error::BadParam(format!("cannot determine the format of {} from its extension", path.display())))
+}
+
+fn
+manifest_store_json (format: &str, asset: &[u8]) -> Result<serde_json::Value, c2pa::Error> {
+    Ok(serde_json::from_str(&load_manifest_store(format, asset)?.json())?)
+}
+
+/**
+ * A signer loaded from a signer profile, the JSON file `sign
+ * --signer-profile` takes. The key is read once, when it is constructed,
+ * and the profile's `max_concurrent` caps signatures in flight.
+ */
+#[napi(js_name = "Signer")]
+pub struct NodeSigner {
+    signer: Arc<SharedSigner>
+}
+
+#[napi]
+impl NodeSigner {
+    #[napi(constructor)]
+    pub fn
+    new (profile: String) -> napi::Result<Self> {
+        let signer = SignerConfig::from_file(&profile)
+            .and_then(|config| config.create_shared_signer())
+            .map_err(error)?;
+        Ok(NodeSigner { signer: Arc::new(signer) })
+    }
+
+    /**
+     * Signs the file at `source` and writes it to `dest`, which may be the
+     * same path, with a manifest built from the `manifest` definition, or
+     * the default definition if it is omitted. Resolves to the manifest
+     * store of the signed file.
+     */
+    #[napi(ts_return_type = "Promise<object>")]
+    pub fn
+    sign_file (&self, source: String, dest: String, manifest: Option<serde_json::Value>) -> AsyncTask<SignTask> {
+        AsyncTask::new(SignTask {
+            signer: self.signer.clone(),
+            source: PathBuf::from(source),
+            dest: PathBuf::from(dest),
+            manifest_json: manifest.map_or_else(default_manifest_definition, |manifest| manifest.to_string())
+        })
+    }
+}
+
+pub struct SignTask {
+    signer: Arc<SharedSigner>,
+    source: PathBuf,
+    dest: PathBuf,
+    manifest_json: String
+}
+
+impl Task for SignTask {
+    type Output = serde_json::Value;
+    type JsValue = JsUnknown;
+
+    fn
+    compute (&mut self) -> napi::Result<serde_json::Value> {
+        (|| {
+            let format = local_format(&self.source)?;
+            let signed = sign_asset_with_signer(&format, &read_file(&self.source)?, &self.manifest_json, &*self.signer)?;
+            write_file_atomically(&self.dest, &signed)?;
+            manifest_store_json(&format, &signed)
+        })().map_err(error)
+    }
+
+    fn
+    resolve (&mut self, env: Env, output: serde_json::Value) -> napi::Result<JsUnknown> {
+        env.to_js_value(&output)
+    }
+}
+
+/* Reads a file's manifest store, as a verdict or whole */
+pub struct ReadTask {
+    path: PathBuf,
+    verdict: bool
+}
+
+impl Task for ReadTask {
+    type Output = serde_json::Value;
+    type JsValue = JsUnknown;
+
+    fn
+    compute (&mut self) -> napi::Result<serde_json::Value> {
+        (|| {
+            let asset = read_file(&self.path)?;
+            let format = local_format(&self.path)?;
+            if !self.verdict {
+                return manifest_store_json(&format, &asset);
+            }
+
+            let manifest_store = load_manifest_store(&format, &asset)?;
+            let errors = validation_errors(&manifest_store);
+            Ok(json!({
+                "valid": is_valid(&manifest_store),
+                "validation_errors": errors,
+                "active_manifest": manifest_store.active_label()
+            }))
+        })().map_err(error)
+    }
+
+    fn
+    resolve (&mut self, env: Env, output: serde_json::Value) -> napi::Result<JsUnknown> {
+        env.to_js_value(&output)
+    }
+}
+
+/**
+ * Validates the manifests in the file at `path`, resolving to a verdict
+ * such as `{valid: false, validation_errors: ["assertion.dataHash.mismatch"],
+ * active_manifest: "urn:uuid:..."}`. A file without manifests rejects
+ * rather than resolving to a verdict.
+ */
+#[napi(ts_return_type = "Promise<{ valid: boolean, validation_errors: string[], active_manifest: string | null }>")]
+pub fn
+verify_file (path: String) -> AsyncTask<ReadTask> {
+    AsyncTask::new(ReadTask { path: PathBuf::from(path), verdict: true })
+}
+
+/* Resolves to the manifest store of the file at `path`, including any `validation_status` */
+#[napi(ts_return_type = "Promise<object>")]
+pub fn
+read_manifest (path: String) -> AsyncTask<ReadTask> {
+    AsyncTask::new(ReadTask { path: PathBuf::from(path), verdict: false })
+}
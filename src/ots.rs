@@ -0,0 +1,348 @@
+use std::io::Read;
+
+use sha2::{Digest, Sha256};
+
+/*
+ * OpenTimestamps proofs over signed manifests, for proof of existence which
+ * doesn't depend on a TSA. As with Rekor, the timestamped artifact is the
+ * asset's manifest store: its SHA-256 is submitted to one or more calendar
+ * servers and their responses are combined into a standard `.ots` proof,
+ * kept next to the asset as `<asset>.ots` (readable by the `ots` client).
+ * Calendars anchor the commitment in Bitcoin a few hours later; until then
+ * the proof is pending. Verification replays the proof's operations from
+ * the manifest store hash, asks the calendars for an upgraded proof while
+ * it is pending, and checks Bitcoin attestations against the block's merkle
+ * root from an Esplora API (`OTS_ESPLORA_URL`, by default blockstream.info).
+ */
+
+pub const DEFAULT_CALENDARS: [&str; 2] = ["https://a.pool.opentimestamps.org", "https://b.pool.opentimestamps.org"];
+
+const DEFAULT_ESPLORA_URL: &str = "https://blockstream.info/api";
+
+const HEADER_MAGIC: &[u8] = b"\x00OpenTimestamps\x00\x00Proof\x00\xbf\x89\xe2\xe8\x84\xe8\x92\x94";
+const VERSION: u8 = 1;
+
+const OP_SHA256: u8 = 0x08;
+const OP_APPEND: u8 = 0xf0;
+const OP_PREPEND: u8 = 0xf1;
+const OP_REVERSE: u8 = 0xf2;
+const OP_HEXLIFY: u8 = 0xf3;
+const ATTESTATION: u8 = 0x00;
+const FORK: u8 = 0xff;
+
+const BITCOIN_ATTESTATION: [u8; 8] = [0x05, 0x88, 0x96, 0x0d, 0x73, 0xd7, 0x19, 0x01];
+const PENDING_ATTESTATION: [u8; 8] = [0x83, 0xdf, 0xe3, 0x0d, 0x2e, 0xf9, 0x0c, 0x8e];
+
+// Guards against stack exhaustion on hostile proofs
+const MAX_DEPTH: usize = 256;
+
+/* Where a commitment in a proof is attested */
+enum Attestation {
+    Bitcoin(u64),
+    Pending(String),
+    Other
+}
+
+/* Outcome of checking a proof against an asset */
+pub enum OtsStatus {
+    // The manifest store existed by the time of this Bitcoin block
+    Anchored(u64),
+    // Submitted, but not yet anchored; calendar URLs which hold it
+    Pending(Vec<String>),
+    // The proof doesn't hold; a status-code-like reason
+    Invalid(String)
+}
+
+fn 
+http_error (e: ureq::Error) -> c2pa::Error {
+    c2pa::Error::OtherError(Box::new(e))
+}
+
+fn 
+malformed (what: &str) -> c2pa::Error {
+    c2pa::Error::BadParam(format!("malformed OpenTimestamps proof: {}", what))
+}
+
+/* Cursor over a serialized proof */
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize
+}
+
+impl<'a> Reader<'a> {
+    fn 
+    byte (&mut self) -> Result<u8, c2pa::Error> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn 
+    take (&mut self, n: usize) -> Result<&'a [u8], c2pa::Error> {
+        let end = self.pos.checked_add(n).filter(|&end| end <= self.bytes.len()).ok_or_else(|| malformed("truncated"))?;
+        let bytes = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    /* LEB128 unsigned integer */
+    fn 
+    varuint (&mut self) -> Result<u64, c2pa::Error> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let b = self.byte()?;
+            value |= ((b & 0x7f) as u64) << shift;
+            if b & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(malformed("integer too long"))
+    }
+
+    fn 
+    varbytes (&mut self) -> Result<&'a [u8], c2pa::Error> {
+        let len = self.varuint()? as usize;
+        self.take(len)
+    }
+}
+
+/**
+ * Reads the timestamp over `message` at the reader's position, collecting
+ * each attestation with the commitment it attests.
+ */
+fn 
+read_timestamp (reader: &mut Reader, message: &[u8], depth: usize, found: &mut Vec<(Vec<u8>, Attestation)>) -> Result<(), c2pa::Error> {
+    if depth > MAX_DEPTH {
+        return Err(malformed("nested too deeply"));
+    }
+
+    let mut tag = reader.byte()?;
+    while tag == FORK {
+        let branch = reader.byte()?;
+        read_step(reader, branch, message, depth, found)?;
+        tag = reader.byte()?;
+    }
+    read_step(reader, tag, message, depth, found)
+}
+
+fn 
+read_step (reader: &mut Reader, tag: u8, message: &[u8], depth: usize, found: &mut Vec<(Vec<u8>, Attestation)>) -> Result<(), c2pa::Error> {
+    let result = match tag {
+        ATTESTATION => {
+            let kind: [u8; 8] = reader.take(8)?.try_into().unwrap();
+            let mut payload = Reader { bytes: reader.varbytes()?, pos: 0 };
+            let attestation = match kind {
+                BITCOIN_ATTESTATION => Attestation::Bitcoin(payload.varuint()?),
+                PENDING_ATTESTATION => Attestation::Pending(String::from_utf8_lossy(payload.varbytes()?).into_owned()),
+                _ => Attestation::Other
+            };
+            found.push((message.to_vec(), attestation));
+            return Ok(());
+        },
+        OP_SHA256 => Sha256::digest(message).to_vec(),
+        OP_APPEND => [message, reader.varbytes()?].concat(),
+        OP_PREPEND => [reader.varbytes()?, message].concat(),
+        OP_REVERSE => message.iter().rev().copied().collect(),
+        OP_HEXLIFY => hex::encode(message).into_bytes(),
+        _ => return Err(malformed(&format!("unsupported operation 0x{:02x}", tag)))
+    };
+
+    read_timestamp(reader, &result, depth + 1, found)
+}
+
+/* SHA-256 of the manifest store embedded in an asset: the timestamped digest */
+fn 
+manifest_store_digest (format: &str, asset: &[u8]) -> Result<Vec<u8>, c2pa::Error> {
+    let manifest_store = c2pa::jumbf_io::load_jumbf_from_memory(format, asset)?;
+    Ok(Sha256::digest(manifest_store).to_vec())
+}
+
+/**
+ * Submits the manifest store hash of a signed asset to the given
+ * calendars, returning the `.ots` proof. Calendars which can't be reached
+ * are skipped, as long as one accepts the hash.
+ */
+pub fn 
+stamp (calendars: &[String], format: &str, signed: &[u8]) -> Result<Vec<u8>, c2pa::Error> {
+    let digest = manifest_store_digest(format, signed)?;
+
+    let mut timestamps = Vec::new();
+    let mut last_error = None;
+    for calendar in calendars {
+        let response = ureq::post(&format!("{}/digest", calendar.trim_end_matches('/')))
+            .set("Accept", "application/vnd.opentimestamps.v1")
+            .send_bytes(&digest);
+        match response {
+            Ok(response) => {
+                let mut timestamp = Vec::new();
+                response.into_reader().read_to_end(&mut timestamp)?;
+                timestamps.push(timestamp);
+            },
+            Err(e) => last_error = Some(http_error(e))
+        }
+    }
+
+    let Some(last) = timestamps.pop() else {
+        return Err(last_error.unwrap_or_else(|| c2pa::Error::BadParam("no OpenTimestamps calendars given".to_owned())));
+    };
+
+    let mut proof = HEADER_MAGIC.to_vec();
+    proof.push(VERSION);
+    proof.push(OP_SHA256);
+    proof.extend(&digest);
+    // Every calendar's timestamp but the last is a fork off the digest
+    for timestamp in timestamps {
+        proof.push(FORK);
+        proof.extend(timestamp);
+    }
+    proof.extend(last);
+
+    Ok(proof)
+}
+
+/* Asks a calendar for the completed timestamp of a pending commitment, if it has one yet */
+fn 
+upgrade (calendar: &str, commitment: &[u8], found: &mut Vec<(Vec<u8>, Attestation)>) -> Result<(), c2pa::Error> {
+    let url = format!("{}/timestamp/{}", calendar.trim_end_matches('/'), hex::encode(commitment));
+    let response = match ureq::get(&url).set("Accept", "application/vnd.opentimestamps.v1").call() {
+        Err(ureq::Error::Status(404, _)) => return Ok(()),
+        result => result.map_err(http_error)?
+    };
+
+    let mut timestamp = Vec::new();
+    response.into_reader().read_to_end(&mut timestamp)?;
+    read_timestamp(&mut Reader { bytes: &timestamp, pos: 0 }, commitment, 0, found)
+}
+
+/* Whether `commitment` is the merkle root of the Bitcoin block at `height` */
+fn 
+in_bitcoin_block (height: u64, commitment: &[u8]) -> Result<bool, c2pa::Error> {
+    let esplora = std::env::var("OTS_ESPLORA_URL").unwrap_or_else(|_| DEFAULT_ESPLORA_URL.to_owned());
+    let esplora = esplora.trim_end_matches('/');
+
+    let block_hash = ureq::get(&format!("{}/block-height/{}", esplora, height)).call().map_err(http_error)?.into_string()?;
+    let block: serde_json::Value = serde_json::from_str(
+        &ureq::get(&format!("{}/block/{}", esplora, block_hash.trim())).call().map_err(http_error)?.into_string()?
+    )?;
+
+    // Block explorers show hashes byte-reversed
+    let merkle_root: Vec<u8> = commitment.iter().rev().copied().collect();
+    Ok(block["merkle_root"].as_str() == Some(hex::encode(merkle_root).as_str()))
+}
+
+/**
+ * Checks an `.ots` proof against an asset: the proof must be over the
+ * hash of the asset's manifest store, and any Bitcoin attestation in it
+ * (or in an upgraded proof from its calendars) must match the block.
+ */
+pub fn 
+verify (format: &str, asset: &[u8], proof: &[u8]) -> Result<OtsStatus, c2pa::Error> {
+    let mut reader = Reader { bytes: proof, pos: 0 };
+    if reader.take(HEADER_MAGIC.len())? != HEADER_MAGIC || reader.varuint()? != VERSION as u64 {
+        return Err(malformed("not an OpenTimestamps proof"));
+    }
+    if reader.byte()? != OP_SHA256 {
+        return Ok(OtsStatus::Invalid("ots.unsupportedHash".to_owned()));
+    }
+
+    let digest = reader.take(32)?;
+    if digest != manifest_store_digest(format, asset)? {
+        return Ok(OtsStatus::Invalid("ots.hashMismatch".to_owned()));
+    }
+
+    let mut found = Vec::new();
+    read_timestamp(&mut reader, digest, 0, &mut found)?;
+
+    let mut pending = Vec::new();
+    let mut upgraded = Vec::new();
+    for (commitment, attestation) in &found {
+        if let Attestation::Pending(calendar) = attestation {
+            pending.push(calendar.clone());
+            // Unreachable calendars leave the proof as pending
+            upgrade(calendar, commitment, &mut upgraded).ok();
+        }
+    }
+    found.extend(upgraded);
+    pending.sort();
+    pending.dedup();
+
+    let mut anchored = None;
+    for (commitment, attestation) in &found {
+        if let Attestation::Bitcoin(height) = attestation {
+            if !in_bitcoin_block(*height, commitment)? {
+                return Ok(OtsStatus::Invalid("ots.bitcoinMerkleRootMismatch".to_owned()));
+            }
+            anchored = Some(anchored.map_or(*height, |earliest: u64| earliest.min(*height)));
+        }
+    }
+
+    Ok(match anchored {
+        Some(height) => OtsStatus::Anchored(height),
+        None => OtsStatus::Pending(pending)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sign::{sign_asset, SignerConfig, DEFAULT_MANIFEST_DEFINITION};
+
+    fn attestation(kind: [u8; 8], payload: &[u8]) -> Vec<u8> {
+        [&[ATTESTATION][..], &kind, &[payload.len() as u8], payload].concat()
+    }
+
+    fn read(timestamp: &[u8], message: &[u8]) -> Result<Vec<(Vec<u8>, Attestation)>, c2pa::Error> {
+        let mut found = Vec::new();
+        read_timestamp(&mut Reader { bytes: timestamp, pos: 0 }, message, 0, &mut found)?;
+        Ok(found)
+    }
+
+    #[test]
+    fn varuints_are_leb128() {
+        let mut reader = Reader { bytes: &[0x05, 0xe5, 0x8e, 0x26, 0x80], pos: 0 };
+        assert_eq!(reader.varuint().unwrap(), 5);
+        assert_eq!(reader.varuint().unwrap(), 624_485);
+        assert!(reader.varuint().is_err());
+        assert!(Reader { bytes: &[0xff; 10], pos: 0 }.varuint().is_err());
+    }
+
+    #[test]
+    fn operations_are_replayed_on_each_branch() {
+        let timestamp = [
+            &[FORK, OP_APPEND, 2, b'c', b'd'][..], &attestation(BITCOIN_ATTESTATION, &[0xe5, 0x8e, 0x26]),
+            &[OP_PREPEND, 1, b'z', OP_REVERSE, OP_HEXLIFY], &attestation(PENDING_ATTESTATION, b"\x1chttps://calendar.example.com")
+        ].concat();
+        let found = read(&timestamp, b"ab").unwrap();
+
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].0, b"abcd");
+        assert!(matches!(found[0].1, Attestation::Bitcoin(624_485)));
+        assert_eq!(found[1].0, b"62617a");
+        assert!(matches!(&found[1].1, Attestation::Pending(calendar) if calendar == "https://calendar.example.com"));
+
+        let found = read(&[&[OP_SHA256][..], &attestation([1; 8], b"")].concat(), b"ab").unwrap();
+        assert_eq!(found[0].0, Sha256::digest(b"ab").to_vec());
+        assert!(matches!(found[0].1, Attestation::Other));
+    }
+
+    #[test]
+    fn malformed_timestamps_are_rejected() {
+        assert!(matches!(read(&[0x42], b"ab"), Err(c2pa::Error::BadParam(message)) if message.contains("0x42")));
+        assert!(read(&[OP_APPEND, 5, b'c'], b"ab").is_err());
+        assert!(read(&[OP_SHA256], b"ab").is_err());
+
+        let nested = [vec![OP_REVERSE; MAX_DEPTH + 1], attestation([1; 8], b"")].concat();
+        assert!(matches!(read(&nested, b"ab"), Err(c2pa::Error::BadParam(message)) if message.contains("nested")));
+    }
+
+    #[test]
+    fn proofs_must_be_over_the_manifest_store() {
+        let asset = std::fs::read("test_file.jpg").unwrap();
+        let signed = sign_asset("jpg", &asset, DEFAULT_MANIFEST_DEFINITION, &SignerConfig::default()).unwrap();
+        let digest = manifest_store_digest("jpg", &signed).unwrap();
+        let proof = |digest: &[u8]| [HEADER_MAGIC, &[VERSION, OP_SHA256], digest, &attestation([1; 8], b"")].concat();
+
+        assert!(matches!(verify("jpg", &signed, &proof(&digest)).unwrap(), OtsStatus::Pending(calendars) if calendars.is_empty()));
+        assert!(matches!(verify("jpg", &signed, &proof(&[0; 32])).unwrap(), OtsStatus::Invalid(reason) if reason == "ots.hashMismatch"));
+        assert!(matches!(verify("jpg", &signed, &proof(&digest)[1..]), Err(c2pa::Error::BadParam(_))));
+        assert!(verify("jpg", &asset, &proof(&digest)).is_err());
+    }
+}
@@ -0,0 +1,100 @@
+use std::path::PathBuf;
+
+use directories::ProjectDirs;
+
+/*
+ * Where this tool keeps its files, in the platform's usual directories:
+ * the XDG base directories on Linux (`~/.config`, `~/.cache` and
+ * `~/.local/share`, or wherever `XDG_CONFIG_HOME`, `XDG_CACHE_HOME` and
+ * `XDG_DATA_HOME` point), `~/Library/Application Support` and
+ * `~/Library/Caches` on macOS, and AppData on Windows. Without a home
+ * directory, everything goes under the system temporary directory.
+ */
+
+/* One file or directory the tool uses, for `paths` */
+pub struct Location {
+    pub name: &'static str,
+    pub path: PathBuf
+}
+
+fn 
+project_dirs () -> Option<ProjectDirs> {
+    ProjectDirs::from("", "", "c2pa-walkthrough")
+}
+
+fn 
+fallback_dir () -> PathBuf {
+    std::env::temp_dir().join("c2pa-walkthrough")
+}
+
+/* Configuration, such as the signer profiles */
+pub fn 
+config_dir () -> PathBuf {
+    project_dirs().map(|dirs| dirs.config_dir().to_owned()).unwrap_or_else(fallback_dir)
+}
+
+/* Downloads which can be fetched again, such as trust lists and CRLs */
+pub fn 
+cache_dir () -> PathBuf {
+    project_dirs().map(|dirs| dirs.cache_dir().to_owned()).unwrap_or_else(fallback_dir)
+}
+
+/* Records the tool keeps, such as the audit log */
+pub fn 
+data_dir () -> PathBuf {
+    project_dirs().map(|dirs| dirs.data_dir().to_owned()).unwrap_or_else(fallback_dir)
+}
+
+/* The cached trust list bundles */
+pub fn 
+trust_list_cache_dir () -> PathBuf {
+    cache_dir().join("trust-list")
+}
+
+/* The cached CRLs */
+pub fn 
+crl_cache_dir () -> PathBuf {
+    cache_dir().join("crl")
+}
+
+/* The cached remote manifest stores */
+pub fn 
+manifest_cache_dir () -> PathBuf {
+    cache_dir().join("manifests")
+}
+
+/* Sockets and other files for as long as the user is logged in, else the data directory */
+pub fn 
+runtime_dir () -> PathBuf {
+    project_dirs().and_then(|dirs| dirs.runtime_dir().map(|dir| dir.to_owned())).unwrap_or_else(data_dir)
+}
+
+/* The socket `daemon` listens on by default */
+pub fn 
+daemon_socket_path () -> PathBuf {
+    runtime_dir().join("daemon.sock")
+}
+
+/* The audit log signing operations are recorded to with `--audit` */
+pub fn 
+audit_log_path () -> PathBuf {
+    data_dir().join("audit.db")
+}
+
+/* Every file and directory the tool reads or writes on its own account */
+pub fn 
+locations () -> Vec<Location> {
+    vec![
+        Location { name: "config", path: config_dir() },
+        Location { name: "signer profiles", path: crate::profiles::profiles_path() },
+        Location { name: "env file", path: crate::dotenv::env_file().unwrap_or_else(|| PathBuf::from(".env")) },
+        Location { name: "cache", path: cache_dir() },
+        Location { name: "trust list cache", path: trust_list_cache_dir() },
+        Location { name: "CRL cache", path: crl_cache_dir() },
+        Location { name: "manifest cache", path: manifest_cache_dir() },
+        Location { name: "data", path: data_dir() },
+        Location { name: "runtime", path: runtime_dir() },
+        Location { name: "daemon socket", path: daemon_socket_path() },
+        Location { name: "audit log", path: audit_log_path() }
+    ]
+}
@@ -0,0 +1,226 @@
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use c2pa::Manifest;
+use glob::Pattern;
+use openssl::x509::X509;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+/*
+ * Certificate pinning, so a publisher can check that assets claiming to
+ * come from it were signed with its own keys rather than any key the trust
+ * list accepts. A policy pins certificates to the source domain an asset
+ * was fetched from, the claim generator of its active manifest, or both:
+ *
+ *     {"pins": [{"domain": "*.example.com", "claim_generator": "Example Newsroom*",
+ *                "spki_sha256": ["<base64>"], "cert_sha256": ["<hex>"]}]}
+ *
+ * A pin applies when all of its selectors match, and is then satisfied if
+ * any certificate in the active manifest's chain has one of its SPKI
+ * hashes (base64, as in HPKP pin-sha256) or certificate fingerprints (hex,
+ * colons optional), so an issuing CA may be pinned instead of every key.
+ */
+
+/* One pin: which assets it applies to, and the keys they must be signed with */
+pub struct Pin {
+    pub domain: Option<Pattern>,
+    pub claim_generator: Option<Pattern>,
+    pub spki_sha256: Vec<String>,
+    pub cert_sha256: Vec<String>
+}
+
+#[derive(Default)]
+pub struct PinPolicy {
+    pub pins: Vec<Pin>
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PinEntry {
+    domain: Option<String>,
+    claim_generator: Option<String>,
+    #[serde(default)]
+    spki_sha256: Vec<String>,
+    #[serde(default)]
+    cert_sha256: Vec<String>
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PolicyFile {
+    pins: Vec<PinEntry>
+}
+
+/* What a pin policy made of an asset */
+pub enum PinCheck {
+    // No pin applies to the asset
+    Unpinned,
+    // A pin applies and the chain holds one of its keys
+    Matched,
+    // A pin applies but the chain holds none of its keys; the signing certificate's SPKI hash
+    Mismatch { spki_sha256: String }
+}
+
+fn 
+ssl_error (e: openssl::error::ErrorStack) -> c2pa::Error {
+    c2pa::Error::OtherError(Box::new(e))
+}
+
+/* A hex fingerprint, lowercased without colons, for comparison */
+fn 
+normalize_fingerprint (fingerprint: &str) -> String {
+    fingerprint.chars().filter(|c| *c != ':').collect::<String>().to_lowercase()
+}
+
+/* The host of a location, if it is an http(s) URL */
+pub fn 
+location_domain (location: &str) -> Option<String> {
+    let rest = location.strip_prefix("https://").or_else(|| location.strip_prefix("http://"))?;
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let host = authority.rsplit('@').next()?;
+    let host = host.split(':').next()?;
+
+    (!host.is_empty()).then(|| host.to_lowercase())
+}
+
+impl PinPolicy {
+    pub fn from_json(json: &str) -> Result<PinPolicy, c2pa::Error> {
+        let file: PolicyFile = serde_json::from_str(json)?;
+        let compile = |pattern: Option<String>| pattern
+            .map(|pattern| Pattern::new(&pattern).map_err(|e| c2pa::Error::BadParam(format!("bad pin pattern '{}': {}", pattern, e))))
+            .transpose();
+
+        let mut pins = Vec::new();
+        for entry in file.pins {
+            if entry.domain.is_none() && entry.claim_generator.is_none() {
+                return Err(c2pa::Error::BadParam("a pin needs a domain or a claim_generator to apply to".to_owned()));
+            }
+            if entry.spki_sha256.is_empty() && entry.cert_sha256.is_empty() {
+                return Err(c2pa::Error::BadParam("a pin needs at least one spki_sha256 or cert_sha256".to_owned()));
+            }
+            pins.push(Pin {
+                domain: compile(entry.domain)?,
+                claim_generator: compile(entry.claim_generator)?,
+                spki_sha256: entry.spki_sha256,
+                cert_sha256: entry.cert_sha256.iter().map(|fingerprint| normalize_fingerprint(fingerprint)).collect()
+            });
+        }
+
+        Ok(PinPolicy { pins })
+    }
+
+    pub fn from_file(path: &str) -> Result<PinPolicy, c2pa::Error> {
+        PinPolicy::from_json(&std::fs::read_to_string(path)?)
+    }
+
+    /**
+     * Checks the active manifest of an asset fetched from `domain`, if it
+     * was fetched at all, against the pins which apply to it.
+     */
+    pub fn check(&self, domain: Option<&str>, manifest: &Manifest) -> Result<PinCheck, c2pa::Error> {
+        let mut generators: Vec<&str> = manifest.claim_generator().into_iter().collect();
+        generators.extend(manifest.claim_generator_info.iter().flatten().map(|info| info.name.as_str()));
+
+        let applicable: Vec<&Pin> = self.pins.iter()
+            .filter(|pin| pin.domain.as_ref().is_none_or(|pattern| domain.is_some_and(|domain| pattern.matches(domain))))
+            .filter(|pin| pin.claim_generator.as_ref().is_none_or(|pattern| generators.iter().any(|generator| pattern.matches(generator))))
+            .collect();
+        if applicable.is_empty() {
+            return Ok(PinCheck::Unpinned);
+        }
+
+        let chain_pem = manifest.signature_info().map(|signature| signature.cert_chain()).unwrap_or_default();
+        let chain = X509::stack_from_pem(chain_pem.as_bytes()).map_err(ssl_error)?;
+        let mut spki_hashes = Vec::new();
+        let mut fingerprints = Vec::new();
+        for cert in &chain {
+            let spki = cert.public_key().and_then(|key| key.public_key_to_der()).map_err(ssl_error)?;
+            spki_hashes.push(BASE64.encode(Sha256::digest(&spki)));
+            fingerprints.push(hex::encode(Sha256::digest(cert.to_der().map_err(ssl_error)?)));
+        }
+
+        let matched = applicable.iter().all(|pin| {
+            pin.spki_sha256.iter().any(|hash| spki_hashes.contains(hash))
+                || pin.cert_sha256.iter().any(|fingerprint| fingerprints.contains(fingerprint))
+        });
+        match matched {
+            true => Ok(PinCheck::Matched),
+            false => Ok(PinCheck::Mismatch { spki_sha256: spki_hashes.first().cloned().unwrap_or_default() })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use c2pa::Reader;
+
+    use super::*;
+    use crate::read::load_manifest_store;
+    use crate::sign::{sign_asset, SignerConfig, DEFAULT_MANIFEST_DEFINITION};
+
+    /* test_file.jpg signed with the default signer, whose claim generator is mikes-c2pa-test-code */
+    fn signed_store() -> Reader {
+        let signed = sign_asset("jpg", &std::fs::read("test_file.jpg").unwrap(), DEFAULT_MANIFEST_DEFINITION, &SignerConfig::default()).unwrap();
+        load_manifest_store("jpg", &signed).unwrap()
+    }
+
+    fn policy(pin: serde_json::Value) -> PinPolicy {
+        PinPolicy::from_json(&serde_json::json!({ "pins": [pin] }).to_string()).unwrap()
+    }
+
+    #[test]
+    fn location_domains_are_hosts_of_urls() {
+        assert_eq!(location_domain("https://User@Media.Example.com:8443/a.jpg?x#y").as_deref(), Some("media.example.com"));
+        assert_eq!(location_domain("http://example.com").as_deref(), Some("example.com"));
+        assert_eq!(location_domain("s3://bucket/a.jpg"), None);
+        assert_eq!(location_domain("photos/a.jpg"), None);
+        assert_eq!(location_domain("https:///a.jpg"), None);
+    }
+
+    #[test]
+    fn incomplete_pins_are_rejected() {
+        let rejected = |json: &str| matches!(PinPolicy::from_json(json), Err(c2pa::Error::BadParam(_) | c2pa::Error::JsonError(_)));
+
+        assert!(rejected(r#"{"pins": [{"spki_sha256": ["abc="]}]}"#));
+        assert!(rejected(r#"{"pins": [{"domain": "example.com"}]}"#));
+        assert!(rejected(r#"{"pins": [{"domain": "[", "spki_sha256": ["abc="]}]}"#));
+        assert!(rejected(r#"{"pins": [{"domain": "example.com", "spki": ["abc="]}]}"#));
+        assert_eq!(policy(serde_json::json!({ "domain": "example.com", "cert_sha256": ["AB:cd:EF"] })).pins[0].cert_sha256, ["abcdef"]);
+    }
+
+    #[test]
+    fn pins_apply_only_when_every_selector_matches() {
+        let manifest_store = signed_store();
+        let manifest = manifest_store.active_manifest().unwrap();
+        let pin = policy(serde_json::json!({ "domain": "*.example.com", "claim_generator": "mikes-c2pa-*", "spki_sha256": ["abc="] }));
+
+        assert!(matches!(pin.check(None, manifest).unwrap(), PinCheck::Unpinned));
+        assert!(matches!(pin.check(Some("example.org"), manifest).unwrap(), PinCheck::Unpinned));
+        assert!(matches!(pin.check(Some("media.example.com"), manifest).unwrap(), PinCheck::Mismatch { .. }));
+        let other_generator = policy(serde_json::json!({ "claim_generator": "Other*", "spki_sha256": ["abc="] }));
+        assert!(matches!(other_generator.check(Some("media.example.com"), manifest).unwrap(), PinCheck::Unpinned));
+    }
+
+    #[test]
+    fn signing_key_satisfies_its_pin() {
+        let manifest_store = signed_store();
+        let manifest = manifest_store.active_manifest().unwrap();
+
+        let PinCheck::Mismatch { spki_sha256 } = policy(serde_json::json!({ "claim_generator": "mikes-c2pa-*", "spki_sha256": ["abc="] })).check(None, manifest).unwrap() else {
+            panic!("a pin to another key matched");
+        };
+        let pin = policy(serde_json::json!({ "claim_generator": "mikes-c2pa-*", "spki_sha256": ["abc=", spki_sha256] }));
+        assert!(matches!(pin.check(None, manifest).unwrap(), PinCheck::Matched));
+    }
+
+    #[test]
+    fn issuing_ca_satisfies_a_fingerprint_pin() {
+        let manifest_store = signed_store();
+        let manifest = manifest_store.active_manifest().unwrap();
+        let chain = X509::stack_from_pem(manifest.signature_info().unwrap().cert_chain().as_bytes()).unwrap();
+        let ca_fingerprint = hex::encode_upper(Sha256::digest(chain.last().unwrap().to_der().unwrap()));
+
+        let pin = policy(serde_json::json!({ "claim_generator": "mikes-c2pa-*", "cert_sha256": [ca_fingerprint] }));
+        assert!(matches!(pin.check(None, manifest).unwrap(), PinCheck::Matched));
+    }
+}
@@ -0,0 +1,103 @@
+use image::{DynamicImage, GenericImageView, ImageFormat};
+use serde::Serialize;
+
+/*
+ * Pixel-level comparison of two images, with the `pixel-integrity`
+ * feature. Embedding a manifest should only add a manifest store to an
+ * image; an embedding path which decodes and re-encodes it instead, such
+ * as a careless JPEG writer, changes pixels while the signature over the
+ * result still validates. Both images are decoded and compared sample by
+ * sample at 16 bits per channel with alpha, so an 8-bit and a 16-bit PNG
+ * of the same pixels compare equal, and a lossless re-encode passes.
+ */
+
+/* Result of comparing the pixels of two images */
+#[derive(Clone, Debug, Serialize)]
+pub struct PixelComparison {
+    // Width and height of each image
+    pub dimensions: [(u32, u32); 2],
+    // Decoded color type of each image, e.g. Rgb8 or La16
+    pub color_types: [String; 2],
+    // Pixels differing in any channel; every pixel when the dimensions differ
+    pub differing: u64,
+    pub total: u64,
+    // Largest difference in any one channel, scaled to 0..=255
+    pub max_difference: u8,
+    // First differing pixel in row order
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub first_difference: Option<(u32, u32)>
+}
+
+impl PixelComparison {
+    pub fn 
+    identical (&self) -> bool {
+        self.differing == 0 && self.dimensions[0] == self.dimensions[1]
+    }
+
+    /* e.g. "1204 of 786432 pixels differ, by up to 9 of 255, the first at (16, 0)" */
+    pub fn 
+    summary (&self) -> String {
+        let [(w0, h0), (w1, h1)] = self.dimensions;
+        match self.first_difference {
+            _ if (w0, h0) != (w1, h1) => format!("{}x{} against {}x{}", w0, h0, w1, h1),
+            Some((x, y)) => format!("{} of {} pixels differ, by up to {} of 255, the first at ({}, {})", self.differing, self.total, self.max_difference, x, y),
+            None => format!("all {} pixels of {}x{} identical", self.total, w0, h0)
+        }
+    }
+}
+
+fn 
+image_error (e: image::ImageError) -> c2pa::Error {
+    c2pa::Error::OtherError(Box::new(e))
+}
+
+/* Decodes an image given its format as an extension or media type, e.g. jpg or image/png */
+pub fn 
+decode (format: &str, asset: &[u8]) -> Result<DynamicImage, c2pa::Error> {
+    let image_format = ImageFormat::from_extension(format)
+        .or_else(|| ImageFormat::from_mime_type(format))
+        .ok_or_else(|| c2pa::Error::BadParam(format!("{} images can't be decoded to compare their pixels", format)))?;
+
+    image::load_from_memory_with_format(asset, image_format).map_err(image_error)
+}
+
+/* Compares two decoded images */
+pub fn 
+compare (a: &DynamicImage, b: &DynamicImage) -> PixelComparison {
+    let dimensions = [a.dimensions(), b.dimensions()];
+    let color_types = [format!("{:?}", a.color()), format!("{:?}", b.color())];
+    let (width, height) = dimensions[0];
+    let total = width as u64 * height as u64;
+    if dimensions[0] != dimensions[1] {
+        return PixelComparison { dimensions, color_types, differing: total, total, max_difference: u8::MAX, first_difference: Some((0, 0)) };
+    }
+
+    let (a, b) = (a.to_rgba16(), b.to_rgba16());
+    let mut differing = 0;
+    let mut max_difference = 0;
+    let mut first_difference = None;
+    for ((x, y, pixel), other) in a.enumerate_pixels().zip(b.pixels()) {
+        let difference = pixel.0.iter().zip(other.0).map(|(a, b)| a.abs_diff(b)).max().unwrap_or_default();
+        if difference > 0 {
+            differing += 1;
+            max_difference = max_difference.max(difference);
+            first_difference.get_or_insert((x, y));
+        }
+    }
+
+    PixelComparison {
+        dimensions,
+        color_types,
+        differing,
+        total,
+        // Rounded up, so a difference too small to show at 8 bits still counts
+        max_difference: max_difference.div_ceil(257) as u8,
+        first_difference
+    }
+}
+
+/* Decodes and compares two images, each with its format */
+pub fn 
+compare_pixels (a_format: &str, a: &[u8], b_format: &str, b: &[u8]) -> Result<PixelComparison, c2pa::Error> {
+    Ok(compare(&decode(a_format, a)?, &decode(b_format, b)?))
+}
@@ -0,0 +1,179 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use openssl::asn1::Asn1Time;
+use openssl::x509::{X509, X509Ref};
+use serde::Deserialize;
+
+use crate::clock;
+use crate::paths;
+use crate::sign::SignerConfig;
+use crate::storage::write_file_atomically;
+use crate::trust::name_string;
+
+/*
+ * Named signer profiles, so scripts select a signer by name, such as
+ * "staging" or "production-2025", rather than by the paths of its
+ * certificate and key. They are kept in one JSON file alongside the name of
+ * the default profile, which commands sign with when given no profile:
+ *
+ *     {"default": "production-2025",
+ *      "profiles": {"production-2025": {"signcert_path": "prod-2025.pub", "pkey_path": "prod-2025.pem", "alg": "es256"}}}
+ *
+ * Relative paths in a profile are taken from the file's directory. Keys are
+ * rotated by adding the new key's profile and pointing the default at it,
+ * which every script relying on the default then follows.
+ */
+
+// Environment variable naming the profiles file, in place of the one in the config directory
+pub const PROFILES_VAR: &str = "C2PA_SIGNER_PROFILES";
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SignerProfiles {
+    pub default: Option<String>,
+    #[serde(default)]
+    pub profiles: BTreeMap<String, SignerConfig>
+}
+
+/**
+ * Where signer profiles are kept: the file named by `C2PA_SIGNER_PROFILES`,
+ * else `profiles.json` in the config directory, such as
+ * `~/.config/c2pa-walkthrough` on Linux.
+ */
+pub fn 
+profiles_path () -> PathBuf {
+    match std::env::var_os(PROFILES_VAR).filter(|path| !path.is_empty()) {
+        Some(path) => PathBuf::from(path),
+        None => paths::config_dir().join("profiles.json")
+    }
+}
+
+/* `path` as written in a profile, from the directory of the profiles file if relative */
+fn 
+resolve (dir: &Path, path: &str) -> String {
+    dir.join(path).to_string_lossy().into_owned()
+}
+
+impl SignerProfiles {
+    pub fn from_file(path: &Path) -> Result<SignerProfiles, c2pa::Error> {
+        let mut profiles: SignerProfiles = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+        let dir = path.parent().unwrap_or(Path::new(""));
+        for config in profiles.profiles.values_mut() {
+            config.signcert_path = resolve(dir, &config.signcert_path);
+            config.pkey_path = resolve(dir, &config.pkey_path);
+        }
+        if let Some(default) = profiles.default.as_ref().filter(|default| !profiles.profiles.contains_key(*default)) {
+            return Err(c2pa::Error::BadParam(format!("the default profile '{}' isn't defined", default)));
+        }
+
+        Ok(profiles)
+    }
+
+    /* The profiles in `path`, or None if there is no such file */
+    pub fn load(path: &Path) -> Result<Option<SignerProfiles>, c2pa::Error> {
+        match path.exists() {
+            true => SignerProfiles::from_file(path).map(Some),
+            false => Ok(None)
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Result<&SignerConfig, c2pa::Error> {
+        self.profiles.get(name).ok_or_else(|| {
+            let names: Vec<&str> = self.profiles.keys().map(String::as_str).collect();
+            c2pa::Error::BadParam(format!("no signer profile '{}'; defined are: {}", name, names.join(", ")))
+        })
+    }
+
+    /* The default profile, if one is set */
+    pub fn default_profile(&self) -> Option<&SignerConfig> {
+        self.default.as_ref().and_then(|name| self.profiles.get(name))
+    }
+}
+
+/* When a profile's signing certificate, the first in its chain, expires */
+pub fn 
+not_after (config: &SignerConfig) -> Result<String, c2pa::Error> {
+    let chain = std::fs::read(&config.signcert_path)?;
+    let cert = X509::from_pem(&chain).map_err(|e| c2pa::Error::OtherError(Box::new(e)))?;
+    Ok(cert.not_after().to_string())
+}
+
+/* A certificate in a signer's chain which expires soon, or has expired */
+pub struct ExpiringCert {
+    pub subject: String,
+    // The signing certificate, rather than a CA certificate in its chain
+    pub signing: bool,
+    pub not_after: String,
+    // Whole days left, negative once expired
+    pub days_left: i64
+}
+
+impl ExpiringCert {
+    /* When it expires, or expired, e.g. "expires Nov 14 11:51:03 2026 GMT, in 29 days" */
+    pub fn when(&self) -> String {
+        match self.days_left {
+            days if days < 0 => format!("expired {}", self.not_after),
+            days => format!("expires {}, in {} day{}", self.not_after, days, if days == 1 { "" } else { "s" })
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        let which = if self.signing { "the signing certificate" } else { "the CA certificate" };
+        format!("{} {} {}", which, self.subject, self.when())
+    }
+}
+
+/**
+ * `cert`, the signing certificate or one in its chain, if it expires
+ * within `window_days` of now, as `clock` tells it, or already has.
+ */
+pub fn 
+cert_expiry (cert: &X509Ref, signing: bool, window_days: u32) -> Result<Option<ExpiringCert>, c2pa::Error> {
+    let ssl_error = |e: openssl::error::ErrorStack| c2pa::Error::OtherError(Box::new(e));
+    let now = Asn1Time::from_unix(clock::now().timestamp()).map_err(ssl_error)?;
+    let left = now.diff(cert.not_after()).map_err(ssl_error)?;
+    let seconds_left = left.days as i64 * 86400 + left.secs as i64;
+    if seconds_left >= window_days as i64 * 86400 {
+        return Ok(None);
+    }
+
+    Ok(Some(ExpiringCert {
+        subject: name_string(cert.subject_name()),
+        signing,
+        not_after: cert.not_after().to_string(),
+        days_left: seconds_left.div_euclid(86400)
+    }))
+}
+
+/**
+ * The certificates in a signer's chain which expire within `window_days`
+ * of now, or already have, signing certificate first.
+ */
+pub fn 
+expiring_certs (config: &SignerConfig, window_days: u32) -> Result<Vec<ExpiringCert>, c2pa::Error> {
+    let chain = X509::stack_from_pem(&std::fs::read(&config.signcert_path)?).map_err(|e| c2pa::Error::OtherError(Box::new(e)))?;
+    let mut expiring = Vec::new();
+    for (i, cert) in chain.iter().enumerate() {
+        expiring.extend(cert_expiry(cert, i == 0, window_days)?);
+    }
+
+    Ok(expiring)
+}
+
+/**
+ * Points the default profile in `path` at `name`, which must be defined,
+ * returning the profile it pointed at before. The profiles themselves are
+ * written back unchanged.
+ */
+pub fn 
+rotate (path: &Path, name: &str) -> Result<Option<String>, c2pa::Error> {
+    let profiles = SignerProfiles::from_file(path)?;
+    profiles.get(name)?;
+
+    let mut file: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+    file["default"] = serde_json::Value::String(name.to_owned());
+    write_file_atomically(path, format!("{}\n", serde_json::to_string_pretty(&file)?).as_bytes())?;
+
+    Ok(profiles.default)
+}
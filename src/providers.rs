@@ -0,0 +1,98 @@
+use std::sync::{Arc, Mutex};
+
+use c2pa::Builder;
+
+/*
+ * Pluggable assertion generators, so downstream code can attach its own
+ * assertions, such as a perceptual hash or ML content labels, to every
+ * manifest this library signs for new content without forking
+ * `create_new_manifest` or the signing functions. Providers are registered
+ * process-wide, like the thumbnail settings, and asked in registration
+ * order for assertions about each asset as it is about to be signed:
+ *
+ *     struct Labels;
+ *
+ *     impl AssertionProvider for Labels {
+ *         fn name(&self) -> &str { "labels" }
+ *         fn assertions(&self, format: &str, asset: &[u8]) -> Result<Vec<ProvidedAssertion>, c2pa::Error> {
+ *             Ok(vec![ProvidedAssertion::json("com.example.labels", serde_json::json!({"labels": classify(asset)?}))])
+ *         }
+ *     }
+ *
+ *     providers::register(Box::new(Labels));
+ *
+ * Endorsements describe a review rather than the asset, and are left alone.
+ */
+
+/* An assertion a provider attaches */
+#[derive(Clone, Debug)]
+pub struct ProvidedAssertion {
+    pub label: String,
+    pub data: serde_json::Value,
+    // Kept as JSON in the manifest rather than written as CBOR, as `"kind": "Json"` does in a definition
+    pub json: bool
+}
+
+impl ProvidedAssertion {
+    /* An assertion written as CBOR, as the SDK writes most */
+    pub fn cbor(label: &str, data: serde_json::Value) -> ProvidedAssertion {
+        ProvidedAssertion { label: label.to_owned(), data, json: false }
+    }
+
+    pub fn json(label: &str, data: serde_json::Value) -> ProvidedAssertion {
+        ProvidedAssertion { label: label.to_owned(), data, json: true }
+    }
+}
+
+/* Generates assertions about the assets being signed */
+pub trait AssertionProvider: Send + Sync {
+    /* What the provider is called in errors, e.g. "phash" */
+    fn name(&self) -> &str;
+
+    /**
+     * The assertions to attach to the manifest of `asset`, in `format`, as
+     * it is about to be signed; none leaves the manifest as it is. An
+     * error fails the signing.
+     */
+    fn assertions(&self, format: &str, asset: &[u8]) -> Result<Vec<ProvidedAssertion>, c2pa::Error>;
+}
+
+// The providers asked about every asset signed, in registration order
+static PROVIDERS: Mutex<Vec<Arc<dyn AssertionProvider>>> = Mutex::new(Vec::new());
+
+/* Asks `provider` for assertions about every asset signed from now on */
+pub fn 
+register (provider: Box<dyn AssertionProvider>) {
+    PROVIDERS.lock().unwrap().push(Arc::from(provider));
+}
+
+/* The names of the registered providers, in order */
+pub fn 
+registered () -> Vec<String> {
+    PROVIDERS.lock().unwrap().iter().map(|provider| provider.name().to_owned()).collect()
+}
+
+/* Removes every registered provider */
+pub fn 
+clear () {
+    PROVIDERS.lock().unwrap().clear();
+}
+
+/* Adds the assertions of every registered provider about `asset` to `builder` */
+pub fn 
+apply_providers (builder: &mut Builder, format: &str, asset: &[u8]) -> Result<(), c2pa::Error> {
+    // Not locked while providers run, so concurrent signings don't wait on each other's
+    let providers = PROVIDERS.lock().unwrap().clone();
+    for provider in providers {
+        let assertions = provider.assertions(format, asset)
+            .map_err(|e| c2pa::Error::BadParam(format!("assertion provider {}: {}", provider.name(), e)))?;
+        for assertion in assertions {
+            match assertion.json {
+                true => builder.add_assertion_json(&assertion.label, &assertion.data)?,
+                false => builder.add_assertion(&assertion.label, &assertion.data)?
+            };
+        }
+    }
+
+    Ok(())
+}
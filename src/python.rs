@@ -0,0 +1,133 @@
+use std::path::{Path, PathBuf};
+
+use pyo3::create_exception;
+use pyo3::exceptions::PyException;
+use pyo3::prelude::*;
+use serde_json::json;
+
+use crate::read::{is_valid, load_manifest_store, validation_errors};
+use crate::sign::{default_manifest_definition, sign_asset_with_signer, SharedSigner, SignerConfig};
+use crate::storage::{format_from_location, read_file, write_file_atomically};
+
+/*
+ * The Python module `c2pa_walkthrough`, for signing, verifying and reading
+ * local files from Python without running the command line tool:
+ *
+ *     signer = c2pa_walkthrough.Signer("signer.json")
+ *     signer.sign_file("photo.jpg", "signed/photo.jpg", {"title": "photo.jpg"})
+ *     c2pa_walkthrough.verify_file("signed/photo.jpg")["valid"]
+ *
+ * Manifest definitions and results are dicts, in the same shape as their
+ * JSON elsewhere. The GIL is released while files are read, signed and
+ * written, so threads sharing one Signer sign in parallel.
+ */
+
+create_exception!(c2pa_walkthrough, C2paError, PyException, "Signing, verifying or reading an asset failed");
+
+fn
+error (e: c2pa::Error) -> PyErr {
+    C2paError::new_err(e.to_string())
+}
+
+fn
+local_format (path: &Path) -> Result<String, c2pa::Error> {
+    format_from_location(&path.to_string_lossy())
+        .ok_or_else(|| c2pa::Error::BadParam(format!("cannot determine the format of {} from its extension", path.display())))
+}
+
+/* A dict, or any other JSON-serializable object, as JSON */
+fn
+to_json (object: &Bound<'_, PyAny>) -> PyResult<String> {
+    object.py().import("json")?.call_method1("dumps", (object,))?.extract()
+}
+
+fn
+from_json (py: Python<'_>, json: &str) -> PyResult<PyObject> {
+    Ok(py.import("json")?.call_method1("loads", (json,))?.unbind())
+}
+
+/**
+ * A signer loaded from a signer profile, the JSON file `sign
+ * --signer-profile` takes. The key is read once, when it is constructed,
+ * and the profile's `max_concurrent` caps signatures across threads.
+ */
+#[pyclass(name = "Signer", frozen)]
+struct PySigner {
+    signer: SharedSigner
+}
+
+#[pymethods]
+impl PySigner {
+    #[new]
+    fn
+    new (profile: PathBuf) -> PyResult<Self> {
+        let signer = SignerConfig::from_file(&profile.to_string_lossy())
+            .and_then(|config| config.create_shared_signer())
+            .map_err(error)?;
+        Ok(PySigner { signer })
+    }
+
+    /**
+     * Signs the file at `source` and writes it to `dest`, which may be the
+     * same path, with a manifest built from the `manifest` definition, or
+     * the default definition if it is None.
+     */
+    #[pyo3(signature = (source, dest, manifest=None))]
+    fn
+    sign_file (&self, py: Python<'_>, source: PathBuf, dest: PathBuf, manifest: Option<&Bound<'_, PyAny>>) -> PyResult<()> {
+        let manifest_json = match manifest {
+            Some(manifest) => to_json(manifest)?,
+            None => default_manifest_definition()
+        };
+
+        py.allow_threads(|| {
+            let asset = read_file(&source)?;
+            let signed = sign_asset_with_signer(&local_format(&source)?, &asset, &manifest_json, &self.signer)?;
+            write_file_atomically(&dest, &signed)
+        }).map_err(error)
+    }
+}
+
+/**
+ * Validates the manifests in the file at `path`, returning a dict such as
+ * `{"valid": False, "validation_errors": ["assertion.dataHash.mismatch"],
+ * "active_manifest": "urn:uuid:..."}`. A file without manifests raises
+ * C2paError rather than returning a verdict.
+ */
+#[pyfunction]
+fn
+verify_file (py: Python<'_>, path: PathBuf) -> PyResult<PyObject> {
+    let verdict = py.allow_threads(|| {
+        let manifest_store = load_manifest_store(&local_format(&path)?, &read_file(&path)?)?;
+        let errors = validation_errors(&manifest_store);
+        Ok(json!({
+            "valid": is_valid(&manifest_store),
+            "validation_errors": errors,
+            "active_manifest": manifest_store.active_label()
+        }).to_string())
+    }).map_err(error)?;
+
+    from_json(py, &verdict)
+}
+
+/* The manifest store of the file at `path` as a dict, including any `validation_status` */
+#[pyfunction]
+fn
+read_manifest (py: Python<'_>, path: PathBuf) -> PyResult<PyObject> {
+    let store_json = py.allow_threads(|| {
+        let manifest_store = load_manifest_store(&local_format(&path)?, &read_file(&path)?)?;
+        Ok::<_, c2pa::Error>(manifest_store.json())
+    }).map_err(error)?;
+
+    from_json(py, &store_json)
+}
+
+#[pymodule]
+fn
+c2pa_walkthrough (m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PySigner>()?;
+    m.add_function(wrap_pyfunction!(verify_file, m)?)?;
+    m.add_function(wrap_pyfunction!(read_manifest, m)?)?;
+    m.add("C2paError", m.py().get_type::<C2paError>())?;
+    Ok(())
+}
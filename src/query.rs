@@ -0,0 +1,158 @@
+use c2pa::Reader;
+use serde_json::Value;
+
+/*
+ * Picking parts out of a manifest store's JSON, for scripts which want one
+ * field rather than the whole dump. A selector is a jq-style path:
+ * `.key` and `."key.with.dots"` (or `["key"]`) descend into objects, `[N]`
+ * indexes arrays, and `[]` or `.*` fan out over every array element or
+ * object value, e.g. `.manifests[].assertions[].label`. Assertions can
+ * also be picked by label glob (`stds.exif`, `c2pa.actions*`). Like `read`,
+ * this has no file-system dependencies.
+ */
+
+/* One step of a selector */
+enum Step {
+    Key(String),
+    Index(usize),
+    Each
+}
+
+fn 
+bad_selector (selector: &str, why: &str) -> c2pa::Error {
+    c2pa::Error::BadParam(format!("invalid selector '{}': {}", selector, why))
+}
+
+/* Reads a quoted key starting just after its opening quote; returns the key and the rest */
+fn 
+quoted_key<'a> (selector: &str, rest: &'a str) -> Result<(String, &'a str), c2pa::Error> {
+    let mut key = String::new();
+    let mut chars = rest.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Ok((key, &rest[i + 1..])),
+            '\\' => key.push(chars.next().ok_or_else(|| bad_selector(selector, "dangling escape"))?.1),
+            c => key.push(c)
+        }
+    }
+    Err(bad_selector(selector, "unterminated quote"))
+}
+
+fn 
+parse (selector: &str) -> Result<Vec<Step>, c2pa::Error> {
+    let mut steps = Vec::new();
+    let mut rest = selector.trim();
+    if rest == "." {
+        return Ok(steps);
+    }
+
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix('.') {
+            if let Some(after) = after.strip_prefix('"') {
+                let (key, after) = quoted_key(selector, after)?;
+                steps.push(Step::Key(key));
+                rest = after;
+            } else if let Some(after) = after.strip_prefix('*') {
+                steps.push(Step::Each);
+                rest = after;
+            } else {
+                let end = after.find(['.', '[']).unwrap_or(after.len());
+                if end == 0 {
+                    return Err(bad_selector(selector, "empty key"));
+                }
+                steps.push(Step::Key(after[..end].to_owned()));
+                rest = &after[end..];
+            }
+        } else if let Some(after) = rest.strip_prefix('[') {
+            let after = if let Some(quoted) = after.strip_prefix('"') {
+                let (key, after) = quoted_key(selector, quoted)?;
+                steps.push(Step::Key(key));
+                after
+            } else {
+                let end = after.find(']').ok_or_else(|| bad_selector(selector, "unclosed ["))?;
+                match after[..end].trim() {
+                    "" => steps.push(Step::Each),
+                    n => steps.push(Step::Index(n.parse().map_err(|_| bad_selector(selector, "array index should be a number"))?))
+                }
+                &after[end..]
+            };
+            rest = after.strip_prefix(']').ok_or_else(|| bad_selector(selector, "unclosed ["))?;
+        } else {
+            return Err(bad_selector(selector, "steps start with . or ["));
+        }
+    }
+
+    Ok(steps)
+}
+
+/**
+ * Evaluates a selector against a JSON value, returning every match; paths
+ * which don't exist produce no matches rather than an error.
+ */
+pub fn 
+select (value: &Value, selector: &str) -> Result<Vec<Value>, c2pa::Error> {
+    let mut current = vec![value.clone()];
+
+    for step in parse(selector)? {
+        current = current.into_iter().flat_map(|value| match (&step, value) {
+            (Step::Key(key), Value::Object(mut map)) => map.remove(key).into_iter().collect(),
+            (Step::Index(i), Value::Array(mut items)) if *i < items.len() => vec![items.swap_remove(*i)],
+            (Step::Each, Value::Array(items)) => items,
+            (Step::Each, Value::Object(map)) => map.into_iter().map(|(_, v)| v).collect(),
+            _ => Vec::new()
+        }).collect();
+    }
+
+    Ok(current)
+}
+
+/* Shell-style glob match supporting `*` and `?` */
+pub fn 
+glob_match (pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    // Position to resume from after the most recent `*`, in pattern and text
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack = None;
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            t = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/* The manifest store as JSON, in the same shape `read` prints it */
+pub fn 
+manifest_store_json (manifest_store: &Reader) -> Result<Value, c2pa::Error> {
+    Ok(serde_json::from_str(&manifest_store.json())?)
+}
+
+/**
+ * Every assertion whose label matches `label_glob`, from the named manifest
+ * or from every manifest in the store.
+ */
+pub fn 
+assertions_matching (store_json: &Value, manifest_label: Option<&str>, label_glob: &str) -> Vec<Value> {
+    let manifests = match manifest_label {
+        Some(label) => store_json["manifests"].get(label).cloned().into_iter().collect(),
+        None => select(store_json, ".manifests[]").unwrap_or_default()
+    };
+    let assertions = manifests.iter().flat_map(|manifest| select(manifest, ".assertions[]").unwrap_or_default());
+
+    assertions
+        .filter(|assertion| assertion["label"].as_str().is_some_and(|label| glob_match(label_glob, label)))
+        .collect()
+}
@@ -0,0 +1,298 @@
+use std::io::Cursor;
+
+use c2pa::Reader;
+use serde::Deserialize;
+
+use crate::jumbf;
+
+/**
+ * Loads the manifest store of in-memory asset bytes of the given MIME
+ * format, validating it in the process. This is the entry point shared by
+ * the CLI and in-browser verification, since it never touches the file system.
+ */
+pub fn 
+load_manifest_store (format: &str, bytes: &[u8]) -> Result<Reader, c2pa::Error> {
+    Reader::from_stream(format, Cursor::new(bytes))
+}
+
+/**
+ * Returns the validation status codes reported while loading the manifest
+ * store; an empty result means the store validated cleanly.
+ */
+pub fn 
+validation_errors (manifest_store: &Reader) -> Vec<String> {
+    match manifest_store.validation_status() {
+        Some(statuses) => statuses.iter().map(|status| status.code().to_owned()).collect(),
+        None => Vec::new()
+    }
+}
+
+/**
+ * Whether the active manifest's claim signature was seen to validate. The
+ * SDK reports no failure for a signature it can't check at all, such as a
+ * placeholder, so an empty `validation_errors` alone doesn't show this.
+ */
+pub fn 
+signature_validated (manifest_store: &Reader) -> bool {
+    active_success(manifest_store, "claimSignature.validated")
+}
+
+/**
+ * Whether the SDK found the active manifest's signing certificate on the
+ * trust list it was given. Without one applied, trust is never checked.
+ */
+pub fn 
+signer_trusted (manifest_store: &Reader) -> bool {
+    active_success(manifest_store, "signingCredential.trusted")
+}
+
+/* Whether the SDK reported `code` among the active manifest's successes */
+fn 
+active_success (manifest_store: &Reader, code: &str) -> bool {
+    manifest_store.validation_results().and_then(|results| results.active_manifest())
+        .is_some_and(|codes| codes.success().iter().any(|status| status.code() == code))
+}
+
+/**
+ * Whether the manifest store is valid: nothing failed validation and the
+ * active manifest's claim signature validated, which a placeholder such as
+ * an unfinalized `prepare` leaves never does.
+ */
+pub fn 
+is_valid (manifest_store: &Reader) -> bool {
+    validation_errors(manifest_store).is_empty() && signature_validated(manifest_store)
+}
+
+/**
+ * Returns the certificate chain from the COSE signature of the active
+ * manifest, as PEM, signing certificate first.
+ */
+pub fn 
+cert_chain_pem (format: &str, bytes: &[u8]) -> Result<String, c2pa::Error> {
+    let manifest_store = load_manifest_store(format, bytes)?;
+    let manifest = manifest_store.active_manifest().ok_or(c2pa::Error::ProvenanceMissing)?;
+    let signature = manifest.signature_info().ok_or(c2pa::Error::CoseX5ChainMissing)?;
+
+    Ok(signature.cert_chain().to_owned())
+}
+
+// Labels of the hash assertions binding a manifest to its asset's bytes
+const HARD_BINDINGS: [&str; 4] = ["c2pa.hash.data", "c2pa.hash.bmff", "c2pa.hash.bmff.v2", "c2pa.hash.boxes"];
+
+/* The hard binding of a manifest: the hash assertion tying it to the asset, and what it was hashed with */
+pub struct HashBinding {
+    pub manifest_label: String,
+    pub assertion: String,
+    pub alg: String,
+    // The hash itself, hex-encoded; BMFF hashes kept in a Merkle tree have none
+    pub hash: Option<String>
+}
+
+// The fields of a claim or hash assertion `hash_bindings` reads
+#[derive(Deserialize)]
+struct HashFields {
+    alg: Option<String>,
+    hash: Option<serde_cbor::Value>
+}
+
+/* The algorithm and hash in the CBOR content of a claim or hash assertion superbox */
+fn 
+hash_fields (children: &[jumbf::JumbfBox<'_>]) -> Result<(Option<String>, Option<String>), c2pa::Error> {
+    let Some(content) = children.iter().find(|child| &child.kind == b"cbor") else {
+        return Ok((None, None));
+    };
+    let fields: HashFields = serde_cbor::from_slice(content.payload)?;
+    let hash = match fields.hash {
+        Some(serde_cbor::Value::Bytes(hash)) => Some(hash.iter().map(|b| format!("{:02x}", b)).collect()),
+        _ => None
+    };
+
+    Ok((fields.alg, hash))
+}
+
+/**
+ * The hard binding of every manifest in an asset's store. A hash assertion
+ * without an algorithm of its own uses its claim's. Hash assertions aren't
+ * in what the SDK reads manifests into, so they are read from the store's
+ * JUMBF.
+ */
+pub fn 
+hash_bindings (format: &str, bytes: &[u8]) -> Result<Vec<HashBinding>, c2pa::Error> {
+    let jumbf = c2pa::jumbf_io::load_jumbf_from_memory(format, bytes)?;
+    let mut bindings = Vec::new();
+
+    for manifest in jumbf::manifests(&jumbf)? {
+        let (description, children) = jumbf::superbox(&manifest)?;
+        let mut claim_alg = None;
+        let mut assertions = Vec::new();
+        for child in children.iter().filter(|child| &child.kind == b"jumb") {
+            let (part, contents) = jumbf::superbox(child)?;
+            match part.label.as_deref() {
+                Some("c2pa.claim" | "c2pa.claim.v2") => claim_alg = hash_fields(&contents)?.0,
+                Some("c2pa.assertions") => {
+                    for assertion in contents.iter().filter(|assertion| &assertion.kind == b"jumb") {
+                        let (assertion, contents) = jumbf::superbox(assertion)?;
+                        let label = assertion.label.unwrap_or_default();
+                        // Repeated assertions are labeled with an instance, as c2pa.hash.data__1
+                        if HARD_BINDINGS.contains(&label.split("__").next().unwrap_or(&label)) {
+                            let (alg, hash) = hash_fields(&contents)?;
+                            assertions.push((label, alg, hash));
+                        }
+                    }
+                },
+                _ => ()
+            }
+        }
+
+        for (assertion, alg, hash) in assertions {
+            bindings.push(HashBinding {
+                manifest_label: description.label.clone().unwrap_or_default(),
+                assertion,
+                alg: alg.or_else(|| claim_alg.clone()).unwrap_or_else(|| "unknown".to_owned()),
+                hash
+            });
+        }
+    }
+
+    Ok(bindings)
+}
+
+/* The claim version of a manifest: 1 for claims of the 1.x specs, 2 for those of 2.0 on */
+pub struct ClaimVersion {
+    pub manifest_label: String,
+    pub version: u8
+}
+
+/**
+ * The claim version of every manifest in an asset's store, told by the
+ * label of its claim box, `c2pa.claim` or `c2pa.claim.v2`. The SDK reads
+ * either into the same manifest JSON, which doesn't say which it was.
+ */
+pub fn 
+claim_versions (format: &str, bytes: &[u8]) -> Result<Vec<ClaimVersion>, c2pa::Error> {
+    let jumbf = c2pa::jumbf_io::load_jumbf_from_memory(format, bytes)?;
+    let mut versions = Vec::new();
+
+    for manifest in jumbf::manifests(&jumbf)? {
+        let (description, children) = jumbf::superbox(&manifest)?;
+        for child in children.iter().filter(|child| &child.kind == b"jumb") {
+            let version = match jumbf::superbox(child)?.0.label.as_deref() {
+                Some("c2pa.claim") => 1,
+                Some("c2pa.claim.v2") => 2,
+                _ => continue
+            };
+            versions.push(ClaimVersion { manifest_label: description.label.clone().unwrap_or_default(), version });
+        }
+    }
+
+    Ok(versions)
+}
+
+/* A thumbnail image carried in a manifest, for the claim itself or one of its ingredients */
+pub struct Thumbnail {
+    pub manifest_label: String,
+    // None for the claim thumbnail, else the ingredient's position and title
+    pub ingredient: Option<(usize, String)>,
+    pub format: String,
+    pub bytes: Vec<u8>
+}
+
+/**
+ * The MIME type of a thumbnail: the format the SDK gives, unless that isn't
+ * an image type, as for WebP thumbnails, which the SDK labels in a way it
+ * then reads back as application/octet-stream. Those are identified by
+ * their first bytes.
+ */
+fn 
+thumbnail_format (format: &str, bytes: &[u8]) -> String {
+    if format.starts_with("image/") && format != "image/none" {
+        return format.to_owned();
+    }
+
+    match bytes {
+        [0xff, 0xd8, 0xff, ..] => "image/jpeg",
+        [0x89, b'P', b'N', b'G', ..] => "image/png",
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => "image/webp",
+        [b'G', b'I', b'F', b'8', ..] => "image/gif",
+        _ => format
+    }.to_owned()
+}
+
+/* Every claim and ingredient thumbnail in the store, manifest by manifest */
+pub fn 
+thumbnails (manifest_store: &Reader) -> Vec<Thumbnail> {
+    let mut thumbnails = Vec::new();
+
+    for manifest in manifest_store.iter_manifests() {
+        let label = manifest.label().unwrap_or_default();
+        if let Some((format, bytes)) = manifest.thumbnail() {
+            thumbnails.push(Thumbnail {
+                manifest_label: label.to_owned(),
+                ingredient: None,
+                format: thumbnail_format(format, &bytes),
+                bytes: bytes.into_owned()
+            });
+        }
+
+        for (i, ingredient) in manifest.ingredients().iter().enumerate() {
+            if let Some((format, bytes)) = ingredient.thumbnail() {
+                thumbnails.push(Thumbnail {
+                    manifest_label: label.to_owned(),
+                    ingredient: Some((i, ingredient.title().unwrap_or_default().to_owned())),
+                    format: thumbnail_format(format, &bytes),
+                    bytes: bytes.into_owned()
+                });
+            }
+        }
+    }
+
+    thumbnails
+}
+
+/* File extension for a thumbnail format, which may be a MIME type or already an extension */
+pub fn 
+thumbnail_extension (format: &str) -> &str {
+    match format {
+        "image/jpeg" => "jpg",
+        "image/svg+xml" => "svg",
+        _ => format.rsplit('/').next().unwrap_or(format)
+    }
+}
+
+/**
+ * Prints the manifest store, its active manifest and every manifest it
+ * contains to stdout. Panics if the store failed validation.
+ */
+pub fn 
+print_manifest_store (manifest_store: &Reader) {
+    let errors = validation_errors(manifest_store);
+    if !errors.is_empty() {
+        println!("Loading manifest resulted in validation errors:");
+        for code in errors {
+            println!("Validation status code: {}", code);
+        }
+
+        panic!("data validation errors");
+    }
+
+    println!("manifest store: {}", manifest_store);
+
+    // active manifest is the most recently added manifest in the store.
+    let manifest = manifest_store.active_manifest().unwrap();
+    println!("active manifest: {}", manifest);
+
+    println!("all manifests:\n----------------------");
+    for manifest in manifest_store.iter_manifests() {
+        println!("manifest {},{}", manifest.label().unwrap_or_default(), manifest);
+    }
+}
+
+#[cfg(feature = "file_io")]
+pub fn 
+read_manifest (path: &std::path::Path, format: &str) -> Result<(), c2pa::Error> {
+
+    let manifest_store = load_manifest_store(format, &crate::storage::read_file(path)?)?;
+    print_manifest_store(&manifest_store);
+
+    Ok(())
+}
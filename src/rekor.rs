@@ -0,0 +1,464 @@
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use c2pa::{Signer, SigningAlg};
+use openssl::hash::MessageDigest;
+use openssl::pkey::{PKey, Public};
+use openssl::sign::Verifier;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256, Sha384, Sha512};
+
+/*
+ * Submission of signed manifests to a Rekor transparency log. The artifact
+ * logged is the asset's manifest store (its JUMBF bytes), as a `hashedrekord`
+ * entry carrying the store's hash, a signature over it made with the C2PA
+ * signing key, and the signing certificate. The entry Rekor returns,
+ * including its inclusion proof, is kept in a `<asset>.rekor.json` record
+ * next to the signed asset; verification checks the record against the
+ * asset, checks the inclusion proof, and confirms the entry is still in the
+ * log. The record alone proves nothing, since whoever wrote it could have
+ * made up its tree root, so the log's signatures over the entry (its signed
+ * entry timestamp) and over the tree root the proof leads to (its
+ * checkpoint) are checked against the log's public key. Rekor only accepts
+ * ECDSA signatures for certificates here, so submission needs an es256,
+ * es384 or es512 signer.
+ */
+
+pub const DEFAULT_REKOR_URL: &str = "https://rekor.sigstore.dev";
+
+#[derive(Debug, Deserialize, Serialize)]
+/* What is kept of a logged manifest: the log, the entry's id, and the entry as Rekor returned it */
+pub struct RekorRecord {
+    pub url: String,
+    pub uuid: String,
+    pub entry: serde_json::Value
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LogEntry {
+    body: String,
+    integrated_time: i64,
+    #[serde(rename = "logID")]
+    log_id: String,
+    log_index: u64,
+    verification: Verification
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Verification {
+    inclusion_proof: InclusionProof,
+    // The log's signature over the entry, base64 DER; missing from entries of old Rekor versions
+    signed_entry_timestamp: Option<String>
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct InclusionProof {
+    log_index: u64,
+    tree_size: u64,
+    root_hash: String,
+    hashes: Vec<String>,
+    // The log's signed note naming the tree size and root the proof leads to
+    checkpoint: Option<String>
+}
+
+#[derive(Deserialize)]
+struct EntryBody {
+    spec: EntrySpec
+}
+
+#[derive(Deserialize)]
+struct EntrySpec {
+    data: EntryData
+}
+
+#[derive(Deserialize)]
+struct EntryData {
+    hash: EntryHash
+}
+
+#[derive(Deserialize)]
+struct EntryHash {
+    algorithm: String,
+    value: String
+}
+
+fn 
+rekor_error (e: ureq::Error) -> c2pa::Error {
+    c2pa::Error::OtherError(Box::new(e))
+}
+
+fn 
+ssl_error (e: openssl::error::ErrorStack) -> c2pa::Error {
+    c2pa::Error::OtherError(Box::new(e))
+}
+
+/* Hex digest of `data` with the named hashedrekord algorithm */
+fn 
+digest (algorithm: &str, data: &[u8]) -> Result<String, c2pa::Error> {
+    match algorithm {
+        "sha256" => Ok(hex::encode(Sha256::digest(data))),
+        "sha384" => Ok(hex::encode(Sha384::digest(data))),
+        "sha512" => Ok(hex::encode(Sha512::digest(data))),
+        _ => Err(c2pa::Error::BadParam(format!("unsupported Rekor hash algorithm '{}'", algorithm)))
+    }
+}
+
+/* The digest an ECDSA signing algorithm signs with; Rekor can't verify PSS or Ed25519 signatures over a hash */
+fn 
+digest_algorithm (alg: SigningAlg) -> Result<&'static str, c2pa::Error> {
+    match alg {
+        SigningAlg::Es256 => Ok("sha256"),
+        SigningAlg::Es384 => Ok("sha384"),
+        SigningAlg::Es512 => Ok("sha512"),
+        _ => Err(c2pa::Error::BadParam(format!("Rekor submission needs an es256, es384 or es512 signer, not {}", alg)))
+    }
+}
+
+fn 
+der_length (len: usize, out: &mut Vec<u8>) {
+    if len < 0x80 {
+        out.push(len as u8);
+    } else {
+        out.push(0x81);
+        out.push(len as u8);
+    }
+}
+
+fn 
+der_integer (bytes: &[u8], out: &mut Vec<u8>) {
+    let start = bytes.iter().position(|&b| b != 0).unwrap_or(bytes.len() - 1);
+    let bytes = &bytes[start..];
+    let pad = bytes[0] & 0x80 != 0;
+
+    out.push(0x02);
+    der_length(bytes.len() + pad as usize, out);
+    if pad {
+        out.push(0);
+    }
+    out.extend_from_slice(bytes);
+}
+
+/**
+ * Re-encodes a COSE ECDSA signature (r and s concatenated) as the ASN.1
+ * `SEQUENCE { r INTEGER, s INTEGER }` Rekor expects.
+ */
+fn 
+p1363_to_der (signature: &[u8]) -> Vec<u8> {
+    let (r, s) = signature.split_at(signature.len() / 2);
+
+    let mut integers = Vec::new();
+    der_integer(r, &mut integers);
+    der_integer(s, &mut integers);
+
+    let mut der = vec![0x30];
+    der_length(integers.len(), &mut der);
+    der.extend(integers);
+    der
+}
+
+fn 
+pem_certificate (der: &[u8]) -> String {
+    let encoded = BASE64.encode(der);
+    let mut pem = "-----BEGIN CERTIFICATE-----\n".to_owned();
+    for line in encoded.as_bytes().chunks(64) {
+        pem.push_str(std::str::from_utf8(line).unwrap());
+        pem.push('\n');
+    }
+    pem.push_str("-----END CERTIFICATE-----\n");
+    pem
+}
+
+/**
+ * Logs the manifest store of a freshly signed asset to the Rekor instance
+ * at `url`, signing it with the key that signed the manifest.
+ */
+pub fn 
+submit (url: &str, format: &str, signed: &[u8], signer: &dyn Signer) -> Result<RekorRecord, c2pa::Error> {
+    let algorithm = digest_algorithm(signer.alg())?;
+    let manifest_store = c2pa::jumbf_io::load_jumbf_from_memory(format, signed)?;
+
+    let certs = signer.certs()?;
+    let signing_cert = certs.first()
+        .ok_or_else(|| c2pa::Error::BadParam("signer has no certificates".to_owned()))?;
+    let signature = p1363_to_der(&signer.sign(&manifest_store)?);
+
+    let proposed = json!({
+        "apiVersion": "0.0.1",
+        "kind": "hashedrekord",
+        "spec": {
+            "signature": {
+                "content": BASE64.encode(signature),
+                "publicKey": {"content": BASE64.encode(pem_certificate(signing_cert))}
+            },
+            "data": {
+                "hash": {"algorithm": algorithm, "value": digest(algorithm, &manifest_store)?}
+            }
+        }
+    });
+
+    let response = ureq::post(&format!("{}/api/v1/log/entries", url.trim_end_matches('/')))
+        .set("Content-Type", "application/json")
+        .send_string(&proposed.to_string())
+        .map_err(rekor_error)?;
+    let entries: serde_json::Map<String, serde_json::Value> = serde_json::from_str(&response.into_string()?)?;
+
+    let (uuid, entry) = entries.into_iter().next()
+        .ok_or_else(|| c2pa::Error::BadParam("Rekor returned no log entry".to_owned()))?;
+    Ok(RekorRecord { url: url.to_owned(), uuid, entry })
+}
+
+/* Recomputes a Merkle tree root from an RFC 6962 inclusion proof */
+fn 
+root_from_proof (leaf_hash: Vec<u8>, proof: &InclusionProof) -> Option<Vec<u8>> {
+    let node = |left: &[u8], right: &[u8]| Sha256::new().chain_update([1]).chain_update(left).chain_update(right).finalize().to_vec();
+
+    if proof.log_index >= proof.tree_size {
+        return None;
+    }
+
+    let mut index = proof.log_index;
+    let mut last = proof.tree_size - 1;
+    let mut root = leaf_hash;
+    for sibling in &proof.hashes {
+        let sibling = hex::decode(sibling).ok()?;
+        if last == 0 {
+            return None;
+        }
+
+        if index & 1 == 1 || index == last {
+            root = node(&sibling, &root);
+            while index & 1 == 0 && index != 0 {
+                index >>= 1;
+                last >>= 1;
+            }
+        } else {
+            root = node(&root, &sibling);
+        }
+        index >>= 1;
+        last >>= 1;
+    }
+
+    (last == 0).then_some(root)
+}
+
+/**
+ * The public key, PEM, which the Rekor instance at `url` signs its entries
+ * and checkpoints with. Fetching it from the log only trusts the connection
+ * to it; pinning the key, as sigstore's own clients do, trusts the log.
+ */
+pub fn 
+log_public_key (url: &str) -> Result<Vec<u8>, c2pa::Error> {
+    let response = ureq::get(&format!("{}/api/v1/log/publicKey", url.trim_end_matches('/'))).call().map_err(rekor_error)?;
+    Ok(response.into_string()?.into_bytes())
+}
+
+/* Whether `signature`, DER, is the log key's ECDSA signature over `data` */
+fn 
+log_signed (log_key: &PKey<Public>, data: &[u8], signature: &[u8]) -> Result<bool, c2pa::Error> {
+    let mut verifier = Verifier::new(MessageDigest::sha256(), log_key).map_err(ssl_error)?;
+    verifier.update(data).map_err(ssl_error)?;
+    // A malformed signature doesn't verify, rather than failing the check
+    Ok(verifier.verify(signature).unwrap_or(false))
+}
+
+/**
+ * Whether the log signed the entry: its signed entry timestamp is over the
+ * canonical JSON of the body, integration time, log ID and log index, with
+ * keys sorted and no whitespace.
+ */
+fn 
+entry_signed (entry: &LogEntry, log_key: &PKey<Public>) -> Result<bool, c2pa::Error> {
+    let Some(set) = &entry.verification.signed_entry_timestamp else {
+        return Ok(false);
+    };
+    let Ok(signature) = BASE64.decode(set) else {
+        return Ok(false);
+    };
+    let canonical = format!(
+        r#"{{"body":{},"integratedTime":{},"logID":{},"logIndex":{}}}"#,
+        serde_json::to_string(&entry.body)?, entry.integrated_time, serde_json::to_string(&entry.log_id)?, entry.log_index
+    );
+
+    log_signed(log_key, canonical.as_bytes(), &signature)
+}
+
+/**
+ * Whether the log signed a checkpoint for the tree the inclusion proof
+ * leads to. A checkpoint is a signed note: an origin line, the tree size,
+ * the base64 root hash and any further lines, then a blank line and one
+ * or more "— <name> <base64 key hash and signature>" lines over the text
+ * above the blank line.
+ */
+fn 
+checkpoint_signed (proof: &InclusionProof, log_key: &PKey<Public>) -> Result<bool, c2pa::Error> {
+    let Some((note, signatures)) = proof.checkpoint.as_deref().and_then(|checkpoint| checkpoint.split_once("\n\n")) else {
+        return Ok(false);
+    };
+
+    let mut lines = note.lines().skip(1);
+    let tree_size = lines.next().and_then(|size| size.parse::<u64>().ok());
+    let root_hash = lines.next().and_then(|root| BASE64.decode(root).ok()).map(hex::encode);
+    if tree_size != Some(proof.tree_size) || root_hash.as_deref() != Some(proof.root_hash.as_str()) {
+        return Ok(false);
+    }
+
+    let signed_text = format!("{}\n", note);
+    for line in signatures.lines().filter_map(|line| line.strip_prefix("\u{2014} ")) {
+        let Some(signature) = line.rsplit(' ').next().and_then(|signature| BASE64.decode(signature).ok()) else {
+            continue;
+        };
+        // The first four bytes name the key by hash; the signature follows
+        if signature.len() > 4 && log_signed(log_key, signed_text.as_bytes(), &signature[4..])? {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+/* The checks of a log entry which need no network: its inclusion proof, and the log's signatures over it */
+fn 
+entry_failures (entry: &LogEntry, body_bytes: &[u8], log_key_pem: &[u8]) -> Result<Vec<String>, c2pa::Error> {
+    let mut failures = Vec::new();
+    let log_key = PKey::public_key_from_pem(log_key_pem).map_err(ssl_error)?;
+
+    let leaf_hash = Sha256::new().chain_update([0]).chain_update(body_bytes).finalize().to_vec();
+    let proof = &entry.verification.inclusion_proof;
+    if root_from_proof(leaf_hash, proof).map(hex::encode).as_deref() != Some(proof.root_hash.as_str()) {
+        failures.push("rekor.inclusionProofInvalid".to_owned());
+    }
+    if !entry_signed(entry, &log_key)? {
+        failures.push("rekor.signedEntryTimestampInvalid".to_owned());
+    }
+    if !checkpoint_signed(proof, &log_key)? {
+        failures.push("rekor.checkpointInvalid".to_owned());
+    }
+
+    Ok(failures)
+}
+
+/**
+ * Checks the Rekor record of an asset: the logged hash must be that of the
+ * asset's manifest store, the inclusion proof must lead to its root hash,
+ * the log's key, `log_key_pem` or else the one the log serves, must have
+ * signed both the entry and a checkpoint of that root, and the log must
+ * still hold the entry. Returns the failed checks, as status-code-like
+ * strings; an empty list means the record holds up.
+ */
+pub fn 
+verify (format: &str, asset: &[u8], record: &RekorRecord, log_key_pem: Option<&[u8]>) -> Result<Vec<String>, c2pa::Error> {
+    let mut failures = Vec::new();
+    let entry: LogEntry = serde_json::from_value(record.entry.clone())?;
+    let body_bytes = BASE64.decode(&entry.body).map_err(|e| c2pa::Error::OtherError(Box::new(e)))?;
+    let body: EntryBody = serde_json::from_slice(&body_bytes)?;
+
+    let manifest_store = c2pa::jumbf_io::load_jumbf_from_memory(format, asset)?;
+    if digest(&body.spec.data.hash.algorithm, &manifest_store)? != body.spec.data.hash.value {
+        failures.push("rekor.hashMismatch".to_owned());
+    }
+
+    let log_key_pem = match log_key_pem {
+        Some(pem) => pem.to_vec(),
+        None => log_public_key(&record.url)?
+    };
+    failures.extend(entry_failures(&entry, &body_bytes, &log_key_pem)?);
+
+    let url = format!("{}/api/v1/log/entries/{}", record.url.trim_end_matches('/'), record.uuid);
+    match ureq::get(&url).call() {
+        Ok(response) => {
+            let logged: serde_json::Map<String, serde_json::Value> = serde_json::from_str(&response.into_string()?)?;
+            let logged_body = logged.values().next().and_then(|entry| entry.get("body")).and_then(|body| body.as_str());
+            if logged_body != Some(entry.body.as_str()) {
+                failures.push("rekor.entryMismatch".to_owned());
+            }
+        },
+        Err(ureq::Error::Status(404, _)) => failures.push("rekor.entryMissing".to_owned()),
+        Err(e) => return Err(rekor_error(e))
+    }
+
+    Ok(failures)
+}
+
+#[cfg(test)]
+mod tests {
+    use openssl::ec::{EcGroup, EcKey};
+    use openssl::nid::Nid;
+    use openssl::pkey::Private;
+
+    use super::*;
+
+    fn 
+    log_key () -> PKey<Private> {
+        PKey::from_ec_key(EcKey::generate(&EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap()).unwrap()).unwrap()
+    }
+
+    fn 
+    log_sign (key: &PKey<Private>, data: &[u8]) -> Vec<u8> {
+        let mut signer = openssl::sign::Signer::new(MessageDigest::sha256(), key).unwrap();
+        signer.update(data).unwrap();
+        signer.sign_to_vec().unwrap()
+    }
+
+    /* An entry alone in a tree of one, as `key`'s log would return it: the leaf is the root */
+    fn 
+    signed_entry (key: &PKey<Private>) -> (LogEntry, Vec<u8>) {
+        let body = br#"{"kind":"hashedrekord","spec":{"data":{"hash":{"algorithm":"sha256","value":"00"}}}}"#.to_vec();
+        let root = Sha256::new().chain_update([0]).chain_update(&body).finalize();
+        let note = format!("rekor.example.com - 1\n1\n{}\n", BASE64.encode(root));
+        let checkpoint_signature = [&[0u8; 4][..], &log_sign(key, note.as_bytes())].concat();
+
+        let mut entry = LogEntry {
+            body: BASE64.encode(&body),
+            integrated_time: 1700000000,
+            log_id: "c0d23d6ad406973f9559f3ba2d1ca01f84147d8ffc5b8445c224f98b9591801d".to_owned(),
+            log_index: 7,
+            verification: Verification {
+                inclusion_proof: InclusionProof {
+                    log_index: 0,
+                    tree_size: 1,
+                    root_hash: hex::encode(root),
+                    hashes: Vec::new(),
+                    checkpoint: Some(format!("{}\n\u{2014} rekor.example.com {}\n", note, BASE64.encode(checkpoint_signature)))
+                },
+                signed_entry_timestamp: None
+            }
+        };
+        let canonical = format!(
+            r#"{{"body":"{}","integratedTime":{},"logID":"{}","logIndex":{}}}"#,
+            entry.body, entry.integrated_time, entry.log_id, entry.log_index
+        );
+        entry.verification.signed_entry_timestamp = Some(BASE64.encode(log_sign(key, canonical.as_bytes())));
+        (entry, body)
+    }
+
+    #[test]
+    fn entry_signed_by_the_log_holds_up() {
+        let key = log_key();
+        let (entry, body) = signed_entry(&key);
+        let failures = entry_failures(&entry, &body, &key.public_key_to_pem().unwrap()).unwrap();
+        assert!(failures.is_empty(), "{:?}", failures);
+    }
+
+    #[test]
+    fn entry_signed_by_another_key_fails() {
+        let (entry, body) = signed_entry(&log_key());
+        let failures = entry_failures(&entry, &body, &log_key().public_key_to_pem().unwrap()).unwrap();
+        assert_eq!(failures, ["rekor.signedEntryTimestampInvalid", "rekor.checkpointInvalid"]);
+    }
+
+    #[test]
+    fn made_up_root_fails_the_checkpoint() {
+        let key = log_key();
+        // The log's signatures kept, with a body and root of someone else's making
+        let (mut entry, _) = signed_entry(&key);
+        let forged = b"forged".to_vec();
+        entry.body = BASE64.encode(&forged);
+        entry.verification.inclusion_proof.root_hash = hex::encode(Sha256::new().chain_update([0]).chain_update(&forged).finalize());
+
+        let failures = entry_failures(&entry, &forged, &key.public_key_to_pem().unwrap()).unwrap();
+        assert_eq!(failures, ["rekor.signedEntryTimestampInvalid", "rekor.checkpointInvalid"]);
+    }
+}
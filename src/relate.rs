@@ -0,0 +1,152 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::index::content_hash;
+use crate::query::manifest_store_json;
+use crate::read::{hash_bindings, load_manifest_store};
+
+/*
+ * Duplicates and derivatives in a collection of assets. Files are the same
+ * asset when their bytes hash the same. They are the same content when the
+ * hard bindings of their active manifests hash the same, since a hard
+ * binding hashes the asset less its manifest; that finds copies re-signed
+ * or endorsed since. And one is derived from another when its active
+ * manifest names the other's as an ingredient. Ingredients are matched by
+ * manifest label: the ingredient assertion hashes the manifest it
+ * embeds, and validation checks that hash, so the label identifies it.
+ */
+
+/* What relating assets needs of one */
+#[derive(Clone, Debug)]
+pub struct RelatedAsset {
+    pub path: String,
+    // SHA-256 of the whole file, hex-encoded
+    pub sha256: String,
+    // Algorithm and hash of the active manifest's hard binding, as alg:hex
+    pub binding_hash: Option<String>,
+    pub manifest: Option<String>,
+    // Manifest label and relationship of each ingredient of the active manifest which carried one
+    pub ingredients: Vec<(String, String)>
+}
+
+/* An asset whose active manifest names another's as an ingredient */
+#[derive(Clone, Debug, Serialize)]
+pub struct IngredientLink {
+    pub ingredient: String,
+    pub asset: String,
+    pub relationship: String
+}
+
+/* How the assets of a collection relate, each group and link by path */
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Relations {
+    // Groups of byte-identical files
+    pub identical: Vec<Vec<String>>,
+    // Groups sharing a hard binding hash, whose bytes aren't all identical
+    pub same_content: Vec<Vec<String>>,
+    pub ingredients: Vec<IngredientLink>
+}
+
+impl Relations {
+    pub fn is_empty(&self) -> bool {
+        self.identical.is_empty() && self.same_content.is_empty() && self.ingredients.is_empty()
+    }
+}
+
+/**
+ * What relating needs of the asset at `path`. Files without a manifest
+ * store, or in a format the SDK can't read one from, still relate by their
+ * bytes; a store which can't be read fails.
+ */
+pub fn 
+related_asset (path: &str, format: &str, asset: &[u8]) -> Result<RelatedAsset, c2pa::Error> {
+    let mut related = RelatedAsset {
+        path: path.to_owned(),
+        sha256: content_hash(asset),
+        binding_hash: None,
+        manifest: None,
+        ingredients: Vec::new()
+    };
+    let manifest_store = match load_manifest_store(format, asset) {
+        Ok(manifest_store) => manifest_store,
+        Err(c2pa::Error::JumbfNotFound | c2pa::Error::ProvenanceMissing | c2pa::Error::UnsupportedType) => return Ok(related),
+        Err(e) => return Err(e)
+    };
+    let Some(active) = manifest_store.active_label().map(str::to_owned) else {
+        return Ok(related);
+    };
+
+    let store_json = manifest_store_json(&manifest_store)?;
+    related.ingredients = store_json["manifests"][&active]["ingredients"].as_array().into_iter().flatten()
+        .filter_map(|ingredient| {
+            let label = ingredient["active_manifest"].as_str()?;
+            let relationship = ingredient["relationship"].as_str().unwrap_or("parentOf");
+            Some((label.to_owned(), relationship.to_owned()))
+        })
+        .collect();
+    related.binding_hash = hash_bindings(format, asset)?.into_iter()
+        .filter(|binding| binding.manifest_label == active)
+        .find_map(|binding| binding.hash.map(|hash| format!("{}:{}", binding.alg, hash)));
+    related.manifest = Some(active);
+
+    Ok(related)
+}
+
+/* Paths grouped by a key, keeping groups of more than one in order of their first path */
+fn 
+groups (assets: &[RelatedAsset], key: impl Fn(&RelatedAsset) -> Option<&str>) -> Vec<Vec<&RelatedAsset>> {
+    let mut by_key: BTreeMap<&str, Vec<&RelatedAsset>> = BTreeMap::new();
+    for asset in assets {
+        if let Some(key) = key(asset) {
+            by_key.entry(key).or_default().push(asset);
+        }
+    }
+
+    let mut groups: Vec<Vec<&RelatedAsset>> = by_key.into_values().filter(|group| group.len() > 1).collect();
+    groups.sort_by(|a, b| a[0].path.cmp(&b[0].path));
+    groups
+}
+
+fn 
+paths (group: &[&RelatedAsset]) -> Vec<String> {
+    group.iter().map(|asset| asset.path.clone()).collect()
+}
+
+/**
+ * How a collection of assets relate: which are byte-identical, which share
+ * content under different manifests, and which were made from which.
+ * Groups and links are sorted by path.
+ */
+pub fn 
+relate (assets: &[RelatedAsset]) -> Relations {
+    let mut assets = assets.to_vec();
+    assets.sort_by(|a, b| a.path.cmp(&b.path));
+
+    let identical = groups(&assets, |asset| Some(&asset.sha256)).iter().map(|group| paths(group)).collect();
+    let same_content = groups(&assets, |asset| asset.binding_hash.as_deref()).iter()
+        .filter(|group| group.iter().any(|asset| asset.sha256 != group[0].sha256))
+        .map(|group| paths(group))
+        .collect();
+
+    let mut by_manifest: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for asset in &assets {
+        if let Some(manifest) = &asset.manifest {
+            by_manifest.entry(manifest).or_default().push(&asset.path);
+        }
+    }
+    let ingredients = assets.iter()
+        .flat_map(|asset| asset.ingredients.iter().map(move |ingredient| (asset, ingredient)))
+        .flat_map(|(asset, (label, relationship))| {
+            by_manifest.get(label.as_str()).into_iter().flatten()
+                .filter(move |path| **path != asset.path)
+                .map(move |path| IngredientLink {
+                    ingredient: path.to_string(),
+                    asset: asset.path.clone(),
+                    relationship: relationship.clone()
+                })
+        })
+        .collect();
+
+    Relations { identical, same_content, ingredients }
+}
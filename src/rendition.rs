@@ -0,0 +1,96 @@
+use std::io::Cursor;
+use std::path::Path;
+use std::str::FromStr;
+
+use c2pa::assertions::c2pa_action;
+use c2pa::Signer;
+use image::{DynamicImage, ImageFormat};
+
+use crate::manifest::{embed_edits, parent_ingredient, EditAction};
+
+/*
+ * Renditions of a signed image in other formats, with the `renditions`
+ * feature, for publishing pipelines which serve WebP or AVIF next to the
+ * original. Each is transcoded from the signed image and signed with a
+ * derived manifest recording the transcode, which takes the signed image,
+ * manifest and all, as its parent ingredient; a rendition's provenance so
+ * leads back through the original. WebP is encoded losslessly, the only
+ * way `image` writes it, and AVIF with cavif's default speed and quality.
+ */
+
+/* A format renditions can be written in */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RenditionFormat {
+    Webp,
+    Avif,
+    Png,
+    Jpeg
+}
+
+impl FromStr for RenditionFormat {
+    type Err = c2pa::Error;
+
+    fn from_str(s: &str) -> Result<RenditionFormat, c2pa::Error> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "webp" => Ok(RenditionFormat::Webp),
+            "avif" => Ok(RenditionFormat::Avif),
+            "png" => Ok(RenditionFormat::Png),
+            "jpg" | "jpeg" => Ok(RenditionFormat::Jpeg),
+            _ => Err(c2pa::Error::BadParam(format!("unknown rendition format '{}'; expected webp, avif, png or jpg", s)))
+        }
+    }
+}
+
+impl RenditionFormat {
+    /* The file extension renditions are written with, which is also the format the SDK takes */
+    pub fn extension(&self) -> &'static str {
+        match self {
+            RenditionFormat::Webp => "webp",
+            RenditionFormat::Avif => "avif",
+            RenditionFormat::Png => "png",
+            RenditionFormat::Jpeg => "jpg"
+        }
+    }
+
+    fn image_format(&self) -> ImageFormat {
+        match self {
+            RenditionFormat::Webp => ImageFormat::WebP,
+            RenditionFormat::Avif => ImageFormat::Avif,
+            RenditionFormat::Png => ImageFormat::Png,
+            RenditionFormat::Jpeg => ImageFormat::Jpeg
+        }
+    }
+}
+
+fn
+image_error (e: image::ImageError) -> c2pa::Error {
+    c2pa::Error::OtherError(Box::new(e))
+}
+
+/**
+ * Transcodes `signed`, an image in `format` written to `signed_location`,
+ * into `rendition`, and signs the result with a manifest titled `title`
+ * recording the transcode against `signed` as the parent. Returns the
+ * signed rendition.
+ */
+pub fn
+render (signed: &[u8], format: &str, signed_location: &str, rendition: RenditionFormat, title: &str, signer: &dyn Signer) -> Result<Vec<u8>, c2pa::Error> {
+    let source_format = ImageFormat::from_extension(format)
+        .or_else(|| ImageFormat::from_mime_type(format))
+        .ok_or_else(|| c2pa::Error::BadParam(format!("{} images can't be transcoded", format)))?;
+    let mut image = image::load_from_memory_with_format(signed, source_format).map_err(image_error)?;
+
+    // JPEGs have no alpha channel, and the WebP and AVIF encoders only take 8-bit samples
+    image = match (rendition, image.color().has_alpha()) {
+        (RenditionFormat::Jpeg, _) | (RenditionFormat::Webp | RenditionFormat::Avif, false) => DynamicImage::ImageRgb8(image.to_rgb8()),
+        (RenditionFormat::Webp | RenditionFormat::Avif, true) => DynamicImage::ImageRgba8(image.to_rgba8()),
+        (RenditionFormat::Png, _) => image
+    };
+    let mut encoded = Cursor::new(Vec::new());
+    image.write_to(&mut encoded, rendition.image_format()).map_err(image_error)?;
+
+    let parent = parent_ingredient(Path::new(signed_location), format, signed)?;
+    let transcoded = EditAction::new(c2pa_action::TRANSCODED)
+        .with_parameter("format", rendition.image_format().to_mime_type())?;
+    embed_edits(parent, &encoded.into_inner(), title, rendition.extension(), &[transcoded], signer)
+}
@@ -0,0 +1,159 @@
+use std::collections::VecDeque;
+use std::path::Path;
+
+use c2pa::Reader;
+use handlebars::{handlebars_helper, Handlebars};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::clock;
+use crate::read::{is_valid, validation_errors};
+use crate::timeline::{timeline, TimelineEntry};
+
+/*
+ * Provenance reports rendered from Handlebars templates, so organizations
+ * can brand them or fit them into their own pages. A template is given the
+ * manifest store as structured context:
+ *
+ *     asset              where the asset was read from
+ *     format             its format, e.g. jpg
+ *     generated_at       when the report was rendered
+ *     valid              whether the store validated and its signature was checked
+ *     validation_errors  the failure codes, if any
+ *     active_manifest    the label of the active manifest
+ *     manifests          each manifest as `read` prints it, plus its `label` and
+ *                        whether it is `active`; the active manifest first, then
+ *                        its ingredients' manifests, and so on back
+ *     timeline           every action, oldest first, as `timeline --json` prints it
+ *     manifest_store     the whole store as `read` prints it
+ *
+ * `{{json value}}` writes any of it out as JSON, e.g. into a script tag.
+ */
+
+/* The plain text report, for when no template is given */
+pub const DEFAULT_TEMPLATE: &str = "\
+Provenance report for {{asset}}
+Generated {{generated_at}}
+
+{{#if valid}}Valid{{else}}Invalid{{/if}}
+{{#each validation_errors}}
+  {{this}}
+{{/each}}
+
+{{#each manifests}}
+{{label}}{{#if active}} (active){{/if}}
+  title:            {{title}}
+  claim generator:  {{claim_generator}}
+  signed by:        {{signature_info.issuer}}{{#if signature_info.time}} at {{signature_info.time}}{{/if}}
+{{#each ingredients}}
+  ingredient:       {{title}}{{#if active_manifest}} ({{active_manifest}}){{/if}}
+{{/each}}
+
+{{/each}}
+History
+{{#each timeline}}
+  {{when}}  {{action}}  by {{who}}  with {{tool}}
+{{/each}}
+";
+
+handlebars_helper!(json: |value: Json| serde_json::to_string_pretty(value).unwrap_or_default());
+
+/* What a report template is rendered with */
+#[derive(Serialize)]
+pub struct ReportContext {
+    pub asset: String,
+    pub format: String,
+    pub generated_at: String,
+    pub valid: bool,
+    pub validation_errors: Vec<String>,
+    pub active_manifest: Option<String>,
+    pub manifests: Vec<Value>,
+    pub timeline: Vec<TimelineEntry>,
+    pub manifest_store: Value
+}
+
+/* The store's manifests, active first and then back through the ingredients, each labeled */
+fn 
+manifests_in_order (store_json: &Value, active: Option<&str>) -> Vec<Value> {
+    let Some(manifests) = store_json["manifests"].as_object() else {
+        return Vec::new();
+    };
+
+    let mut order: Vec<&str> = Vec::new();
+    let mut queue: VecDeque<&str> = active.into_iter().collect();
+    while let Some(label) = queue.pop_front() {
+        if order.contains(&label) || !manifests.contains_key(label) {
+            continue;
+        }
+        order.push(label);
+        if let Some(ingredients) = manifests[label]["ingredients"].as_array() {
+            queue.extend(ingredients.iter().filter_map(|ingredient| ingredient["active_manifest"].as_str()));
+        }
+    }
+    // Manifests no ingredient leads to, which a well-formed store doesn't have
+    for label in manifests.keys() {
+        if !order.contains(&label.as_str()) {
+            order.push(label);
+        }
+    }
+
+    order.into_iter()
+        .map(|label| {
+            let mut manifest = manifests[label].clone();
+            manifest["label"] = Value::String(label.to_owned());
+            manifest["active"] = Value::Bool(Some(label) == active);
+            manifest
+        })
+        .collect()
+}
+
+/**
+ * The context a report on the asset read from `location` is rendered with.
+ * `store_json` is its manifest store as `read` prints it, which may carry
+ * more than the SDK's own JSON.
+ */
+pub fn 
+report_context (location: &str, format: &str, manifest_store: &Reader, store_json: Value) -> ReportContext {
+    let errors = validation_errors(manifest_store);
+    let active = manifest_store.active_label();
+
+    ReportContext {
+        asset: location.to_owned(),
+        format: format.to_owned(),
+        generated_at: clock::now().to_rfc3339(),
+        valid: is_valid(manifest_store),
+        validation_errors: errors,
+        active_manifest: active.map(str::to_owned),
+        manifests: manifests_in_order(&store_json, active),
+        timeline: timeline(&store_json),
+        manifest_store: store_json
+    }
+}
+
+/**
+ * Whether a template at `path` writes HTML, whose output is escaped: a
+ * `.html` or `.htm` file, or one of those with `.hbs` after it.
+ */
+pub fn 
+escapes_html (path: &Path) -> bool {
+    let name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default().to_ascii_lowercase();
+    let name = name.strip_suffix(".hbs").unwrap_or(&name);
+
+    name.ends_with(".html") || name.ends_with(".htm")
+}
+
+/**
+ * Renders `template` with `context`, escaping the values it writes when
+ * `html` is set. Fields the context doesn't have render as nothing.
+ */
+pub fn 
+render (template: &str, html: bool, context: &ReportContext) -> Result<String, c2pa::Error> {
+    let mut handlebars = Handlebars::new();
+    if !html {
+        handlebars.register_escape_fn(handlebars::no_escape);
+    }
+    handlebars.register_helper("json", Box::new(json));
+
+    handlebars.render_template(template, context)
+        .map_err(|e| c2pa::Error::BadParam(format!("rendering the report: {}", e)))
+}
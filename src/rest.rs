@@ -0,0 +1,327 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::thread;
+
+use serde_json::json;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::metrics;
+use crate::read::{is_valid, load_manifest_store, validation_errors};
+use crate::sign::{sign_asset_with_signer, SharedSigner};
+use crate::webhook;
+
+// Number of threads accepting requests; each request signs synchronously
+const WORKER_THREADS: usize = 4;
+
+/* Server-wide settings shared by all request handlers */
+pub struct RestConfig {
+    // Built once, and shared by every request thread
+    pub signer: SharedSigner,
+    // Directory of named manifest-definition templates, `<name>.json`
+    pub templates_dir: Option<PathBuf>
+}
+
+/* One part of a multipart/form-data body */
+struct Part {
+    name: String,
+    filename: Option<String>,
+    content_type: Option<String>,
+    data: Vec<u8>
+}
+
+/* An HTTP error status and a message, returned to clients as JSON */
+struct HttpError(u16, String);
+
+impl From<c2pa::Error> for HttpError {
+    fn from(e: c2pa::Error) -> HttpError {
+        match e {
+            c2pa::Error::JumbfNotFound | c2pa::Error::ProvenanceMissing => HttpError(404, e.to_string()),
+            c2pa::Error::IoError(_) | c2pa::Error::RawSignerError(_) | c2pa::Error::InvalidSigningKey | c2pa::Error::CoseSignature => HttpError(500, e.to_string()),
+            _ => HttpError(400, e.to_string())
+        }
+    }
+}
+
+fn 
+find_subslice (haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
+
+/**
+ * Minimal multipart/form-data parser; enough for the `asset`, `manifest`,
+ * `template` and `format` fields accepted by `POST /sign`.
+ */
+fn 
+parse_multipart (content_type: &str, body: &[u8]) -> Result<Vec<Part>, HttpError> {
+    let boundary = content_type
+        .split(';')
+        .filter_map(|param| param.trim().strip_prefix("boundary="))
+        .next()
+        .map(|b| b.trim_matches('"'))
+        .ok_or_else(|| HttpError(400, "multipart body without a boundary".to_owned()))?;
+    let delimiter = format!("--{}", boundary).into_bytes();
+
+    let mut parts = Vec::new();
+    let mut rest = match find_subslice(body, &delimiter) {
+        Some(start) => &body[start + delimiter.len()..],
+        None => return Err(HttpError(400, "malformed multipart body".to_owned()))
+    };
+
+    // Each part is "\r\n<headers>\r\n\r\n<data>\r\n--boundary"; the final delimiter is followed by "--"
+    while !rest.starts_with(b"--") {
+        let end = find_subslice(rest, &delimiter)
+            .ok_or_else(|| HttpError(400, "unterminated multipart body".to_owned()))?;
+        let part = rest[..end].strip_prefix(b"\r\n").unwrap_or(&rest[..end]);
+        let part = part.strip_suffix(b"\r\n").unwrap_or(part);
+        rest = &rest[end + delimiter.len()..];
+
+        let header_end = find_subslice(part, b"\r\n\r\n")
+            .ok_or_else(|| HttpError(400, "multipart part without headers".to_owned()))?;
+        let headers = String::from_utf8_lossy(&part[..header_end]);
+
+        let mut name = None;
+        let mut filename = None;
+        let mut content_type = None;
+        for line in headers.lines() {
+            let (key, value) = match line.split_once(':') {
+                Some(kv) => kv,
+                None => continue
+            };
+
+            if key.eq_ignore_ascii_case("content-disposition") {
+                for param in value.split(';').map(str::trim) {
+                    if let Some(v) = param.strip_prefix("name=") {
+                        name = Some(v.trim_matches('"').to_owned());
+                    } else if let Some(v) = param.strip_prefix("filename=") {
+                        filename = Some(v.trim_matches('"').to_owned());
+                    }
+                }
+            } else if key.eq_ignore_ascii_case("content-type") {
+                content_type = Some(value.trim().to_owned());
+            }
+        }
+
+        parts.push(Part {
+            name: name.ok_or_else(|| HttpError(400, "multipart part without a name".to_owned()))?,
+            filename,
+            content_type,
+            data: part[header_end + 4..].to_vec()
+        });
+    }
+
+    Ok(parts)
+}
+
+fn 
+header_value (request: &Request, name: &'static str) -> Option<String> {
+    request.headers()
+        .iter()
+        .find(|header| header.field.equiv(name))
+        .map(|header| header.value.as_str().to_owned())
+}
+
+fn 
+read_body (request: &mut Request) -> Result<Vec<u8>, HttpError> {
+    let mut body = Vec::new();
+    request.as_reader()
+        .read_to_end(&mut body)
+        .map_err(|e| HttpError(400, format!("could not read request body: {}", e)))?;
+
+    Ok(body)
+}
+
+/**
+ * Resolves the manifest definition for a signing request: either inline
+ * JSON in the `manifest` field, or the name of a template in the
+ * configured templates directory in the `template` field.
+ */
+fn 
+manifest_definition (parts: &[Part], config: &RestConfig) -> Result<String, HttpError> {
+    if let Some(part) = parts.iter().find(|part| part.name == "manifest") {
+        return String::from_utf8(part.data.clone())
+            .map_err(|_| HttpError(400, "manifest is not valid UTF-8".to_owned()));
+    }
+
+    let template = parts.iter()
+        .find(|part| part.name == "template")
+        .map(|part| String::from_utf8_lossy(&part.data).trim().to_owned())
+        .ok_or_else(|| HttpError(400, "provide either a `manifest` or a `template` field".to_owned()))?;
+
+    // Template names map directly onto file names, so keep them to a safe alphabet
+    if template.is_empty() || !template.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err(HttpError(400, format!("invalid template name '{}'", template)));
+    }
+
+    let dir = config.templates_dir.as_ref()
+        .ok_or_else(|| HttpError(400, "this server has no manifest templates configured".to_owned()))?;
+
+    fs::read_to_string(dir.join(format!("{}.json", template)))
+        .map_err(|_| HttpError(404, format!("unknown template '{}'", template)))
+}
+
+/**
+ * `POST /sign`: multipart body with the asset in an `asset` field and the
+ * manifest definition in `manifest` (or a `template` name). The asset
+ * format comes from a `format` field, the asset part's content type, or
+ * its filename extension, in that order.
+ */
+fn 
+handle_sign (request: &mut Request, config: &RestConfig) -> Result<Response<std::io::Cursor<Vec<u8>>>, HttpError> {
+    let content_type = header_value(request, "Content-Type")
+        .filter(|ct| ct.starts_with("multipart/form-data"))
+        .ok_or_else(|| HttpError(415, "expected a multipart/form-data body".to_owned()))?;
+    let body = read_body(request)?;
+    let parts = parse_multipart(&content_type, &body)?;
+
+    let asset = parts.iter()
+        .find(|part| part.name == "asset")
+        .ok_or_else(|| HttpError(400, "missing `asset` field".to_owned()))?;
+
+    let format = parts.iter()
+        .find(|part| part.name == "format")
+        .map(|part| String::from_utf8_lossy(&part.data).trim().to_owned())
+        .or_else(|| asset.content_type.clone().filter(|ct| ct != "application/octet-stream"))
+        .or_else(|| asset.filename.as_ref().and_then(|f| f.rsplit_once('.')).map(|(_, ext)| ext.to_owned()))
+        .ok_or_else(|| HttpError(400, "could not determine the asset format".to_owned()))?;
+
+    let manifest_json = manifest_definition(&parts, config)?;
+    let signed = metrics::timed("sign", || sign_asset_with_signer(&format, &asset.data, &manifest_json, &config.signer))?;
+    webhook::notify_in_background(&format, &asset.data, &signed, asset.filename.as_deref());
+
+    let mime = if format.contains('/') { format } else { "application/octet-stream".to_owned() };
+    Ok(Response::from_data(signed).with_header(Header::from_bytes("Content-Type", mime).unwrap()))
+}
+
+/**
+ * `POST /verify`: the raw asset as the body, with its format as the
+ * Content-Type. Responds with the validation verdict as JSON.
+ */
+fn 
+handle_verify (request: &mut Request) -> Result<Response<std::io::Cursor<Vec<u8>>>, HttpError> {
+    let format = header_value(request, "Content-Type")
+        .ok_or_else(|| HttpError(400, "Content-Type should be the asset format".to_owned()))?;
+    let body = read_body(request)?;
+
+    let manifest_store = metrics::timed("verify", || load_manifest_store(&format, &body))?;
+    let errors = validation_errors(&manifest_store);
+    metrics::record_validation_errors(&errors);
+
+    Ok(json_response(200, json!({
+        "valid": is_valid(&manifest_store),
+        "validation_errors": errors,
+        "active_manifest": manifest_store.active_label()
+    })))
+}
+
+fn 
+json_response (status: u16, body: serde_json::Value) -> Response<std::io::Cursor<Vec<u8>>> {
+    Response::from_data(body.to_string().into_bytes())
+        .with_status_code(status)
+        .with_header(Header::from_bytes("Content-Type", "application/json").unwrap())
+}
+
+fn 
+handle (mut request: Request, config: &RestConfig) {
+    let result = match (request.method(), request.url()) {
+        (Method::Get, "/metrics") => Ok(metrics::metrics_response()),
+        (Method::Post, "/sign") => handle_sign(&mut request, config),
+        (Method::Post, "/verify") => handle_verify(&mut request),
+        (_, "/sign") | (_, "/verify") => Err(HttpError(405, "use POST".to_owned())),
+        _ => Err(HttpError(404, "not found".to_owned()))
+    };
+
+    let response = result.unwrap_or_else(|HttpError(status, message)| json_response(status, json!({ "error": message })));
+
+    if let Err(e) = request.respond(response) {
+        eprintln!("failed to send response: {}", e);
+    }
+}
+
+/**
+ * Serves the REST API on the given address until the process is
+ * terminated.
+ */
+pub fn 
+serve (addr: &str, config: RestConfig) -> Result<(), c2pa::Error> {
+    let server = Arc::new(Server::http(addr).map_err(c2pa::Error::OtherError)?);
+    let config = Arc::new(config);
+
+    let workers: Vec<_> = (0..WORKER_THREADS).map(|_| {
+        let server = Arc::clone(&server);
+        let config = Arc::clone(&config);
+
+        thread::spawn(move || {
+            for request in server.incoming_requests() {
+                handle(request, &config);
+            }
+        })
+    }).collect();
+
+    for worker in workers {
+        let _ = worker.join();
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONTENT_TYPE: &str = "multipart/form-data; boundary=XyZ";
+
+    /* The message of the 400 `parse_multipart` fails with */
+    fn rejection(content_type: &str, body: &[u8]) -> String {
+        match parse_multipart(content_type, body) {
+            Err(HttpError(400, message)) => message,
+            Err(HttpError(status, message)) => panic!("failed with {} rather than 400: {}", status, message),
+            Ok(parts) => panic!("parsed {} parts", parts.len())
+        }
+    }
+
+    #[test]
+    fn parts_are_parsed_with_their_headers() {
+        let body = b"--XyZ\r\n\
+            Content-Disposition: form-data; name=\"asset\"; filename=\"photo.jpg\"\r\n\
+            Content-Type: image/jpeg\r\n\r\n\
+            \xff\xd8\r\n--XyZ\r\n\
+            Content-Disposition: form-data; name=\"format\"\r\n\r\n\
+            jpg\r\n--XyZ--\r\n";
+        let parts = parse_multipart("multipart/form-data; boundary=\"XyZ\"", body).unwrap_or_else(|HttpError(_, message)| panic!("{}", message));
+
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0].name, "asset");
+        assert_eq!(parts[0].filename.as_deref(), Some("photo.jpg"));
+        assert_eq!(parts[0].content_type.as_deref(), Some("image/jpeg"));
+        assert_eq!(parts[0].data, b"\xff\xd8");
+        assert_eq!(parts[1].name, "format");
+        assert_eq!(parts[1].filename, None);
+        assert_eq!(parts[1].data, b"jpg");
+    }
+
+    #[test]
+    fn missing_boundary_is_rejected() {
+        let body = b"--XyZ\r\nContent-Disposition: form-data; name=\"format\"\r\n\r\njpg\r\n--XyZ--\r\n";
+        assert!(rejection("multipart/form-data", body).contains("boundary"));
+        assert!(rejection(CONTENT_TYPE, b"no delimiter at all").contains("malformed"));
+    }
+
+    #[test]
+    fn unterminated_body_is_rejected() {
+        let body = b"--XyZ\r\nContent-Disposition: form-data; name=\"format\"\r\n\r\njpg";
+        assert!(rejection(CONTENT_TYPE, body).contains("unterminated"));
+    }
+
+    #[test]
+    fn part_without_a_name_is_rejected() {
+        let body = b"--XyZ\r\nContent-Disposition: form-data; filename=\"photo.jpg\"\r\n\r\n\xff\xd8\r\n--XyZ--\r\n";
+        assert!(rejection(CONTENT_TYPE, body).contains("without a name"));
+    }
+
+    #[test]
+    fn part_without_headers_is_rejected() {
+        let body = b"--XyZ\r\nContent-Disposition: form-data; name=\"format\"\r\njpg\r\n--XyZ--\r\n";
+        assert!(rejection(CONTENT_TYPE, body).contains("without headers"));
+    }
+}
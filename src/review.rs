@@ -0,0 +1,144 @@
+use c2pa::assertions::{labels, Metadata, ReviewRating};
+use c2pa::{Ingredient, Reader};
+use serde::Deserialize;
+
+use crate::clock;
+
+/*
+ * Review ratings, the spec's way for an editor or a validator to record
+ * how far they trust part of a claim: a value from 1 (worst) to 5 (best),
+ * an explanation and optionally a code such as ingredient.possiblyModified.
+ * Ratings of an ingredient go in the `metadata` of its ingredient
+ * assertion, so a desk can record its review of the version it was handed.
+ * Ratings of the asset as a whole go in a standalone assertion metadata
+ * assertion (c2pa.assertion.metadata). Either way the claim covers them, so
+ * editorial review status travels with the asset and can't be changed
+ * without breaking the signature.
+ */
+
+// Review codes the spec defines; entity-specific codes take a reverse-domain name, e.g. com.example.sourceUnverified
+pub const KNOWN_CODES: [&str; 10] = [
+    "actions.unknownActionsPerformed",
+    "actions.missing",
+    "actions.possiblyMissing",
+    "depthMap.sceneMismatch",
+    "ingredient.modified",
+    "ingredient.possiblyModified",
+    "thumbnail.primaryMismatch",
+    "stds.iptc.location.inaccurate",
+    "stds.schema-org.CreativeWork.misattributed",
+    "stds.schema-org.CreativeWork.missingAttribution"
+];
+
+/* A review rating found in a manifest store */
+pub struct FoundReview {
+    // Label of the manifest carrying it
+    pub manifest: String,
+    // Title of the ingredient rated, or None for a rating of the asset as a whole
+    pub ingredient: Option<String>,
+    pub rating: ReviewRating
+}
+
+/* The part of an assertion metadata assertion holding its ratings */
+#[derive(Deserialize)]
+struct ReviewAssertion {
+    #[serde(rename = "reviewRatings", default)]
+    reviews: Vec<ReviewRating>
+}
+
+/**
+ * Parses a `value[:code]=explanation` argument, e.g. `4=checked against the
+ * wire original` or `2:ingredient.possiblyModified=crop not recorded`.
+ */
+pub fn 
+parse_review (arg: &str) -> Result<ReviewRating, c2pa::Error> {
+    let invalid = || c2pa::Error::BadParam(format!("'{}' isn't VALUE[:CODE]=EXPLANATION, e.g. 2:ingredient.possiblyModified=crop not recorded", arg));
+    let (rating, explanation) = arg.split_once('=').filter(|(_, explanation)| !explanation.trim().is_empty()).ok_or_else(invalid)?;
+    let (value, code) = match rating.split_once(':') {
+        Some((value, code)) => (value, Some(code)),
+        None => (rating, None)
+    };
+
+    let value: u8 = value.trim().parse().map_err(|_| invalid())?;
+    if !(1..=5).contains(&value) {
+        return Err(c2pa::Error::BadParam(format!("review value {} is out of range; ratings go from 1 (worst) to 5 (best)", value)));
+    }
+    if let Some(code) = code.filter(|code| !KNOWN_CODES.contains(code)) {
+        if !code.contains('.') || code.contains(char::is_whitespace) {
+            return Err(c2pa::Error::BadParam(format!("'{}' isn't a review code; use one of {} or a reverse-domain name of your own", code, KNOWN_CODES.join(", "))));
+        }
+    }
+
+    Ok(ReviewRating::new(explanation.trim(), code.map(str::to_owned), value))
+}
+
+/* Adds ratings to the metadata of an ingredient, after any it already has */
+pub fn 
+review_ingredient (ingredient: &mut Ingredient, reviews: &[ReviewRating]) {
+    if reviews.is_empty() {
+        return;
+    }
+    let metadata = reviews.iter().cloned().fold(ingredient.metadata().cloned().unwrap_or_default(), Metadata::add_review);
+    ingredient.set_metadata(metadata);
+}
+
+/**
+ * The standalone assertion rating the asset as a whole, as a
+ * manifest-definition entry.
+ */
+pub fn 
+review_assertion (reviews: &[ReviewRating]) -> Result<serde_json::Value, c2pa::Error> {
+    Ok(serde_json::json!({
+        "label": labels::ASSERTION_METADATA,
+        "data": {
+            "reviewRatings": serde_json::to_value(reviews)?,
+            "dateTime": clock::recorded_time()
+        }
+    }))
+}
+
+/**
+ * Every review rating in a manifest store, oldest manifest first along the
+ * chain of parent ingredients: those of each manifest's ingredients, then
+ * those of its asset as a whole.
+ */
+pub fn 
+reviews (manifest_store: &Reader) -> Vec<FoundReview> {
+    // Back from the active manifest through each parent ingredient's, then reversed
+    let mut chain: Vec<&str> = Vec::new();
+    let mut next = manifest_store.active_label();
+    while let Some(label) = next.filter(|label| !chain.contains(label)) {
+        chain.push(label);
+        next = manifest_store.get_manifest(label)
+            .and_then(|manifest| manifest.ingredients().iter().find(|ingredient| ingredient.is_parent()))
+            .and_then(|parent| parent.active_manifest());
+    }
+    chain.reverse();
+    let mut manifests: Vec<&c2pa::Manifest> = manifest_store.iter_manifests().collect();
+    manifests.sort_by_key(|manifest| chain.iter().position(|label| manifest.label() == Some(*label)).unwrap_or(chain.len()));
+
+    let mut found = Vec::new();
+    for manifest in manifests {
+        let label = manifest.label().unwrap_or_default();
+        for ingredient in manifest.ingredients() {
+            for rating in ingredient.metadata().and_then(Metadata::reviews).unwrap_or_default() {
+                found.push(FoundReview { manifest: label.to_owned(), ingredient: ingredient.title().map(str::to_owned), rating: rating.clone() });
+            }
+        }
+        let assertions = manifest.assertions().iter().filter(|assertion| assertion.label() == labels::ASSERTION_METADATA);
+        for assertion in assertions.filter_map(|assertion| assertion.to_assertion::<ReviewAssertion>().ok()) {
+            found.extend(assertion.reviews.into_iter().map(|rating| FoundReview { manifest: label.to_owned(), ingredient: None, rating }));
+        }
+    }
+
+    found
+}
+
+/* e.g. "2/5 (ingredient.possiblyModified): crop not recorded" */
+pub fn 
+describe (rating: &ReviewRating) -> String {
+    match &rating.code {
+        Some(code) => format!("{}/5 ({}): {}", rating.value, code, rating.explanation),
+        None => format!("{}/5: {}", rating.value, rating.explanation)
+    }
+}
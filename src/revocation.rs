@@ -0,0 +1,313 @@
+use std::path::{Path, PathBuf};
+
+use c2pa::Reader;
+use openssl::asn1::Asn1Time;
+use openssl::x509::{CrlStatus, X509Crl, X509};
+use sha2::{Digest, Sha256};
+
+use crate::storage::{read_asset, write_file_atomically};
+use crate::trust::name_string;
+
+/*
+ * Revocation checks of signing certificates against the CRLs they name in
+ * their CRL distribution points, alongside the OCSP responses the SDK
+ * checks. Each certificate in a manifest's COSE chain whose issuer is also
+ * in the chain is looked up in its issuer's CRL, which must be signed by
+ * that issuer. CRLs are cached by URL and used until their nextUpdate time,
+ * then fetched again; an expired CRL which can't be refreshed leaves the
+ * state unknown rather than trusted.
+ */
+
+/* The revocation state of a manifest's certificate chain */
+pub enum RevocationStatus {
+    // Every certificate checked was on a current CRL, not revoked
+    Good,
+    Revoked { subject: String, date: String },
+    // No certificate could be checked, or one couldn't be, and why
+    Unknown(String)
+}
+
+/* The revocation state of one manifest in a store */
+pub struct ManifestRevocation {
+    pub manifest_label: String,
+    pub status: RevocationStatus
+}
+
+fn 
+ssl_error (e: openssl::error::ErrorStack) -> c2pa::Error {
+    c2pa::Error::OtherError(Box::new(e))
+}
+
+/* The http(s) URLs of a certificate's CRL distribution points */
+fn 
+crl_urls (cert: &X509) -> Vec<String> {
+    let Some(points) = cert.crl_distribution_points() else {
+        return Vec::new();
+    };
+
+    points.iter()
+        .filter_map(|point| point.distpoint()?.fullname())
+        .flat_map(|names| names.iter().filter_map(|name| name.uri()).map(str::to_owned).collect::<Vec<_>>())
+        .filter(|uri| uri.starts_with("http://") || uri.starts_with("https://"))
+        .collect()
+}
+
+/* A CRL in DER or PEM */
+fn 
+parse_crl (bytes: &[u8]) -> Result<X509Crl, c2pa::Error> {
+    X509Crl::from_der(bytes).or_else(|_| X509Crl::from_pem(bytes)).map_err(ssl_error)
+}
+
+/* Whether a CRL is past its nextUpdate time, when a newer one should be out */
+fn 
+is_expired (crl: &X509Crl) -> Result<bool, c2pa::Error> {
+    let now = Asn1Time::days_from_now(0).map_err(ssl_error)?;
+    Ok(crl.next_update().is_some_and(|next_update| next_update < now))
+}
+
+/* Where the CRL fetched from `url` is cached, named for the URL's hash */
+fn 
+cache_path (dir: &Path, url: &str) -> PathBuf {
+    let name = hex::encode(Sha256::digest(url.as_bytes()));
+    dir.join(format!("{}.crl", &name[..16]))
+}
+
+/**
+ * The CRL at `url`, from the cache in `dir` until its nextUpdate time,
+ * else fetched and cached. A CRL which has expired is an error.
+ */
+fn 
+crl (dir: &Path, url: &str) -> Result<X509Crl, c2pa::Error> {
+    let path = cache_path(dir, url);
+    if let Ok(cached) = std::fs::read(&path).map_err(c2pa::Error::from).and_then(|bytes| parse_crl(&bytes)) {
+        if !is_expired(&cached)? {
+            return Ok(cached);
+        }
+    }
+
+    let bytes = read_asset(url)?;
+    let crl = parse_crl(&bytes)?;
+    if is_expired(&crl)? {
+        return Err(c2pa::Error::BadParam(format!("the CRL at {} has expired", url)));
+    }
+    std::fs::create_dir_all(dir)?;
+    write_file_atomically(&path, &bytes)?;
+
+    Ok(crl)
+}
+
+/**
+ * Checks a PEM certificate chain, signing certificate first, against the
+ * CRLs its certificates name, caching them in `dir`.
+ */
+pub fn 
+check_chain (chain_pem: &[u8], dir: &Path) -> Result<RevocationStatus, c2pa::Error> {
+    let chain = X509::stack_from_pem(chain_pem).map_err(ssl_error)?;
+    let mut checked = 0;
+    let mut unknown = None;
+
+    for (cert, issuer) in chain.iter().zip(chain.iter().skip(1)) {
+        let subject = name_string(cert.subject_name());
+        let urls = crl_urls(cert);
+        if urls.is_empty() {
+            unknown.get_or_insert(format!("{} names no CRL", subject));
+            continue;
+        }
+
+        // Any distribution point will do; the first which gives a current CRL from the issuer is used
+        let issuer_key = issuer.public_key().map_err(ssl_error)?;
+        let mut failure = None;
+        let mut found = None;
+        for url in &urls {
+            match crl(dir, url) {
+                Ok(crl) if crl.verify(&issuer_key).map_err(ssl_error)? => {
+                    found = Some(crl);
+                    break;
+                },
+                Ok(_) => failure = Some(format!("the CRL at {} isn't signed by {}'s issuer", url, subject)),
+                Err(e) => failure = Some(format!("{}: {}", url, e))
+            }
+        }
+        let Some(crl) = found else {
+            unknown.get_or_insert(failure.unwrap_or_default());
+            continue;
+        };
+
+        if let CrlStatus::Revoked(entry) = crl.get_by_cert(cert) {
+            return Ok(RevocationStatus::Revoked { subject, date: entry.revocation_date().to_string() });
+        }
+        checked += 1;
+    }
+
+    Ok(match unknown {
+        Some(reason) => RevocationStatus::Unknown(reason),
+        None if checked == 0 => RevocationStatus::Unknown("the chain has no certificate with its issuer".to_owned()),
+        None => RevocationStatus::Good
+    })
+}
+
+/* The revocation state of every manifest in the store */
+pub fn 
+check_store (manifest_store: &Reader, dir: &Path) -> Result<Vec<ManifestRevocation>, c2pa::Error> {
+    let mut revocations = Vec::new();
+
+    for manifest in manifest_store.iter_manifests() {
+        let status = match manifest.signature_info() {
+            Some(signature) => check_chain(signature.cert_chain().as_bytes(), dir)?,
+            None => RevocationStatus::Unknown("no signature".to_owned())
+        };
+        revocations.push(ManifestRevocation { manifest_label: manifest.label().unwrap_or_default().to_owned(), status });
+    }
+
+    Ok(revocations)
+}
+
+#[cfg(test)]
+mod tests {
+    use openssl::asn1::{Asn1Object, Asn1OctetString};
+    use openssl::bn::BigNum;
+    use openssl::ec::{EcGroup, EcKey};
+    use openssl::hash::MessageDigest;
+    use openssl::nid::Nid;
+    use openssl::pkey::{PKey, Private};
+    use openssl::x509::extension::{AuthorityKeyIdentifier, BasicConstraints};
+    use openssl::x509::{CrlNumber, X509Builder, X509CrlBuilder, X509Extension, X509NameBuilder, X509RevokedBuilder};
+
+    use super::*;
+
+    const CRL_URL: &str = "http://crl.example.invalid/ca.crl";
+
+    /* An empty directory of the test's own */
+    fn scratch_dir(test: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("c2pa-walkthrough-revocation-{}-{}", test, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn key() -> PKey<Private> {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+        PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap()
+    }
+
+    /* A CRL distribution points extension naming just `url` */
+    fn distribution_point(url: &str) -> X509Extension {
+        let mut der = url.as_bytes().to_vec();
+        for tag in [0x86, 0xa0, 0xa0, 0x30, 0x30] {
+            der.splice(0..0, [tag, der.len() as u8]);
+        }
+        let oid = Asn1Object::from_str("2.5.29.31").unwrap();
+        X509Extension::new_from_der(&oid, false, &Asn1OctetString::new_from_bytes(&der).unwrap()).unwrap()
+    }
+
+    /* A certificate numbered `serial` from `issuer`, or a self-signed CA without one, naming any `crl_url` */
+    fn certificate(common_name: &str, serial: u32, key: &PKey<Private>, issuer: Option<(&X509, &PKey<Private>)>, crl_url: Option<&str>) -> X509 {
+        let mut name = X509NameBuilder::new().unwrap();
+        name.append_entry_by_nid(Nid::COMMONNAME, common_name).unwrap();
+        let name = name.build();
+
+        let mut builder = X509Builder::new().unwrap();
+        builder.set_version(2).unwrap();
+        builder.set_serial_number(&BigNum::from_u32(serial).unwrap().to_asn1_integer().unwrap()).unwrap();
+        builder.set_subject_name(&name).unwrap();
+        builder.set_issuer_name(issuer.map_or(&name, |(cert, _)| cert.subject_name())).unwrap();
+        builder.set_pubkey(key).unwrap();
+        builder.set_not_before(&Asn1Time::days_from_now(0).unwrap()).unwrap();
+        builder.set_not_after(&Asn1Time::days_from_now(30).unwrap()).unwrap();
+        if issuer.is_none() {
+            builder.append_extension(BasicConstraints::new().critical().ca().build().unwrap()).unwrap();
+        }
+        if let Some(url) = crl_url {
+            builder.append_extension(distribution_point(url)).unwrap();
+        }
+        builder.sign(issuer.map_or(key, |(_, key)| key), MessageDigest::sha256()).unwrap();
+        builder.build()
+    }
+
+    /* A CA and a leaf it issued, numbered 2 and naming `CRL_URL`, as a PEM chain */
+    fn chain() -> (X509, PKey<Private>, Vec<u8>) {
+        let ca_key = key();
+        let ca = certificate("Test CA", 1, &ca_key, None, None);
+        let leaf = certificate("Test Signer", 2, &key(), Some((&ca, &ca_key)), Some(CRL_URL));
+
+        let mut pem = leaf.to_pem().unwrap();
+        pem.extend(ca.to_pem().unwrap());
+        (ca, ca_key, pem)
+    }
+
+    /* Caches in `dir` a CRL for `CRL_URL` revoking `serial`, signed by `key` and due for update in `days` */
+    fn cache_crl(dir: &Path, ca: &X509, key: &PKey<Private>, serial: u32, days: i64) {
+        let mut revoked = X509RevokedBuilder::new().unwrap();
+        revoked.set_serial_number(&BigNum::from_u32(serial).unwrap().to_asn1_integer().unwrap()).unwrap();
+        revoked.set_revocation_date(&Asn1Time::days_from_now(0).unwrap()).unwrap();
+
+        let mut builder = X509CrlBuilder::new().unwrap();
+        builder.set_issuer_name(ca.subject_name()).unwrap();
+        builder.set_last_update(&Asn1Time::from_unix(chrono::Utc::now().timestamp() + (days - 1) * 86400).unwrap()).unwrap();
+        builder.set_next_update(&Asn1Time::from_unix(chrono::Utc::now().timestamp() + days * 86400).unwrap()).unwrap();
+        let authority_key_id = AuthorityKeyIdentifier::new().issuer(true).build(&X509Builder::new().unwrap().x509v3_context(Some(ca), None)).unwrap();
+        builder.append_extension(authority_key_id).unwrap();
+        builder.append_extension(CrlNumber::new(BigNum::from_u32(1).unwrap()).unwrap().build().unwrap()).unwrap();
+        builder.add_revoked(revoked.build()).unwrap();
+        builder.sign(key, MessageDigest::sha256()).unwrap();
+
+        std::fs::write(cache_path(dir, CRL_URL), builder.build().unwrap().to_der().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn certificate_on_the_crl_is_revoked() {
+        let dir = scratch_dir("revoked");
+        let (ca, ca_key, pem) = chain();
+        cache_crl(&dir, &ca, &ca_key, 2, 7);
+
+        let RevocationStatus::Revoked { subject, .. } = check_chain(&pem, &dir).unwrap() else {
+            panic!("the leaf isn't revoked");
+        };
+        assert!(subject.contains("Test Signer"));
+    }
+
+    #[test]
+    fn certificate_missing_from_the_crl_is_good() {
+        let dir = scratch_dir("good");
+        let (ca, ca_key, pem) = chain();
+        cache_crl(&dir, &ca, &ca_key, 3, 7);
+
+        assert!(matches!(check_chain(&pem, &dir).unwrap(), RevocationStatus::Good));
+    }
+
+    #[test]
+    fn crl_from_another_issuer_is_not_trusted() {
+        let dir = scratch_dir("forged");
+        let (ca, _, pem) = chain();
+        cache_crl(&dir, &ca, &key(), 3, 7);
+
+        let RevocationStatus::Unknown(reason) = check_chain(&pem, &dir).unwrap() else {
+            panic!("a forged CRL was trusted");
+        };
+        assert!(reason.contains("isn't signed"));
+    }
+
+    #[test]
+    fn chain_without_crls_is_unknown() {
+        let ca_key = key();
+        let ca = certificate("Test CA", 1, &ca_key, None, None);
+        let leaf = certificate("Test Signer", 2, &key(), Some((&ca, &ca_key)), None);
+        let mut pem = leaf.to_pem().unwrap();
+        pem.extend(ca.to_pem().unwrap());
+
+        let RevocationStatus::Unknown(reason) = check_chain(&pem, &scratch_dir("no-crl")).unwrap() else {
+            panic!("a chain without CRLs was checked");
+        };
+        assert!(reason.contains("names no CRL"));
+        assert!(matches!(check_chain(&ca.to_pem().unwrap(), &scratch_dir("no-issuer")).unwrap(), RevocationStatus::Unknown(_)));
+    }
+
+    #[test]
+    fn expired_crl_is_not_used() {
+        let dir = scratch_dir("expired");
+        let (ca, ca_key, pem) = chain();
+        cache_crl(&dir, &ca, &ca_key, 3, -1);
+
+        assert!(matches!(check_chain(&pem, &dir).unwrap(), RevocationStatus::Unknown(_)));
+    }
+}
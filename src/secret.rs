@@ -0,0 +1,136 @@
+use std::fs::File;
+use std::io::Read;
+use std::ops::Deref;
+use std::path::Path;
+
+use zeroize::Zeroize;
+
+/*
+ * Private key material held only as long as it takes to build a signer or
+ * decrypter from it. The bytes are read straight into one buffer of the
+ * file's size, so no partial copies are left behind by reallocation, which
+ * is locked into memory where the platform allows (mlock on Unix) so it is
+ * never swapped out, and zeroed before it is freed. Keys the SDK or OpenSSL
+ * parse from it are theirs to clear; OpenSSL clears private key numbers
+ * when they are freed. Keys can come from files, stdin, or environment
+ * variables set by a secret manager.
+ */
+
+// Keys piped in have no size to go by; this fits a PEM RSA key of 8192 bits with room to spare
+pub const MAX_STDIN_KEY: usize = 16 * 1024;
+
+/* Bytes which are zeroed, and unlocked, once dropped */
+pub struct SecretBytes {
+    bytes: Vec<u8>,
+    // Whether the buffer's pages were locked, and so need unlocking
+    locked: bool
+}
+
+impl SecretBytes {
+    fn with_capacity(capacity: usize) -> SecretBytes {
+        let bytes = vec![0; capacity];
+        let locked = lock(&bytes);
+        SecretBytes { bytes, locked }
+    }
+
+    /**
+     * Reads up to `limit` bytes from `reader` into one locked buffer, or
+     * fails if it has more.
+     */
+    fn read_from(reader: &mut impl Read, limit: usize) -> Result<SecretBytes, std::io::Error> {
+        // One byte over, to tell a key which fits from one which doesn't
+        let mut secret = SecretBytes::with_capacity(limit + 1);
+        let mut filled = 0;
+        while filled < secret.bytes.len() {
+            match reader.read(&mut secret.bytes[filled..]) {
+                Ok(0) => break,
+                Ok(n) => filled += n,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => (),
+                Err(e) => return Err(e)
+            }
+        }
+        if filled > limit {
+            return Err(std::io::Error::other(format!("the key is over {} bytes, or changed while it was read", limit)));
+        }
+        secret.bytes[filled..].fill(0);
+        secret.bytes.truncate(filled);
+
+        Ok(secret)
+    }
+
+    /* Reads the key file at `path` */
+    pub fn read(path: impl AsRef<Path>) -> Result<SecretBytes, std::io::Error> {
+        let mut file = File::open(path)?;
+        let len = file.metadata()?.len() as usize;
+        SecretBytes::read_from(&mut file, len)
+    }
+
+    /* Reads a key from stdin, up to `MAX_STDIN_KEY` bytes */
+    pub fn read_stdin() -> Result<SecretBytes, std::io::Error> {
+        SecretBytes::read_from(&mut std::io::stdin().lock(), MAX_STDIN_KEY)
+    }
+
+    /**
+     * Takes a key from the environment variable `name`, removing it so that
+     * child processes, such as hooks, don't inherit it.
+     */
+    pub fn take_env(name: &str) -> Result<SecretBytes, std::io::Error> {
+        let mut value = std::env::var(name)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::NotFound, format!("{}: {}", name, e)))?;
+        std::env::remove_var(name);
+
+        let mut secret = SecretBytes::with_capacity(value.len());
+        secret.bytes.copy_from_slice(value.as_bytes());
+        value.zeroize();
+
+        Ok(secret)
+    }
+}
+
+impl Deref for SecretBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+// Never prints the key itself
+impl std::fmt::Debug for SecretBytes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SecretBytes({} bytes)", self.bytes.len())
+    }
+}
+
+impl Drop for SecretBytes {
+    fn drop(&mut self) {
+        // Clears the whole allocation, not just the bytes in use, and keeps it
+        self.bytes.zeroize();
+        if self.locked {
+            unlock(&self.bytes);
+        }
+    }
+}
+
+#[cfg(unix)]
+fn 
+lock (bytes: &Vec<u8>) -> bool {
+    // Locking can fail, e.g. past RLIMIT_MEMLOCK; the key is still zeroed after use
+    bytes.capacity() > 0 && unsafe { libc::mlock(bytes.as_ptr().cast(), bytes.capacity()) } == 0
+}
+
+#[cfg(unix)]
+fn 
+unlock (bytes: &Vec<u8>) {
+    unsafe { libc::munlock(bytes.as_ptr().cast(), bytes.capacity()) };
+}
+
+#[cfg(not(unix))]
+fn 
+lock (_bytes: &Vec<u8>) -> bool {
+    false
+}
+
+#[cfg(not(unix))]
+fn 
+unlock (_bytes: &Vec<u8>) {}
@@ -0,0 +1,70 @@
+use qrcode::render::unicode::Dense1x2;
+use qrcode::QrCode;
+use sha2::{Digest, Sha256};
+
+use crate::storage::{percent_encode, write_asset};
+
+/*
+ * Check-it-yourself links to Content Credentials Verify. The site fetches
+ * the asset from a public URL, so the asset is first uploaded to a shared
+ * location (a local directory a web server publishes, an http(s) prefix
+ * taking PUTs, or an object store prefix) and the link points at the URL it
+ * is served from. Uploads are named after the asset's hash, so assets of
+ * the same name never replace one another and a link handed out keeps
+ * showing the asset it was made for.
+ */
+
+pub const VERIFY_URL: &str = "https://verify.contentauthenticity.org/inspect";
+
+/* Where shared assets are uploaded, and the public URL that location is served from */
+pub struct ShareConfig {
+    pub upload_to: String,
+    pub public_url: String
+}
+
+impl ShareConfig {
+    /**
+     * A share config uploading to `upload_to`, served from `public_url`; an
+     * http(s) `upload_to` may be served from itself.
+     */
+    pub fn 
+    new (upload_to: &str, public_url: Option<&str>) -> Result<Self, c2pa::Error> {
+        let public_url = match public_url {
+            Some(public_url) => public_url,
+            None if upload_to.starts_with("http://") || upload_to.starts_with("https://") => upload_to,
+            None => return Err(c2pa::Error::BadParam(format!("{} isn't a public URL, so the URL it is served from is needed too", upload_to)))
+        };
+
+        Ok(ShareConfig {
+            upload_to: upload_to.trim_end_matches('/').to_owned(),
+            public_url: public_url.trim_end_matches('/').to_owned()
+        })
+    }
+
+    /* Uploads an asset named `file_name`, returning the public URL it can be fetched from */
+    pub fn 
+    upload (&self, file_name: &str, asset: &[u8]) -> Result<String, c2pa::Error> {
+        let name = format!("{}-{}", &hex::encode(Sha256::digest(asset))[..16], file_name);
+        write_asset(&format!("{}/{}", self.upload_to, name), asset)?;
+
+        Ok(format!("{}/{}", self.public_url, percent_encode(&name)))
+    }
+}
+
+/* The Verify link which inspects the asset at the public URL `asset_url` */
+pub fn 
+verify_link (asset_url: &str) -> String {
+    format!("{}?source={}", VERIFY_URL, percent_encode(asset_url))
+}
+
+/* `text` as a QR code drawn with Unicode half blocks, light on dark so it scans from a terminal */
+pub fn 
+qr_code (text: &str) -> Result<String, c2pa::Error> {
+    let code = QrCode::new(text.as_bytes())
+        .map_err(|e| c2pa::Error::BadParam(format!("can't encode {} as a QR code: {}", text, e)))?;
+
+    Ok(code.render::<Dense1x2>()
+        .dark_color(Dense1x2::Light)
+        .light_color(Dense1x2::Dark)
+        .build())
+}
@@ -0,0 +1,469 @@
+use std::io::Cursor;
+use std::path::Path;
+use std::sync::{Arc, Condvar, Mutex};
+
+use c2pa::{Builder, Reader, Signer, SigningAlg};
+use c2pa_crypto::raw_signature::{signer_from_cert_chain_and_private_key, RawSigner};
+use serde::{Deserialize, Deserializer};
+
+use crate::clock;
+use crate::metrics;
+use crate::providers;
+use crate::secret::SecretBytes;
+
+// Manifest definition used when the caller doesn't supply one
+pub const DEFAULT_MANIFEST_DEFINITION: &str = r#"{"claim_generator_info": [{"name": "mikes-c2pa-test-code", "version": "0.1"}]}"#;
+
+// Environment variables configuring the signer and default definition, for deployments where flags are awkward
+pub const SIGNCERT_PATH_VAR: &str = "C2PA_SIGNCERT_PATH";
+pub const PKEY_PATH_VAR: &str = "C2PA_PKEY_PATH";
+pub const ALG_VAR: &str = "C2PA_ALG";
+pub const TSA_URL_VAR: &str = "C2PA_TSA_URL";
+pub const CLAIM_GENERATOR_VAR: &str = "C2PA_CLAIM_GENERATOR";
+
+// Claim version of generated manifests, set by `set_claim_version`; the SDK's default, 1, until then
+static CLAIM_VERSION: Mutex<Option<u8>> = Mutex::new(None);
+
+#[derive(Clone, Debug, Deserialize)]
+/* Location of the signing certificate chain and private key, and the algorithm used with them */
+pub struct SignerConfig {
+    pub signcert_path: String,
+    pub pkey_path: String,
+    #[serde(deserialize_with = "deserialize_alg")]
+    pub alg: SigningAlg,
+    #[serde(default)]
+    pub tsa_url: Option<String>,
+    // Most signatures made with it at once, e.g. the sessions an HSM allows; unlimited if unset
+    #[serde(default)]
+    pub max_concurrent: Option<usize>,
+    // The key itself, as given on stdin or in the environment, used in place of the file at pkey_path
+    #[serde(skip)]
+    pub pkey: Option<Arc<SecretBytes>>
+}
+
+/* `SigningAlg` has no serde support of its own; profiles spell it as e.g. "ps256" */
+fn 
+deserialize_alg<'de, D: Deserializer<'de>> (deserializer: D) -> Result<SigningAlg, D::Error> {
+    let alg = String::deserialize(deserializer)?;
+    alg.parse().map_err(serde::de::Error::custom)
+}
+
+impl Default for SignerConfig {
+    fn default() -> SignerConfig {
+        // ps256 test certs and key from the c2pa-rs SDK checkout
+        SignerConfig {
+            signcert_path: "../c2pa-rs/sdk/tests/fixtures/certs/ps256.pub".to_owned(),
+            pkey_path: "../c2pa-rs/sdk/tests/fixtures/certs/ps256.pem".to_owned(),
+            alg: SigningAlg::Ps256,
+            tsa_url: None,
+            max_concurrent: None,
+            pkey: None
+        }
+    }
+}
+
+impl SignerConfig {
+    /**
+     * Loads a signer profile from a JSON file such as
+     * `{"signcert_path": "certs/es256.pub", "pkey_path": "certs/es256.pem", "alg": "es256"}`
+     */
+    pub fn from_file(path: &str) -> Result<SignerConfig, c2pa::Error> {
+        let json = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&json)?)
+    }
+
+    /**
+     * The signer named by `C2PA_SIGNCERT_PATH`, `C2PA_PKEY_PATH` and
+     * `C2PA_ALG`, or None if none of them are set. Setting only some of
+     * them is an error rather than a mix with another signer's.
+     */
+    pub fn from_env() -> Result<Option<SignerConfig>, c2pa::Error> {
+        let vars = [SIGNCERT_PATH_VAR, PKEY_PATH_VAR, ALG_VAR].map(|name| std::env::var(name).ok().filter(|value| !value.is_empty()));
+        match vars {
+            [None, None, None] => Ok(None),
+            [Some(signcert_path), Some(pkey_path), Some(alg)] => Ok(Some(SignerConfig {
+                signcert_path,
+                pkey_path,
+                alg: alg.parse().map_err(|_| c2pa::Error::BadParam(format!("{} '{}' isn't a signing algorithm", ALG_VAR, alg)))?,
+                tsa_url: None,
+                max_concurrent: None,
+                pkey: None
+            })),
+            _ => Err(c2pa::Error::BadParam(format!("{}, {} and {} must be set together", SIGNCERT_PATH_VAR, PKEY_PATH_VAR, ALG_VAR)))
+        }
+    }
+
+    /* This signer with the TSA in `C2PA_TSA_URL`, if it is set, in place of its own */
+    pub fn with_env_tsa_url(mut self) -> SignerConfig {
+        if let Some(tsa_url) = std::env::var(TSA_URL_VAR).ok().filter(|tsa_url| !tsa_url.is_empty()) {
+            self.tsa_url = Some(tsa_url);
+        }
+        self
+    }
+
+    /* This signer with `pkey` as its key, in place of the file at pkey_path */
+    pub fn with_pkey(mut self, pkey: SecretBytes) -> SignerConfig {
+        self.pkey = Some(Arc::new(pkey));
+        self
+    }
+
+    pub fn create_signer(&self) -> Result<Box<dyn Signer>, c2pa::Error> {
+        Ok(Box::new(self.create_shared_signer()?))
+    }
+
+    /**
+     * Builds the signer once, reading and parsing its key, for sharing
+     * across a batch or a server's threads.
+     */
+    pub fn create_shared_signer(&self) -> Result<SharedSigner, c2pa::Error> {
+        let signer = || -> Result<SharedSigner, c2pa::Error> {
+            let permits = match self.max_concurrent {
+                Some(0) => return Err(c2pa::Error::BadParam("max_concurrent must be at least 1".to_owned())),
+                Some(max) => Some(Permits { available: Mutex::new(max), freed: Condvar::new() }),
+                None => None
+            };
+            let cert_chain = std::fs::read(&self.signcert_path)?;
+            let raw = match &self.pkey {
+                Some(pkey) => signer_from_cert_chain_and_private_key(&cert_chain, pkey, self.alg, self.tsa_url.clone())?,
+                None => {
+                    // Zeroed as soon as the signer is built
+                    let private_key = SecretBytes::read(&self.pkey_path)?;
+                    signer_from_cert_chain_and_private_key(&cert_chain, &private_key, self.alg, self.tsa_url.clone())?
+                }
+            };
+            Ok(SharedSigner { raw, permits })
+        };
+        signer().inspect_err(|_| metrics::record_signer_init_error())
+    }
+}
+
+/* A counting semaphore over the signatures a signer is making */
+struct Permits {
+    available: Mutex<usize>,
+    freed: Condvar
+}
+
+/* One signature's turn, given back when dropped */
+struct Permit<'a>(&'a Permits);
+
+impl Permits {
+    fn acquire(&self) -> Permit<'_> {
+        let mut available = self.freed.wait_while(self.available.lock().unwrap(), |available| *available == 0).unwrap();
+        *available -= 1;
+        Permit(self)
+    }
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        *self.0.available.lock().unwrap() += 1;
+        self.0.freed.notify_one();
+    }
+}
+
+/**
+ * A signer built once and shared by every operation of a batch, server or
+ * worker, rather than each re-reading its key. It can be used from several
+ * threads at once; with `max_concurrent` set, signatures past that many
+ * wait for one to finish. It signs as the SDK's own signers do, timestamps
+ * and OCSP included.
+ */
+pub struct SharedSigner {
+    raw: Box<dyn RawSigner + Send + Sync>,
+    permits: Option<Permits>
+}
+
+impl Signer for SharedSigner {
+    fn sign(&self, data: &[u8]) -> Result<Vec<u8>, c2pa::Error> {
+        let _permit = self.permits.as_ref().map(Permits::acquire);
+        Ok(self.raw.sign(data)?)
+    }
+
+    fn alg(&self) -> SigningAlg {
+        self.raw.alg()
+    }
+
+    fn certs(&self) -> Result<Vec<Vec<u8>>, c2pa::Error> {
+        Ok(self.raw.cert_chain()?)
+    }
+
+    fn reserve_size(&self) -> usize {
+        self.raw.reserve_size()
+    }
+
+    fn ocsp_val(&self) -> Option<Vec<u8>> {
+        self.raw.ocsp_response()
+    }
+
+    fn time_authority_url(&self) -> Option<String> {
+        self.raw.time_stamp_service_url()
+    }
+
+    fn timestamp_request_headers(&self) -> Option<Vec<(String, String)>> {
+        self.raw.time_stamp_request_headers()
+    }
+
+    fn timestamp_request_body(&self, message: &[u8]) -> Result<Vec<u8>, c2pa::Error> {
+        Ok(self.raw.time_stamp_request_body(message)?)
+    }
+
+    fn send_timestamp_request(&self, message: &[u8]) -> Option<Result<Vec<u8>, c2pa::Error>> {
+        self.raw.send_time_stamp_request(message).map(|response| response.map_err(c2pa::Error::from))
+    }
+
+    // raw_signer is left as None: the SDK signs through a raw signer directly when given one, bypassing the permits
+}
+
+/**
+ * The manifest definition used when the caller doesn't supply one, with
+ * the claim generator in `C2PA_CLAIM_GENERATOR`, such as "newsroom/1.0", in
+ * place of this tool if it is set.
+ */
+pub fn 
+default_manifest_definition () -> String {
+    match std::env::var(CLAIM_GENERATOR_VAR).ok().filter(|generator| !generator.trim().is_empty()) {
+        Some(generator) => serde_json::json!({"claim_generator": generator}).to_string(),
+        None => DEFAULT_MANIFEST_DEFINITION.to_owned()
+    }
+}
+
+/**
+ * Signs an in-memory asset with a manifest built from a manifest-definition
+ * JSON string (the same format `c2patool` accepts), returning the bytes of
+ * the asset with the signed manifest embedded.
+ */
+pub fn 
+sign_asset (format: &str, asset: &[u8], manifest_json: &str, signer_config: &SignerConfig) -> Result<Vec<u8>, c2pa::Error> {
+    let signer = signer_config.create_signer()?;
+    sign_asset_with_signer(format, asset, manifest_json, &*signer)
+}
+
+/**
+ * As `sign_asset`, with an already constructed signer, for long-running
+ * callers which load the key once.
+ */
+pub fn 
+sign_asset_with_signer (format: &str, asset: &[u8], manifest_json: &str, signer: &dyn Signer) -> Result<Vec<u8>, c2pa::Error> {
+    let mut builder = builder_from_json(manifest_json)?;
+    sign_manifest(format, asset, &mut builder, signer)
+}
+
+/**
+ * A `Builder` for a manifest-definition JSON string. Definitions written
+ * for the older `Manifest` API still work: a `claim_generator` string such
+ * as "newsroom/1.0 plugin/2.1", which `Builder` ignores, becomes its
+ * `claim_generator_info` entries, and assertions of `"kind": "Json"` stay
+ * JSON rather than being written as CBOR.
+ */
+pub fn 
+builder_from_json (manifest_json: &str) -> Result<Builder, c2pa::Error> {
+    let mut definition: serde_json::Value = serde_json::from_str(manifest_json)?;
+
+    if let Some(generator) = definition.as_object_mut().and_then(|definition| definition.remove("claim_generator")) {
+        let info: Vec<serde_json::Value> = generator.as_str().unwrap_or_default().split_whitespace()
+            .map(|product| match product.split_once('/') {
+                Some((name, version)) => serde_json::json!({"name": name, "version": version}),
+                None => serde_json::json!({"name": product})
+            })
+            .collect();
+        if definition.get("claim_generator_info").is_none() && !info.is_empty() {
+            definition["claim_generator_info"] = serde_json::Value::Array(info);
+        }
+    }
+    // Added one by one, in order, since `Builder` takes any assertion in a definition as CBOR
+    let assertions = definition.as_object_mut().and_then(|definition| definition.remove("assertions"));
+
+    let mut builder = Builder::from_json(&definition.to_string())?;
+    for assertion in assertions.as_ref().and_then(serde_json::Value::as_array).into_iter().flatten() {
+        let label = assertion["label"].as_str()
+            .ok_or_else(|| c2pa::Error::BadParam("manifest definition has an assertion without a label".to_owned()))?;
+        match assertion["kind"] == "Json" {
+            true => builder.add_assertion_json(label, &assertion["data"])?,
+            false => builder.add_assertion(label, &assertion["data"])?
+        };
+    }
+
+    Ok(builder)
+}
+
+/**
+ * Adds the assertions kept in `dir` to a manifest definition, one per
+ * `<label>.json` file holding the assertion's data, such as
+ * `com.example.licensing.json`, in file name order and after any the
+ * definition already has. Teams keep reusable sets, such as licensing terms
+ * or contact details, this way. Other files are ignored. Returns how many
+ * were added.
+ */
+pub fn 
+add_assertions_dir (definition: &mut serde_json::Value, dir: &Path) -> Result<usize, c2pa::Error> {
+    let mut files: Vec<_> = std::fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<_, _>>()?;
+    files.retain(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "json"));
+    files.sort();
+
+    let mut assertions = Vec::new();
+    for path in &files {
+        let label = path.file_stem().and_then(|stem| stem.to_str()).unwrap_or_default();
+        if label.is_empty() || label.contains(char::is_whitespace) {
+            return Err(c2pa::Error::BadParam(format!("{} isn't named for an assertion label", path.display())));
+        }
+        let data: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(path)?)
+            .map_err(|e| c2pa::Error::BadParam(format!("{}: {}", path.display(), e)))?;
+        assertions.push(serde_json::json!({"label": label, "data": data}));
+    }
+
+    let count = assertions.len();
+    append_assertions(definition, assertions)?;
+
+    Ok(count)
+}
+
+/* Adds assertions, as manifest-definition entries, after any a manifest definition already has */
+pub fn 
+append_assertions (definition: &mut serde_json::Value, assertions: Vec<serde_json::Value>) -> Result<(), c2pa::Error> {
+    let definition = definition.as_object_mut()
+        .ok_or_else(|| c2pa::Error::BadParam("the manifest definition isn't a JSON object".to_owned()))?;
+    match definition.entry("assertions").or_insert_with(|| serde_json::Value::Array(Vec::new())) {
+        serde_json::Value::Array(existing) => existing.extend(assertions),
+        _ => return Err(c2pa::Error::BadParam("the manifest definition's assertions aren't a list".to_owned()))
+    }
+
+    Ok(())
+}
+
+/**
+ * Has every manifest generated from here on written with this claim
+ * version: 1 for the `c2pa.claim` boxes of C2PA 1.x, 2 for the
+ * `c2pa.claim.v2` boxes of 2.0 and later. Definitions which give their own
+ * `claim_version` keep it.
+ */
+pub fn 
+set_claim_version (version: u8) {
+    *CLAIM_VERSION.lock().unwrap() = Some(version);
+}
+
+/* Gives a manifest the claim version `set_claim_version` set, unless its definition gives one */
+pub fn 
+apply_claim_version (builder: &mut Builder) {
+    if builder.definition.claim_version.is_none() {
+        builder.definition.claim_version = *CLAIM_VERSION.lock().unwrap();
+    }
+}
+
+/* Signs an in-memory asset with the manifest `builder` builds, returning the signed asset */
+pub fn 
+embed (format: &str, asset: &[u8], builder: &mut Builder, signer: &dyn Signer) -> Result<Vec<u8>, c2pa::Error> {
+    apply_claim_version(builder);
+    let mut signed = Cursor::new(Vec::new());
+    builder.sign(signer, format, &mut Cursor::new(asset), &mut signed)?;
+
+    Ok(signed.into_inner())
+}
+
+/**
+ * Signs an in-memory asset with an already built manifest, for callers
+ * which set options such as a remote manifest URL first.
+ */
+pub fn 
+sign_manifest (format: &str, asset: &[u8], builder: &mut Builder, signer: &dyn Signer) -> Result<Vec<u8>, c2pa::Error> {
+    clock::apply_instance_id(builder);
+    providers::apply_providers(builder, format, asset)?;
+    #[cfg(feature = "thumbnails")]
+    crate::thumbnail::apply_claim_thumbnail(builder, format, asset)?;
+    let signed = embed(format, asset, builder, signer)?;
+
+    #[cfg(feature = "audit")]
+    crate::audit::record_signing(format, asset, &signed, signer)?;
+
+    Ok(signed)
+}
+
+/**
+ * Signs an in-memory asset without writing the result, returning the
+ * manifest store it would carry as read back and validated, along with the
+ * signed asset, so a manifest definition and signer can be checked before
+ * anything is written. Nothing is recorded to the audit log.
+ */
+pub fn 
+dry_run (format: &str, asset: &[u8], manifest_json: &str, signer: &dyn Signer) -> Result<(Reader, Vec<u8>), c2pa::Error> {
+    let mut builder = builder_from_json(manifest_json)?;
+    clock::apply_instance_id(&mut builder);
+    providers::apply_providers(&mut builder, format, asset)?;
+    #[cfg(feature = "thumbnails")]
+    crate::thumbnail::apply_claim_thumbnail(&mut builder, format, asset)?;
+    let signed = embed(format, asset, &mut builder, signer)?;
+
+    Ok((crate::read::load_manifest_store(format, &signed)?, signed))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    use c2pa_crypto::raw_signature::RawSignerError;
+    use c2pa_crypto::time_stamp::TimeStampProvider;
+
+    use super::*;
+
+    // Counts the signatures under way, remembering the most at once
+    #[derive(Default)]
+    struct Concurrency {
+        signing: AtomicUsize,
+        peak: AtomicUsize
+    }
+
+    /* The test signer, slowed so that signatures overlap unless something holds them back */
+    struct CountingSigner {
+        raw: Box<dyn RawSigner + Send + Sync>,
+        concurrency: Arc<Concurrency>
+    }
+
+    impl RawSigner for CountingSigner {
+        fn sign(&self, data: &[u8]) -> Result<Vec<u8>, RawSignerError> {
+            let signing = self.concurrency.signing.fetch_add(1, Ordering::SeqCst) + 1;
+            self.concurrency.peak.fetch_max(signing, Ordering::SeqCst);
+            std::thread::sleep(Duration::from_millis(50));
+            let signature = self.raw.sign(data);
+            self.concurrency.signing.fetch_sub(1, Ordering::SeqCst);
+            signature
+        }
+
+        fn alg(&self) -> SigningAlg {
+            self.raw.alg()
+        }
+
+        fn cert_chain(&self) -> Result<Vec<Vec<u8>>, RawSignerError> {
+            self.raw.cert_chain()
+        }
+
+        fn reserve_size(&self) -> usize {
+            self.raw.reserve_size()
+        }
+    }
+
+    impl TimeStampProvider for CountingSigner {}
+
+    #[test]
+    fn max_concurrent_caps_signing_through_the_sdk() {
+        const MAX_CONCURRENT: usize = 2;
+        const THREADS: usize = 6;
+
+        let config = SignerConfig { max_concurrent: Some(MAX_CONCURRENT), ..SignerConfig::default() };
+        let SharedSigner { raw, permits } = config.create_shared_signer().unwrap();
+        let concurrency = Arc::new(Concurrency::default());
+        let signer = SharedSigner { raw: Box::new(CountingSigner { raw, concurrency: concurrency.clone() }), permits };
+        let asset = std::fs::read("test_file.jpg").unwrap();
+
+        std::thread::scope(|scope| {
+            for _ in 0..THREADS {
+                scope.spawn(|| sign_asset_with_signer("jpg", &asset, DEFAULT_MANIFEST_DEFINITION, &signer).unwrap());
+            }
+        });
+
+        assert_eq!(concurrency.signing.load(Ordering::SeqCst), 0);
+        assert!(concurrency.peak.load(Ordering::SeqCst) > 0, "the SDK never signed through the shared signer");
+        assert!(concurrency.peak.load(Ordering::SeqCst) <= MAX_CONCURRENT, "{} signatures at once", concurrency.peak.load(Ordering::SeqCst));
+    }
+}
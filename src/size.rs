@@ -0,0 +1,69 @@
+use serde::Serialize;
+
+use crate::jumbf::{boxes, superbox};
+
+/*
+ * How many bytes a manifest store adds to an asset, and where they go. Each
+ * manifest in the store's JUMBF is a superbox holding its claim, signature,
+ * credentials and a superbox of assertions, so the size of every assertion,
+ * thumbnails included, can be read off its box.
+ */
+
+/* One labeled part of a manifest, such as an assertion or the signature */
+#[derive(Debug, Serialize)]
+pub struct PartSize {
+    pub label: String,
+    pub bytes: usize
+}
+
+/* The size of one manifest in a store, and of its parts, largest first */
+#[derive(Debug, Serialize)]
+pub struct ManifestSize {
+    pub label: String,
+    pub bytes: usize,
+    pub parts: Vec<PartSize>
+}
+
+/* The size of a manifest store, manifest by manifest in store order, the active manifest last */
+#[derive(Debug, Serialize)]
+pub struct StoreSize {
+    pub bytes: usize,
+    pub manifests: Vec<ManifestSize>
+}
+
+/* The size of a manifest store given as its JUMBF */
+pub fn 
+store_size (jumbf: &[u8]) -> Result<StoreSize, c2pa::Error> {
+    let store = boxes(jumbf, 0)?;
+    let store = store.first().ok_or_else(|| c2pa::Error::BadParam("empty JUMBF manifest store".to_owned()))?;
+    let (_, manifests) = superbox(store)?;
+
+    let mut sizes = Vec::new();
+    for manifest in manifests.iter().filter(|manifest| &manifest.kind == b"jumb") {
+        let (description, children) = superbox(manifest)?;
+        let label = description.label.unwrap_or_default();
+        let mut parts = Vec::new();
+        for child in children.iter().filter(|child| &child.kind == b"jumb") {
+            let (description, contents) = superbox(child)?;
+            let part = description.label.unwrap_or_default();
+            // The assertion store is broken down into its assertions
+            if part == "c2pa.assertions" {
+                for assertion in contents.iter().filter(|assertion| &assertion.kind == b"jumb") {
+                    parts.push(PartSize { label: superbox(assertion)?.0.label.unwrap_or_default(), bytes: assertion.len });
+                }
+            } else {
+                parts.push(PartSize { label: part, bytes: child.len });
+            }
+        }
+        parts.sort_by_key(|part| std::cmp::Reverse(part.bytes));
+        sizes.push(ManifestSize { label, bytes: manifest.len, parts });
+    }
+
+    Ok(StoreSize { bytes: store.len, manifests: sizes })
+}
+
+/* The size of the manifest store embedded in an asset of the given format */
+pub fn 
+manifest_store_size (format: &str, asset: &[u8]) -> Result<StoreSize, c2pa::Error> {
+    store_size(&c2pa::jumbf_io::load_jumbf_from_memory(format, asset)?)
+}
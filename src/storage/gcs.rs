@@ -0,0 +1,98 @@
+use std::io::Read;
+use std::process::Command;
+
+use serde::Deserialize;
+
+use super::percent_encode;
+
+/*
+ * gs://bucket/object locations, via the Cloud Storage JSON API. Requests
+ * carry an OAuth access token from, in order: `GOOGLE_OAUTH_ACCESS_TOKEN`,
+ * the GCE/GKE metadata server, or `gcloud auth print-access-token`. When
+ * `STORAGE_EMULATOR_HOST` is set (e.g. fake-gcs-server) requests go there
+ * unauthenticated instead.
+ */
+
+const GCS_HOST: &str = "https://storage.googleapis.com";
+const METADATA_TOKEN_URL: &str = "http://metadata.google.internal/computeMetadata/v1/instance/service-accounts/default/token";
+
+#[derive(Deserialize)]
+struct MetadataToken {
+    access_token: String
+}
+
+fn 
+gcs_error (e: ureq::Error) -> c2pa::Error {
+    c2pa::Error::OtherError(Box::new(e))
+}
+
+fn 
+access_token () -> Result<String, c2pa::Error> {
+    if let Ok(token) = std::env::var("GOOGLE_OAUTH_ACCESS_TOKEN") {
+        return Ok(token);
+    }
+
+    let metadata = ureq::get(METADATA_TOKEN_URL)
+        .set("Metadata-Flavor", "Google")
+        .timeout(std::time::Duration::from_secs(2))
+        .call();
+    if let Ok(response) = metadata {
+        let token: MetadataToken = serde_json::from_str(&response.into_string()?)?;
+        return Ok(token.access_token);
+    }
+
+    let output = Command::new("gcloud").args(["auth", "print-access-token"]).output();
+    match output {
+        Ok(output) if output.status.success() => Ok(String::from_utf8_lossy(&output.stdout).trim().to_owned()),
+        _ => Err(c2pa::Error::BadParam(
+            "no Google Cloud credentials; set GOOGLE_OAUTH_ACCESS_TOKEN or run on GCP or with gcloud logged in".to_owned()
+        ))
+    }
+}
+
+/* Base URL and, outside the emulator, the bearer token to send */
+fn 
+endpoint () -> Result<(String, Option<String>), c2pa::Error> {
+    match std::env::var("STORAGE_EMULATOR_HOST") {
+        Ok(host) if host.starts_with("http") => Ok((host, None)),
+        Ok(host) => Ok((format!("http://{}", host), None)),
+        Err(_) => Ok((GCS_HOST.to_owned(), Some(access_token()?)))
+    }
+}
+
+fn 
+authorize (request: ureq::Request, token: &Option<String>) -> ureq::Request {
+    match token {
+        Some(token) => request.set("Authorization", &format!("Bearer {}", token)),
+        None => request
+    }
+}
+
+pub fn 
+read (bucket: &str, object: &str, uri: &str) -> Result<Vec<u8>, c2pa::Error> {
+    let (base, token) = endpoint()?;
+    let url = format!("{}/storage/v1/b/{}/o/{}?alt=media", base, percent_encode(bucket), percent_encode(object));
+
+    let response = match authorize(ureq::get(&url), &token).call() {
+        Err(ureq::Error::Status(404, _)) => return Err(c2pa::Error::FileNotFound(uri.to_owned())),
+        result => result.map_err(gcs_error)?
+    };
+
+    let mut bytes = Vec::new();
+    response.into_reader().read_to_end(&mut bytes)?;
+
+    Ok(bytes)
+}
+
+pub fn 
+write (bucket: &str, object: &str, _uri: &str, bytes: &[u8]) -> Result<(), c2pa::Error> {
+    let (base, token) = endpoint()?;
+    let url = format!("{}/upload/storage/v1/b/{}/o?uploadType=media&name={}", base, percent_encode(bucket), percent_encode(object));
+
+    authorize(ureq::post(&url), &token)
+        .set("Content-Type", "application/octet-stream")
+        .send_bytes(bytes)
+        .map_err(gcs_error)?;
+
+    Ok(())
+}
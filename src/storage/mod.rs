@@ -0,0 +1,467 @@
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/*
+ * Reading and writing assets by location. A location is a local path, an
+ * http(s) URL, a `gs://bucket/object` or `az://container/blob` URI, an
+ * `s3://bucket/key` URI (with the `s3` feature), or an
+ * `sftp://user@host/path` URI (with the `sftp` feature). URLs are fetched with GET
+ * and written with PUT, which covers plain web servers as well as
+ * pre-signed object store URLs and Azure SAS URLs. Objects are transferred
+ * in memory, without intermediate temp files; local files are written to a
+ * temporary file beside them and renamed into place. Local files and URLs
+ * are read and written `IoOptions::chunk_size` bytes at a time, and local
+ * reads ask the kernel to read ahead of them.
+ */
+
+mod azure;
+mod gcs;
+mod s3;
+mod sftp;
+
+fn 
+is_url (location: &str) -> bool {
+    location.starts_with("http://") || location.starts_with("https://")
+}
+
+/* Whether `location` names a remote asset, rather than a local path */
+pub fn 
+is_remote (location: &str) -> bool {
+    is_url(location) || ["s3://", "gs://", "az://", "sftp://"].iter().any(|scheme| location.starts_with(scheme))
+}
+
+fn 
+too_large (location: &str, max_size: u64) -> c2pa::Error {
+    c2pa::Error::BadParam(format!("{} is larger than the limit of {} bytes", location, max_size))
+}
+
+fn 
+http_error (e: ureq::Error) -> c2pa::Error {
+    c2pa::Error::OtherError(Box::new(e))
+}
+
+/**
+ * Splits `s3://bucket/some/key.jpg` into ("bucket", "some/key.jpg").
+ */
+fn 
+split_bucket_uri<'a> (uri: &'a str, scheme: &str) -> Option<(&'a str, &'a str)> {
+    uri.strip_prefix(scheme)?
+        .split_once('/')
+        .filter(|(bucket, key)| !bucket.is_empty() && !key.is_empty())
+}
+
+/* Percent-encodes everything but RFC 3986 unreserved characters, for object names in URLs */
+pub fn 
+percent_encode (name: &str) -> String {
+    name.bytes().map(|b| match b {
+        b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (b as char).to_string(),
+        _ => format!("%{:02X}", b)
+    }).collect()
+}
+
+fn 
+bad_uri (location: &str) -> c2pa::Error {
+    c2pa::Error::BadParam(format!("malformed object URI '{}'; expected scheme://bucket/key", location))
+}
+
+/* How assets are read and written */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IoOptions {
+    // Bytes read or written per call
+    pub chunk_size: usize,
+    // Bytes past each read the kernel is asked to fetch early; 0 leaves it to the kernel (Linux only)
+    pub read_ahead: u64
+}
+
+impl Default for IoOptions {
+    fn default() -> IoOptions {
+        IoOptions {
+            chunk_size: 1024 * 1024,
+            read_ahead: 0
+        }
+    }
+}
+
+static IO_OPTIONS: Mutex<Option<IoOptions>> = Mutex::new(None);
+
+/* Sets how assets are read and written from now on */
+pub fn 
+configure_io (options: IoOptions) {
+    *IO_OPTIONS.lock().unwrap() = Some(options);
+}
+
+/* The configured I/O options, else the defaults */
+pub fn 
+io_options () -> IoOptions {
+    IO_OPTIONS.lock().unwrap().unwrap_or_default()
+}
+
+// Most of a claimed length reserved up front, since a server can claim any Content-Length
+const MAX_SIZE_HINT: usize = 256 * 1024 * 1024;
+
+/**
+ * Reads all of `reader` a chunk at a time, into a buffer of `size_hint`
+ * bytes to start, calling `before_chunk` with the offset of each chunk.
+ */
+fn 
+read_chunked (reader: &mut impl Read, size_hint: usize, mut before_chunk: impl FnMut(u64)) -> Result<Vec<u8>, std::io::Error> {
+    let chunk_size = io_options().chunk_size.max(1);
+    let mut bytes = Vec::with_capacity(size_hint.min(MAX_SIZE_HINT));
+    loop {
+        before_chunk(bytes.len() as u64);
+        let read = reader.by_ref().take(chunk_size as u64).read_to_end(&mut bytes)?;
+        if read == 0 {
+            return Ok(bytes);
+        }
+    }
+}
+
+/* Asks the kernel to start reading the `len` bytes of `file` from `offset` */
+#[cfg(target_os = "linux")]
+fn 
+advise_read_ahead (file: &fs::File, offset: u64, len: u64) {
+    use std::os::fd::AsRawFd;
+
+    // Only a hint; a failure just means no read-ahead
+    unsafe { libc::posix_fadvise(file.as_raw_fd(), offset as libc::off_t, len as libc::off_t, libc::POSIX_FADV_WILLNEED) };
+}
+
+#[cfg(not(target_os = "linux"))]
+fn 
+advise_read_ahead (_file: &fs::File, _offset: u64, _len: u64) {}
+
+/* Reads a local file a chunk at a time, keeping `IoOptions::read_ahead` bytes in flight ahead of the reads */
+pub fn 
+read_file (path: impl AsRef<Path>) -> Result<Vec<u8>, c2pa::Error> {
+    let file = fs::File::open(path)?;
+    let len = file.metadata()?.len();
+    let options = io_options();
+
+    let mut advised = 0;
+    let bytes = read_chunked(&mut &file, len as usize, |offset| {
+        if options.read_ahead > 0 && offset + options.chunk_size as u64 > advised {
+            let end = (offset + options.chunk_size as u64 + options.read_ahead).min(len);
+            advise_read_ahead(&file, advised.max(offset), end.saturating_sub(advised.max(offset)));
+            advised = end;
+        }
+    })?;
+
+    Ok(bytes)
+}
+
+pub fn 
+read_asset (location: &str) -> Result<Vec<u8>, c2pa::Error> {
+    if location.starts_with("s3://") {
+        let (bucket, key) = split_bucket_uri(location, "s3://").ok_or_else(|| bad_uri(location))?;
+        s3::read(bucket, key, location)
+    } else if location.starts_with("gs://") {
+        let (bucket, object) = split_bucket_uri(location, "gs://").ok_or_else(|| bad_uri(location))?;
+        gcs::read(bucket, object, location)
+    } else if location.starts_with("az://") {
+        let (container, blob) = split_bucket_uri(location, "az://").ok_or_else(|| bad_uri(location))?;
+        azure::read(container, blob, location)
+    } else if location.starts_with("sftp://") {
+        sftp::read(location)
+    } else if is_url(location) {
+        let response = ureq::get(location).call().map_err(http_error)?;
+        let len = response.header("Content-Length").and_then(|len| len.parse().ok()).unwrap_or(0);
+
+        Ok(read_chunked(&mut response.into_reader(), len, |_| ())?)
+    } else {
+        read_file(location)
+    }
+}
+
+/**
+ * As `read_asset`, refusing an asset larger than `max_size` bytes. Local
+ * files and URLs are refused before more than that is read; assets in
+ * object stores and on SFTP servers once downloaded.
+ */
+pub fn 
+read_asset_at_most (location: &str, max_size: u64) -> Result<Vec<u8>, c2pa::Error> {
+    let bytes = if is_url(location) {
+        let response = ureq::get(location).call().map_err(http_error)?;
+        let len: u64 = response.header("Content-Length").and_then(|len| len.parse().ok()).unwrap_or(0);
+        if len > max_size {
+            return Err(too_large(location, max_size));
+        }
+        // One byte past the limit is enough to tell the body is over it
+        read_chunked(&mut response.into_reader().take(max_size + 1), len as usize, |_| ())?
+    } else if !is_remote(location) {
+        if fs::metadata(location)?.len() > max_size {
+            return Err(too_large(location, max_size));
+        }
+        read_file(location)?
+    } else {
+        read_asset(location)?
+    };
+
+    match bytes.len() as u64 > max_size {
+        true => Err(too_large(location, max_size)),
+        false => Ok(bytes)
+    }
+}
+
+pub fn 
+write_asset (location: &str, bytes: &[u8]) -> Result<(), c2pa::Error> {
+    if location.starts_with("s3://") {
+        let (bucket, key) = split_bucket_uri(location, "s3://").ok_or_else(|| bad_uri(location))?;
+        s3::write(bucket, key, location, bytes)
+    } else if location.starts_with("gs://") {
+        let (bucket, object) = split_bucket_uri(location, "gs://").ok_or_else(|| bad_uri(location))?;
+        gcs::write(bucket, object, location, bytes)
+    } else if location.starts_with("az://") {
+        let (container, blob) = split_bucket_uri(location, "az://").ok_or_else(|| bad_uri(location))?;
+        azure::write(container, blob, location, bytes)
+    } else if location.starts_with("sftp://") {
+        sftp::write(location, bytes)
+    } else if azure::is_blob_url(location) {
+        azure::put_blob(location, bytes)
+    } else if is_url(location) {
+        ureq::put(location).send_bytes(bytes).map_err(http_error)?;
+        Ok(())
+    } else {
+        write_file_atomically(Path::new(location), bytes)
+    }
+}
+
+// Distinguishes temporary files of concurrent writes within one process
+static TEMP_FILE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/**
+ * Writes a local file by way of a temporary file in the same directory,
+ * renamed over `path` only once fully written and synced, so an interrupted
+ * or failed write never leaves a truncated file where the output was
+ * expected. The temporary file is removed if anything fails, and takes the
+ * permissions of any file it replaces. The directory is synced after the
+ * rename too, so the new name survives a crash as well as the contents.
+ */
+pub fn 
+write_file_atomically (path: &Path, bytes: &[u8]) -> Result<(), c2pa::Error> {
+    let file_name = path.file_name()
+        .ok_or_else(|| c2pa::Error::BadParam(format!("'{}' is not a file path", path.display())))?;
+    let mut temp_name = std::ffi::OsString::from(".");
+    temp_name.push(file_name);
+    temp_name.push(format!(".{}-{}.tmp", std::process::id(), TEMP_FILE_COUNTER.fetch_add(1, Ordering::Relaxed)));
+    let temp_path: PathBuf = path.with_file_name(temp_name);
+
+    let result = (|| {
+        let mut file = fs::File::create(&temp_path)?;
+        for chunk in bytes.chunks(io_options().chunk_size.max(1)) {
+            file.write_all(chunk)?;
+        }
+        if let Ok(existing) = fs::metadata(path) {
+            file.set_permissions(existing.permissions())?;
+        }
+        file.sync_all()?;
+        drop(file);
+
+        fs::rename(&temp_path, path)
+    })();
+
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result?;
+    sync_parent_dir(path)
+}
+
+/* Syncs the directory holding `path`, making a rename into it durable */
+#[cfg(unix)]
+fn
+sync_parent_dir (path: &Path) -> Result<(), c2pa::Error> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new(".")
+    };
+    fs::File::open(dir)?.sync_all()?;
+    Ok(())
+}
+
+/* Directories can't be opened to sync them off unix, where renames are left to the filesystem */
+#[cfg(not(unix))]
+fn
+sync_parent_dir (_path: &Path) -> Result<(), c2pa::Error> {
+    Ok(())
+}
+
+/* What to do about a local output which already exists */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Collision {
+    Overwrite,
+    Skip,
+    // writes to the first free name of the form <stem>-2.<ext>, <stem>-3.<ext>, ...
+    Number
+}
+
+/**
+ * Where to write a local output meant for `path`: `path` itself if nothing
+ * is there yet or with `Overwrite`, nowhere with `Skip`, else the first free
+ * numbered name beside it, e.g. photo_c2pa-2.jpg for photo_c2pa.jpg.
+ */
+pub fn 
+resolve_collision (path: &Path, collision: Collision) -> Option<PathBuf> {
+    // Broken links count as taken too, so they aren't silently replaced
+    let taken = |path: &Path| path.symlink_metadata().is_ok();
+    if collision == Collision::Overwrite || !taken(path) {
+        return Some(path.to_owned());
+    }
+    if collision == Collision::Skip {
+        return None;
+    }
+
+    let stem = path.file_stem().unwrap_or_default();
+    (2..).map(|n| {
+        let mut name = stem.to_owned();
+        name.push(format!("-{}", n));
+        if let Some(extension) = path.extension() {
+            name.push(".");
+            name.push(extension);
+        }
+        path.with_file_name(name)
+    }).find(|candidate| !taken(candidate))
+}
+
+/* Copies a local file to `<path>.bak` before it is replaced, returning where the copy went */
+pub fn 
+back_up_file (path: &Path) -> Result<PathBuf, c2pa::Error> {
+    let mut backup_name = path.file_name()
+        .ok_or_else(|| c2pa::Error::BadParam(format!("'{}' is not a file path", path.display())))?
+        .to_owned();
+    backup_name.push(".bak");
+    let backup = path.with_file_name(backup_name);
+
+    write_file_atomically(&backup, &fs::read(path)?)?;
+    Ok(backup)
+}
+
+/**
+ * An advisory lock on a local file, released when dropped. Runs that read
+ * an asset share its lock and a run that writes one holds it alone, so two
+ * runs can't both replace the same file and lose one's changes. Locking an
+ * output that doesn't exist yet claims its name with an empty placeholder,
+ * which the output is renamed over or which is removed on drop.
+ */
+pub struct FileLock {
+    file: fs::File,
+    // the placeholder made for an output that didn't exist yet
+    placeholder: Option<PathBuf>
+}
+
+impl Drop for FileLock {
+    fn
+    drop (&mut self) {
+        if let Some(path) = &self.placeholder {
+            if is_same_file(&self.file, path) {
+                let _ = fs::remove_file(path);
+            }
+        }
+    }
+}
+
+/* Whether `path` is still the file open as `file`, rather than one renamed over it */
+#[cfg(unix)]
+fn
+is_same_file (file: &fs::File, path: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    match (file.metadata(), fs::metadata(path)) {
+        (Ok(open), Ok(current)) => open.dev() == current.dev() && open.ino() == current.ino(),
+        _ => false
+    }
+}
+
+#[cfg(not(unix))]
+fn
+is_same_file (_file: &fs::File, path: &Path) -> bool {
+    fs::metadata(path).is_ok_and(|current| current.len() == 0)
+}
+
+/* Takes a shared lock on a local input, or None if a run writing it holds the lock */
+pub fn
+lock_input (path: &Path) -> Result<Option<FileLock>, c2pa::Error> {
+    let file = fs::File::open(path)?;
+    match file.try_lock_shared() {
+        Ok(()) => Ok(Some(FileLock { file, placeholder: None })),
+        Err(fs::TryLockError::WouldBlock) => Ok(None),
+        Err(fs::TryLockError::Error(e)) => Err(e.into())
+    }
+}
+
+/* Takes the only lock on a local output, or None if another run holds a lock on it */
+pub fn
+lock_output (path: &Path) -> Result<Option<FileLock>, c2pa::Error> {
+    let (file, placeholder) = match fs::OpenOptions::new().write(true).create_new(true).open(path) {
+        Ok(file) => (file, Some(path.to_owned())),
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => (fs::File::open(path)?, None),
+        Err(e) => return Err(e.into())
+    };
+
+    match file.try_lock() {
+        Ok(()) => Ok(Some(FileLock { file, placeholder })),
+        // Another run opened the placeholder before it was locked, and now owns it
+        Err(fs::TryLockError::WouldBlock) => Ok(None),
+        Err(fs::TryLockError::Error(e)) => {
+            if let Some(placeholder) = placeholder {
+                let _ = fs::remove_file(placeholder);
+            }
+            Err(e.into())
+        }
+    }
+}
+
+/**
+ * Guesses an asset format from the extension of a location, ignoring any
+ * URL query string, e.g. "jpg" for "https://host/photo.jpg?sig=...".
+ */
+pub fn 
+format_from_location (location: &str) -> Option<String> {
+    let path = location.split(['?', '#']).next().unwrap_or(location);
+    // Local paths may use Windows separators
+    let file_name = path.rsplit(['/', '\\']).next().unwrap_or(path);
+
+    // Dotfiles such as .profile have no extension
+    file_name.rsplit_once('.')
+        .filter(|(stem, ext)| !stem.is_empty() && !ext.is_empty())
+        .map(|(_, ext)| ext.to_ascii_lowercase())
+}
+
+/**
+ * Location of a file kept alongside the asset at `location`, such as a
+ * transparency log record, named by appending `suffix` ahead of any URL
+ * query string.
+ */
+pub fn 
+sidecar_location (location: &str, suffix: &str) -> String {
+    match location.split_once('?') {
+        Some((path, query)) => format!("{}{}?{}", path, suffix, query),
+        None => format!("{}{}", location, suffix)
+    }
+}
+
+/**
+ * Location of a version of the asset at `location` in another format,
+ * named by replacing its extension ahead of any URL query string, e.g.
+ * photo.webp for photo.jpg.
+ */
+pub fn 
+with_extension (location: &str, extension: &str) -> String {
+    let (path, query) = match location.split_once('?') {
+        Some((path, query)) => (path, Some(query)),
+        None => (location, None)
+    };
+    // Only a dot in the file name starts an extension, and not a leading one
+    let name_start = path.rfind(['/', '\\']).map_or(0, |i| i + 1);
+    let stem = match path[name_start..].rfind('.') {
+        Some(dot) if dot > 0 => &path[..name_start + dot],
+        _ => path
+    };
+
+    match query {
+        Some(query) => format!("{}.{}?{}", stem, extension, query),
+        None => format!("{}.{}", stem, extension)
+    }
+}
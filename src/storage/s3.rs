@@ -0,0 +1,77 @@
+/*
+ * s3://bucket/key locations, via rust-s3 with the `s3` feature.
+ */
+
+#[cfg(feature = "s3")]
+mod imp {
+    use s3::creds::Credentials;
+    use s3::{Bucket, Region};
+
+    fn 
+    s3_error<E: std::error::Error + Send + Sync + 'static> (e: E) -> c2pa::Error {
+        c2pa::Error::OtherError(Box::new(e))
+    }
+
+    /**
+     * Opens a bucket using the standard AWS credential chain (environment,
+     * shared profile, instance metadata). `AWS_REGION` (or
+     * `AWS_DEFAULT_REGION`) selects the region, and `AWS_ENDPOINT_URL`
+     * points at S3-compatible stores such as MinIO, using path-style URLs.
+     */
+    fn 
+    bucket (name: &str) -> Result<Box<Bucket>, c2pa::Error> {
+        let region_name = std::env::var("AWS_REGION")
+            .or_else(|_| std::env::var("AWS_DEFAULT_REGION"))
+            .unwrap_or_else(|_| "us-east-1".to_owned());
+        let credentials = Credentials::default().map_err(s3_error)?;
+
+        match std::env::var("AWS_ENDPOINT_URL") {
+            Ok(endpoint) => {
+                let region = Region::Custom { region: region_name, endpoint };
+                Ok(Bucket::new(name, region, credentials).map_err(s3_error)?.with_path_style())
+            },
+            Err(_) => {
+                let region = region_name.parse().map_err(s3_error)?;
+                Bucket::new(name, region, credentials).map_err(s3_error)
+            }
+        }
+    }
+
+    fn 
+    check_status (status: u16, uri: &str) -> Result<(), c2pa::Error> {
+        match status {
+            200..=299 => Ok(()),
+            404 => Err(c2pa::Error::FileNotFound(uri.to_owned())),
+            _ => Err(c2pa::Error::BadParam(format!("S3 request for {} failed with HTTP {}", uri, status)))
+        }
+    }
+
+    pub fn 
+    read (bucket_name: &str, key: &str, uri: &str) -> Result<Vec<u8>, c2pa::Error> {
+        let response = bucket(bucket_name)?.get_object(key).map_err(s3_error)?;
+        check_status(response.status_code(), uri)?;
+
+        Ok(response.to_vec())
+    }
+
+    pub fn 
+    write (bucket_name: &str, key: &str, uri: &str, bytes: &[u8]) -> Result<(), c2pa::Error> {
+        let response = bucket(bucket_name)?.put_object(key, bytes).map_err(s3_error)?;
+        check_status(response.status_code(), uri)
+    }
+}
+
+#[cfg(not(feature = "s3"))]
+mod imp {
+    pub fn 
+    read (_bucket_name: &str, _key: &str, _uri: &str) -> Result<Vec<u8>, c2pa::Error> {
+        Err(c2pa::Error::BadParam("s3:// locations require building with --features s3".to_owned()))
+    }
+
+    pub fn 
+    write (_bucket_name: &str, _key: &str, _uri: &str, _bytes: &[u8]) -> Result<(), c2pa::Error> {
+        Err(c2pa::Error::BadParam("s3:// locations require building with --features s3".to_owned()))
+    }
+}
+
+pub use imp::{read, write};
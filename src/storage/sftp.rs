@@ -0,0 +1,180 @@
+/*
+ * sftp://[user@]host[:port]/path locations, via libssh2 with the `sftp`
+ * feature. Sessions are pooled per user@host:port for the life of the
+ * process, so batch runs over many files from the same drop reuse one
+ * connection. Host keys must be present in ~/.ssh/known_hosts.
+ * Authentication tries the SSH agent, then the key in `SFTP_KEY` (or
+ * ~/.ssh/id_ed25519, ~/.ssh/id_rsa), then the password in `SFTP_PASSWORD`.
+ */
+
+#[cfg(feature = "sftp")]
+mod imp {
+    use std::collections::BTreeMap;
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+    use std::path::{Path, PathBuf};
+    use std::sync::Mutex;
+
+    use ssh2::{CheckResult, KnownHostFileKind, Session, Sftp};
+
+    // Open SFTP channels keyed by "user@host:port"
+    static POOL: Mutex<BTreeMap<String, Sftp>> = Mutex::new(BTreeMap::new());
+
+    /* The parts of an sftp:// URI */
+    struct Target<'a> {
+        user: String,
+        host: &'a str,
+        port: u16,
+        path: &'a str
+    }
+
+    fn 
+    sftp_error (e: ssh2::Error) -> c2pa::Error {
+        c2pa::Error::OtherError(Box::new(e))
+    }
+
+    fn 
+    parse (uri: &str) -> Result<Target<'_>, c2pa::Error> {
+        let bad_uri = || c2pa::Error::BadParam(format!("malformed SFTP URI '{}'; expected sftp://user@host:port/path", uri));
+
+        let rest = uri.strip_prefix("sftp://").ok_or_else(bad_uri)?;
+        let (authority, path) = rest.find('/').map(|i| rest.split_at(i)).ok_or_else(bad_uri)?;
+        let (user, host_port) = match authority.split_once('@') {
+            Some((user, host_port)) => (user.to_owned(), host_port),
+            None => (std::env::var("USER").unwrap_or_else(|_| "root".to_owned()), authority)
+        };
+        let (host, port) = match host_port.rsplit_once(':') {
+            Some((host, port)) => (host, port.parse().map_err(|_| bad_uri())?),
+            None => (host_port, 22)
+        };
+
+        if host.is_empty() || path.len() < 2 {
+            return Err(bad_uri());
+        }
+
+        Ok(Target { user, host, port, path })
+    }
+
+    fn 
+    home () -> PathBuf {
+        PathBuf::from(std::env::var("HOME").unwrap_or_default())
+    }
+
+    fn 
+    verify_host_key (session: &Session, host: &str, port: u16) -> Result<(), c2pa::Error> {
+        let mut known_hosts = session.known_hosts().map_err(sftp_error)?;
+        // A missing known_hosts file just means nothing matches below
+        let _ = known_hosts.read_file(&home().join(".ssh/known_hosts"), KnownHostFileKind::OpenSSH);
+
+        let (key, _) = session.host_key()
+            .ok_or_else(|| c2pa::Error::BadParam(format!("{} presented no host key", host)))?;
+
+        match known_hosts.check_port(host, port, key) {
+            CheckResult::Match => Ok(()),
+            CheckResult::Mismatch => Err(c2pa::Error::BadParam(format!("host key for {} does not match known_hosts", host))),
+            _ => Err(c2pa::Error::BadParam(format!("{} is not in ~/.ssh/known_hosts; connect once with ssh to add it", host)))
+        }
+    }
+
+    fn 
+    authenticate (session: &Session, user: &str) -> Result<(), c2pa::Error> {
+        if session.userauth_agent(user).is_ok() && session.authenticated() {
+            return Ok(());
+        }
+
+        let key_files = match std::env::var("SFTP_KEY") {
+            Ok(key) => vec![PathBuf::from(key)],
+            Err(_) => vec![home().join(".ssh/id_ed25519"), home().join(".ssh/id_rsa")]
+        };
+        let passphrase = std::env::var("SFTP_KEY_PASSPHRASE").ok();
+        for key_file in key_files.iter().filter(|key_file| key_file.exists()) {
+            if session.userauth_pubkey_file(user, None, key_file, passphrase.as_deref()).is_ok() && session.authenticated() {
+                return Ok(());
+            }
+        }
+
+        if let Ok(password) = std::env::var("SFTP_PASSWORD") {
+            session.userauth_password(user, &password).map_err(sftp_error)?;
+            return Ok(());
+        }
+
+        Err(c2pa::Error::BadParam(format!("SFTP authentication failed for {}", user)))
+    }
+
+    fn 
+    connect (target: &Target) -> Result<Sftp, c2pa::Error> {
+        let tcp = TcpStream::connect((target.host, target.port))?;
+        let mut session = Session::new().map_err(sftp_error)?;
+        session.set_tcp_stream(tcp);
+        session.handshake().map_err(sftp_error)?;
+
+        verify_host_key(&session, target.host, target.port)?;
+        authenticate(&session, &target.user)?;
+
+        session.sftp().map_err(sftp_error)
+    }
+
+    /**
+     * Runs `op` on a pooled SFTP channel for the target, reconnecting once
+     * if a pooled channel turns out to be dead.
+     */
+    fn 
+    with_sftp<T, F: Fn(&Sftp, &Path) -> Result<T, c2pa::Error>> (uri: &str, op: F) -> Result<T, c2pa::Error> {
+        let target = parse(uri)?;
+        let key = format!("{}@{}:{}", target.user, target.host, target.port);
+        let path = Path::new(target.path);
+
+        let mut pool = POOL.lock().unwrap();
+        if let Some(sftp) = pool.get(&key) {
+            match op(sftp, path) {
+                Err(c2pa::Error::OtherError(_)) => { pool.remove(&key); },
+                result => return result
+            }
+        }
+
+        let sftp = connect(&target)?;
+        let result = op(&sftp, path);
+        pool.insert(key, sftp);
+
+        result
+    }
+
+    pub fn 
+    read (uri: &str) -> Result<Vec<u8>, c2pa::Error> {
+        with_sftp(uri, |sftp, path| {
+            let mut file = sftp.open(path).map_err(|e| match e.code() {
+                // LIBSSH2_FX_NO_SUCH_FILE
+                ssh2::ErrorCode::SFTP(2) => c2pa::Error::FileNotFound(uri.to_owned()),
+                _ => sftp_error(e)
+            })?;
+
+            let mut bytes = Vec::new();
+            file.read_to_end(&mut bytes)?;
+            Ok(bytes)
+        })
+    }
+
+    pub fn 
+    write (uri: &str, bytes: &[u8]) -> Result<(), c2pa::Error> {
+        with_sftp(uri, |sftp, path| {
+            let mut file = sftp.create(path).map_err(sftp_error)?;
+            file.write_all(bytes)?;
+            Ok(())
+        })
+    }
+}
+
+#[cfg(not(feature = "sftp"))]
+mod imp {
+    pub fn 
+    read (_uri: &str) -> Result<Vec<u8>, c2pa::Error> {
+        Err(c2pa::Error::BadParam("sftp:// locations require building with --features sftp".to_owned()))
+    }
+
+    pub fn 
+    write (_uri: &str, _bytes: &[u8]) -> Result<(), c2pa::Error> {
+        Err(c2pa::Error::BadParam("sftp:// locations require building with --features sftp".to_owned()))
+    }
+}
+
+pub use imp::{read, write};
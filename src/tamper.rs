@@ -0,0 +1,76 @@
+use c2pa::Reader;
+use serde::Serialize;
+
+/*
+ * Where in an asset validation failed, for reports more useful than a list
+ * of status codes. The SDK attaches to each failure a JUMBF URL naming the
+ * assertion, ingredient or signature checked, and sometimes an explanation;
+ * these are turned into a description of what part of the asset the
+ * failure implicates. How far a hash mismatch can be narrowed depends on
+ * the hash binding: a `c2pa.hash.data` assertion is a single hash over
+ * every byte outside the manifest store, while `c2pa.hash.bmff` hashes
+ * boxes (and chunks of them) separately, and its explanation says which
+ * failed. Like `read`, this has no file-system dependencies.
+ */
+
+#[derive(Debug, Serialize)]
+/* One failed validation check and what it implicates */
+pub struct Finding {
+    pub code: String,
+    pub url: Option<String>,
+    pub explanation: Option<String>,
+    pub location: String
+}
+
+/* The manifest and the item within it that a JUMBF URL such as `self#jumbf=/c2pa/<manifest>/c2pa.assertions/<label>` names */
+fn 
+jumbf_path (url: &str) -> (Option<&str>, Option<&str>) {
+    let path = url.split_once("jumbf=").map_or(url, |(_, path)| path);
+    let mut parts = path.trim_start_matches('/').split('/').skip_while(|part| *part == "c2pa");
+
+    (parts.next(), parts.last())
+}
+
+/* What part of the asset a failed check with the given code and URL implicates */
+fn 
+describe (code: &str, url: Option<&str>) -> String {
+    let (manifest, item) = url.map(jumbf_path).unwrap_or_default();
+    let manifest = manifest.unwrap_or("unknown manifest");
+
+    // Assertion labels may carry an instance suffix, e.g. c2pa.ingredient__1
+    let label = item.map(|item| item.split("__").next().unwrap_or(item));
+    match (code, label) {
+        (_, Some("c2pa.hash.data")) =>
+            format!("asset content outside the manifest store, covered as a whole by the data hash of {}", manifest),
+        (_, Some("c2pa.hash.bmff")) =>
+            format!("boxes of the asset hashed by the BMFF hash of {}", manifest),
+        (_, Some("c2pa.hash.boxes")) =>
+            format!("boxes of the asset hashed by the box hash of {}", manifest),
+        (_, Some("c2pa.signature")) =>
+            format!("claim signature of {}", manifest),
+        (_, Some("c2pa.claim")) =>
+            format!("claim of {}", manifest),
+        (_, Some("c2pa.ingredient")) =>
+            format!("ingredient {} of {}, or the manifest it references", item.unwrap(), manifest),
+        (code, Some(_)) if code.starts_with("ingredient.") =>
+            format!("manifest of an ingredient of {}", manifest),
+        (_, Some(label)) if label != manifest =>
+            format!("assertion {} in {}", label, manifest),
+        _ => manifest.to_owned()
+    }
+}
+
+/* Every failed validation check in the store, with what it implicates */
+pub fn 
+findings (manifest_store: &Reader) -> Vec<Finding> {
+    let Some(statuses) = manifest_store.validation_status() else {
+        return Vec::new();
+    };
+
+    statuses.iter().map(|status| Finding {
+        code: status.code().to_owned(),
+        url: status.url().map(str::to_owned),
+        explanation: status.explanation().map(str::to_owned),
+        location: describe(status.code(), status.url())
+    }).collect()
+}
@@ -0,0 +1,151 @@
+use std::io::Cursor;
+use std::str::FromStr;
+use std::sync::Mutex;
+
+use c2pa::{Builder, Ingredient};
+use image::codecs::jpeg::JpegEncoder;
+use image::{DynamicImage, ImageFormat};
+
+/*
+ * Claim and ingredient thumbnails, with the `thumbnails` feature, made the
+ * way the SDK's own `add_thumbnails` makes them but to configurable
+ * settings. The SDK's are JPEGs 1024 pixels along the long edge at quality
+ * 80, which can add more to an image than web delivery can bear. Settings
+ * are process-wide, like the pinned time in `clock`, since every manifest
+ * a run signs should get the same. Thumbnails a manifest definition gives,
+ * or a parent's manifest already carries, are kept; assets which can't be
+ * decoded as images simply get none. The SDK only labels JPEG and PNG
+ * thumbnails properly, and reads WebP ones back as application/octet-stream;
+ * `read::thumbnails` identifies them by their content.
+ */
+
+/* A format thumbnails can be written in */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ThumbnailFormat {
+    Jpeg,
+    Png,
+    // Lossless, the only WebP `image` writes
+    Webp
+}
+
+impl FromStr for ThumbnailFormat {
+    type Err = c2pa::Error;
+
+    fn from_str(s: &str) -> Result<ThumbnailFormat, c2pa::Error> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "jpeg" | "jpg" => Ok(ThumbnailFormat::Jpeg),
+            "png" => Ok(ThumbnailFormat::Png),
+            "webp" => Ok(ThumbnailFormat::Webp),
+            _ => Err(c2pa::Error::BadParam(format!("unknown thumbnail format '{}'; expected jpeg, png or webp", s)))
+        }
+    }
+}
+
+impl ThumbnailFormat {
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            ThumbnailFormat::Jpeg => "image/jpeg",
+            ThumbnailFormat::Png => "image/png",
+            ThumbnailFormat::Webp => "image/webp"
+        }
+    }
+}
+
+/* How thumbnails are made */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ThumbnailOptions {
+    // None follows the SDK: PNG for images with an alpha channel, else JPEG
+    pub format: Option<ThumbnailFormat>,
+    // JPEG quality, 1 to 100; PNG and WebP thumbnails are lossless
+    pub quality: u8,
+    // Images longer than this along either edge are scaled down to it
+    pub long_edge: u32
+}
+
+impl Default for ThumbnailOptions {
+    fn default() -> ThumbnailOptions {
+        ThumbnailOptions {
+            format: None,
+            quality: 80,
+            long_edge: 1024
+        }
+    }
+}
+
+static OPTIONS: Mutex<Option<ThumbnailOptions>> = Mutex::new(None);
+
+/* Sets how every thumbnail made from now on is made */
+pub fn 
+configure (options: ThumbnailOptions) {
+    *OPTIONS.lock().unwrap() = Some(options);
+}
+
+/* The configured thumbnail options, else the defaults */
+pub fn 
+options () -> ThumbnailOptions {
+    OPTIONS.lock().unwrap().unwrap_or_default()
+}
+
+/**
+ * A thumbnail of an asset in `format`, with its MIME type, or None if the
+ * asset isn't an image `image` can decode.
+ */
+pub fn 
+make_thumbnail (format: &str, asset: &[u8], options: &ThumbnailOptions) -> Result<Option<(&'static str, Vec<u8>)>, c2pa::Error> {
+    let Some(image) = ImageFormat::from_extension(format)
+        .or_else(|| ImageFormat::from_mime_type(format))
+        .and_then(|image_format| image::load_from_memory_with_format(asset, image_format).ok()) else {
+        return Ok(None);
+    };
+
+    let mut image = match image.width().max(image.height()) > options.long_edge {
+        true => image.thumbnail(options.long_edge, options.long_edge),
+        false => image
+    };
+    let thumbnail_format = options.format.unwrap_or(match image.color().has_alpha() {
+        true => ThumbnailFormat::Png,
+        false => ThumbnailFormat::Jpeg
+    });
+
+    let mut thumbnail = Cursor::new(Vec::new());
+    let written = match thumbnail_format {
+        ThumbnailFormat::Jpeg => DynamicImage::ImageRgb8(image.to_rgb8())
+            .write_with_encoder(JpegEncoder::new_with_quality(&mut thumbnail, options.quality)),
+        ThumbnailFormat::Png => image.write_to(&mut thumbnail, ImageFormat::Png),
+        ThumbnailFormat::Webp => {
+            // The WebP encoder only takes 8-bit samples
+            image = match image.color().has_alpha() {
+                true => DynamicImage::ImageRgba8(image.to_rgba8()),
+                false => DynamicImage::ImageRgb8(image.to_rgb8())
+            };
+            image.write_to(&mut thumbnail, ImageFormat::WebP)
+        }
+    };
+    written.map_err(|e| c2pa::Error::OtherError(Box::new(e)))?;
+
+    Ok(Some((thumbnail_format.mime_type(), thumbnail.into_inner())))
+}
+
+/* Gives a manifest a claim thumbnail of the asset it signs, unless it has one */
+pub fn 
+apply_claim_thumbnail (builder: &mut Builder, format: &str, asset: &[u8]) -> Result<(), c2pa::Error> {
+    if builder.definition.thumbnail.is_none() {
+        if let Some((mime_type, thumbnail)) = make_thumbnail(format, asset, &options())? {
+            builder.set_thumbnail(mime_type, &mut Cursor::new(thumbnail))?;
+        }
+    }
+
+    Ok(())
+}
+
+/* Gives an ingredient a thumbnail of its asset, unless its manifest provided one */
+pub fn 
+apply_ingredient_thumbnail (ingredient: &mut Ingredient, format: &str, asset: &[u8]) -> Result<(), c2pa::Error> {
+    if ingredient.thumbnail_ref().is_none() {
+        if let Some((mime_type, thumbnail)) = make_thumbnail(format, asset, &options())? {
+            ingredient.set_thumbnail(mime_type, thumbnail)?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,85 @@
+use chrono::{DateTime, FixedOffset};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::query::{assertions_matching, select};
+
+/*
+ * A single chronological history of an asset, built from the actions
+ * assertions of every manifest in its store. Actions are ordered by their
+ * `when` timestamps; those without one come last, in store order. Who did
+ * each action is taken from its `actors` when present, falling back to the
+ * issuer of the manifest's signing certificate, and the tool from its
+ * `softwareAgent`, falling back to the manifest's claim generator. Like
+ * `read`, this has no file-system dependencies.
+ */
+
+#[derive(Debug, Serialize)]
+/* One action in the history */
+pub struct TimelineEntry {
+    pub when: Option<String>,
+    pub action: String,
+    pub who: Option<String>,
+    pub tool: Option<String>,
+    pub manifest: String
+}
+
+/* An action's software agent: a plain string in actions v1, a claim generator info map in v2 */
+fn 
+software_agent (agent: &Value) -> Option<String> {
+    match agent {
+        Value::String(name) => Some(name.clone()),
+        Value::Object(info) => {
+            let name = info.get("name")?.as_str()?;
+            Some(match info.get("version").and_then(Value::as_str) {
+                Some(version) => format!("{}/{}", name, version),
+                None => name.to_owned()
+            })
+        },
+        _ => None
+    }
+}
+
+/* Names of an action's actors, by whichever of their fields identifies them */
+fn 
+actors (action: &Value) -> Option<String> {
+    let names: Vec<String> = select(action, ".actors[]").unwrap_or_default().iter()
+        .filter_map(|actor| ["name", "identifier", "@id"].iter().find_map(|key| actor.get(*key)?.as_str().map(str::to_owned)))
+        .collect();
+
+    (!names.is_empty()).then(|| names.join(", "))
+}
+
+/* Every action in the store (given as `read` prints it), oldest first */
+pub fn 
+timeline (store_json: &Value) -> Vec<TimelineEntry> {
+    let mut entries = Vec::new();
+
+    if let Some(manifests) = store_json["manifests"].as_object() {
+        for (label, manifest) in manifests {
+            let issuer = manifest["signature_info"]["issuer"].as_str();
+            let claim_generator = manifest["claim_generator"].as_str();
+
+            for assertion in assertions_matching(store_json, Some(label), "c2pa.actions*") {
+                for action in select(&assertion, ".data.actions[]").unwrap_or_default() {
+                    entries.push(TimelineEntry {
+                        when: action["when"].as_str().map(str::to_owned),
+                        action: action["action"].as_str().unwrap_or("unknown").to_owned(),
+                        who: actors(&action).or_else(|| issuer.map(str::to_owned)),
+                        tool: software_agent(&action["softwareAgent"]).or_else(|| claim_generator.map(str::to_owned)),
+                        manifest: label.clone()
+                    });
+                }
+            }
+        }
+    }
+
+    // Timestamps may carry different offsets, so compare them as instants
+    let instant = |entry: &TimelineEntry| entry.when.as_deref().and_then(|when| DateTime::<FixedOffset>::parse_from_rfc3339(when).ok());
+    entries.sort_by_key(|entry| {
+        let when = instant(entry);
+        (when.is_none(), when)
+    });
+
+    entries
+}
@@ -0,0 +1,415 @@
+use chrono::{DateTime, NaiveDateTime, Utc};
+use openssl::asn1::{Asn1Time, Asn1TimeRef};
+use openssl::cms::{CMSOptions, CmsContentInfo};
+use openssl::x509::X509;
+use serde::Serialize;
+use serde_cbor::Value;
+
+use crate::jumbf;
+use crate::trust::name_string;
+
+/*
+ * RFC 3161 timestamps countersigning manifest signatures. A signer with a
+ * TSA URL has its COSE signature timestamped, and the token carried in the
+ * signature's unprotected header, as `sigTst` (or `sigTst2` from C2PA 2.0).
+ * The token is a CMS SignedData over a TSTInfo, whose genTime is when the
+ * TSA saw the signature. So long as that falls within the signing
+ * certificate's validity, the manifest stays valid after the certificate
+ * expires: it was signed while the certificate could sign.
+ */
+
+/* The timestamp of one manifest's signature, and what it says of the signing certificate */
+#[derive(Serialize)]
+pub struct Timestamp {
+    #[serde(skip)]
+    pub manifest_label: String,
+    // Subject of the TSA certificate which signed the token
+    pub tsa: String,
+    pub time: String,
+    // Whether the token's CMS signature verifies with the TSA certificate it carries
+    pub signature_valid: bool,
+    pub signer_not_before: String,
+    pub signer_not_after: String,
+    // Whether the time falls within the signing certificate's validity
+    pub within_validity: bool,
+    pub signer_expired: bool,
+    // Whether the signature stands however long the certificate has expired
+    pub remains_valid: bool
+}
+
+fn 
+ssl_error (e: openssl::error::ErrorStack) -> c2pa::Error {
+    c2pa::Error::OtherError(Box::new(e))
+}
+
+fn 
+bad_token (what: &str) -> c2pa::Error {
+    c2pa::Error::BadParam(format!("malformed timestamp token: {}", what))
+}
+
+/* The tag, contents and what follows of the DER element at the start of `bytes` */
+fn 
+der (bytes: &[u8]) -> Result<(u8, &[u8], &[u8]), c2pa::Error> {
+    let (&tag, rest) = bytes.split_first().ok_or_else(|| bad_token("truncated"))?;
+    let (&first, rest) = rest.split_first().ok_or_else(|| bad_token("truncated"))?;
+    let (len, rest) = match first {
+        0..=0x7f => (first as usize, rest),
+        0x81..=0x84 => {
+            let count = (first & 0x7f) as usize;
+            let len = rest.get(..count).ok_or_else(|| bad_token("truncated length"))?;
+            (len.iter().fold(0, |len, b| len << 8 | *b as usize), &rest[count..])
+        },
+        _ => return Err(bad_token("unsupported length"))
+    };
+    let contents = rest.get(..len).ok_or_else(|| bad_token("truncated contents"))?;
+
+    Ok((tag, contents, &rest[len..]))
+}
+
+// A DER element: its tag, its contents, and the whole element, header included
+type Element<'a> = (u8, &'a [u8], &'a [u8]);
+
+/* The elements of a DER sequence or set's contents */
+fn 
+der_elements (mut contents: &[u8]) -> Result<Vec<Element<'_>>, c2pa::Error> {
+    let mut elements = Vec::new();
+    while !contents.is_empty() {
+        let (tag, inner, rest) = der(contents)?;
+        elements.push((tag, inner, &contents[..contents.len() - rest.len()]));
+        contents = rest;
+    }
+
+    Ok(elements)
+}
+
+/* A GeneralizedTime, as YYYYMMDDHHMMSS with optional fraction and Z, in UTC */
+fn 
+generalized_time (contents: &[u8]) -> Result<DateTime<Utc>, c2pa::Error> {
+    let text = std::str::from_utf8(contents).map_err(|_| bad_token("genTime isn't text"))?;
+    let seconds = text.get(..14).ok_or_else(|| bad_token("genTime is too short"))?;
+    NaiveDateTime::parse_from_str(seconds, "%Y%m%d%H%M%S")
+        .map(|time| time.and_utc())
+        .map_err(|_| bad_token("genTime isn't a time"))
+}
+
+fn 
+asn1_time (time: &Asn1TimeRef) -> Result<DateTime<Utc>, c2pa::Error> {
+    let diff = Asn1Time::from_unix(0).map_err(ssl_error)?.diff(time).map_err(ssl_error)?;
+    DateTime::from_timestamp(diff.days as i64 * 86400 + diff.secs as i64, 0)
+        .ok_or_else(|| c2pa::Error::BadParam("certificate time out of range".to_owned()))
+}
+
+/**
+ * The TimeStampToken of a token as C2PA carries it: the SDK keeps the TSA's
+ * whole TimeStampResp, its PKIStatusInfo then the token, where the spec
+ * has the bare token, a ContentInfo which starts with its content type.
+ */
+fn 
+time_stamp_token (val: &[u8]) -> Result<&[u8], c2pa::Error> {
+    let (_, contents, _) = der(val)?;
+    match der_elements(contents)?.as_slice() {
+        [(0x30, _, _), (0x30, _, token), ..] => Ok(token),
+        _ => Ok(val)
+    }
+}
+
+/**
+ * The genTime of a timestamp token and the certificates it carries. The
+ * token is a ContentInfo holding SignedData, whose encapsulated content is
+ * the TSTInfo.
+ */
+fn 
+parse_token (token: &[u8]) -> Result<(DateTime<Utc>, Vec<X509>), c2pa::Error> {
+    let (_, content_info, _) = der(token)?;
+    let content_info = der_elements(content_info)?;
+    let (_, explicit, _) = content_info.get(1).ok_or_else(|| bad_token("no SignedData"))?;
+    let (_, signed_data, _) = der(explicit)?;
+    let signed_data = der_elements(signed_data)?;
+
+    // version, digestAlgorithms, encapContentInfo, then [0] certificates if present
+    let (_, encap, _) = signed_data.get(2).ok_or_else(|| bad_token("no encapsulated content"))?;
+    let encap = der_elements(encap)?;
+    let (_, explicit, _) = encap.get(1).ok_or_else(|| bad_token("no TSTInfo"))?;
+    let (_, tst_info, _) = der(explicit)?;
+    let (_, tst_info, _) = der(tst_info)?;
+
+    // version, policy, messageImprint, serialNumber, genTime
+    let tst_info = der_elements(tst_info)?;
+    let gen_time = match tst_info.get(4) {
+        Some((0x18, contents, _)) => generalized_time(contents)?,
+        _ => return Err(bad_token("no genTime"))
+    };
+
+    let mut certs = Vec::new();
+    if let Some((_, contents, _)) = signed_data.iter().find(|(tag, _, _)| *tag == 0xa0) {
+        for (_, _, cert) in der_elements(contents)? {
+            certs.push(X509::from_der(cert).map_err(ssl_error)?);
+        }
+    }
+
+    Ok((gen_time, certs))
+}
+
+/* The TSA's own certificate of those a token carries: the one which issued none of the others */
+fn 
+tsa_cert (certs: &[X509]) -> Option<&X509> {
+    certs.iter().find(|cert| {
+        let subject = name_string(cert.subject_name());
+        // A certificate repeated in the token, as some TSAs do, doesn't count as issuing itself
+        !certs.iter().any(|other| name_string(other.subject_name()) != subject && name_string(other.issuer_name()) == subject)
+    })
+}
+
+/* Whether a token's CMS signature verifies with the certificates it carries, whoever issued them */
+fn 
+signature_verifies (token: &[u8]) -> bool {
+    CmsContentInfo::from_der(token)
+        .and_then(|mut cms| cms.verify(None, None, None, None, CMSOptions::NO_SIGNER_CERT_VERIFY))
+        .is_ok()
+}
+
+/* A map's value for a text key, as COSE headers and C2PA's timestamp maps are keyed */
+fn 
+entry<'a> (map: &'a Value, key: &str) -> Option<&'a Value> {
+    match map {
+        Value::Map(map) => map.get(&Value::Text(key.to_owned())),
+        _ => None
+    }
+}
+
+/* The signing certificate of a COSE_Sign1, from x5chain (label 33) in either header */
+fn 
+signing_cert (protected: &Value, unprotected: &Value) -> Result<Option<X509>, c2pa::Error> {
+    let x5chain = [protected, unprotected].into_iter().find_map(|header| match header {
+        Value::Map(map) => map.get(&Value::Integer(33)),
+        _ => None
+    });
+    let der = match x5chain {
+        Some(Value::Bytes(der)) => der,
+        Some(Value::Array(chain)) => match chain.first() {
+            Some(Value::Bytes(der)) => der,
+            _ => return Ok(None)
+        },
+        _ => return Ok(None)
+    };
+
+    Ok(Some(X509::from_der(der).map_err(ssl_error)?))
+}
+
+/**
+ * Decodes the timestamp of a COSE_Sign1 and checks it against its signing
+ * certificate, or None if it wasn't timestamped.
+ */
+fn 
+cose_timestamp (manifest_label: &str, cose: &[u8]) -> Result<Option<Timestamp>, c2pa::Error> {
+    let cose = match serde_cbor::from_slice(cose)? {
+        Value::Tag(_, cose) => *cose,
+        cose => cose
+    };
+    let Value::Array(parts) = cose else {
+        return Err(c2pa::Error::BadParam("the signature isn't a COSE_Sign1".to_owned()));
+    };
+    let protected = match parts.first() {
+        Some(Value::Bytes(bytes)) if !bytes.is_empty() => serde_cbor::from_slice(bytes)?,
+        _ => Value::Null
+    };
+    let unprotected = parts.get(1).cloned().unwrap_or(Value::Null);
+
+    let token = ["sigTst2", "sigTst"].iter()
+        .find_map(|key| entry(&unprotected, key))
+        .and_then(|timestamps| entry(timestamps, "tstTokens"))
+        .and_then(|tokens| match tokens {
+            Value::Array(tokens) => tokens.first(),
+            _ => None
+        })
+        .and_then(|token| match entry(token, "val") {
+            Some(Value::Bytes(token)) => Some(token.clone()),
+            _ => None
+        });
+    let Some(token) = token else {
+        return Ok(None);
+    };
+    let Some(signer) = signing_cert(&protected, &unprotected)? else {
+        return Err(c2pa::Error::CoseX5ChainMissing);
+    };
+
+    let token = time_stamp_token(&token)?;
+    let (time, certs) = parse_token(token)?;
+    let tsa = tsa_cert(&certs).map(|cert| name_string(cert.subject_name())).unwrap_or_else(|| "unknown".to_owned());
+    let signature_valid = signature_verifies(token);
+    let not_before = asn1_time(signer.not_before())?;
+    let not_after = asn1_time(signer.not_after())?;
+    let within_validity = not_before <= time && time <= not_after;
+
+    Ok(Some(Timestamp {
+        manifest_label: manifest_label.to_owned(),
+        tsa,
+        time: time.to_rfc3339(),
+        signature_valid,
+        signer_not_before: not_before.to_rfc3339(),
+        signer_not_after: not_after.to_rfc3339(),
+        within_validity,
+        signer_expired: not_after < Utc::now(),
+        remains_valid: signature_valid && within_validity
+    }))
+}
+
+/**
+ * The timestamp of every timestamped manifest in an asset's store. The SDK
+ * checks tokens against the signatures they cover, but only gives their
+ * time, so they are decoded from the store's JUMBF.
+ */
+pub fn 
+timestamps (format: &str, bytes: &[u8]) -> Result<Vec<Timestamp>, c2pa::Error> {
+    let jumbf = c2pa::jumbf_io::load_jumbf_from_memory(format, bytes)?;
+    let mut timestamps = Vec::new();
+
+    for manifest in jumbf::manifests(&jumbf)? {
+        let (description, children) = jumbf::superbox(&manifest)?;
+        let manifest_label = description.label.unwrap_or_default();
+        for child in children.iter().filter(|child| &child.kind == b"jumb") {
+            let (part, contents) = jumbf::superbox(child)?;
+            if part.label.as_deref() != Some("c2pa.signature") {
+                continue;
+            }
+            if let Some(cose) = contents.iter().find(|content| &content.kind == b"cbor") {
+                timestamps.extend(cose_timestamp(&manifest_label, cose.payload)?);
+            }
+        }
+    }
+
+    Ok(timestamps)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use openssl::ec::{EcGroup, EcKey};
+    use openssl::hash::MessageDigest;
+    use openssl::nid::Nid;
+    use openssl::pkey::PKey;
+    use openssl::x509::{X509Builder, X509NameBuilder};
+
+    use super::*;
+
+    /* A DER element of `tag` around `contents`, in the short or long length form */
+    fn tlv(tag: u8, contents: &[u8]) -> Vec<u8> {
+        let mut element = vec![tag];
+        match contents.len() {
+            len @ 0..=0x7f => element.push(len as u8),
+            len => {
+                let len = (len as u32).to_be_bytes();
+                let len = &len[len.iter().position(|b| *b != 0).unwrap()..];
+                element.push(0x80 | len.len() as u8);
+                element.extend(len);
+            }
+        }
+        element.extend(contents);
+        element
+    }
+
+    /* A timestamp token, unsigned and without certificates, whose TSTInfo has `gen_time` */
+    fn token(gen_time: &str) -> Vec<u8> {
+        let oid = tlv(0x06, &[0x2a, 0x03]);
+        let tst_info = tlv(0x30, &[tlv(0x02, &[1]), oid.clone(), tlv(0x30, &[]), tlv(0x02, &[7]), tlv(0x18, gen_time.as_bytes())].concat());
+        let encap = tlv(0x30, &[oid.clone(), tlv(0xa0, &tlv(0x04, &tst_info))].concat());
+        let signed_data = tlv(0x30, &[tlv(0x02, &[3]), tlv(0x31, &[]), encap, tlv(0x31, &[])].concat());
+        tlv(0x30, &[oid, tlv(0xa0, &signed_data)].concat())
+    }
+
+    /* A self-signed certificate valid from `not_before` to `not_after`, as Unix times */
+    fn certificate(not_before: i64, not_after: i64) -> X509 {
+        let key = PKey::from_ec_key(EcKey::generate(&EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap()).unwrap()).unwrap();
+        let mut name = X509NameBuilder::new().unwrap();
+        name.append_entry_by_nid(Nid::COMMONNAME, "Test Signer").unwrap();
+        let name = name.build();
+
+        let mut builder = X509Builder::new().unwrap();
+        builder.set_version(2).unwrap();
+        builder.set_subject_name(&name).unwrap();
+        builder.set_issuer_name(&name).unwrap();
+        builder.set_pubkey(&key).unwrap();
+        builder.set_not_before(&Asn1Time::from_unix(not_before).unwrap()).unwrap();
+        builder.set_not_after(&Asn1Time::from_unix(not_after).unwrap()).unwrap();
+        builder.sign(&key, MessageDigest::sha256()).unwrap();
+        builder.build()
+    }
+
+    /* A COSE_Sign1 by `signer`, carrying `token` as sigTst2 if given */
+    fn cose(signer: Option<&X509>, token: Option<Vec<u8>>) -> Vec<u8> {
+        let mut protected = BTreeMap::new();
+        if let Some(signer) = signer {
+            protected.insert(Value::Integer(33), Value::Bytes(signer.to_der().unwrap()));
+        }
+        let mut unprotected = BTreeMap::new();
+        if let Some(token) = token {
+            let token = Value::Map(BTreeMap::from([(Value::Text("val".to_owned()), Value::Bytes(token))]));
+            let timestamps = Value::Map(BTreeMap::from([(Value::Text("tstTokens".to_owned()), Value::Array(vec![token]))]));
+            unprotected.insert(Value::Text("sigTst2".to_owned()), timestamps);
+        }
+
+        let parts = vec![
+            Value::Bytes(serde_cbor::to_vec(&Value::Map(protected)).unwrap()),
+            Value::Map(unprotected),
+            Value::Null,
+            Value::Bytes(vec![0; 64])
+        ];
+        serde_cbor::to_vec(&Value::Tag(18, Box::new(Value::Array(parts)))).unwrap()
+    }
+
+    #[test]
+    fn der_lengths_are_decoded() {
+        let long = vec![7; 300];
+        assert_eq!(der(&tlv(0x04, &long)).unwrap(), (0x04, &long[..], &[][..]));
+        assert_eq!(der(&[0x02, 0x01, 0x05, 0xff]).unwrap(), (0x02, &[0x05][..], &[0xff][..]));
+
+        for truncated in [&[][..], &[0x30], &[0x30, 0x82, 0x01], &[0x30, 0x03, 0x00], &[0x30, 0x80]] {
+            assert!(matches!(der(truncated), Err(c2pa::Error::BadParam(_))), "{:?}", truncated);
+        }
+    }
+
+    #[test]
+    fn tokens_are_found_in_time_stamp_responses() {
+        let token = token("20240102030405.5Z");
+        let response = tlv(0x30, &[tlv(0x30, &tlv(0x02, &[0])), token.clone()].concat());
+
+        assert_eq!(time_stamp_token(&response).unwrap(), &token[..]);
+        assert_eq!(time_stamp_token(&token).unwrap(), &token[..]);
+    }
+
+    #[test]
+    fn gen_time_is_read_from_the_token() {
+        let (time, certs) = parse_token(&token("20240102030405.5Z")).unwrap();
+        assert_eq!(time.to_rfc3339(), "2024-01-02T03:04:05+00:00");
+        assert!(certs.is_empty());
+
+        assert!(matches!(parse_token(&token("2024")), Err(c2pa::Error::BadParam(_))));
+        assert!(matches!(parse_token(&tlv(0x30, &tlv(0x06, &[0x2a]))), Err(c2pa::Error::BadParam(_))));
+    }
+
+    #[test]
+    fn timestamp_is_checked_against_the_signing_certificate() {
+        // 2024-01-02, and a certificate valid through 2023 and another through 2024
+        let expired = certificate(1_672_531_200, 1_704_067_199);
+        let valid = certificate(1_672_531_200, 1_735_689_599);
+
+        let timestamp = cose_timestamp("urn:test", &cose(Some(&valid), Some(token("20240102030405Z")))).unwrap().unwrap();
+        assert!(timestamp.within_validity);
+        assert!(timestamp.signer_expired);
+        // Nothing signs the token, so it proves nothing
+        assert!(!timestamp.signature_valid);
+        assert!(!timestamp.remains_valid);
+        assert_eq!(timestamp.tsa, "unknown");
+
+        let timestamp = cose_timestamp("urn:test", &cose(Some(&expired), Some(token("20240102030405Z")))).unwrap().unwrap();
+        assert!(!timestamp.within_validity);
+    }
+
+    #[test]
+    fn signatures_without_tokens_have_no_timestamp() {
+        assert!(cose_timestamp("urn:test", &cose(Some(&certificate(0, 1)), None)).unwrap().is_none());
+        assert!(matches!(cose_timestamp("urn:test", &cose(None, Some(token("20240102030405Z")))), Err(c2pa::Error::CoseX5ChainMissing)));
+        assert!(matches!(cose_timestamp("urn:test", &serde_cbor::to_vec(&Value::Text("no".to_owned())).unwrap()), Err(c2pa::Error::BadParam(_))));
+    }
+}
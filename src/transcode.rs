@@ -0,0 +1,135 @@
+use std::path::Path;
+use std::process::Command;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use c2pa::assertions::c2pa_action;
+use c2pa::Signer;
+
+use crate::manifest::{parent_ingredient, record_edits, EditAction};
+use crate::storage::read_file;
+
+/*
+ * Re-encoding video with ffmpeg, which is run as a separate program and
+ * so needs to be installed, not compiled in. The transcoded video is
+ * signed with a derived manifest recording `c2pa.transcoded` with the
+ * codecs and settings of the preset, which takes the original, manifest
+ * and all, as its parent ingredient. ffmpeg drops the original's manifest
+ * store along with the rest of the container it doesn't copy.
+ */
+
+// Distinguishes the temporary files of concurrent transcodes within one process
+static TRANSCODE_FILE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/* A set of ffmpeg encoding settings */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Preset {
+    // H.264 and AAC in an MP4 which starts playing before it is fully downloaded, at most 1080 lines high
+    Web
+}
+
+impl FromStr for Preset {
+    type Err = c2pa::Error;
+
+    fn from_str(s: &str) -> Result<Preset, c2pa::Error> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "web" => Ok(Preset::Web),
+            _ => Err(c2pa::Error::BadParam(format!("unknown transcode preset '{}'; expected web", s)))
+        }
+    }
+}
+
+impl Preset {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Preset::Web => "web"
+        }
+    }
+
+    /* The format of the container the preset writes, as the SDK takes it */
+    pub fn format(&self) -> &'static str {
+        match self {
+            Preset::Web => "mp4"
+        }
+    }
+
+    /* The ffmpeg output options of the preset */
+    fn arguments(&self) -> &'static [&'static str] {
+        match self {
+            Preset::Web => &[
+                "-map", "0:v:0", "-map", "0:a:0?",
+                "-c:v", "libx264", "-preset", "medium", "-crf", "23", "-profile:v", "high", "-pix_fmt", "yuv420p",
+                "-vf", "scale=-2:'min(1080,ih)'",
+                "-c:a", "aac", "-b:a", "128k",
+                "-movflags", "+faststart",
+                "-f", "mp4"
+            ]
+        }
+    }
+
+    /* The codec parameters the preset encodes with, as recorded in the transcode action */
+    fn parameters(&self) -> serde_json::Value {
+        match self {
+            Preset::Web => serde_json::json!({
+                "container": "mp4",
+                "video": { "codec": "h264", "encoder": "libx264", "profile": "high", "crf": 23, "pixelFormat": "yuv420p", "maxHeight": 1080 },
+                "audio": { "codec": "aac", "bitrate": "128k" }
+            })
+        }
+    }
+}
+
+/* The version ffmpeg reports, e.g. "6.1.1-3ubuntu5", or an error if it can't be run */
+pub fn 
+ffmpeg_version (ffmpeg: &str) -> Result<String, c2pa::Error> {
+    let output = Command::new(ffmpeg).arg("-version").output()
+        .map_err(|e| c2pa::Error::BadParam(format!("cannot run {}: {}; install ffmpeg or pass --ffmpeg", ffmpeg, e)))?;
+    let banner = String::from_utf8_lossy(&output.stdout);
+
+    // "ffmpeg version 6.1.1-3ubuntu5 Copyright (c) ..."
+    Ok(banner.split_whitespace().nth(2).unwrap_or("unknown").to_owned())
+}
+
+/**
+ * Transcodes the local video `src_path`, in `format`, with `preset` using
+ * the `ffmpeg` program, and writes it to `dest_path` signed with a derived
+ * manifest recording the transcode against the original. Returns the
+ * action recorded.
+ */
+pub fn 
+transcode (ffmpeg: &str, src_path: &Path, dest_path: &Path, format: &str, preset: Preset, signer: &dyn Signer) -> Result<EditAction, c2pa::Error> {
+    let version = ffmpeg_version(ffmpeg)?;
+    let asset = read_file(src_path)?;
+    let parent = parent_ingredient(src_path, format, &asset)?;
+
+    let encoded_path = std::env::temp_dir().join(format!(
+        "c2pa-walkthrough-transcode-{}-{}.{}", std::process::id(), TRANSCODE_FILE_COUNTER.fetch_add(1, Ordering::Relaxed), preset.format()
+    ));
+    let result = Command::new(ffmpeg)
+        .args(["-nostdin", "-hide_banner", "-loglevel", "error", "-y", "-i"])
+        .arg(src_path)
+        .args(preset.arguments())
+        .arg(&encoded_path)
+        .output();
+    let encoded = match result {
+        Ok(output) if output.status.success() => std::fs::read(&encoded_path),
+        Ok(output) => {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            Err(std::io::Error::other(match stderr.trim() {
+                "" => format!("ffmpeg failed ({})", output.status),
+                stderr => format!("ffmpeg failed: {}", stderr)
+            }))
+        },
+        Err(e) => Err(e)
+    };
+    let _ = std::fs::remove_file(&encoded_path);
+    let encoded = encoded?;
+
+    let transcoded = EditAction::new(c2pa_action::TRANSCODED)
+        .with_parameter("preset", preset.name())?
+        .with_parameter("codecs", preset.parameters())?
+        .with_parameter("ffmpeg", version)?;
+    record_edits(parent, &encoded, dest_path, preset.format(), std::slice::from_ref(&transcoded), signer)?;
+
+    Ok(transcoded)
+}
@@ -0,0 +1,164 @@
+use std::io::Cursor;
+use std::path::Path;
+use std::str::FromStr;
+
+use c2pa::Signer;
+use image::{DynamicImage, GenericImageView, ImageFormat};
+
+use crate::manifest::{parent_ingredient, record_edits, EditAction};
+use crate::storage::read_file;
+
+/*
+ * Edits which are actually carried out on an image's pixels, with the
+ * `image-edits` feature, as opposed to `edit`, which only records that
+ * they happened. Each transform changes the image and yields the C2PA
+ * action describing it, with the parameters it was applied with; the
+ * result is re-encoded in the source's format and signed with a derived
+ * manifest taking the original as its parent ingredient.
+ */
+
+/* One operation on an image's pixels */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Transform {
+    Crop { x: u32, y: u32, width: u32, height: u32 },
+    // A missing dimension follows the aspect ratio
+    Resize { width: Option<u32>, height: Option<u32> },
+    // Clockwise, in multiples of 90 degrees
+    Rotate(u32),
+    Grayscale
+}
+
+fn 
+bad_transform (spec: &str, why: &str) -> c2pa::Error {
+    c2pa::Error::BadParam(format!("invalid transform '{}': {}", spec, why))
+}
+
+fn 
+numbers (spec: &str, list: &str, separator: char) -> Result<Vec<u32>, c2pa::Error> {
+    list.split(separator)
+        .map(|n| n.trim().parse().map_err(|_| bad_transform(spec, "expected whole numbers")))
+        .collect()
+}
+
+impl FromStr for Transform {
+    type Err = c2pa::Error;
+
+    /* `crop=x,y,w,h`, `resize=W`, `resize=xH`, `resize=WxH`, `rotate=90` or `grayscale` */
+    fn from_str(spec: &str) -> Result<Transform, c2pa::Error> {
+        let (name, args) = spec.split_once('=').unwrap_or((spec, ""));
+        match name {
+            "crop" => match numbers(spec, args, ',')?.as_slice() {
+                [x, y, width, height] if *width > 0 && *height > 0 =>
+                    Ok(Transform::Crop { x: *x, y: *y, width: *width, height: *height }),
+                _ => Err(bad_transform(spec, "crop takes x,y,width,height with a nonzero width and height"))
+            },
+            "resize" => {
+                let (width, height) = args.split_once('x').unwrap_or((args, ""));
+                let dimension = |n: &str| -> Result<Option<u32>, c2pa::Error> {
+                    match n {
+                        "" => Ok(None),
+                        n => Ok(Some(numbers(spec, n, ',')?[0]).filter(|n| *n > 0))
+                    }
+                };
+                match (dimension(width)?, dimension(height)?) {
+                    (None, None) => Err(bad_transform(spec, "resize takes a nonzero WIDTH, xHEIGHT or WIDTHxHEIGHT")),
+                    (width, height) => Ok(Transform::Resize { width, height })
+                }
+            },
+            "rotate" => match numbers(spec, args, ',')?.as_slice() {
+                [degrees] if degrees % 90 == 0 => Ok(Transform::Rotate(degrees % 360)),
+                _ => Err(bad_transform(spec, "rotate takes a multiple of 90 degrees"))
+            },
+            "grayscale" | "greyscale" if args.is_empty() => Ok(Transform::Grayscale),
+            _ => Err(bad_transform(spec, "expected crop=x,y,w,h, resize=WxH, rotate=DEGREES or grayscale"))
+        }
+    }
+}
+
+impl Transform {
+    /**
+     * Carries out the transform, returning the new image and the action
+     * recording it. Crops must lie within the image.
+     */
+    pub fn apply(&self, image: DynamicImage) -> Result<(DynamicImage, EditAction), c2pa::Error> {
+        let (width, height) = image.dimensions();
+
+        match *self {
+            Transform::Crop { x, y, width: w, height: h } => {
+                if x.checked_add(w).is_none_or(|right| right > width) || y.checked_add(h).is_none_or(|bottom| bottom > height) {
+                    return Err(c2pa::Error::BadParam(format!("crop {}x{} at ({}, {}) is outside the {}x{} image", w, h, x, y, width, height)));
+                }
+                let action = EditAction::new(c2pa::assertions::c2pa_action::CROPPED)
+                    .with_parameter("region", serde_json::json!({"x": x, "y": y, "width": w, "height": h}))?;
+                Ok((image.crop_imm(x, y, w, h), action))
+            },
+            Transform::Resize { width: w, height: h } => {
+                let (w, h) = match (w, h) {
+                    (Some(w), Some(h)) => (w, h),
+                    (Some(w), None) => (w, ((height as u64 * w as u64) / width as u64).max(1) as u32),
+                    (None, Some(h)) => (((width as u64 * h as u64) / height as u64).max(1) as u32, h),
+                    (None, None) => (width, height)
+                };
+                let action = EditAction::new(c2pa::assertions::c2pa_action::RESIZED)
+                    .with_parameter("from", serde_json::json!({"width": width, "height": height}))?
+                    .with_parameter("to", serde_json::json!({"width": w, "height": h}))?;
+                Ok((image.resize_exact(w, h, image::imageops::FilterType::Lanczos3), action))
+            },
+            Transform::Rotate(degrees) => {
+                let rotated = match degrees {
+                    90 => image.rotate90(),
+                    180 => image.rotate180(),
+                    270 => image.rotate270(),
+                    _ => image
+                };
+                let action = EditAction::new(c2pa::assertions::c2pa_action::ORIENTATION)
+                    .with_parameter("rotation", degrees)?;
+                Ok((rotated, action))
+            },
+            Transform::Grayscale => {
+                let action = EditAction::new(c2pa::assertions::c2pa_action::COLOR_ADJUSTMENTS)
+                    .with_parameter("adjustment", "grayscale")?;
+                Ok((image.grayscale(), action))
+            }
+        }
+    }
+}
+
+fn 
+image_error (e: image::ImageError) -> c2pa::Error {
+    c2pa::Error::OtherError(Box::new(e))
+}
+
+/**
+ * Carries out `transforms` in order on the image at `src_path`, and writes
+ * the result to `dest_path` in the same format, signed with a manifest
+ * recording each of them against the original. Returns the actions
+ * recorded.
+ */
+pub fn 
+apply_transforms (src_path: &Path, dest_path: &Path, format: &str, transforms: &[Transform], signer: &dyn Signer) -> Result<Vec<EditAction>, c2pa::Error> {
+    let image_format = ImageFormat::from_extension(format)
+        .or_else(|| ImageFormat::from_mime_type(format))
+        .ok_or_else(|| c2pa::Error::BadParam(format!("{} images can't be edited", format)))?;
+
+    let asset = read_file(src_path)?;
+    let parent = parent_ingredient(src_path, format, &asset)?;
+    let mut image = image::load_from_memory_with_format(&asset, image_format).map_err(image_error)?;
+
+    let mut actions = Vec::new();
+    for transform in transforms {
+        let (transformed, action) = transform.apply(image)?;
+        image = transformed;
+        actions.push(action);
+    }
+
+    // JPEGs have no alpha channel, and grayscale images stay grayscale
+    if image_format == ImageFormat::Jpeg && image.color().has_alpha() {
+        image = DynamicImage::ImageRgb8(image.to_rgb8());
+    }
+    let mut encoded = Cursor::new(Vec::new());
+    image.write_to(&mut encoded, image_format).map_err(image_error)?;
+
+    record_edits(parent, &encoded.into_inner(), dest_path, format, &actions, signer)?;
+    Ok(actions)
+}
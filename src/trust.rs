@@ -0,0 +1,91 @@
+use openssl::nid::Nid;
+use openssl::stack::Stack;
+use openssl::x509::store::X509StoreBuilder;
+use openssl::x509::{X509NameRef, X509StoreContext, X509};
+
+/*
+ * Certificate chain validation against a caller's own CA bundle, separate
+ * from the C2PA trust list. The chain is the one carried in the active
+ * manifest's COSE signature; OpenSSL builds a path from its signing
+ * certificate to a root in the bundle, using the rest of the chain as
+ * intermediates, and checks each link's signature and validity period at
+ * the current time. A failure names the certificate in the path where it
+ * happened.
+ */
+
+/* Where and why chain validation failed */
+pub struct ChainFailure {
+    // Position in the built path, 0 being the signing certificate
+    pub depth: u32,
+    pub subject: String,
+    pub reason: String
+}
+
+fn 
+ssl_error (e: openssl::error::ErrorStack) -> c2pa::Error {
+    c2pa::Error::OtherError(Box::new(e))
+}
+
+/* A certificate name as `CN=..., O=...` */
+pub fn 
+name_string (name: &X509NameRef) -> String {
+    name.entries()
+        .map(|entry| format!(
+            "{}={}",
+            entry.object().nid().short_name().unwrap_or("?"),
+            entry.data().to_string().unwrap_or_default()
+        ))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/* The common name of the signing certificate in a PEM chain, signing certificate first, if it has one */
+pub fn 
+signer_common_name (chain_pem: &[u8]) -> Option<String> {
+    let chain = X509::stack_from_pem(chain_pem).ok()?;
+    let signing_cert = chain.first()?;
+    let entry = signing_cert.subject_name().entries_by_nid(Nid::COMMONNAME).next()?;
+    entry.data().to_string().ok()
+}
+
+/**
+ * Validates a PEM certificate chain, signing certificate first, against
+ * the roots in a PEM CA bundle. Returns None if a path to one of them
+ * holds up.
+ */
+pub fn 
+verify_chain (chain_pem: &[u8], ca_bundle_pem: &[u8]) -> Result<Option<ChainFailure>, c2pa::Error> {
+    let mut chain = X509::stack_from_pem(chain_pem).map_err(ssl_error)?.into_iter();
+    let signing_cert = chain.next().ok_or_else(|| c2pa::Error::BadParam("certificate chain is empty".to_owned()))?;
+
+    let roots = X509::stack_from_pem(ca_bundle_pem).map_err(ssl_error)?;
+    if roots.is_empty() {
+        return Err(c2pa::Error::BadParam("CA bundle holds no certificates".to_owned()));
+    }
+    let mut store = X509StoreBuilder::new().map_err(ssl_error)?;
+    for root in roots {
+        store.add_cert(root).map_err(ssl_error)?;
+    }
+    let store = store.build();
+
+    let mut intermediates = Stack::new().map_err(ssl_error)?;
+    for cert in chain {
+        intermediates.push(cert).map_err(ssl_error)?;
+    }
+
+    let mut context = X509StoreContext::new().map_err(ssl_error)?;
+    context.init(&store, &signing_cert, &intermediates, |context| {
+        if context.verify_cert()? {
+            return Ok(None);
+        }
+
+        let subject = context.current_cert()
+            .map(|cert| name_string(cert.subject_name()))
+            .unwrap_or_else(|| name_string(signing_cert.subject_name()));
+        Ok(Some(ChainFailure {
+            depth: context.error_depth(),
+            subject,
+            reason: context.error().error_string().to_owned()
+        }))
+    }).map_err(ssl_error)
+}
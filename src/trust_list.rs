@@ -0,0 +1,148 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use sha2::{Digest, Sha256};
+
+use crate::storage::{read_asset, write_file_atomically};
+
+/*
+ * The published C2PA trust list, for the SDK's own trust checks. It comes
+ * as PEM bundles of trust anchors, the roots a signing certificate must
+ * chain to, alongside the Content Authenticity Initiative's known-certs
+ * list of end-entity certificates trusted on their own and its EKU
+ * configuration. Each bundle is cached under the user's cache directory and
+ * fetched again once older than a TTL; if that fails, the stale copy is
+ * used rather than failing verification offline. The SDK reads trust
+ * settings process-wide, so once applied they hold for every manifest store
+ * loaded afterwards.
+ */
+
+/* The official C2PA trust list, and the CAI's interim list of anchors from before it */
+pub const TRUST_ANCHOR_URLS: [&str; 2] = [
+    "https://raw.githubusercontent.com/c2pa-org/conformance-public/main/trust-list/C2PA-TRUST-LIST.pem",
+    "https://contentcredentials.org/trust/anchors.pem"
+];
+
+/* SHA-256 hashes of known end-entity certificates, trusted without a chain to an anchor */
+pub const ALLOWED_LIST_URL: &str = "https://contentcredentials.org/trust/allowed.sha256.txt";
+
+/* The extended key usages signing certificates may carry */
+pub const TRUST_CONFIG_URL: &str = "https://contentcredentials.org/trust/store.cfg";
+
+/* The bundles of a trust list, as handed to the SDK */
+pub struct TrustList {
+    pub anchors: String,
+    pub allowed: String,
+    pub config: String,
+    // Bundles which couldn't be refreshed, and why, their stale copies used instead
+    pub stale: Vec<(String, String)>
+}
+
+/* Where a bundle fetched from `url` is cached, named for the URL's hash */
+fn 
+cache_path (dir: &Path, url: &str) -> PathBuf {
+    let name = hex::encode(Sha256::digest(url.as_bytes()));
+    dir.join(&name[..16])
+}
+
+/* Whether a cached file was written within `ttl` */
+fn 
+is_fresh (path: &Path, ttl: Duration) -> bool {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .is_some_and(|age| age < ttl)
+}
+
+/**
+ * A bundle from `url`, from the cache in `dir` while fresher than `ttl`,
+ * else fetched and cached. If fetching fails, a stale cached copy is used
+ * and the failure recorded in `stale`; without one, the failure is returned.
+ */
+fn 
+bundle (dir: &Path, url: &str, ttl: Duration, stale: &mut Vec<(String, String)>) -> Result<String, c2pa::Error> {
+    let path = cache_path(dir, url);
+    if is_fresh(&path, ttl) {
+        return Ok(std::fs::read_to_string(&path)?);
+    }
+
+    match read_asset(url) {
+        Ok(bytes) => {
+            std::fs::create_dir_all(dir)?;
+            write_file_atomically(&path, &bytes)?;
+            Ok(String::from_utf8_lossy(&bytes).into_owned())
+        },
+        Err(e) => match std::fs::read_to_string(&path) {
+            Ok(cached) => {
+                stale.push((url.to_owned(), e.to_string()));
+                Ok(cached)
+            },
+            Err(_) => Err(c2pa::Error::BadParam(format!("fetching the trust list bundle {}: {}", url, e)))
+        }
+    }
+}
+
+/**
+ * Loads the trust list, refreshing bundles cached in `dir` for longer than
+ * `ttl`. `anchor_urls` are the PEM bundles of trust anchors, by default
+ * `TRUST_ANCHOR_URLS`, and may be any location `read_asset` takes.
+ */
+pub fn 
+load (dir: &Path, ttl: Duration, anchor_urls: &[String]) -> Result<TrustList, c2pa::Error> {
+    let mut stale = Vec::new();
+
+    let mut anchors = String::new();
+    for url in anchor_urls {
+        anchors.push_str(bundle(dir, url, ttl, &mut stale)?.trim_end());
+        anchors.push('\n');
+    }
+    let allowed = bundle(dir, ALLOWED_LIST_URL, ttl, &mut stale)?;
+    let config = bundle(dir, TRUST_CONFIG_URL, ttl, &mut stale)?;
+
+    Ok(TrustList { anchors, allowed, config, stale })
+}
+
+/**
+ * Has the SDK check signing certificates against `trust_list` when loading
+ * manifest stores from now on, reporting those it doesn't trust as
+ * `signingCredential.untrusted`.
+ */
+pub fn 
+apply (trust_list: &TrustList) -> Result<(), c2pa::Error> {
+    let settings = serde_json::json!({
+        "trust": {
+            "trust_anchors": trust_list.anchors,
+            "allowed_list": trust_list.allowed,
+            "trust_config": trust_list.config
+        },
+        "verify": {
+            "verify_trust": true
+        }
+    });
+    c2pa::settings::load_settings_from_str(&settings.to_string(), "json")
+}
+
+/**
+ * As `apply`, with trust anchors, an allowed list and a trust config each
+ * read from a path or URL as c2patool's `trust` takes them. Those not given
+ * are left at the SDK's defaults.
+ */
+pub fn 
+apply_bundles (trust_anchors: Option<&str>, allowed_list: Option<&str>, trust_config: Option<&str>) -> Result<(), c2pa::Error> {
+    let mut trust = serde_json::Map::new();
+    for (key, location) in [("trust_anchors", trust_anchors), ("allowed_list", allowed_list), ("trust_config", trust_config)] {
+        if let Some(location) = location {
+            let bundle = read_asset(location)?;
+            trust.insert(key.to_owned(), serde_json::Value::String(String::from_utf8_lossy(&bundle).into_owned()));
+        }
+    }
+
+    let settings = serde_json::json!({
+        "trust": trust,
+        "verify": {
+            "verify_trust": true
+        }
+    });
+    c2pa::settings::load_settings_from_str(&settings.to_string(), "json")
+}
@@ -0,0 +1,87 @@
+use chrono::{DateTime, FixedOffset, TimeDelta};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::query::{assertions_matching, select};
+
+/*
+ * Claimed times against trusted ones. The `when` of an action is whatever
+ * the signer's tool wrote, and can say anything; the time an RFC 3161 TSA
+ * countersigned the signature is attested by a third party. Together they
+ * give a window each manifest's actions must fall in: no later than its
+ * own trusted time, since the actions were signed by then, and no earlier
+ * than its parent's, since they were done to a version signed at that
+ * time. TSAs give whole seconds, and the signer's clock may be a little
+ * off theirs, so the window is widened by SKEW at either end. Like `read`,
+ * this has no file-system dependencies.
+ */
+
+// Leeway for the signer's clock against the TSA's
+const SKEW: TimeDelta = TimeDelta::minutes(1);
+
+/* What one manifest claims about when its actions happened, and what its timestamps attest */
+#[derive(Clone, Debug, Serialize)]
+pub struct ManifestTimes {
+    #[serde(skip)]
+    pub manifest_label: String,
+    // The `when` of each of its actions
+    pub claimed: Vec<String>,
+    // When a TSA countersigned its signature, if a token which verifies says so
+    pub trusted: Option<String>,
+    // The trusted time of its parent ingredient's manifest
+    pub not_before: Option<String>,
+    // Claimed times outside the window the trusted times allow
+    pub outside_window: Vec<String>
+}
+
+fn 
+instant (time: &str) -> Option<DateTime<FixedOffset>> {
+    DateTime::parse_from_rfc3339(time).ok()
+}
+
+/* The TSA time of a manifest, as `read` prints it, if its token verifies */
+fn 
+trusted_time (manifest: &Value) -> Option<String> {
+    let timestamp = &manifest["timestamp"];
+    match timestamp["signature_valid"].as_bool() {
+        Some(true) => timestamp["time"].as_str().map(str::to_owned),
+        _ => None
+    }
+}
+
+/**
+ * The claimed and trusted times of each manifest in a store, as `read`
+ * prints it with decoded timestamps, leaving out manifests with neither.
+ */
+pub fn 
+manifest_times (store_json: &Value) -> Vec<ManifestTimes> {
+    let Some(manifests) = store_json["manifests"].as_object() else {
+        return Vec::new();
+    };
+
+    manifests.iter()
+        .filter_map(|(label, manifest)| {
+            let claimed: Vec<String> = assertions_matching(store_json, Some(label), "c2pa.actions*").iter()
+                .flat_map(|assertion| select(assertion, ".data.actions[].when").unwrap_or_default())
+                .filter_map(|when| when.as_str().map(str::to_owned))
+                .collect();
+            let trusted = trusted_time(manifest);
+            if claimed.is_empty() && trusted.is_none() {
+                return None;
+            }
+
+            let not_before = manifest["ingredients"].as_array().into_iter().flatten()
+                .find(|ingredient| ingredient["relationship"] == "parentOf")
+                .and_then(|parent| parent["active_manifest"].as_str())
+                .and_then(|parent| trusted_time(manifests.get(parent)?));
+            let earliest = not_before.as_deref().and_then(instant).map(|earliest| earliest - SKEW);
+            let latest = trusted.as_deref().and_then(instant).map(|latest| latest + SKEW);
+            let outside_window = claimed.iter()
+                .filter(|when| instant(when).is_some_and(|when| earliest.is_some_and(|earliest| when < earliest) || latest.is_some_and(|latest| when > latest)))
+                .cloned()
+                .collect();
+
+            Some(ManifestTimes { manifest_label: label.clone(), claimed, trusted, not_before, outside_window })
+        })
+        .collect()
+}
@@ -0,0 +1,289 @@
+use std::sync::Mutex;
+
+use base64::Engine;
+use c2pa::{Signer, SigningAlg};
+use c2pa_crypto::cose::{self, TimeStampStorage};
+use c2pa_crypto::raw_signature::{RawSigner, RawSignerError};
+use c2pa_crypto::time_stamp::TimeStampProvider;
+use openssl::bn::BigNum;
+use openssl::ecdsa::EcdsaSig;
+use openssl::x509::X509;
+use serde::{Deserialize, Serialize};
+
+use crate::clock;
+use crate::providers;
+use crate::read;
+use crate::sign::{self, SignerConfig};
+
+/*
+ * Two-phase signing, for organizations which require dual control over
+ * their publication keys. `prepare` embeds the manifest with a placeholder
+ * where the claim signature goes, and hands out the COSE to-be-signed
+ * payload. The key holders sign that payload however their policy demands,
+ * by each approving a release from an HSM or by combining threshold
+ * signature shares into one ordinary signature, and `finalize` writes the
+ * assembled signature over the placeholder. The placeholder is exactly the
+ * signature's length, so no hash in the manifest changes.
+ *
+ * V1 claims are timestamped over the payload rather than the signature, so
+ * the signer's TSA is asked for its timestamp during `prepare`.
+ */
+
+/* What `finalize` needs to know about a prepared asset */
+#[derive(Serialize, Deserialize)]
+pub struct Prepared {
+    pub format: String,
+    pub alg: String,
+    // The signing certificate chain, PEM
+    pub cert_chain: String,
+    // The COSE Sig_structure to sign, base64
+    pub payload: String,
+    // The bytes standing in for the signature in the prepared asset, hex
+    pub placeholder: String
+}
+
+/* A raw signer which records what it is asked to sign and answers with a placeholder */
+struct PlaceholderSigner {
+    alg: SigningAlg,
+    certs: Vec<Vec<u8>>,
+    tsa_url: Option<String>,
+    // Where the claim's timestamp goes, which depends on its version
+    time_stamp_storage: TimeStampStorage,
+    placeholder: Vec<u8>,
+    payload: Mutex<Option<Vec<u8>>>
+}
+
+impl RawSigner for PlaceholderSigner {
+    fn sign(&self, data: &[u8]) -> Result<Vec<u8>, RawSignerError> {
+        *self.payload.lock().unwrap_or_else(|e| e.into_inner()) = Some(data.to_vec());
+        Ok(self.placeholder.clone())
+    }
+
+    fn alg(&self) -> SigningAlg {
+        self.alg
+    }
+
+    fn cert_chain(&self) -> Result<Vec<Vec<u8>>, RawSignerError> {
+        Ok(self.certs.clone())
+    }
+
+    fn reserve_size(&self) -> usize {
+        // As the SDK's own signers: room for the chain, the signature and a timestamp
+        1024 + self.certs.iter().map(Vec::len).sum::<usize>() + self.tsa_url.as_ref().map_or(0, |_| 10000)
+    }
+}
+
+impl TimeStampProvider for PlaceholderSigner {
+    fn time_stamp_service_url(&self) -> Option<String> {
+        self.tsa_url.clone()
+    }
+}
+
+/**
+ * Builds the COSE signature itself, so the SDK takes it as it is. The SDK
+ * checks each signature it builds, which a placeholder can't pass; switching
+ * that check off is a global setting, which would race other signing.
+ */
+impl Signer for PlaceholderSigner {
+    fn sign(&self, claim: &[u8]) -> c2pa::Result<Vec<u8>> {
+        Ok(cose::sign(self, claim, Some(RawSigner::reserve_size(self)), self.time_stamp_storage)?)
+    }
+
+    fn alg(&self) -> SigningAlg {
+        self.alg
+    }
+
+    fn certs(&self) -> c2pa::Result<Vec<Vec<u8>>> {
+        Ok(self.certs.clone())
+    }
+
+    fn reserve_size(&self) -> usize {
+        RawSigner::reserve_size(self)
+    }
+
+    fn direct_cose_handling(&self) -> bool {
+        true
+    }
+}
+
+fn 
+ssl_error (e: openssl::error::ErrorStack) -> c2pa::Error {
+    c2pa::Error::OtherError(Box::new(e))
+}
+
+/* The length of a raw `alg` signature made with `cert`'s key: r || s for ECDSA */
+fn 
+signature_len (alg: SigningAlg, cert: &X509) -> Result<usize, c2pa::Error> {
+    match alg {
+        SigningAlg::Es256 | SigningAlg::Ed25519 => Ok(64),
+        SigningAlg::Es384 => Ok(96),
+        SigningAlg::Es512 => Ok(132),
+        SigningAlg::Ps256 | SigningAlg::Ps384 | SigningAlg::Ps512 => Ok(cert.public_key().map_err(ssl_error)?.size())
+    }
+}
+
+/**
+ * Embeds the manifest `manifest_json` defines in `asset`, signed by
+ * `signer_config`'s certificate chain but with a placeholder for the
+ * signature, returning the prepared asset and what `finalize` needs. The
+ * private key is never read.
+ */
+pub fn 
+prepare (format: &str, asset: &[u8], manifest_json: &str, signer_config: &SignerConfig) -> Result<(Vec<u8>, Prepared), c2pa::Error> {
+    let cert_chain = std::fs::read(&signer_config.signcert_path)?;
+    let certs = X509::stack_from_pem(&cert_chain).map_err(ssl_error)?;
+    let signing_cert = certs.first()
+        .ok_or_else(|| c2pa::Error::BadParam(format!("no certificates in {}", signer_config.signcert_path)))?;
+
+    let mut placeholder = vec![0u8; signature_len(signer_config.alg, signing_cert)?];
+    openssl::rand::rand_bytes(&mut placeholder).map_err(ssl_error)?;
+    // Never a DER SEQUENCE, which the SDK would take for a DER ECDSA signature and convert
+    placeholder[0] = 0;
+
+    let mut builder = sign::builder_from_json(manifest_json)?;
+    clock::apply_instance_id(&mut builder);
+    sign::apply_claim_version(&mut builder);
+    providers::apply_providers(&mut builder, format, asset)?;
+    #[cfg(feature = "thumbnails")]
+    crate::thumbnail::apply_claim_thumbnail(&mut builder, format, asset)?;
+
+    let signer = PlaceholderSigner {
+        alg: signer_config.alg,
+        certs: certs.iter().map(|cert| cert.to_der()).collect::<Result<_, _>>().map_err(ssl_error)?,
+        tsa_url: signer_config.tsa_url.clone(),
+        time_stamp_storage: match builder.definition.claim_version {
+            Some(version) if version > 1 => TimeStampStorage::V2_sigTst2_CTT,
+            _ => TimeStampStorage::V1_sigTst
+        },
+        placeholder: placeholder.clone(),
+        payload: Mutex::new(None)
+    };
+    let prepared = sign::embed(format, asset, &mut builder, &signer)?;
+
+    let payload = signer.payload.into_inner().unwrap_or_else(|e| e.into_inner())
+        .ok_or_else(|| c2pa::Error::BadParam("the manifest was embedded without being signed".to_owned()))?;
+    if occurrences(&prepared, &placeholder) != 1 {
+        return Err(c2pa::Error::BadParam("the signature placeholder isn't unique in the prepared asset".to_owned()));
+    }
+
+    Ok((prepared, Prepared {
+        format: format.to_owned(),
+        alg: signer_config.alg.to_string(),
+        cert_chain: String::from_utf8_lossy(&cert_chain).into_owned(),
+        payload: base64::engine::general_purpose::STANDARD.encode(&payload),
+        placeholder: hex::encode(&placeholder)
+    }))
+}
+
+fn 
+occurrences (haystack: &[u8], needle: &[u8]) -> usize {
+    haystack.windows(needle.len()).filter(|window| *window == needle).count()
+}
+
+impl Prepared {
+    /* The payload the key holders sign */
+    pub fn payload(&self) -> Result<Vec<u8>, c2pa::Error> {
+        base64::engine::general_purpose::STANDARD.decode(&self.payload)
+            .map_err(|e| c2pa::Error::BadParam(format!("prepared payload: {}", e)))
+    }
+
+    /**
+     * `signature` as the COSE signature: as is when it is already the
+     * raw length, while DER ECDSA signatures, as `openssl dgst -sign` and
+     * most HSMs give, become r || s.
+     */
+    fn raw_signature(&self, signature: &[u8]) -> Result<Vec<u8>, c2pa::Error> {
+        let placeholder_len = self.placeholder.len() / 2;
+        if signature.len() == placeholder_len {
+            return Ok(signature.to_vec());
+        }
+        let alg: SigningAlg = self.alg.parse()
+            .map_err(|_| c2pa::Error::BadParam(format!("'{}' isn't a signing algorithm", self.alg)))?;
+        let wrong_length = || c2pa::Error::BadParam(format!("the signature is {} bytes; {} takes {}", signature.len(), self.alg, placeholder_len));
+
+        match alg {
+            SigningAlg::Es256 | SigningAlg::Es384 | SigningAlg::Es512 => {
+                let signature = EcdsaSig::from_der(signature).map_err(|_| wrong_length())?;
+                let half = (placeholder_len / 2) as i32;
+                let r = signature.r().to_vec_padded(half).map_err(|_| wrong_length())?;
+                let s = signature.s().to_vec_padded(half).map_err(|_| wrong_length())?;
+                Ok([r, s].concat())
+            },
+            _ => Err(wrong_length())
+        }
+    }
+}
+
+/* Whether `signature`, raw r || s, verifies over `payload` with `cert`'s key */
+fn 
+verifies (alg: SigningAlg, cert: &X509, payload: &[u8], signature: &[u8]) -> Result<bool, c2pa::Error> {
+    use openssl::hash::MessageDigest;
+    use openssl::rsa::Padding;
+    use openssl::sign::{RsaPssSaltlen, Verifier};
+
+    let key = cert.public_key().map_err(ssl_error)?;
+    let digest = match alg {
+        SigningAlg::Es256 | SigningAlg::Ps256 => MessageDigest::sha256(),
+        SigningAlg::Es384 | SigningAlg::Ps384 => MessageDigest::sha384(),
+        SigningAlg::Es512 | SigningAlg::Ps512 => MessageDigest::sha512(),
+        SigningAlg::Ed25519 => {
+            let mut verifier = Verifier::new_without_digest(&key).map_err(ssl_error)?;
+            return verifier.verify_oneshot(signature, payload).map_err(ssl_error);
+        }
+    };
+
+    match alg {
+        SigningAlg::Es256 | SigningAlg::Es384 | SigningAlg::Es512 => {
+            let (r, s) = signature.split_at(signature.len() / 2);
+            let signature = EcdsaSig::from_private_components(BigNum::from_slice(r).map_err(ssl_error)?, BigNum::from_slice(s).map_err(ssl_error)?)
+                .map_err(ssl_error)?;
+            let key = key.ec_key().map_err(ssl_error)?;
+            let hash = openssl::hash::hash(digest, payload).map_err(ssl_error)?;
+            Ok(signature.verify(&hash, &key).unwrap_or(false))
+        },
+        _ => {
+            let mut verifier = Verifier::new(digest, &key).map_err(ssl_error)?;
+            verifier.set_rsa_padding(Padding::PKCS1_PSS).map_err(ssl_error)?;
+            verifier.set_rsa_pss_saltlen(RsaPssSaltlen::DIGEST_LENGTH).map_err(ssl_error)?;
+            verifier.set_rsa_mgf1_md(digest).map_err(ssl_error)?;
+            verifier.update(payload).map_err(ssl_error)?;
+            Ok(verifier.verify(signature).unwrap_or(false))
+        }
+    }
+}
+
+/**
+ * Writes `signature` over the placeholder in `prepared_asset`, returning
+ * the finished asset once its manifest store validates. The signature must
+ * verify over the prepared payload with the signing certificate's key, so
+ * a wrong share or payload is caught before anything is published.
+ */
+pub fn 
+finalize (prepared_asset: &[u8], prepared: &Prepared, signature: &[u8]) -> Result<Vec<u8>, c2pa::Error> {
+    let placeholder = hex::decode(&prepared.placeholder)
+        .map_err(|e| c2pa::Error::BadParam(format!("prepared placeholder: {}", e)))?;
+    let alg: SigningAlg = prepared.alg.parse()
+        .map_err(|_| c2pa::Error::BadParam(format!("'{}' isn't a signing algorithm", prepared.alg)))?;
+    let cert = X509::from_pem(prepared.cert_chain.as_bytes()).map_err(ssl_error)?;
+
+    let signature = prepared.raw_signature(signature)?;
+    if !verifies(alg, &cert, &prepared.payload()?, &signature)? {
+        return Err(c2pa::Error::BadParam("the signature doesn't verify over the prepared payload with the signing certificate".to_owned()));
+    }
+
+    let start = prepared_asset.windows(placeholder.len()).position(|window| window == placeholder.as_slice())
+        .ok_or_else(|| c2pa::Error::BadParam("the asset has no signature placeholder; is it the prepared one, and not already finalized?".to_owned()))?;
+    let mut finished = prepared_asset.to_vec();
+    finished[start..start + placeholder.len()].copy_from_slice(&signature);
+
+    let manifest_store = read::load_manifest_store(&prepared.format, &finished)?;
+    let errors = read::validation_errors(&manifest_store);
+    if !errors.is_empty() {
+        return Err(c2pa::Error::BadParam(format!("the finalized asset doesn't validate: {}", errors.join("; "))));
+    }
+    if !read::signature_validated(&manifest_store) {
+        return Err(c2pa::Error::BadParam("the finalized asset's claim signature wasn't validated".to_owned()));
+    }
+
+    Ok(finished)
+}
@@ -0,0 +1,178 @@
+/*
+ * Webhook notifications of signing operations, so digital asset management
+ * systems can track the provenance this process issues. Once a webhook is
+ * set with `configure`, each signed asset is announced by POSTing a JSON
+ * event to it:
+ *
+ *     {"event": "c2pa.signed", "signed_at": "2026-10-15T12:00:00+00:00", "format": "jpg",
+ *      "source": "photo.jpg", "output": "signed/photo.jpg",
+ *      "input_sha256": "…", "output_sha256": "…", "manifest_label": "urn:uuid:…",
+ *      "signer": {"subject": "CN=…", "issuer": "CN=…", "serial_number": "…", "fingerprint": "…"}}
+ *
+ * Failed deliveries, whether the request can't be made or the webhook
+ * answers 429 or 5xx, are retried with exponential backoff. An event which
+ * still can't be delivered is reported on stderr; the asset stays signed.
+ */
+
+use std::sync::Mutex;
+use std::time::Duration;
+
+use openssl::x509::X509;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::read::load_manifest_store;
+
+// The webhook signing events are sent to, once configured
+static WEBHOOK: Mutex<Option<Webhook>> = Mutex::new(None);
+
+// Wait before the first retry, doubled for each one after
+const FIRST_BACKOFF: Duration = Duration::from_secs(1);
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Clone)]
+pub struct Webhook {
+    pub url: String,
+    // Attempts after the first before an event is given up on
+    pub retries: u32
+}
+
+/* The certificate which signed a manifest */
+#[derive(Serialize)]
+pub struct EventSigner {
+    pub subject: String,
+    pub issuer: String,
+    pub serial_number: String,
+    // SHA-256 of its DER encoding, as the audit log records it
+    pub fingerprint: String
+}
+
+/* One signed asset, as announced to the webhook */
+#[derive(Serialize)]
+pub struct SigningEvent {
+    pub event: &'static str,
+    pub signed_at: String,
+    pub format: String,
+    // Where the asset was read from, when it came from a location rather than a request body
+    pub source: Option<String>,
+    // Where the signed asset was written, when it was written rather than returned
+    pub output: Option<String>,
+    pub input_sha256: String,
+    pub output_sha256: String,
+    pub manifest_label: Option<String>,
+    pub signer: Option<EventSigner>
+}
+
+/* Sends every signing operation in this process to `webhook` from now on */
+pub fn 
+configure (webhook: Webhook) {
+    *WEBHOOK.lock().unwrap() = Some(webhook);
+}
+
+fn 
+names (name: &openssl::x509::X509NameRef) -> String {
+    name.entries()
+        .map(|entry| format!("{}={}", entry.object().nid().short_name().unwrap_or("?"), String::from_utf8_lossy(entry.data().as_slice())))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+/* The signing certificate, first in the PEM chain of a manifest's signature */
+fn 
+event_signer (cert_chain: &str) -> Option<EventSigner> {
+    let cert = X509::from_pem(cert_chain.as_bytes()).ok()?;
+    Some(EventSigner {
+        subject: names(cert.subject_name()),
+        issuer: names(cert.issuer_name()),
+        serial_number: cert.serial_number().to_bn().ok()?.to_dec_str().ok()?.to_string(),
+        fingerprint: hex::encode(Sha256::digest(cert.to_der().ok()?))
+    })
+}
+
+/**
+ * The event for signing `input` into `output`, whose manifest label and
+ * signer are read back from the signed asset.
+ */
+pub fn 
+signing_event (format: &str, input: &[u8], output: &[u8], source: Option<&str>, output_location: Option<&str>) -> SigningEvent {
+    let manifest_store = load_manifest_store(format, output).ok();
+    let manifest = manifest_store.as_ref().and_then(|store| store.active_manifest());
+
+    SigningEvent {
+        event: "c2pa.signed",
+        signed_at: chrono::Utc::now().to_rfc3339(),
+        format: format.to_owned(),
+        source: source.map(str::to_owned),
+        output: output_location.map(str::to_owned),
+        input_sha256: hex::encode(Sha256::digest(input)),
+        output_sha256: hex::encode(Sha256::digest(output)),
+        manifest_label: manifest_store.as_ref().and_then(|store| store.active_label()).map(str::to_owned),
+        signer: manifest.and_then(|manifest| manifest.signature_info()).and_then(|info| event_signer(info.cert_chain()))
+    }
+}
+
+/* POSTs `event` to `webhook`, retrying failures, and returns the last error if every attempt fails */
+pub fn 
+deliver (webhook: &Webhook, event: &SigningEvent) -> Result<(), c2pa::Error> {
+    let body = serde_json::to_string(event)?;
+    let mut backoff = FIRST_BACKOFF;
+
+    for attempt in 0..=webhook.retries {
+        if attempt > 0 {
+            std::thread::sleep(backoff);
+            backoff *= 2;
+        }
+        let response = ureq::post(&webhook.url)
+            .timeout(TIMEOUT)
+            .set("Content-Type", "application/json")
+            .send_string(&body);
+        match response {
+            Ok(_) => return Ok(()),
+            // The webhook refused the event itself, which sending it again won't change
+            Err(ureq::Error::Status(status, _)) if status != 429 && status < 500 => {
+                return Err(c2pa::Error::BadParam(format!("{} refused the event with status {}", webhook.url, status)));
+            },
+            Err(e) if attempt == webhook.retries => {
+                return Err(c2pa::Error::BadParam(format!("{} attempts failed, the last with: {}", webhook.retries + 1, e)));
+            },
+            Err(_) => ()
+        }
+    }
+
+    Ok(())
+}
+
+/**
+ * Announces the signing of `input` into `output` to the configured
+ * webhook, if there is one, reporting an event which can't be delivered on
+ * stderr. `source` and `output_location` are where the asset was read from
+ * and written to, when known.
+ */
+pub fn 
+notify (format: &str, input: &[u8], output: &[u8], source: Option<&str>, output_location: Option<&str>) {
+    let Some(webhook) = WEBHOOK.lock().unwrap().clone() else {
+        return;
+    };
+
+    let event = signing_event(format, input, output, source, output_location);
+    if let Err(e) = deliver(&webhook, &event) {
+        let signed = event.output.as_deref().or(event.manifest_label.as_deref()).unwrap_or("a signed asset");
+        eprintln!("webhook: couldn't announce {}: {}", signed, e);
+    }
+}
+
+/**
+ * As `notify`, on a thread of its own, for servers which answer the
+ * request without waiting for the webhook. Signed assets returned rather
+ * than written have no output location.
+ */
+pub fn 
+notify_in_background (format: &str, input: &[u8], output: &[u8], source: Option<&str>) {
+    if WEBHOOK.lock().unwrap().is_none() {
+        return;
+    }
+
+    let (format, input, output, source) = (format.to_owned(), input.to_vec(), output.to_vec(), source.map(str::to_owned));
+    std::thread::spawn(move || notify(&format, &input, &output, source.as_deref(), None));
+}
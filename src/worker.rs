@@ -0,0 +1,291 @@
+use std::path::{Path, PathBuf};
+
+use c2pa::Signer;
+use serde::Deserialize;
+
+use crate::metrics;
+use crate::sign::{sign_asset_with_signer, SignerConfig};
+use crate::storage::{format_from_location, is_remote, read_asset_at_most, write_asset};
+use crate::webhook;
+
+#[derive(Deserialize)]
+/*
+ * A signing job, as the JSON body of a queue message:
+ * `{"source": "https://.../in.jpg", "destination": "https://.../out.jpg", "manifest": {...}}`
+ * `format` defaults to the source extension, `manifest` to the worker's default definition.
+ */
+pub struct Job {
+    pub source: String,
+    pub destination: String,
+    #[serde(default)]
+    pub format: Option<String>,
+    #[serde(default)]
+    pub manifest: Option<serde_json::Value>
+}
+
+/**
+ * What jobs may ask of the worker. Whoever can publish to the queue picks
+ * each job's locations, so by default they must be remote: a local path
+ * would let a job read any file the worker can, such as its own keys, or
+ * overwrite one. Local paths are only taken under `local_dirs`.
+ */
+#[derive(Clone, Debug, Default)]
+pub struct JobPolicy {
+    // Directories jobs may read sources from and write destinations to, canonicalized
+    pub local_dirs: Vec<PathBuf>,
+    // Largest source asset a job may have downloaded, in bytes
+    pub max_size: u64
+}
+
+impl JobPolicy {
+    pub fn new(local_dirs: &[PathBuf], max_size: u64) -> Result<JobPolicy, c2pa::Error> {
+        let local_dirs = local_dirs.iter()
+            .map(|dir| std::fs::canonicalize(dir).map_err(|e| c2pa::Error::BadParam(format!("{}: {}", dir.display(), e))))
+            .collect::<Result<Vec<PathBuf>, c2pa::Error>>()?;
+
+        Ok(JobPolicy { local_dirs, max_size })
+    }
+
+    /* Refuses a job's source or destination if it is a local path outside `local_dirs` */
+    fn check(&self, location: &str) -> Result<(), c2pa::Error> {
+        if is_remote(location) {
+            return Ok(());
+        }
+        if self.local_dirs.is_empty() {
+            return Err(c2pa::Error::BadParam(format!("{} is a local path; jobs may only name remote locations without --allow-local-dir", location)));
+        }
+
+        // A destination needn't exist yet, so the directory holding it is resolved instead
+        let path = Path::new(location);
+        let resolved = std::fs::canonicalize(path).ok().or_else(|| {
+            let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+            Some(std::fs::canonicalize(dir).ok()?.join(path.file_name()?))
+        });
+        match resolved {
+            Some(resolved) if self.local_dirs.iter().any(|dir| resolved.starts_with(dir)) => Ok(()),
+            _ => Err(c2pa::Error::BadParam(format!("{} is outside the directories jobs may use", location)))
+        }
+    }
+}
+
+/* Where the worker consumes jobs from, parsed from a `--queue` URL */
+pub enum QueueConfig {
+    // amqp[s]://user:pass@host:port/vhost, consuming the named queue
+    Amqp { url: String, queue: String },
+    // kafka://host:port[,host:port...], consuming the topic as a consumer group member
+    Kafka { brokers: Vec<String>, topic: String, group: String }
+}
+
+impl QueueConfig {
+    pub fn from_url(url: &str, topic: &str, group: &str) -> Result<QueueConfig, c2pa::Error> {
+        if url.starts_with("amqp://") || url.starts_with("amqps://") {
+            Ok(QueueConfig::Amqp { url: url.to_owned(), queue: topic.to_owned() })
+        } else if let Some(brokers) = url.strip_prefix("kafka://") {
+            Ok(QueueConfig::Kafka {
+                brokers: brokers.split(',').map(str::to_owned).collect(),
+                topic: topic.to_owned(),
+                group: group.to_owned()
+            })
+        } else {
+            Err(c2pa::Error::BadParam(format!("unsupported queue URL '{}'; expected amqp://, amqps:// or kafka://", url)))
+        }
+    }
+}
+
+#[cfg(any(feature = "amqp", feature = "kafka"))]
+fn 
+queue_error<E: std::error::Error + Send + Sync + 'static> (e: E) -> c2pa::Error {
+    c2pa::Error::OtherError(Box::new(e))
+}
+
+/**
+ * Downloads the job's source asset, signs it and uploads the result to the
+ * job's destination, once `policy` allows both.
+ */
+pub fn 
+process_job (message: &[u8], default_manifest: &str, signer: &dyn Signer, policy: &JobPolicy) -> Result<(), c2pa::Error> {
+    let job: Job = serde_json::from_slice(message)?;
+    policy.check(&job.source)?;
+    policy.check(&job.destination)?;
+
+    let format = job.format.clone()
+        .or_else(|| format_from_location(&job.source))
+        .ok_or_else(|| c2pa::Error::BadParam(format!("cannot determine the format of {}", job.source)))?;
+    let manifest_json = match &job.manifest {
+        Some(manifest) => manifest.to_string(),
+        None => default_manifest.to_owned()
+    };
+
+    let asset = read_asset_at_most(&job.source, policy.max_size)?;
+    let signed = metrics::timed("sign", || sign_asset_with_signer(&format, &asset, &manifest_json, signer))?;
+    write_asset(&job.destination, &signed)?;
+    webhook::notify(&format, &asset, &signed, Some(&job.source), Some(&job.destination));
+
+    Ok(())
+}
+
+#[cfg(feature = "amqp")]
+fn 
+run_amqp (url: &str, queue_name: &str, default_manifest: &str, signer: &dyn Signer, policy: &JobPolicy) -> Result<(), c2pa::Error> {
+    use amiquip::{Connection, ConsumerMessage, ConsumerOptions, QueueDeclareOptions};
+
+    let mut connection = Connection::insecure_open(url).map_err(queue_error)?;
+    let channel = connection.open_channel(None).map_err(queue_error)?;
+    let queue = channel.queue_declare(queue_name, QueueDeclareOptions { durable: true, ..QueueDeclareOptions::default() })
+        .map_err(queue_error)?;
+    let consumer = queue.consume(ConsumerOptions::default()).map_err(queue_error)?;
+
+    for message in consumer.receiver().iter() {
+        match message {
+            ConsumerMessage::Delivery(delivery) => {
+                match process_job(&delivery.body, default_manifest, signer, policy) {
+                    Ok(()) => consumer.ack(delivery).map_err(queue_error)?,
+                    Err(e) => {
+                        // Rejected without requeueing, so a dead-letter exchange (if any) gets the job
+                        eprintln!("job failed: {}", e);
+                        consumer.reject(delivery, false).map_err(queue_error)?;
+                    }
+                }
+            },
+            other => {
+                eprintln!("consumer ended: {:?}", other);
+                break;
+            }
+        }
+    }
+
+    connection.close().map_err(queue_error)
+}
+
+#[cfg(not(feature = "amqp"))]
+fn 
+run_amqp (_url: &str, _queue_name: &str, _default_manifest: &str, _signer: &dyn Signer, _policy: &JobPolicy) -> Result<(), c2pa::Error> {
+    Err(c2pa::Error::BadParam("AMQP queues require building with --features amqp".to_owned()))
+}
+
+#[cfg(feature = "kafka")]
+fn 
+run_kafka (brokers: &[String], topic: &str, group: &str, default_manifest: &str, signer: &dyn Signer, policy: &JobPolicy) -> Result<(), c2pa::Error> {
+    use kafka::consumer::{Consumer, FetchOffset, GroupOffsetStorage};
+
+    let mut consumer = Consumer::from_hosts(brokers.to_vec())
+        .with_topic(topic.to_owned())
+        .with_group(group.to_owned())
+        .with_fallback_offset(FetchOffset::Earliest)
+        .with_offset_storage(Some(GroupOffsetStorage::Kafka))
+        .create()
+        .map_err(queue_error)?;
+
+    loop {
+        for message_set in consumer.poll().map_err(queue_error)?.iter() {
+            for message in message_set.messages() {
+                // Kafka has no per-message reject; failed jobs are logged and
+                // committed past so one bad job can't stall the partition
+                if let Err(e) = process_job(message.value, default_manifest, signer, policy) {
+                    eprintln!("job at {}:{}@{} failed: {}", topic, message_set.partition(), message.offset, e);
+                }
+            }
+
+            consumer.consume_messageset(message_set).map_err(queue_error)?;
+        }
+
+        consumer.commit_consumed().map_err(queue_error)?;
+    }
+}
+
+#[cfg(not(feature = "kafka"))]
+fn 
+run_kafka (_brokers: &[String], _topic: &str, _group: &str, _default_manifest: &str, _signer: &dyn Signer, _policy: &JobPolicy) -> Result<(), c2pa::Error> {
+    Err(c2pa::Error::BadParam("Kafka queues require building with --features kafka".to_owned()))
+}
+
+/**
+ * Consumes signing jobs from the configured queue until it closes, acking
+ * each message once its signed asset has been uploaded, and failing jobs
+ * `policy` refuses. The signer is constructed once for the lifetime of the
+ * worker.
+ */
+pub fn 
+run (queue: &QueueConfig, default_manifest: &str, signer_config: &SignerConfig, policy: &JobPolicy) -> Result<(), c2pa::Error> {
+    let signer = signer_config.create_signer()?;
+
+    match queue {
+        QueueConfig::Amqp { url, queue } => run_amqp(url, queue, default_manifest, &*signer, policy),
+        QueueConfig::Kafka { brokers, topic, group } => run_kafka(brokers, topic, group, default_manifest, &*signer, policy)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /* An empty directory of the test's own, canonicalized as `JobPolicy::new` leaves allowed directories */
+    fn scratch_dir(test: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("c2pa-walkthrough-worker-{}-{}", test, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::canonicalize(dir).unwrap()
+    }
+
+    fn job(source: &Path, destination: &Path) -> Vec<u8> {
+        serde_json::json!({ "source": source, "destination": destination }).to_string().into_bytes()
+    }
+
+    fn process(message: &[u8], policy: &JobPolicy) -> Result<(), c2pa::Error> {
+        let signer = SignerConfig::default().create_signer().unwrap();
+        process_job(message, crate::sign::DEFAULT_MANIFEST_DEFINITION, &*signer, policy)
+    }
+
+    #[test]
+    fn malformed_jobs_are_rejected() {
+        let policy = JobPolicy { max_size: u64::MAX, ..JobPolicy::default() };
+        for message in [&b"not json"[..], br#"{"source": "https://example.com/in.jpg"}"#, br#"{"source": 1, "destination": "https://example.com/out.jpg"}"#] {
+            assert!(matches!(process(message, &policy), Err(c2pa::Error::JsonError(_))), "{}", String::from_utf8_lossy(message));
+        }
+    }
+
+    #[test]
+    fn local_paths_are_refused_by_default() {
+        let dir = scratch_dir("default");
+        let policy = JobPolicy { max_size: u64::MAX, ..JobPolicy::default() };
+
+        let refused = process(&job(Path::new("test_file.jpg"), &dir.join("out.jpg")), &policy);
+        assert!(matches!(refused, Err(c2pa::Error::BadParam(message)) if message.contains("--allow-local-dir")));
+        let refused = process(br#"{"source": "https://example.com/in.jpg", "destination": "/tmp/out.jpg"}"#, &policy);
+        assert!(matches!(refused, Err(c2pa::Error::BadParam(message)) if message.contains("--allow-local-dir")));
+        assert!(!dir.join("out.jpg").exists());
+    }
+
+    #[test]
+    fn local_paths_outside_the_allowed_dirs_are_refused() {
+        let dir = scratch_dir("outside");
+        let policy = JobPolicy::new(std::slice::from_ref(&dir), u64::MAX).unwrap();
+
+        assert!(matches!(process(&job(Path::new("test_file.jpg"), &dir.join("out.jpg")), &policy), Err(c2pa::Error::BadParam(_))));
+        std::fs::copy("test_file.jpg", dir.join("in.jpg")).unwrap();
+        let escaping = dir.join("..").join(format!("c2pa-walkthrough-worker-escaped-{}.jpg", std::process::id()));
+        assert!(matches!(process(&job(&dir.join("in.jpg"), &escaping), &policy), Err(c2pa::Error::BadParam(_))));
+        assert!(!escaping.exists());
+    }
+
+    #[test]
+    fn local_paths_in_an_allowed_dir_are_signed() {
+        let dir = scratch_dir("allowed");
+        std::fs::copy("test_file.jpg", dir.join("in.jpg")).unwrap();
+        let policy = JobPolicy::new(std::slice::from_ref(&dir), u64::MAX).unwrap();
+
+        process(&job(&dir.join("in.jpg"), &dir.join("out.jpg")), &policy).unwrap();
+        assert!(crate::read::load_manifest_store("jpg", &std::fs::read(dir.join("out.jpg")).unwrap()).is_ok());
+    }
+
+    #[test]
+    fn sources_over_the_size_limit_are_refused() {
+        let dir = scratch_dir("too-large");
+        std::fs::copy("test_file.jpg", dir.join("in.jpg")).unwrap();
+        let policy = JobPolicy::new(std::slice::from_ref(&dir), 1024).unwrap();
+
+        let refused = process(&job(&dir.join("in.jpg"), &dir.join("out.jpg")), &policy);
+        assert!(matches!(refused, Err(c2pa::Error::BadParam(message)) if message.contains("larger than the limit")));
+        assert!(!dir.join("out.jpg").exists());
+    }
+}
@@ -0,0 +1,72 @@
+#![allow(dead_code)]
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+/* The asset the tests sign, from the repository root, where cargo runs them */
+pub const TEST_FILE: &str = "test_file.jpg";
+
+/* An empty directory of its own for each test, under the system temp directory */
+pub fn 
+scratch_dir (test: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("c2pa-walkthrough-{}-{}", test, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/* The binary with `args`, ready to run, for tests which set up more of its environment */
+pub fn 
+command (args: &[&str]) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_c2pa-walkthrough"));
+    command.args(["--no-color"])
+        .args(args)
+        .env_remove("C2PA_SIGNER_PROFILE")
+        .env_remove("C2PA_OUTPUT_DIR");
+    command
+}
+
+/* Runs the binary with `args`, whether or not it succeeds */
+pub fn 
+run_unchecked (args: &[&str]) -> Output {
+    command(args).output().unwrap()
+}
+
+/* Runs the binary with `args`, panicking with its stderr if it fails */
+pub fn 
+run (args: &[&str]) -> Output {
+    let output = run_unchecked(args);
+    assert!(output.status.success(), "{:?} failed: {}", args, String::from_utf8_lossy(&output.stderr));
+    output
+}
+
+/* Runs the binary with `args`, panicking if it succeeds */
+pub fn 
+run_failing (args: &[&str]) -> Output {
+    let output = run_unchecked(args);
+    assert!(!output.status.success(), "{:?} succeeded: {}", args, String::from_utf8_lossy(&output.stdout));
+    output
+}
+
+/* The JSON `read` prints of the asset at `path`, with any further `args` */
+pub fn 
+read_json (path: &Path, args: &[&str]) -> serde_json::Value {
+    let path = path.to_str().unwrap();
+    let output = run(&[&["read", path], args].concat());
+    serde_json::from_slice(&output.stdout).unwrap()
+}
+
+/* The active manifest in `read` JSON */
+pub fn 
+active_manifest (store: &serde_json::Value) -> &serde_json::Value {
+    &store["manifests"][store["active_manifest"].as_str().unwrap()]
+}
+
+/* The data of the assertion labeled `label` in a manifest of `read` JSON */
+pub fn 
+assertion<'a> (manifest: &'a serde_json::Value, label: &str) -> &'a serde_json::Value {
+    let assertion = manifest["assertions"].as_array().unwrap().iter()
+        .find(|assertion| assertion["label"] == label)
+        .unwrap_or_else(|| panic!("no {} assertion in {}", label, manifest));
+    &assertion["data"]
+}
@@ -0,0 +1,45 @@
+mod common;
+
+use common::{run, scratch_dir, TEST_FILE};
+
+/*
+ * Signs test_file.jpg with tests/golden/definition.json at a pinned time
+ * and compares the --canonical-json output of the dry run and of reading
+ * the signed asset with the golden files beside it. After a deliberate
+ * change, UPDATE_GOLDEN=1 rewrites the golden files instead.
+ */
+
+const DEFINITION: &str = "tests/golden/definition.json";
+const WHEN: &str = "2024-01-01T00:00:00Z";
+
+/* Compares `actual` with the golden file at `path`, or rewrites it under UPDATE_GOLDEN */
+fn 
+assert_golden (path: &str, actual: &[u8]) {
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::write(path, actual).unwrap();
+        return;
+    }
+
+    let expected = std::fs::read_to_string(path).unwrap();
+    let actual = String::from_utf8_lossy(actual);
+    if let Some((line, (expected, actual))) = expected.lines().zip(actual.lines()).enumerate().find(|(_, (expected, actual))| expected != actual) {
+        panic!("{} differs at line {}:\n  golden: {}\n  actual: {}\nrerun with UPDATE_GOLDEN=1 if the change is deliberate", path, line + 1, expected, actual);
+    }
+    assert_eq!(expected.lines().count(), actual.lines().count(), "{} differs in length; rerun with UPDATE_GOLDEN=1 if the change is deliberate", path);
+}
+
+#[test]
+fn dry_run_matches_golden_manifest() {
+    let output = run(&["sign", TEST_FILE, "--manifest", DEFINITION, "--when", WHEN, "--dry-run", "--canonical-json"]);
+    assert_golden("tests/golden/manifest.json", &output.stdout);
+}
+
+#[test]
+fn signed_store_matches_golden_store() {
+    let signed = scratch_dir("golden").join("signed.jpg");
+    let signed = signed.to_str().unwrap();
+    run(&["sign", TEST_FILE, "--manifest", DEFINITION, "--when", WHEN, "-o", signed]);
+
+    let output = run(&["read", signed, "--canonical-json"]);
+    assert_golden("tests/golden/store.json", &output.stdout);
+}
@@ -0,0 +1,104 @@
+mod common;
+
+use common::{active_manifest, assertion, read_json, run, run_failing, scratch_dir, TEST_FILE};
+
+/* Checks the active manifest of `read` JSON validated without failures */
+fn 
+assert_valid (store: &serde_json::Value) {
+    let failures = &store["validation_results"]["activeManifest"]["failure"];
+    assert!(failures.as_array().is_none_or(Vec::is_empty), "validation failed: {}", failures);
+}
+
+#[test]
+fn labeled_assertions_are_read_back() {
+    let dir = scratch_dir("labeled-assertions");
+    let manifest = dir.join("manifest.json");
+    std::fs::write(&manifest, r#"{
+        "claim_generator_info": [{"name": "tests", "version": "1.0"}],
+        "assertions": [
+            {"label": "com.example.note", "data": {"note": "hello"}},
+            {"label": "com.example.settings", "kind": "Json", "data": {"iso": 200, "lens": "50mm"}}
+        ]
+    }"#).unwrap();
+    let signed = dir.join("signed.jpg");
+    run(&["sign", "--manifest", manifest.to_str().unwrap(), TEST_FILE, "-o", signed.to_str().unwrap()]);
+
+    let store = read_json(&signed, &[]);
+    assert_valid(&store);
+    let manifest = active_manifest(&store);
+    assert_eq!(manifest["claim_generator_info"][0]["name"], "tests");
+    assert_eq!(assertion(manifest, "com.example.note"), &serde_json::json!({"note": "hello"}));
+    assert_eq!(assertion(manifest, "com.example.settings"), &serde_json::json!({"iso": 200, "lens": "50mm"}));
+    let settings = manifest["assertions"].as_array().unwrap().iter().find(|assertion| assertion["label"] == "com.example.settings").unwrap();
+    assert_eq!(settings["kind"], "Json");
+}
+
+#[test]
+fn edit_records_the_signed_asset_as_parent_ingredient() {
+    let dir = scratch_dir("parent-ingredient");
+    let signed = dir.join("signed.jpg");
+    let edited = dir.join("edited.jpg");
+    run(&["sign", TEST_FILE, "-o", signed.to_str().unwrap()]);
+    run(&["edit", "--action", "cropped", "-o", edited.to_str().unwrap(), signed.to_str().unwrap()]);
+
+    let parent = read_json(&signed, &[])["active_manifest"].clone();
+    let store = read_json(&edited, &[]);
+    assert_valid(&store);
+    let manifest = active_manifest(&store);
+    let ingredients = manifest["ingredients"].as_array().unwrap();
+    assert_eq!(ingredients.len(), 1);
+    assert_eq!(ingredients[0]["relationship"], "parentOf");
+    assert_eq!(ingredients[0]["title"], "signed.jpg");
+    assert_eq!(ingredients[0]["active_manifest"], parent);
+    assert!(store["manifests"][parent.as_str().unwrap()].is_object());
+
+    let actions = assertion(manifest, "c2pa.actions.v2")["actions"].as_array().unwrap();
+    assert!(actions.iter().any(|action| action["action"] == "c2pa.cropped"));
+}
+
+#[test]
+fn exif_assertions_are_read_back() {
+    let dir = scratch_dir("exif");
+    let manifest = dir.join("manifest.json");
+    std::fs::write(&manifest, r#"{
+        "claim_generator_info": [{"name": "tests", "version": "1.0"}],
+        "assertions": [{
+            "label": "stds.exif",
+            "kind": "Json",
+            "data": {
+                "@context": {"exif": "http://ns.adobe.com/exif/1.0/"},
+                "exif:FNumber": 5.6,
+                "exif:LensModel": "50mm"
+            }
+        }]
+    }"#).unwrap();
+    let signed = dir.join("signed.jpg");
+    run(&["sign", "--manifest", manifest.to_str().unwrap(), TEST_FILE, "-o", signed.to_str().unwrap()]);
+
+    let exif = read_json(&signed, &["--label", "stds.exif", "--select", ".data"]);
+    assert_eq!(exif["exif:FNumber"], 5.6);
+    assert_eq!(exif["exif:LensModel"], "50mm");
+    assert_valid(&read_json(&signed, &[]));
+}
+
+#[test]
+fn exif_captured_from_the_asset_is_read_back() {
+    let dir = scratch_dir("captured-exif");
+    let signed = dir.join("signed.jpg");
+    run(&["sign", "--strip-metadata", "exif", TEST_FILE, "-o", signed.to_str().unwrap()]);
+
+    let store = read_json(&signed, &[]);
+    assert_valid(&store);
+    let exif = assertion(active_manifest(&store), "stds.exif");
+    assert_eq!(exif["exif:DateTimeOriginal"], "2023:07:23 09:01:54");
+    assert_eq!(exif["exif:PixelXDimension"], 8082);
+    assert_eq!(exif["exif:PixelYDimension"], 3862);
+}
+
+#[test]
+fn backup_without_in_place_is_refused() {
+    let signed = scratch_dir("backup-without-in-place").join("signed.jpg");
+    let output = run_failing(&["sign", TEST_FILE, "-o", signed.to_str().unwrap(), "--backup"]);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--in-place"));
+    assert!(!signed.exists());
+}
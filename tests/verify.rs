@@ -0,0 +1,90 @@
+mod common;
+
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+
+use common::{command, run, run_failing, scratch_dir, TEST_FILE};
+
+/* The root the default signer's certificate chains to, and one it doesn't */
+const SIGNER_ROOT: &str = "../c2pa-rs/sdk/tests/fixtures/certs/ca.pem";
+const UNTRUSTED_ROOT: &str = "tests/fixtures/untrusted_root.pem";
+
+/**
+ * A cache directory already holding the trust list bundles verify fetches
+ * besides its anchors, so it runs offline: an allowed list trusting none of
+ * the certificates the tests sign with, and a trust config allowing their
+ * email protection EKU.
+ */
+fn 
+offline_trust_cache (dir: &Path) -> PathBuf {
+    let cache = dir.join("cache");
+    let trust_list = cache.join("c2pa-walkthrough").join("trust-list");
+    std::fs::create_dir_all(&trust_list).unwrap();
+    let bundles = [
+        (c2pa_walkthrough::trust_list::ALLOWED_LIST_URL, std::fs::read_to_string(UNTRUSTED_ROOT).unwrap()),
+        (c2pa_walkthrough::trust_list::TRUST_CONFIG_URL, "1.3.6.1.5.5.7.3.4\n".to_owned())
+    ];
+    for (url, bundle) in bundles {
+        let name = hex::encode(Sha256::digest(url.as_bytes()));
+        std::fs::write(trust_list.join(&name[..16]), bundle).unwrap();
+    }
+    cache
+}
+
+#[test]
+fn signed_asset_verifies() {
+    let signed = scratch_dir("verify-signed").join("signed.jpg");
+    let signed = signed.to_str().unwrap();
+    run(&["sign", TEST_FILE, "-o", signed]);
+
+    let output = run(&["verify", "--no-trust-list", signed]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains(": valid ("), "{}", stdout);
+    assert!(!stdout.contains("signer on the trust list"), "{}", stdout);
+}
+
+#[test]
+fn unfinalized_prepared_asset_fails_verify() {
+    let prepared = scratch_dir("verify-prepared").join("prepared.jpg");
+    let prepared = prepared.to_str().unwrap();
+    run(&["prepare", TEST_FILE, "-o", prepared]);
+
+    let output = run_failing(&["verify", "--no-trust-list", prepared]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("claimSignature.notValidated"), "{}", String::from_utf8_lossy(&output.stdout));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn signer_chaining_to_a_trust_anchor_is_noted_as_trusted() {
+    let dir = scratch_dir("verify-trusted");
+    let signed = dir.join("signed.jpg");
+    let signed = signed.to_str().unwrap();
+    run(&["sign", TEST_FILE, "-o", signed]);
+
+    let output = command(&["verify", "--trust-list-url", SIGNER_ROOT, signed])
+        .env("XDG_CACHE_HOME", offline_trust_cache(&dir))
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}{}", stdout, String::from_utf8_lossy(&output.stderr));
+    assert!(stdout.contains("signer on the trust list"), "{}", stdout);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn signer_outside_the_trust_list_is_not_noted_as_trusted() {
+    let dir = scratch_dir("verify-untrusted");
+    let signed = dir.join("signed.jpg");
+    let signed = signed.to_str().unwrap();
+    run(&["sign", TEST_FILE, "-o", signed]);
+
+    let output = command(&["verify", "--trust-list-url", UNTRUSTED_ROOT, signed])
+        .env("XDG_CACHE_HOME", offline_trust_cache(&dir))
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!output.status.success(), "{}", stdout);
+    assert!(stdout.contains("signingCredential.untrusted"), "{}", stdout);
+    assert!(!stdout.contains("signer on the trust list"), "{}", stdout);
+}